@@ -0,0 +1,122 @@
+use crate::{
+    default::Default, error::ManifestError, first_issue, project::Project, remote::Remote, Manifest,
+};
+
+/// Builds a [`Manifest`] in code, for tools generating one rather than reading it, e.g. from a list of
+/// repositories.
+///
+/// The elements are built with [`Remote::new`], [`Default::new`] and [`Project::new`] and their `with_`
+/// setters. [`ManifestBuilder::build`] checks the result like [`Manifest::validate`] does.
+#[derive(Debug, Clone)]
+pub struct ManifestBuilder {
+    remotes: Vec<Remote>,
+    default: Option<Default>,
+    projects: Vec<Project>,
+}
+
+impl ManifestBuilder {
+    pub fn new() -> Self {
+        ManifestBuilder {
+            remotes: Vec::new(),
+            default: None,
+            projects: Vec::new(),
+        }
+    }
+
+    /// Adds a remote element.
+    pub fn remote(mut self, remote: Remote) -> Self {
+        self.remotes.push(remote);
+        self
+    }
+
+    /// Sets the default element, replacing any set before.
+    pub fn default(mut self, default: Default) -> Self {
+        self.default = Some(default);
+        self
+    }
+
+    /// Adds a project element.
+    pub fn project(mut self, project: Project) -> Self {
+        self.projects.push(project);
+        self
+    }
+
+    /// The manifest, or the first problem [`Manifest::validate`] finds in it.
+    pub fn build(self) -> Result<Manifest, ManifestError> {
+        let manifest = Manifest {
+            notice: None,
+            remote: (!self.remotes.is_empty()).then_some(self.remotes),
+            default: self.default,
+            manifest_server: None,
+            remove_project: None,
+            project: (!self.projects.is_empty()).then_some(self.projects),
+            extend_project: None,
+            repo_hooks: None,
+            superproject: None,
+            submanifest: None,
+            contact_info: None,
+            include: None,
+            conflicting_defaults: false,
+        };
+
+        first_issue(manifest.validate())?;
+        Ok(manifest)
+    }
+}
+
+impl std::default::Default for ManifestBuilder {
+    fn default() -> Self {
+        ManifestBuilder::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ManifestBuilder;
+    use crate::{
+        default::Default, error::ManifestError, project::Project, remote::Remote, Manifest,
+    };
+    use quick_xml::{de::from_str, se::to_string};
+
+    #[test]
+    fn test_build() {
+        let manifest = ManifestBuilder::new()
+            .remote(Remote::new("origin", "https://example.com").with_review("review.example.com"))
+            .default(Default::new().with_remote("origin").with_revision("main"))
+            .project(
+                Project::new("platform/app")
+                    .with_path("app")
+                    .with_groups("apps")
+                    .with_annotation("TIER", "1"),
+            )
+            .project(Project::new("tools").with_revision("refs/tags/v1"))
+            .build()
+            .unwrap();
+
+        let projects = manifest.projects();
+        assert_eq!(projects[0].checkout_path(), "app");
+        assert_eq!(
+            manifest.project_revision(&projects[0]).as_deref(),
+            Some("main")
+        );
+        assert_eq!(
+            manifest.project_revision(&projects[1]).as_deref(),
+            Some("refs/tags/v1")
+        );
+
+        let reparsed: Manifest = from_str(&to_string(&manifest).unwrap()).unwrap();
+        assert_eq!(reparsed.projects().len(), 2);
+        assert_eq!(
+            reparsed.find_remote("origin").and_then(Remote::review),
+            Some("review.example.com")
+        );
+
+        assert!(matches!(
+            ManifestBuilder::new()
+                .remote(Remote::new("origin", "https://example.com"))
+                .project(Project::new("app").with_remote("upstream"))
+                .build(),
+            Err(ManifestError::UnknownRemote { .. })
+        ));
+    }
+}
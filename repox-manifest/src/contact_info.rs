@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+
+/// See [Google's documentation](https://gerrit.googlesource.com/git-repo/+/master/docs/manifest-format.md#Element-contactinfo)
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ContactInfo {
+    /// The URL to file a bug against the manifest owner.
+    #[serde(rename = "@bugurl")]
+    bug_url: String,
+}
+
+impl ContactInfo {
+    pub fn bug_url(&self) -> &str {
+        &self.bug_url
+    }
+}
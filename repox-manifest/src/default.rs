@@ -1,44 +1,154 @@
-use serde::Deserialize;
+use crate::{deserialize_bool, deserialize_count};
+use serde::{Deserialize, Serialize};
+use std::num::NonZeroUsize;
 
 /// See [Google's documentation](https://gerrit.googlesource.com/git-repo/+/master/docs/manifest-format.md#Element-default)
-#[derive(Debug, Clone, Deserialize)]
-pub(super) struct Default {
+#[derive(Debug, Clone, PartialEq, Eq, Default, Deserialize, Serialize)]
+pub struct Default {
     /// Name of a previously defined remote element.
     /// Project elements lacking a remote attribute of their own will use this remote.
-    #[serde(rename = "@remote")]
-    remote: Option<String>,
+    #[serde(rename = "@remote", skip_serializing_if = "Option::is_none")]
+    pub(super) remote: Option<String>,
 
     /// Name of a Git branch (e.g. master or refs/heads/master).
     /// Project elements lacking their own revision attribute will use this revision.
-    #[serde(rename = "@revision")]
-    revision: Option<String>,
+    #[serde(rename = "@revision", skip_serializing_if = "Option::is_none")]
+    pub(super) revision: Option<String>,
 
     /// Name of a Git branch (e.g. master).
     /// Project elements not setting their own dest-branch will inherit this value.
     /// If this value is not set, projects will use revision by default instead.
-    #[serde(rename = "@dest-branch")]
-    dest_branch: Option<String>,
+    #[serde(rename = "@dest-branch", skip_serializing_if = "Option::is_none")]
+    pub(super) dest_branch: Option<String>,
 
     /// Name of the Git ref in which a sha1 can be found.
     /// Used when syncing a revision locked manifest in -c mode to avoid having to sync the entire ref space.
     /// Project elements not setting their own upstream will inherit this value.
-    #[serde(rename = "@upstream")]
-    upstream: Option<String>,
+    #[serde(rename = "@upstream", skip_serializing_if = "Option::is_none")]
+    pub(super) upstream: Option<String>,
 
     /// Number of parallel jobs to use when synching.
-    #[serde(rename = "@sync-j")]
-    sync_j: Option<String>,
+    #[serde(
+        rename = "@sync-j",
+        default,
+        deserialize_with = "deserialize_count",
+        skip_serializing_if = "Option::is_none"
+    )]
+    sync_j: Option<NonZeroUsize>,
 
     /// Set to true to only sync the given Git branch (specified in the revision attribute) rather than the whole ref space.
     /// Project elements lacking a sync-c element of their own will use this value.
-    #[serde(rename = "@sync-c")]
-    sync_c: Option<String>,
+    #[serde(
+        rename = "@sync-c",
+        default,
+        deserialize_with = "deserialize_bool",
+        skip_serializing_if = "Option::is_none"
+    )]
+    sync_c: Option<bool>,
 
     /// Set to true to also sync sub-projects.
-    #[serde(rename = "@sync-s")]
-    sync_s: Option<String>,
+    #[serde(
+        rename = "@sync-s",
+        default,
+        deserialize_with = "deserialize_bool",
+        skip_serializing_if = "Option::is_none"
+    )]
+    sync_s: Option<bool>,
 
     /// Set to false to only sync the given Git branch (specified in the revision attribute) rather than the other ref tags.
-    #[serde(rename = "@sync-tags")]
-    sync_tags: Option<String>,
+    #[serde(
+        rename = "@sync-tags",
+        default,
+        deserialize_with = "deserialize_bool",
+        skip_serializing_if = "Option::is_none"
+    )]
+    sync_tags: Option<bool>,
+}
+
+impl Default {
+    /// A default element setting nothing.
+    pub fn new() -> Self {
+        <Self as std::default::Default>::default()
+    }
+
+    pub fn with_remote(mut self, remote: impl Into<String>) -> Self {
+        self.remote = Some(remote.into());
+        self
+    }
+
+    pub fn with_revision(mut self, revision: impl Into<String>) -> Self {
+        self.revision = Some(revision.into());
+        self
+    }
+
+    pub fn with_dest_branch(mut self, dest_branch: impl Into<String>) -> Self {
+        self.dest_branch = Some(dest_branch.into());
+        self
+    }
+
+    pub fn with_upstream(mut self, upstream: impl Into<String>) -> Self {
+        self.upstream = Some(upstream.into());
+        self
+    }
+
+    /// Sets sync-j, left unset for zero jobs.
+    pub fn with_sync_j(mut self, jobs: usize) -> Self {
+        self.sync_j = NonZeroUsize::new(jobs);
+        self
+    }
+
+    pub fn with_sync_c(mut self, sync_c: bool) -> Self {
+        self.sync_c = Some(sync_c);
+        self
+    }
+
+    pub fn with_sync_s(mut self, sync_s: bool) -> Self {
+        self.sync_s = Some(sync_s);
+        self
+    }
+
+    pub fn with_sync_tags(mut self, sync_tags: bool) -> Self {
+        self.sync_tags = Some(sync_tags);
+        self
+    }
+
+    /// The remote projects fetch from when they do not name one.
+    pub fn remote(&self) -> Option<&str> {
+        self.remote.as_deref()
+    }
+
+    /// The revision projects track when neither they nor their remote name one.
+    pub fn revision(&self) -> Option<&str> {
+        self.revision.as_deref()
+    }
+
+    /// The branch changes of projects without a dest-branch of their own are uploaded to.
+    pub fn dest_branch(&self) -> Option<&str> {
+        self.dest_branch.as_deref()
+    }
+
+    /// The ref pinned revisions of projects without an upstream of their own are found in.
+    pub fn upstream(&self) -> Option<&str> {
+        self.upstream.as_deref()
+    }
+
+    /// The sync-j attribute, if it is a positive number.
+    pub fn sync_j(&self) -> Option<usize> {
+        self.sync_j.map(NonZeroUsize::get)
+    }
+
+    /// The sync-c attribute: whether projects only fetch their revision rather than every branch.
+    pub fn sync_c(&self) -> Option<bool> {
+        self.sync_c
+    }
+
+    /// The sync-s attribute: whether the git submodules of projects are synced as sub-projects.
+    pub fn sync_s(&self) -> Option<bool> {
+        self.sync_s
+    }
+
+    /// The sync-tags attribute: whether projects fetch tags, which they do unless it is false.
+    pub fn sync_tags(&self) -> Option<bool> {
+        self.sync_tags
+    }
 }
@@ -0,0 +1,157 @@
+use crate::project::Project;
+use miette::Diagnostic;
+use thiserror::Error;
+
+/// A manifest that parsed, but cannot be checked out as described.
+#[derive(Debug, Error, Diagnostic)]
+pub enum ManifestError {
+    #[error(
+        "Projects {first}{} and {second}{} are both checked out at {path}",
+        declared_in(&sources.first),
+        declared_in(&sources.second)
+    )]
+    #[diagnostic(
+        code(repox::manifest::duplicate_path),
+        help("give one of the projects a different path attribute, or remove it with a remove-project element")
+    )]
+    DuplicatePath {
+        path: String,
+        first: String,
+        second: String,
+        sources: Box<Sources>,
+    },
+
+    #[error(
+        "Project {project}{} at {path} is nested inside project {parent}{} at {parent_path}",
+        declared_in(&sources.first),
+        declared_in(&sources.second)
+    )]
+    #[diagnostic(
+        code(repox::manifest::nested_path),
+        help("declare {project} as a child project element of {parent} to check it out inside {parent_path}")
+    )]
+    NestedPath {
+        project: String,
+        path: String,
+        parent: String,
+        parent_path: String,
+        sources: Box<Sources>,
+    },
+
+    #[error("{path} of {owner} and {other_path} of {other_owner} are the same path on case-insensitive filesystems")]
+    #[diagnostic(
+        code(repox::manifest::case_collision),
+        help("macOS and Windows would check one out over the other, give one of them a path differing in more than case")
+    )]
+    CaseCollision {
+        path: String,
+        owner: String,
+        other_path: String,
+        other_owner: String,
+    },
+
+    #[error("Project {project} {element} path {path} leaves the directory it is relative to")]
+    #[diagnostic(
+        code(repox::manifest::unsafe_file_path),
+        help("copyfile and linkfile paths must be relative, free of `..` components and outside .git and .repo directories")
+    )]
+    UnsafeFilePath {
+        project: String,
+        element: &'static str,
+        path: String,
+    },
+
+    #[error("extend-project {name} does not match any project{}", path.as_ref().map(|path| format!(" at {path}")).unwrap_or_default())]
+    #[diagnostic(
+        code(repox::manifest::unknown_extended_project),
+        help("check the name and path against `repox list`, the project may have been removed by a remove-project element")
+    )]
+    UnknownExtendedProject { name: String, path: Option<String> },
+
+    #[error("extend-project {name} moves {count} projects to {dest_path}")]
+    #[diagnostic(
+        code(repox::manifest::ambiguous_dest_path),
+        help("several projects are named {name}, add a path attribute to choose the one to move")
+    )]
+    AmbiguousDestPath {
+        name: String,
+        dest_path: String,
+        count: usize,
+    },
+
+    #[error("Remote {name} is declared more than once")]
+    #[diagnostic(
+        code(repox::manifest::duplicate_remote),
+        help("remove one of the remote elements, or give it another name")
+    )]
+    DuplicateRemote { name: String },
+
+    #[error("Project {project} fetches from remote {remote}, which is not declared")]
+    #[diagnostic(
+        code(repox::manifest::unknown_remote),
+        help("declare a remote element named {remote}, or fix the project's remote attribute")
+    )]
+    UnknownRemote { project: String, remote: String },
+
+    #[error("Project {project} names no remote and there is no default remote")]
+    #[diagnostic(
+        code(repox::manifest::no_remote),
+        help("give the project a remote attribute, or the default element one")
+    )]
+    NoRemote { project: String },
+
+    #[error("The manifest and the files it includes declare differing default elements")]
+    #[diagnostic(
+        code(repox::manifest::conflicting_defaults),
+        help("keep a single default element, the including manifest's is the one used")
+    )]
+    ConflictingDefaults,
+}
+
+/// An include element that could not be resolved.
+#[derive(Debug, Error, Diagnostic)]
+pub enum IncludeError {
+    #[error("Could not load {name}, included by {parent}")]
+    #[diagnostic(
+        code(repox::manifest::include_load),
+        help("include names are relative to the root of the manifest repository")
+    )]
+    LoadError {
+        name: String,
+        parent: String,
+        #[diagnostic_source]
+        error: Box<dyn Diagnostic + Send + Sync>,
+    },
+
+    #[error("Manifest {name} includes itself: {chain}")]
+    #[diagnostic(
+        code(repox::manifest::include_cycle),
+        help("remove one of the include elements along the cycle")
+    )]
+    Cycle { name: String, chain: String },
+}
+
+/// The manifest files declaring two conflicting projects, in the order the error names the projects.
+/// Either is unknown for manifests read without resolving their includes.
+#[derive(Debug, Default)]
+pub struct Sources {
+    pub first: Option<String>,
+    pub second: Option<String>,
+}
+
+impl Sources {
+    pub(crate) fn of(first: &Project, second: &Project) -> Box<Self> {
+        Box::new(Sources {
+            first: first.source().map(str::to_string),
+            second: second.source().map(str::to_string),
+        })
+    }
+}
+
+/// Names the manifest file a project comes from in a message, when it is known.
+fn declared_in(source: &Option<String>) -> String {
+    source
+        .as_ref()
+        .map(|source| format!(" (from {source})"))
+        .unwrap_or_default()
+}
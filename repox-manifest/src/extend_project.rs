@@ -1,27 +1,89 @@
-use serde::Deserialize;
+use crate::project::Project;
+use serde::{Deserialize, Serialize};
 
 /// See [Google's documentation](https://gerrit.googlesource.com/git-repo/+/master/docs/manifest-format.md#Element-extend_project)
-#[derive(Debug, Clone, Deserialize)]
-pub(super) struct ExtendProject {
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ExtendProject {
     #[serde(rename = "@name")]
     name: String,
 
     /// If specified, limit the change to projects checked out at the specified path, rather than all projects with the given name.
-    #[serde(rename = "@path")]
+    #[serde(rename = "@path", skip_serializing_if = "Option::is_none")]
     path: Option<String>,
 
     /// List of additional groups to which this project belongs.
     /// Same syntax as the corresponding element of project.
-    #[serde(rename = "@groups")]
+    #[serde(rename = "@groups", skip_serializing_if = "Option::is_none")]
     groups: Option<String>,
 
     /// If specified, overrides the revision of the original project.
     /// Same syntax as the corresponding element of project.
-    #[serde(rename = "@revision")]
+    #[serde(rename = "@revision", skip_serializing_if = "Option::is_none")]
     revision: Option<String>,
 
     /// If specified, overrides the remote of the original project.
     /// Same syntax as the corresponding element of project.
-    #[serde(rename = "@remote")]
+    #[serde(rename = "@remote", skip_serializing_if = "Option::is_none")]
     remote: Option<String>,
+
+    /// If specified, relocates the project's checkout to this path, relative to the top of the repo client.
+    /// Only allowed when path is given or the name matches a single project.
+    #[serde(rename = "@dest-path", skip_serializing_if = "Option::is_none")]
+    dest_path: Option<String>,
+}
+
+impl ExtendProject {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+
+    pub fn groups(&self) -> Option<&str> {
+        self.groups.as_deref()
+    }
+
+    pub fn revision(&self) -> Option<&str> {
+        self.revision.as_deref()
+    }
+
+    pub fn remote(&self) -> Option<&str> {
+        self.remote.as_deref()
+    }
+
+    pub fn dest_path(&self) -> Option<&str> {
+        self.dest_path.as_deref()
+    }
+
+    /// Whether this element applies to `project`.
+    pub(super) fn matches(&self, project: &Project) -> bool {
+        self.name == project.name
+            && self
+                .path
+                .as_deref()
+                .is_none_or(|path| path == project.checkout_path())
+    }
+
+    /// Applies this element's overrides to a matching project.
+    ///
+    /// The revision and remote replace the project's own, while the groups are added to its groups.
+    pub(super) fn apply(&self, project: &mut Project) {
+        if let Some(revision) = &self.revision {
+            project.revision = Some(revision.clone());
+        }
+        if let Some(remote) = &self.remote {
+            project.remote = Some(remote.clone());
+        }
+        if let Some(groups) = self.groups.as_deref().map(str::trim) {
+            project.groups = match project.groups.as_deref().map(str::trim) {
+                Some(existing) if !existing.is_empty() => Some(format!("{existing},{groups}")),
+                _ => Some(groups.to_string()),
+            };
+        }
+        if let Some(dest_path) = &self.dest_path {
+            project.relocate(dest_path);
+        }
+    }
 }
@@ -0,0 +1,128 @@
+use crate::project::Project;
+use std::{convert::Infallible, fmt, str::FromStr};
+
+/// Splits a groups attribute or group filter on commas and whitespace, as repo does.
+pub(crate) fn split_groups(groups: &str) -> impl Iterator<Item = &str> {
+    groups
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|group| !group.is_empty())
+}
+
+/// The groups a project is in.
+///
+/// These are the groups its groups attribute lists, and the implicit `all`, `name:<name>` and `path:<path>`
+/// groups, and `default` unless it is in `notdefault`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupSet {
+    groups: Vec<String>,
+}
+
+impl GroupSet {
+    pub fn of(project: &Project) -> Self {
+        let mut groups: Vec<String> = project.group_list().into_iter().map(String::from).collect();
+        groups.push("all".to_string());
+        groups.push(format!("name:{}", project.name));
+        groups.push(format!("path:{}", project.checkout_path()));
+        if !groups.iter().any(|group| group == "notdefault") {
+            groups.push("default".to_string());
+        }
+
+        GroupSet { groups }
+    }
+
+    pub fn contains(&self, group: &str) -> bool {
+        self.groups.iter().any(|candidate| candidate == group)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.groups.iter().map(String::as_str)
+    }
+}
+
+/// One group of a [`GroupFilter`], selecting the projects in it, or with a `-` prefix deselecting them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupTerm {
+    pub group: String,
+    pub excluded: bool,
+}
+
+impl fmt::Display for GroupTerm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.excluded {
+            f.write_str("-")?;
+        }
+        f.write_str(&self.group)
+    }
+}
+
+/// A comma or whitespace separated group filter such as `default,-notdefault`, as `--groups` takes it.
+///
+/// Terms are applied in order, a later one overriding an earlier one, so `all,-tools` selects every project
+/// outside the tools group. A project no term speaks for is not selected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupFilter {
+    terms: Vec<GroupTerm>,
+}
+
+impl GroupFilter {
+    pub fn parse(filter: &str) -> Self {
+        let terms = split_groups(filter)
+            .filter_map(|term| match term.strip_prefix('-') {
+                Some("") => None,
+                Some(group) => Some(GroupTerm {
+                    group: group.to_string(),
+                    excluded: true,
+                }),
+                None => Some(GroupTerm {
+                    group: term.to_string(),
+                    excluded: false,
+                }),
+            })
+            .collect();
+
+        GroupFilter { terms }
+    }
+
+    pub fn terms(&self) -> &[GroupTerm] {
+        &self.terms
+    }
+
+    /// Whether a project in `groups` is selected.
+    pub fn matches(&self, groups: &GroupSet) -> bool {
+        self.terms.iter().fold(false, |matched, term| {
+            if groups.contains(&term.group) {
+                !term.excluded
+            } else {
+                matched
+            }
+        })
+    }
+}
+
+/// The filter of workspaces initialized without `--groups`.
+impl Default for GroupFilter {
+    fn default() -> Self {
+        GroupFilter::parse("default")
+    }
+}
+
+impl FromStr for GroupFilter {
+    type Err = Infallible;
+
+    fn from_str(filter: &str) -> Result<Self, Self::Err> {
+        Ok(GroupFilter::parse(filter))
+    }
+}
+
+impl fmt::Display for GroupFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, term) in self.terms.iter().enumerate() {
+            if index > 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{term}")?;
+        }
+
+        Ok(())
+    }
+}
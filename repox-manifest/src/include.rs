@@ -1,9 +1,15 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 /// See [Google's documentation](https://gerrit.googlesource.com/git-repo/+/master/docs/manifest-format.md#Element-include)
-#[derive(Debug, Clone, Deserialize)]
-pub(super) struct Include {
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Include {
     /// the manifest to include, specified relative to the manifest repository's root.
     #[serde(rename = "@name")]
     name: String,
 }
+
+impl Include {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
@@ -0,0 +1,103 @@
+use crate::{url::project_url, Manifest};
+use serde::{Deserialize, Serialize};
+
+/// A manifest as JSON, for tools that would rather not parse the XML, as [`Manifest::to_json`] writes it.
+///
+/// Every project is listed with the remote, revision, dest-branch and upstream it effectively uses, so
+/// readers need not know the default element's rules:
+///
+/// ```json
+/// {
+///   "remotes": [
+///     { "name": "aosp", "alias": null, "fetch": "https://android.googlesource.com",
+///       "pushurl": null, "review": "https://android-review.googlesource.com", "revision": null }
+///   ],
+///   "projects": [
+///     { "name": "platform/build", "path": "build", "remote": "aosp",
+///       "url": "https://android.googlesource.com/platform/build.git", "revision": "main",
+///       "dest_branch": "main", "upstream": null, "groups": ["pdk"], "annotations": [["team", "build"]] }
+///   ]
+/// }
+/// ```
+///
+/// Absent settings are `null`, and annotations are `[name, value]` pairs, the remote's before the project's own.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestJson {
+    pub remotes: Vec<RemoteJson>,
+    pub projects: Vec<ProjectJson>,
+}
+
+/// A remote element as JSON.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RemoteJson {
+    pub name: String,
+    pub alias: Option<String>,
+    pub fetch: String,
+    pub pushurl: Option<String>,
+    pub review: Option<String>,
+    pub revision: Option<String>,
+}
+
+/// A project as JSON, with the settings it inherits resolved.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProjectJson {
+    pub name: String,
+
+    /// Where the project is checked out, relative to the top of the workspace.
+    pub path: String,
+
+    /// The name of the remote the project is fetched from, `null` if it names none the manifest declares.
+    pub remote: Option<String>,
+
+    /// The URL the project is cloned from.
+    pub url: Option<String>,
+
+    pub revision: Option<String>,
+    pub dest_branch: Option<String>,
+    pub upstream: Option<String>,
+    pub groups: Vec<String>,
+    pub annotations: Vec<(String, String)>,
+}
+
+impl From<&Manifest> for ManifestJson {
+    fn from(manifest: &Manifest) -> Self {
+        let remotes = manifest
+            .remotes()
+            .into_iter()
+            .map(|remote| RemoteJson {
+                alias: remote.alias().map(str::to_string),
+                pushurl: remote.pushurl().map(str::to_string),
+                review: remote.review().map(str::to_string),
+                revision: remote.revision().map(str::to_string),
+                name: remote.name,
+                fetch: remote.fetch,
+            })
+            .collect();
+
+        let projects = manifest
+            .projects()
+            .iter()
+            .map(|project| {
+                let remote = manifest.project_remote(project);
+
+                ProjectJson {
+                    name: project.name.clone(),
+                    path: project.checkout_path().to_string(),
+                    remote: remote.map(|remote| remote.name.clone()),
+                    url: remote.map(|remote| project_url(&remote.fetch, &project.name)),
+                    revision: manifest.project_revision(project),
+                    dest_branch: manifest.dest_branch(project),
+                    upstream: manifest.project_upstream(project),
+                    groups: project.group_list().into_iter().map(String::from).collect(),
+                    annotations: manifest
+                        .project_annotations(project)
+                        .into_iter()
+                        .map(|(name, value)| (name.to_string(), value.to_string()))
+                        .collect(),
+                }
+            })
+            .collect();
+
+        ManifestJson { remotes, projects }
+    }
+}
@@ -1,31 +1,202 @@
+pub mod builder;
+pub mod contact_info;
 pub mod default;
+pub mod error;
 pub mod extend_project;
+pub mod groups;
 pub mod include;
+pub mod json;
 pub mod manifest_server;
 pub mod notice;
+pub mod path;
 pub mod project;
 pub mod remote;
 pub mod remove_project;
 pub mod repo_hooks;
+pub mod resolved;
+pub mod submanifest;
+pub mod superproject;
+pub mod url;
 
 use self::{
-    extend_project::ExtendProject, include::Include, manifest_server::ManifestServer,
-    notice::Notice, project::Project, remote::Remote, remove_project::RemoveProject,
+    contact_info::ContactInfo,
+    error::{IncludeError, ManifestError, Sources},
+    extend_project::ExtendProject,
+    include::Include,
+    manifest_server::ManifestServer,
+    notice::Notice,
+    path::normalize_path,
+    project::Project,
+    remote::Remote,
+    remove_project::RemoveProject,
     repo_hooks::RepoHooks,
+    resolved::ResolvedProject,
+    submanifest::Submanifest,
+    superproject::Superproject,
 };
-use serde::Deserialize;
+use miette::Diagnostic;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    num::NonZeroUsize,
+};
+
+/// Parses a boolean attribute the way repo does, accepting `true`/`yes`/`1` and `false`/`no`/`0`.
+fn parse_bool(value: Option<&str>) -> Option<bool> {
+    match value?.to_lowercase().as_str() {
+        "true" | "yes" | "1" => Some(true),
+        "false" | "no" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+/// The value of a typed attribute: text when read from XML, the value it was serialized as when read back
+/// from a format with typed values such as JSON.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AttributeValue {
+    Bool(bool),
+    Count(usize),
+    Text(String),
+}
+
+/// Deserializes a boolean attribute with [`parse_bool`], a value it does not recognize counts as unset.
+pub(crate) fn deserialize_bool<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<bool>, D::Error> {
+    Ok(match Option::<AttributeValue>::deserialize(deserializer)? {
+        Some(AttributeValue::Bool(value)) => Some(value),
+        Some(AttributeValue::Text(value)) => parse_bool(Some(&value)),
+        Some(AttributeValue::Count(_)) | None => None,
+    })
+}
+
+/// Deserializes a count attribute such as sync-j, a value that is not a positive number counts as unset.
+pub(crate) fn deserialize_count<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<NonZeroUsize>, D::Error> {
+    Ok(match Option::<AttributeValue>::deserialize(deserializer)? {
+        Some(AttributeValue::Count(value)) => NonZeroUsize::new(value),
+        Some(AttributeValue::Text(value)) => value.trim().parse().ok(),
+        Some(AttributeValue::Bool(_)) | None => None,
+    })
+}
+
+/// The first of `issues`, as the error of a check stopping there.
+fn first_issue(issues: Vec<ManifestError>) -> Result<(), ManifestError> {
+    issues.into_iter().next().map_or(Ok(()), Err)
+}
+
+/// Whether `project` was nested, directly or not, in the element of the project named `ancestor`.
+fn is_nested_in(projects: &[Project], project: &Project, ancestor: &str) -> bool {
+    let mut parent = project.parent();
+    while let Some(name) = parent {
+        if name == ancestor {
+            return true;
+        }
+        parent = projects
+            .iter()
+            .find(|project| project.name == name)
+            .and_then(Project::parent);
+    }
+    false
+}
+
+/// The attributes of each element repox reads, by element name.
+const ATTRIBUTES: [(&str, &[&str]); 16] = [
+    ("manifest", &[]),
+    ("notice", &[]),
+    (
+        "remote",
+        &["name", "alias", "fetch", "pushurl", "review", "revision"],
+    ),
+    (
+        "default",
+        &[
+            "remote",
+            "revision",
+            "dest-branch",
+            "upstream",
+            "sync-j",
+            "sync-c",
+            "sync-s",
+            "sync-tags",
+        ],
+    ),
+    ("manifest-server", &["url"]),
+    ("remove-project", &["name", "path"]),
+    (
+        "project",
+        &[
+            "name",
+            "path",
+            "remote",
+            "revision",
+            "dest-branch",
+            "groups",
+            "sync-c",
+            "sync-s",
+            "sync-tags",
+            "upstream",
+            "clone-depth",
+            "force-path",
+        ],
+    ),
+    ("annotation", &["name", "value", "keep"]),
+    ("copyfile", &["src", "dest"]),
+    ("linkfile", &["src", "dest"]),
+    (
+        "extend-project",
+        &["name", "path", "groups", "revision", "remote", "dest-path"],
+    ),
+    ("repo-hooks", &["in-project", "enabled-list"]),
+    ("superproject", &["name", "remote", "revision"]),
+    (
+        "submanifest",
+        &[
+            "name",
+            "remote",
+            "project",
+            "manifest-name",
+            "revision",
+            "path",
+            "groups",
+            "default-groups",
+        ],
+    ),
+    ("contactinfo", &["bugurl"]),
+    ("include", &["name"]),
+];
+
+/// The attributes `element` is read with, or `None` when repox does not read the element at all.
+///
+/// Other attributes are dropped when parsing, so spotting them catches typos like `revison=`.
+pub fn known_attributes(element: &str) -> Option<&'static [&'static str]> {
+    ATTRIBUTES
+        .iter()
+        .find(|(name, _)| *name == element)
+        .map(|(_, attributes)| *attributes)
+}
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename = "manifest")]
 /// See [Google's documentation](https://gerrit.googlesource.com/git-repo/+/master/docs/manifest-format.md#Element-manifest) for more.
+///
+/// Serializing writes the elements back as they were read, leaving out absent attributes, so a manifest
+/// round-trips through quick-xml.
 pub struct Manifest {
+    #[serde(skip_serializing_if = "Option::is_none")]
     notice: Option<Notice>,
 
     /// One or more remote elements may be specified.
     /// Each remote element specifies a Git URL shared by one or more projects and (optionally) the Gerrit review server those projects upload changes through.
+    #[serde(skip_serializing_if = "Option::is_none")]
     remote: Option<Vec<Remote>>,
 
     /// At most one default element may be specified.
     /// Its remote and revision attributes are used when a project element does not specify its own remote or revision attribute.
+    #[serde(skip_serializing_if = "Option::is_none")]
     default: Option<self::default::Default>,
 
     /// At most one manifest-server may be specified.
@@ -50,49 +221,747 @@ pub struct Manifest {
     ///
     /// Return a manifest in which each project is pegged to the revision at the specified tag.
     /// This is used by repo sync when the --smart-tag option is given.
-    #[serde(rename = "manifest-server")]
+    #[serde(rename = "manifest-server", skip_serializing_if = "Option::is_none")]
     manifest_server: Option<ManifestServer>,
 
     /// Deletes the named project from the internal manifest table, possibly allowing a subsequent project element in the same manifest file to replace the project with a different source.
     ///
     /// This element is mostly useful in a local manifest file, where the user can remove a project, and possibly replace it with their own definition.
-    #[serde(rename = "remove-project")]
+    #[serde(rename = "remove-project", skip_serializing_if = "Option::is_none")]
     remove_project: Option<Vec<RemoveProject>>,
 
     /// One or more project elements may be specified.
     /// Each element describes a single Git repository to be cloned into the repo client workspace.
     /// You may specify Git-submodules by creating a nested project.
     /// Git-submodules will be automatically recognized and inherit their parent's attributes, but those may be overridden by an explicitly specified project element.
+    #[serde(skip_serializing_if = "Option::is_none")]
     project: Option<Vec<Project>>,
 
     /// Modify the attributes of the named project.
     ///
     /// This element is mostly useful in a local manifest file, to modify the attributes of an existing project without completely replacing the existing project definition.
     /// This makes the local manifest more robust against changes to the original manifest.
-    #[serde(rename = "extend-project")]
+    #[serde(rename = "extend-project", skip_serializing_if = "Option::is_none")]
     extend_project: Option<Vec<ExtendProject>>,
 
-    #[serde(rename = "repo-hooks")]
+    /// At most one repo-hooks element may be specified.
+    /// It names the project holding the hooks and which of them are enabled.
+    #[serde(rename = "repo-hooks", skip_serializing_if = "Option::is_none")]
     repo_hooks: Option<RepoHooks>,
 
+    /// At most one superproject element may be specified.
+    /// It names a Git repository holding every project as a submodule, pinning each to the commit it should be synced to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    superproject: Option<Superproject>,
+
+    /// Zero or more submanifest elements may be specified.
+    /// Each checks out another manifest, with all of its projects, into a subdirectory of this workspace.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    submanifest: Option<Vec<Submanifest>>,
+
+    /// At most one contactinfo element may be specified.
+    /// It lets the manifest's authors say where to report problems with it.
+    #[serde(rename = "contactinfo", skip_serializing_if = "Option::is_none")]
+    contact_info: Option<ContactInfo>,
+
     /// This element provides the capability of including another manifest file into the originating manifest.
     /// Normal rules apply for the target manifest to include - it must be a usable manifest on its own.
+    #[serde(skip_serializing_if = "Option::is_none")]
     include: Option<Vec<Include>>,
+
+    /// Whether an included or local manifest declared a default element differing from the one kept.
+    #[serde(skip)]
+    conflicting_defaults: bool,
 }
 
 impl Manifest {
+    /// The manifest's projects, without those remove-project elements delete and with any extend-project elements applied.
+    ///
+    /// Project elements nested in another are lifted out after it, with their parent's name and path prefixed
+    /// to theirs and the attributes they do not specify inherited from it.
+    ///
+    /// A remove-project element deletes every matching project of the manifest and the files it includes, so
+    /// replacing a project is done by removing it in a local manifest, which then declares the replacement.
     pub fn projects(&self) -> Vec<Project> {
-        self.project.clone().unwrap_or_default()
+        let mut projects = self.kept_projects();
+
+        for extend in self.extend_project.iter().flatten() {
+            projects
+                .iter_mut()
+                .filter(|project| extend.matches(project))
+                .for_each(|project| extend.apply(project));
+        }
+
+        projects
+    }
+
+    /// The manifest as commands work from it: nested project elements lifted out with their parent's name
+    /// and path prefixed, remove-project and extend-project elements applied, and the remote, revision,
+    /// dest-branch and upstream each project inherits set on it.
+    ///
+    /// Includes and local manifests must be applied first. Fails for a project without a declared remote.
+    pub fn flatten(&self) -> Result<Manifest, ManifestError> {
+        let mut projects = self.projects();
+        for project in &mut projects {
+            let resolved = self.resolve_project(project)?;
+            project.inherit(resolved);
+        }
+
+        Ok(Manifest {
+            project: Some(projects),
+            remove_project: None,
+            extend_project: None,
+            include: None,
+            ..self.clone()
+        })
+    }
+
+    /// Replaces the include elements with the contents of the manifests they name, recursively.
+    ///
+    /// `load` parses the manifest an include element names, relative to the root of the manifest repository.
+    /// Its errors are kept as diagnostics, so labels pointing into the included file are shown.
+    /// `name` is this manifest's own name, so an include cycle back to it is reported along with the files
+    /// it runs through. Included remotes, projects, submanifests, remove-project and extend-project elements
+    /// are appended in include order, while the default, notice, manifest-server and repo-hooks elements of the
+    /// including manifest take precedence over the included ones.
+    pub fn resolve_includes<E>(
+        self,
+        name: &str,
+        load: &mut impl FnMut(&str) -> Result<Manifest, E>,
+    ) -> Result<Manifest, IncludeError>
+    where
+        E: Into<Box<dyn Diagnostic + Send + Sync>>,
+    {
+        self.resolve_includes_within(&mut vec![name.to_string()], load)
+    }
+
+    fn resolve_includes_within<E>(
+        mut self,
+        chain: &mut Vec<String>,
+        load: &mut impl FnMut(&str) -> Result<Manifest, E>,
+    ) -> Result<Manifest, IncludeError>
+    where
+        E: Into<Box<dyn Diagnostic + Send + Sync>>,
+    {
+        if let Some(name) = chain.last() {
+            for project in self.project.iter_mut().flatten() {
+                project.declared_in(name);
+            }
+        }
+
+        for include in self.include.take().unwrap_or_default() {
+            let name = include.name();
+            if chain.iter().any(|parent| parent == name) {
+                return Err(IncludeError::Cycle {
+                    name: name.to_string(),
+                    chain: format!("{} -> {name}", chain.join(" -> ")),
+                });
+            }
+
+            let included = load(name).map_err(|error| IncludeError::LoadError {
+                name: name.to_string(),
+                parent: chain.last().cloned().unwrap_or_default(),
+                error: error.into(),
+            })?;
+            chain.push(name.to_string());
+            let included = included.resolve_includes_within(chain, load)?;
+            chain.pop();
+
+            self.merge(included);
+        }
+
+        Ok(self)
+    }
+
+    /// Applies a local manifest, from `.repo/local_manifests/`, on top of this one.
+    ///
+    /// The local manifest's remove-project elements delete the matching projects declared so far, so a
+    /// project can be replaced by the local manifest's own definition. Its remotes, projects and
+    /// extend-project elements are then added as for an include. Its own includes must be resolved first.
+    pub fn apply_local_manifest(&mut self, mut local: Manifest) {
+        let removed = local.remove_project.take().unwrap_or_default();
+        if let Some(projects) = &mut self.project {
+            projects.retain(|project| !removed.iter().any(|remove| remove.matches(project)));
+        }
+
+        self.merge(local);
+    }
+
+    /// Adds the elements of an included or local manifest to this one.
+    fn merge(&mut self, included: Manifest) {
+        fn append<T>(elements: &mut Option<Vec<T>>, included: Option<Vec<T>>) {
+            if let Some(included) = included {
+                elements.get_or_insert_with(Vec::new).extend(included);
+            }
+        }
+
+        self.notice = self.notice.take().or(included.notice);
+        if let (Some(default), Some(included)) = (&self.default, &included.default) {
+            self.conflicting_defaults |= default != included;
+        }
+        self.conflicting_defaults |= included.conflicting_defaults;
+        self.default = self.default.take().or(included.default);
+        self.manifest_server = self.manifest_server.take().or(included.manifest_server);
+        self.repo_hooks = self.repo_hooks.take().or(included.repo_hooks);
+        self.superproject = self.superproject.take().or(included.superproject);
+        self.contact_info = self.contact_info.take().or(included.contact_info);
+        append(&mut self.remote, included.remote);
+        append(&mut self.remove_project, included.remove_project);
+        append(&mut self.project, included.project);
+        append(&mut self.extend_project, included.extend_project);
+        append(&mut self.submanifest, included.submanifest);
+    }
+
+    /// The declared projects, without those a remove-project element drops.
+    fn kept_projects(&self) -> Vec<Project> {
+        let mut projects = Vec::new();
+        for project in self.project.iter().flatten() {
+            project.clone().lift_into(&mut projects);
+        }
+        projects.retain(|project| {
+            !self
+                .remove_projects()
+                .iter()
+                .any(|remove| remove.matches(project))
+        });
+        projects
+    }
+
+    /// Checks that every extend-project element applies to a project, rather than silently doing nothing, and
+    /// that a dest-path moves a single project, as git-repo requires.
+    pub fn check_extend_projects(&self) -> Result<(), ManifestError> {
+        first_issue(self.extend_project_issues())
+    }
+
+    /// Checks that no two projects share a checkout path and that no project is checked out inside another.
+    ///
+    /// Child project elements are checked out inside their parent on purpose and are not reported.
+    pub fn check_paths(&self) -> Result<(), ManifestError> {
+        first_issue(self.path_issues())
+    }
+
+    /// Checks that no project paths and copyfile or linkfile destinations are the same path when case is
+    /// ignored, as it is on macOS and Windows, stopping at the first that is.
+    pub fn check_case_collisions(&self) -> Result<(), ManifestError> {
+        first_issue(self.case_collisions())
+    }
+
+    /// Checks that the copyfile and linkfile elements of every project copy from inside the project and write
+    /// inside the repo client, stopping at the first that does not.
+    pub fn check_file_paths(&self) -> Result<(), ManifestError> {
+        first_issue(self.file_path_issues())
+    }
+
+    /// Lints the manifest for problems that parse fine but break a sync, reporting all of them rather than the first.
+    ///
+    /// Reports remotes declared twice, projects fetching from remotes that are not declared, include and local
+    /// manifests declaring differing default elements, and every problem [`Manifest::check_paths`],
+    /// [`Manifest::check_case_collisions`], [`Manifest::check_file_paths`] and
+    /// [`Manifest::check_extend_projects`] stop at.
+    pub fn validate(&self) -> Vec<ManifestError> {
+        let mut issues = Vec::new();
+
+        let mut declared = HashSet::new();
+        for remote in self.remote.iter().flatten() {
+            if !declared.insert(remote.name.as_str()) {
+                issues.push(ManifestError::DuplicateRemote {
+                    name: remote.name.clone(),
+                });
+            }
+        }
+
+        if self.conflicting_defaults {
+            issues.push(ManifestError::ConflictingDefaults);
+        }
+
+        for project in self.projects() {
+            if let Err(issue) = self.resolve_project(&project) {
+                issues.push(issue);
+            }
+        }
+
+        issues.extend(self.path_issues());
+        issues.extend(self.case_collisions());
+        issues.extend(self.file_path_issues());
+        issues.extend(self.extend_project_issues());
+        issues
+    }
+
+    fn case_collisions(&self) -> Vec<ManifestError> {
+        let projects = self.projects();
+        // Every path a sync writes, what writes it, and whether that is a project's checkout.
+        let mut written: Vec<(Cow<str>, String, bool)> = Vec::new();
+        for project in &projects {
+            let name = &project.name;
+            written.push((
+                normalize_path(project.checkout_path()),
+                format!("project {name}"),
+                true,
+            ));
+            for copyfile in project.copyfiles() {
+                let owner = format!("a copyfile of {name}");
+                written.push((normalize_path(copyfile.dest()), owner, false));
+            }
+            for linkfile in project.linkfiles() {
+                let owner = format!("a linkfile of {name}");
+                written.push((normalize_path(linkfile.dest()), owner, false));
+            }
+        }
+
+        let mut by_folded_path: HashMap<String, usize> = HashMap::new();
+        let mut issues = Vec::new();
+        for (index, (path, owner, checkout)) in written.iter().enumerate() {
+            let Some(&first) = by_folded_path.get(&path.to_lowercase()) else {
+                by_folded_path.insert(path.to_lowercase(), index);
+                continue;
+            };
+            let (other_path, other_owner, other_checkout) = &written[first];
+            // Projects sharing the exact same path are reported as duplicate paths.
+            if path == other_path && *checkout && *other_checkout {
+                continue;
+            }
+            issues.push(ManifestError::CaseCollision {
+                path: other_path.to_string(),
+                owner: other_owner.clone(),
+                other_path: path.to_string(),
+                other_owner: owner.clone(),
+            });
+        }
+
+        // A checkout under a path differing from another project's only in case is nested inside it. Those
+        // nested with the same case are already reported by the path checks.
+        let by_folded_checkout: HashMap<String, &Project> = projects
+            .iter()
+            .map(|project| {
+                (
+                    normalize_path(project.checkout_path()).to_lowercase(),
+                    project,
+                )
+            })
+            .collect();
+        for project in &projects {
+            let path = normalize_path(project.checkout_path());
+            let folded = path.to_lowercase();
+            let parent = folded
+                .match_indices('/')
+                .find_map(|(index, _)| by_folded_checkout.get(&folded[..index]));
+            let Some(parent) = parent else {
+                continue;
+            };
+            let parent_path = normalize_path(parent.checkout_path());
+            if path.starts_with(&format!("{parent_path}/"))
+                || is_nested_in(&projects, project, &parent.name)
+            {
+                continue;
+            }
+            issues.push(ManifestError::NestedPath {
+                project: project.name.clone(),
+                path: path.to_string(),
+                parent: parent.name.clone(),
+                parent_path: parent_path.to_string(),
+                sources: Sources::of(project, parent),
+            });
+        }
+
+        issues
+    }
+
+    fn file_path_issues(&self) -> Vec<ManifestError> {
+        let mut issues = Vec::new();
+        for project in self.projects() {
+            for copyfile in project.copyfiles() {
+                issues.extend(copyfile.check_paths(&project.name).err());
+            }
+            for linkfile in project.linkfiles() {
+                issues.extend(linkfile.check_paths(&project.name).err());
+            }
+        }
+
+        issues
+    }
+
+    fn extend_project_issues(&self) -> Vec<ManifestError> {
+        let projects = self.kept_projects();
+
+        self.extend_projects()
+            .iter()
+            .filter_map(|extend| {
+                let count = projects
+                    .iter()
+                    .filter(|project| extend.matches(project))
+                    .count();
+                match (count, extend.dest_path()) {
+                    (0, _) => Some(ManifestError::UnknownExtendedProject {
+                        name: extend.name().to_string(),
+                        path: extend.path().map(str::to_string),
+                    }),
+                    (2.., Some(dest_path)) => Some(ManifestError::AmbiguousDestPath {
+                        name: extend.name().to_string(),
+                        dest_path: dest_path.to_string(),
+                        count,
+                    }),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+
+    fn path_issues(&self) -> Vec<ManifestError> {
+        let projects = self.projects();
+        // Paths differing only in how their characters are composed collide on macOS.
+        let paths: Vec<_> = projects
+            .iter()
+            .map(|project| normalize_path(project.checkout_path()))
+            .collect();
+        let mut by_path: HashMap<&str, &Project> = HashMap::new();
+        let mut issues = Vec::new();
+
+        for (project, path) in projects.iter().zip(&paths) {
+            if let Some(first) = by_path.insert(path, project) {
+                issues.push(ManifestError::DuplicatePath {
+                    path: path.to_string(),
+                    first: first.name.clone(),
+                    second: project.name.clone(),
+                    sources: Sources::of(first, project),
+                });
+            }
+        }
+
+        for (project, path) in projects.iter().zip(&paths) {
+            let parent = path
+                .match_indices('/')
+                .find_map(|(index, _)| Some((&path[..index], by_path.get(&path[..index])?)));
+
+            if let Some((parent_path, parent)) = parent {
+                if is_nested_in(&projects, project, &parent.name) {
+                    continue;
+                }
+                issues.push(ManifestError::NestedPath {
+                    project: project.name.clone(),
+                    path: path.to_string(),
+                    parent: parent.name.clone(),
+                    parent_path: parent_path.to_string(),
+                    sources: Sources::of(project, parent),
+                });
+            }
+        }
+
+        issues
+    }
+
+    pub fn notice(&self) -> Option<&Notice> {
+        self.notice.as_ref()
+    }
+
+    /// The default element, whose attributes apply to the projects leaving them out. See
+    /// [`Manifest::resolve_project`] for a project's settings with them applied.
+    pub fn default(&self) -> Option<&self::default::Default> {
+        self.default.as_ref()
+    }
+
+    pub fn manifest_server(&self) -> Option<&ManifestServer> {
+        self.manifest_server.as_ref()
+    }
+
+    pub fn remove_projects(&self) -> &[RemoveProject] {
+        self.remove_project.as_deref().unwrap_or_default()
+    }
+
+    pub fn extend_projects(&self) -> &[ExtendProject] {
+        self.extend_project.as_deref().unwrap_or_default()
+    }
+
+    pub fn includes(&self) -> &[Include] {
+        self.include.as_deref().unwrap_or_default()
     }
 
     pub fn remotes(&self) -> Vec<Remote> {
         self.remote.clone().unwrap_or_default()
     }
+
+    pub fn repo_hooks(&self) -> Option<&RepoHooks> {
+        self.repo_hooks.as_ref()
+    }
+
+    pub fn superproject(&self) -> Option<&Superproject> {
+        self.superproject.as_ref()
+    }
+
+    pub fn contact_info(&self) -> Option<&ContactInfo> {
+        self.contact_info.as_ref()
+    }
+
+    pub fn submanifests(&self) -> Vec<Submanifest> {
+        self.submanifest.clone().unwrap_or_default()
+    }
+
+    /// The remote `submanifest`'s manifest project is fetched from: its own remote attribute, or the default element's.
+    pub fn submanifest_remote(&self, submanifest: &Submanifest) -> Option<&Remote> {
+        let name = submanifest
+            .remote()
+            .or_else(|| self.default.as_ref()?.remote.as_deref())?;
+
+        self.find_remote(name)
+    }
+
+    /// The revision of `submanifest`'s manifest project: its own revision attribute, its remote's, or the default element's.
+    pub fn submanifest_revision(&self, submanifest: &Submanifest) -> Option<String> {
+        submanifest
+            .revision()
+            .map(str::to_string)
+            .or_else(|| {
+                Some(
+                    self.submanifest_remote(submanifest)?
+                        .revision()?
+                        .to_string(),
+                )
+            })
+            .or_else(|| self.default.as_ref()?.revision.clone())
+    }
+
+    /// The remote the superproject is fetched from: its own remote attribute, or the default element's.
+    pub fn superproject_remote(&self) -> Option<&Remote> {
+        let superproject = self.superproject.as_ref()?;
+        let name = superproject
+            .remote()
+            .or_else(|| self.default.as_ref()?.remote.as_deref())?;
+
+        self.find_remote(name)
+    }
+
+    /// The branch the superproject is tracked from: its own revision attribute, its remote's, or the default element's.
+    pub fn superproject_revision(&self) -> Option<String> {
+        let superproject = self.superproject.as_ref()?;
+        superproject
+            .revision()
+            .map(str::to_string)
+            .or_else(|| Some(self.superproject_remote()?.revision()?.to_string()))
+            .or_else(|| self.default.as_ref()?.revision.clone())
+    }
+
+    /// Locks `project` to `commit`, as the superproject pins it, keeping the ref it tracked as its upstream.
+    ///
+    /// Fetching only the upstream then finds the commit, as for a manifest whose revisions are locked.
+    pub fn pin_project(&self, project: &mut Project, commit: &str) {
+        let upstream = self
+            .project_upstream(project)
+            .or_else(|| self.project_revision(project));
+        project.dest_branch = self.dest_branch(project);
+        project.pin(commit, upstream);
+    }
+
+    /// Locks the projects to the commits in `commits`, keyed by checkout path, as `repo manifest -r` does.
+    ///
+    /// Each keeps the ref it tracked as its upstream and the branch it uploads to as its dest-branch. Projects
+    /// without a commit are left as they are. Nested projects are only found once the manifest is flattened.
+    pub fn pin_projects(&mut self, commits: &HashMap<String, String>) {
+        let mut projects = self.project.take().unwrap_or_default();
+        for project in &mut projects {
+            if let Some(commit) = commits.get(project.checkout_path()) {
+                self.pin_project(project, commit);
+            }
+        }
+        self.project = Some(projects);
+    }
+
+    /// The remotes no project fetches from.
+    pub fn unused_remotes(&self) -> Vec<&Remote> {
+        let projects = self.projects();
+        let used: Vec<&str> = projects
+            .iter()
+            .filter_map(|project| self.project_remote(project))
+            .map(|remote| remote.name.as_str())
+            .collect();
+
+        self.remote
+            .iter()
+            .flatten()
+            .filter(|remote| !used.contains(&remote.name.as_str()))
+            .collect()
+    }
+
+    /// Finds a remote by its name.
+    pub fn find_remote(&self, name: &str) -> Option<&Remote> {
+        self.remote
+            .as_ref()?
+            .iter()
+            .find(|remote| remote.name == name)
+    }
+
+    /// The remote a project fetches from: its own remote attribute, or the default element's.
+    pub fn project_remote(&self, project: &Project) -> Option<&Remote> {
+        let name = project
+            .remote
+            .as_deref()
+            .or_else(|| self.default.as_ref()?.remote.as_deref())?;
+
+        self.find_remote(name)
+    }
+
+    /// A project's remote, revision, dest-branch and upstream, each inherited as repo documents when the
+    /// project does not set it. Fails when the project has no remote, or names one that is not declared.
+    pub fn resolve_project(&self, project: &Project) -> Result<ResolvedProject<'_>, ManifestError> {
+        let default = self.default.as_ref();
+        let name = project
+            .remote
+            .as_deref()
+            .or_else(|| default?.remote.as_deref())
+            .ok_or_else(|| ManifestError::NoRemote {
+                project: project.name.clone(),
+            })?;
+        let remote = self
+            .find_remote(name)
+            .ok_or_else(|| ManifestError::UnknownRemote {
+                project: project.name.clone(),
+                remote: name.to_string(),
+            })?;
+
+        let revision = project
+            .revision
+            .clone()
+            .or_else(|| remote.revision().map(str::to_string))
+            .or_else(|| default?.revision.clone());
+        let dest_branch = project
+            .dest_branch
+            .clone()
+            .or_else(|| default?.dest_branch.clone())
+            .or_else(|| revision.clone());
+        let upstream = project
+            .upstream()
+            .map(str::to_string)
+            .or_else(|| default?.upstream.clone());
+
+        Ok(ResolvedProject {
+            remote,
+            revision,
+            dest_branch,
+            upstream,
+            sync_c: self.project_sync_c(project),
+            sync_s: self.project_sync_s(project),
+            sync_tags: self.project_sync_tags(project),
+        })
+    }
+
+    /// The revision a project tracks: its own revision attribute, its remote's, or the default element's.
+    pub fn project_revision(&self, project: &Project) -> Option<String> {
+        project
+            .revision
+            .clone()
+            .or_else(|| Some(self.project_remote(project)?.revision()?.to_string()))
+            .or_else(|| self.default.as_ref()?.revision.clone())
+    }
+
+    /// A project's annotations merged with those of its remote, the project's taking precedence.
+    pub fn project_annotations<'a>(&'a self, project: &'a Project) -> Vec<(&'a str, &'a str)> {
+        let mut annotations: Vec<(&str, &str)> = project.annotations().collect();
+
+        if let Some(remote) = self.project_remote(project) {
+            let inherited: Vec<_> = remote
+                .annotations()
+                .filter(|(name, _)| !annotations.iter().any(|(own, _)| own == name))
+                .collect();
+            annotations.splice(0..0, inherited);
+        }
+
+        annotations
+    }
+
+    /// The value of a project's annotation `name`, its remote's when the project has none of its own.
+    pub fn project_annotation<'a>(&'a self, project: &'a Project, name: &str) -> Option<&'a str> {
+        self.project_annotations(project)
+            .into_iter()
+            .find(|(own, _)| *own == name)
+            .map(|(_, value)| value)
+    }
+
+    /// The projects annotated with `name`, with their value of it, only those where it is `value` when
+    /// one is given. Downstream tooling can select projects on metadata the manifest carries this way,
+    /// e.g. the projects whose `BUILD_TARGET` is `firmware`.
+    pub fn annotated_projects(&self, name: &str, value: Option<&str>) -> Vec<(Project, String)> {
+        self.projects()
+            .into_iter()
+            .filter_map(|project| {
+                let annotation = self.project_annotation(&project, name)?.to_string();
+                Some((project, annotation))
+            })
+            .filter(|(_, annotation)| value.is_none_or(|value| annotation == value))
+            .collect()
+    }
+
+    /// The ref a project's pinned revision can be found in: its own upstream attribute, or the default element's.
+    pub fn project_upstream(&self, project: &Project) -> Option<String> {
+        project
+            .upstream()
+            .map(str::to_string)
+            .or_else(|| self.default.as_ref()?.upstream.clone())
+    }
+
+    /// The number of parallel jobs the manifest suggests syncing with, from the default element's sync-j attribute.
+    pub fn sync_j(&self) -> Option<usize> {
+        self.default.as_ref()?.sync_j()
+    }
+
+    /// Whether only a project's revision should be fetched rather than the whole ref space: its own sync-c attribute, or the default element's.
+    pub fn project_sync_c(&self, project: &Project) -> bool {
+        project
+            .sync_c()
+            .or_else(|| self.default.as_ref()?.sync_c())
+            .unwrap_or(false)
+    }
+
+    /// Whether a project's git submodules are synced as sub-projects: its own sync-s attribute, or the default element's.
+    pub fn project_sync_s(&self, project: &Project) -> bool {
+        project
+            .sync_s()
+            .or_else(|| self.default.as_ref()?.sync_s())
+            .unwrap_or(false)
+    }
+
+    /// Whether a project's tags are fetched along with its branches: its own sync-tags attribute, or the default
+    /// element's, which they are unless set to false.
+    pub fn project_sync_tags(&self, project: &Project) -> bool {
+        project
+            .sync_tags()
+            .or_else(|| self.default.as_ref()?.sync_tags())
+            .unwrap_or(true)
+    }
+
+    /// The manifest as JSON, in the shape [`ManifestJson`](json::ManifestJson) documents.
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(&json::ManifestJson::from(self))
+    }
+
+    /// The manifest as JSON like [`Manifest::to_json`], indented for humans to read.
+    pub fn to_json_pretty(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(&json::ManifestJson::from(self))
+    }
+
+    /// The branch `repo upload` submits a project's changes for review on.
+    ///
+    /// Uses the project's dest-branch, falling back to the default element's dest-branch,
+    /// then to the revision the project tracks.
+    pub fn dest_branch(&self, project: &Project) -> Option<String> {
+        let default = self.default.as_ref();
+
+        project
+            .dest_branch
+            .clone()
+            .or_else(|| default.and_then(|default| default.dest_branch.clone()))
+            .or_else(|| self.project_revision(project))
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::Manifest;
+    use crate::{
+        error::{IncludeError, ManifestError},
+        groups::{GroupFilter, GroupTerm},
+        known_attributes,
+        path::normalize_path,
+        project::Project,
+        superproject::parse_gitlinks,
+        Manifest,
+    };
     use insta::assert_debug_snapshot;
     use quick_xml::de::from_str;
 
@@ -102,6 +971,878 @@ mod tests {
 
         let parsed: Manifest = from_str(manifest_contents).unwrap();
 
+        assert!(parsed.check_paths().is_ok());
         assert_debug_snapshot!(parsed);
     }
+
+    #[test]
+    fn test_to_json() {
+        let parsed: Manifest = from_str(
+            r#"<manifest>
+                <remote name="aosp" fetch="https://android.googlesource.com/" />
+                <default remote="aosp" revision="main" />
+                <project name="platform/build" path="build" groups="pdk">
+                    <annotation name="team" value="build" />
+                </project>
+            </manifest>"#,
+        )
+        .unwrap();
+
+        let json: serde_json::Value = serde_json::from_str(&parsed.to_json().unwrap()).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "remotes": [{
+                    "name": "aosp",
+                    "alias": null,
+                    "fetch": "https://android.googlesource.com/",
+                    "pushurl": null,
+                    "review": null,
+                    "revision": null,
+                }],
+                "projects": [{
+                    "name": "platform/build",
+                    "path": "build",
+                    "remote": "aosp",
+                    "url": "https://android.googlesource.com/platform/build.git",
+                    "revision": "main",
+                    "dest_branch": "main",
+                    "upstream": null,
+                    "groups": ["pdk"],
+                    "annotations": [["team", "build"]],
+                }],
+            })
+        );
+    }
+
+    #[test]
+    fn test_serde_json_round_trip() {
+        let parsed: Manifest = from_str(
+            r#"<manifest>
+                <notice>Read me</notice>
+                <remote name="aosp" fetch="https://android.googlesource.com/" />
+                <default remote="aosp" revision="main" sync-j="4" sync-c="yes" />
+                <project name="build" sync-tags="false" clone-depth="1" />
+            </manifest>"#,
+        )
+        .unwrap();
+
+        let json = serde_json::to_string(&parsed).unwrap();
+        let read_back: Manifest = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            quick_xml::se::to_string(&read_back).unwrap(),
+            quick_xml::se::to_string(&parsed).unwrap()
+        );
+        assert_eq!(read_back.sync_j(), Some(4));
+    }
+
+    #[test]
+    fn test_dest_branch_fallback() {
+        let parsed: Manifest = from_str(
+            r#"<manifest>
+                <default revision="main" dest-branch="release" />
+                <project name="explicit" revision="dev" dest-branch="staging" />
+                <project name="inherited" revision="dev" />
+            </manifest>"#,
+        )
+        .unwrap();
+        let projects = parsed.projects();
+
+        assert_eq!(parsed.dest_branch(&projects[0]).as_deref(), Some("staging"));
+        assert_eq!(parsed.dest_branch(&projects[1]).as_deref(), Some("release"));
+
+        let parsed: Manifest = from_str(
+            r#"<manifest>
+                <default revision="main" />
+                <project name="revision" revision="dev" />
+                <project name="default" />
+            </manifest>"#,
+        )
+        .unwrap();
+        let projects = parsed.projects();
+
+        assert_eq!(parsed.dest_branch(&projects[0]).as_deref(), Some("dev"));
+        assert_eq!(parsed.dest_branch(&projects[1]).as_deref(), Some("main"));
+    }
+
+    #[test]
+    fn test_unused_remotes() {
+        let parsed: Manifest = from_str(
+            r#"<manifest>
+                <remote name="origin" fetch="https://example.com" />
+                <remote name="github" fetch="https://github.com" />
+                <remote name="stale" fetch="https://stale.example.com" />
+                <default remote="origin" revision="main" />
+                <project name="app" />
+                <project name="tool" remote="github" />
+            </manifest>"#,
+        )
+        .unwrap();
+
+        let unused: Vec<&str> = parsed
+            .unused_remotes()
+            .iter()
+            .map(|remote| remote.name.as_str())
+            .collect();
+        assert_eq!(unused, vec!["stale"]);
+
+        assert!(known_attributes("project")
+            .unwrap()
+            .contains(&"clone-depth"));
+        assert_eq!(known_attributes("contactinfo"), Some(&["bugurl"][..]));
+        assert_eq!(known_attributes("projects"), None);
+    }
+
+    #[test]
+    fn test_serialize_round_trips() {
+        let xml = concat!(
+            "<manifest>",
+            "<notice>Sync with care.</notice>",
+            r#"<remote name="origin" fetch="https://example.com" review="review.example.com">"#,
+            r#"<annotation name="team" value="infra"/>"#,
+            "</remote>",
+            r#"<default remote="origin" revision="main" sync-j="4"/>"#,
+            r#"<remove-project name="old"/>"#,
+            r#"<project name="app" path="src/app" groups="apps,notdefault">"#,
+            r#"<annotation name="owner" value="me" keep="false"/>"#,
+            r#"<project name="plugin"/>"#,
+            r#"<copyfile src="Makefile" dest="Makefile"/>"#,
+            r#"<linkfile src="tools" dest="tools"/>"#,
+            "</project>",
+            r#"<extend-project name="app" revision="stable"/>"#,
+            r#"<repo-hooks in-project="app" enabled-list="pre-upload"/>"#,
+            r#"<contactinfo bugurl="https://example.com/bugs"/>"#,
+            r#"<include name="extra.xml"/>"#,
+            "</manifest>",
+        );
+
+        let parsed: Manifest = from_str(xml).unwrap();
+        let serialized = quick_xml::se::to_string(&parsed).unwrap();
+        assert_eq!(serialized, xml);
+    }
+
+    #[test]
+    fn test_accessors() {
+        let parsed: Manifest = from_str(
+            r#"<manifest>
+                <notice>Sync with care.</notice>
+                <remote name="origin" fetch="https://example.com" />
+                <default remote="origin" revision="main" sync-c="true" sync-j="8" />
+                <manifest-server url="http://manifest.example.com/RPC2" />
+                <project name="app" clone-depth="1">
+                    <copyfile src="Makefile" dest="Makefile" />
+                </project>
+                <include name="extra.xml" />
+            </manifest>"#,
+        )
+        .unwrap();
+
+        assert_eq!(parsed.notice().unwrap().text(), "Sync with care.");
+        let default = parsed.default().unwrap();
+        assert_eq!(default.remote(), Some("origin"));
+        assert_eq!(default.sync_c(), Some(true));
+        assert_eq!(default.sync_s(), None);
+        assert_eq!(
+            parsed.manifest_server().unwrap().url(),
+            "http://manifest.example.com/RPC2"
+        );
+        assert_eq!(parsed.includes()[0].name(), "extra.xml");
+
+        let projects = parsed.projects();
+        assert_eq!(projects[0].clone_depth(), Some(1));
+        assert_eq!(projects[0].copyfiles()[0].dest(), "Makefile");
+    }
+
+    #[test]
+    fn test_matches_groups() {
+        let parsed: Manifest = from_str(
+            r#"<manifest>
+                <project name="plain" />
+                <project name="tools" path="prebuilts/tools" groups="notdefault,tools" />
+            </manifest>"#,
+        )
+        .unwrap();
+        let projects = parsed.projects();
+
+        assert!(projects[0].matches_groups("default"));
+        assert!(!projects[1].matches_groups("default"));
+        assert!(projects[1].matches_groups("default,tools"));
+        assert!(projects[1].matches_groups("all"));
+        assert!(!projects[0].matches_groups("all,-name:plain"));
+        assert!(projects[1].matches_groups("path:prebuilts/tools"));
+    }
+
+    #[test]
+    fn test_resolve_project() {
+        let parsed: Manifest = from_str(
+            r#"<manifest>
+                <remote name="origin" fetch="https://example.com" />
+                <remote name="vendor" fetch="https://vendor.example.com" revision="stable" />
+                <default remote="origin" revision="main" upstream="main" dest-branch="review" sync-c="true" />
+                <project name="app" />
+                <project name="lib" remote="vendor" upstream="release" sync-c="false" sync-tags="false" />
+                <project name="tool" remote="missing" />
+            </manifest>"#,
+        )
+        .unwrap();
+        let projects = parsed.projects();
+
+        let app = parsed.resolve_project(&projects[0]).unwrap();
+        assert_eq!(app.remote.name, "origin");
+        assert_eq!(app.revision.as_deref(), Some("main"));
+        assert_eq!(app.dest_branch.as_deref(), Some("review"));
+        assert_eq!(app.upstream.as_deref(), Some("main"));
+        assert!(app.sync_c && !app.sync_s && app.sync_tags);
+
+        let lib = parsed.resolve_project(&projects[1]).unwrap();
+        assert_eq!(lib.remote.name, "vendor");
+        assert_eq!(lib.revision.as_deref(), Some("stable"));
+        assert_eq!(lib.upstream.as_deref(), Some("release"));
+        assert!(!lib.sync_c && !lib.sync_tags);
+
+        assert!(matches!(
+            parsed.resolve_project(&projects[2]),
+            Err(ManifestError::UnknownRemote { .. })
+        ));
+
+        let parsed: Manifest =
+            from_str(r#"<manifest><project name="orphan" /></manifest>"#).unwrap();
+        assert!(matches!(
+            parsed.resolve_project(&parsed.projects()[0]),
+            Err(ManifestError::NoRemote { .. })
+        ));
+    }
+
+    #[test]
+    fn test_flatten() {
+        let parsed: Manifest = from_str(
+            r#"<manifest>
+                <remote name="origin" fetch="https://example.com" revision="stable" />
+                <default remote="origin" />
+                <remove-project name="old" />
+                <project name="platform" path="src">
+                    <project name="vendor" path="third_party/">
+                        <project name="zlib" />
+                    </project>
+                </project>
+                <project name="old" />
+                <extend-project name="platform/vendor" revision="pinned" />
+            </manifest>"#,
+        )
+        .unwrap();
+        let flattened = parsed.flatten().unwrap();
+        let projects = flattened.projects();
+
+        assert_eq!(
+            projects
+                .iter()
+                .map(|project| (project.name.as_str(), project.checkout_path()))
+                .collect::<Vec<_>>(),
+            [
+                ("platform", "src"),
+                ("platform/vendor", "src/third_party"),
+                ("platform/vendor/zlib", "src/third_party/zlib"),
+            ]
+        );
+        assert!(projects
+            .iter()
+            .all(|project| project.subprojects().is_empty()));
+        assert_eq!(projects[0].remote.as_deref(), Some("origin"));
+        assert_eq!(projects[0].revision.as_deref(), Some("stable"));
+        assert_eq!(projects[1].revision.as_deref(), Some("pinned"));
+        assert_eq!(projects[1].dest_branch.as_deref(), Some("pinned"));
+        assert!(flattened.remove_projects().is_empty());
+        assert!(flattened.extend_projects().is_empty());
+
+        let parsed: Manifest =
+            from_str(r#"<manifest><project name="orphan" /></manifest>"#).unwrap();
+        assert!(matches!(
+            parsed.flatten(),
+            Err(ManifestError::NoRemote { .. })
+        ));
+    }
+
+    #[test]
+    fn test_nested_projects() {
+        let parsed: Manifest = from_str(
+            r#"<manifest>
+                <remote name="origin" fetch="https://example.com" />
+                <remote name="mirror" fetch="https://mirror.example.com" />
+                <default remote="origin" revision="main" />
+                <project name="platform" path="src" remote="mirror" revision="stable" groups="notdefault">
+                    <project name="vendor" path="third_party" groups="vendor">
+                        <project name="zlib" revision="v1.3" />
+                    </project>
+                </project>
+                <remove-project name="platform/vendor/zlib" />
+            </manifest>"#,
+        )
+        .unwrap();
+        let projects = parsed.projects();
+
+        assert_eq!(
+            projects
+                .iter()
+                .map(|project| (
+                    project.name.as_str(),
+                    project.checkout_path(),
+                    project.parent()
+                ))
+                .collect::<Vec<_>>(),
+            [
+                ("platform", "src", None),
+                ("platform/vendor", "src/third_party", Some("platform")),
+            ]
+        );
+        assert_eq!(projects[1].remote.as_deref(), Some("mirror"));
+        assert_eq!(projects[1].revision.as_deref(), Some("stable"));
+        assert_eq!(projects[1].groups.as_deref(), Some("vendor"));
+        assert!(parsed.check_paths().is_ok());
+
+        let parsed: Manifest = from_str(
+            r#"<manifest>
+                <project name="platform" path="src">
+                    <project name="vendor">
+                        <project name="zlib" revision="v1.3" />
+                    </project>
+                </project>
+                <project name="tools" path="src/tools" />
+            </manifest>"#,
+        )
+        .unwrap();
+        let projects = parsed.projects();
+        assert_eq!(projects[2].checkout_path(), "src/vendor/zlib");
+        assert_eq!(projects[2].revision.as_deref(), Some("v1.3"));
+        assert!(matches!(
+            parsed.check_paths(),
+            Err(ManifestError::NestedPath { project, .. }) if project == "tools"
+        ));
+    }
+
+    #[test]
+    fn test_group_filter() {
+        let parsed: Manifest = from_str(
+            r#"<manifest>
+                <project name="app" groups="apps" />
+                <project name="tools" groups="notdefault tools" />
+            </manifest>"#,
+        )
+        .unwrap();
+        let projects = parsed.projects();
+        let app = projects[0].group_set();
+        let tools = projects[1].group_set();
+        assert_eq!(
+            tools.iter().collect::<Vec<_>>(),
+            ["notdefault", "tools", "all", "name:tools", "path:tools"]
+        );
+
+        let filter = GroupFilter::parse("all, -notdefault,,-");
+        assert_eq!(filter.to_string(), "all,-notdefault");
+        assert_eq!(
+            filter.terms()[1],
+            GroupTerm {
+                group: "notdefault".to_string(),
+                excluded: true,
+            }
+        );
+        assert!(filter.matches(&app));
+        assert!(!filter.matches(&tools));
+
+        let filter: GroupFilter = "-apps default".parse().unwrap();
+        assert!(filter.matches(&app));
+        assert!(!GroupFilter::default().matches(&tools));
+        assert!(!GroupFilter::parse("").matches(&app));
+    }
+
+    #[test]
+    fn test_sync_j() {
+        let parse = |default: &str| -> Manifest {
+            from_str(&format!("<manifest>{default}</manifest>")).unwrap()
+        };
+
+        assert_eq!(parse(r#"<default sync-j="4" />"#).sync_j(), Some(4));
+        assert_eq!(parse(r#"<default sync-j="0" />"#).sync_j(), None);
+        assert_eq!(parse(r#"<default sync-j="many" />"#).sync_j(), None);
+        assert_eq!(parse("").sync_j(), None);
+
+        let default = parse(r#"<default sync-c="1" sync-s="No" sync-tags="maybe" />"#);
+        let default = default.default().unwrap();
+        assert_eq!(default.sync_c(), Some(true));
+        assert_eq!(default.sync_s(), Some(false));
+        assert_eq!(default.sync_tags(), None);
+    }
+
+    #[test]
+    fn test_check_paths() {
+        let parse = |projects: &str| -> Manifest {
+            from_str(&format!("<manifest>{projects}</manifest>")).unwrap()
+        };
+
+        assert!(parse(
+            r#"<project name="a" /><project name="ab" /><project name="b" path="a-b" />"#
+        )
+        .check_paths()
+        .is_ok());
+        assert!(matches!(
+            parse(r#"<project name="a" path="x" /><project name="b" path="x/" />"#).check_paths(),
+            Err(ManifestError::DuplicatePath { first, second, .. }) if first == "a" && second == "b"
+        ));
+        assert!(matches!(
+            parse(r#"<project name="outer" path="x" /><project name="inner" path="x/y/z" />"#)
+                .check_paths(),
+            Err(ManifestError::NestedPath { project, parent_path, .. })
+                if project == "inner" && parent_path == "x"
+        ));
+        assert!(matches!(
+            parse("<project name=\"a\" path=\"caf\u{e9}\" /><project name=\"b\" path=\"cafe\u{301}\" />")
+                .check_paths(),
+            Err(ManifestError::DuplicatePath { path, .. }) if path == "caf\u{e9}"
+        ));
+    }
+
+    #[test]
+    fn test_case_collisions() {
+        let parse = |projects: &str| -> Manifest {
+            from_str(&format!("<manifest>{projects}</manifest>")).unwrap()
+        };
+
+        let manifest = parse(
+            r#"<project name="a" path="x" /><project name="b" path="x" />
+               <project name="tools"><copyfile src="Makefile" dest="Makefile" /></project>"#,
+        );
+        assert!(manifest.check_case_collisions().is_ok());
+
+        assert!(matches!(
+            parse(r#"<project name="a" path="App" /><project name="b" path="app/" />"#)
+                .check_case_collisions(),
+            Err(ManifestError::CaseCollision { path, other_path, other_owner, .. })
+                if path == "App" && other_path == "app" && other_owner == "project b"
+        ));
+        assert!(matches!(
+            parse(r#"<project name="a" path="App" /><project name="b" path="app/lib" />"#)
+                .check_case_collisions(),
+            Err(ManifestError::NestedPath { project, parent, parent_path, .. })
+                if project == "b" && parent == "a" && parent_path == "App"
+        ));
+        assert!(
+            parse(r#"<project name="a" path="app" /><project name="b" path="app/lib" />"#)
+                .check_case_collisions()
+                .is_ok()
+        );
+        let manifest = parse(
+            r#"<remote name="origin" fetch="https://example.com" /><default remote="origin" />
+               <project name="a"><linkfile src="build" dest="Build" /></project>
+               <project name="b"><copyfile src="build" dest="build" /><copyfile src="x" dest="Build" /></project>"#,
+        );
+        let issues: Vec<_> = manifest
+            .validate()
+            .into_iter()
+            .map(|issue| issue.to_string())
+            .collect();
+        assert_eq!(
+            issues,
+            [
+                "Build of a linkfile of a and build of a copyfile of b are the same path on case-insensitive filesystems",
+                "Build of a linkfile of a and Build of a copyfile of b are the same path on case-insensitive filesystems",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_file_paths() {
+        let manifest: Manifest = from_str(
+            r#"<manifest>
+               <project name="build"><copyfile src="core/Makefile" dest="Makefile" /></project>
+               <project name="tools"><linkfile src="../build" dest="build-link" /></project>
+               </manifest>"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            manifest.check_file_paths(),
+            Err(ManifestError::UnsafeFilePath { project, element, path })
+                if project == "tools" && element == "linkfile src" && path == "../build"
+        ));
+    }
+
+    #[test]
+    fn test_non_ascii_paths_round_trip() {
+        let parsed: Manifest = from_str(
+            "<manifest><project name=\"app\" path=\"\u{65e5}\u{672c}/cafe\u{301}\" /></manifest>",
+        )
+        .unwrap();
+        let serialized = quick_xml::se::to_string(&parsed).unwrap();
+        let reparsed: Manifest = from_str(&serialized).unwrap();
+
+        assert_eq!(
+            reparsed.projects()[0].checkout_path(),
+            "\u{65e5}\u{672c}/cafe\u{301}"
+        );
+        assert_eq!(
+            normalize_path(reparsed.projects()[0].checkout_path()),
+            "\u{65e5}\u{672c}/caf\u{e9}"
+        );
+    }
+
+    #[test]
+    fn test_remote_annotations() {
+        let parsed: Manifest = from_str(
+            r#"<manifest>
+                <remote name="origin" fetch="https://example.com">
+                    <annotation name="VENDOR" value="example" />
+                    <annotation name="TIER" value="remote" />
+                </remote>
+                <default remote="origin" />
+                <project name="app">
+                    <annotation name="TIER" value="project" keep="false" />
+                </project>
+            </manifest>"#,
+        )
+        .unwrap();
+        let projects = parsed.projects();
+
+        assert_eq!(
+            parsed.project_annotations(&projects[0]),
+            vec![("VENDOR", "example"), ("TIER", "project")]
+        );
+        assert_eq!(
+            parsed.project_annotation(&projects[0], "VENDOR"),
+            Some("example")
+        );
+
+        let annotated = parsed.annotated_projects("TIER", Some("project"));
+        assert_eq!(annotated.len(), 1);
+        assert_eq!(annotated[0].1, "project");
+        assert!(parsed.annotated_projects("TIER", Some("remote")).is_empty());
+        assert!(parsed.annotated_projects("MISSING", None).is_empty());
+    }
+
+    #[test]
+    fn test_extend_project_dest_path() {
+        let parsed: Manifest = from_str(
+            r#"<manifest>
+                <project name="moved" path="old/moved" />
+                <project name="scoped" path="a" />
+                <project name="scoped" path="b" />
+                <extend-project name="moved" dest-path="new/moved" />
+                <extend-project name="scoped" path="b" dest-path="c" />
+            </manifest>"#,
+        )
+        .unwrap();
+        let projects = parsed.projects();
+
+        assert_eq!(projects[0].checkout_path(), "new/moved");
+        assert_eq!(projects[0].relocated_from(), Some("old/moved"));
+        assert_eq!(projects[1].checkout_path(), "a");
+        assert_eq!(projects[1].relocated_from(), None);
+        assert_eq!(projects[2].checkout_path(), "c");
+        assert_eq!(projects[2].relocated_from(), Some("b"));
+        assert!(parsed.check_extend_projects().is_ok());
+
+        let ambiguous: Manifest = from_str(
+            r#"<manifest>
+                <project name="scoped" path="a" />
+                <project name="scoped" path="b" />
+                <extend-project name="scoped" dest-path="c" />
+            </manifest>"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            ambiguous.check_extend_projects(),
+            Err(ManifestError::AmbiguousDestPath { name, count: 2, .. }) if name == "scoped"
+        ));
+    }
+
+    #[test]
+    fn test_extend_project_overrides() {
+        let parsed: Manifest = from_str(
+            r#"<manifest>
+                <project name="app" remote="origin" revision="main" groups="apps" />
+                <project name="scoped" path="a" />
+                <project name="scoped" path="b" />
+                <extend-project name="app" remote="mirror" revision="stable" groups="tools notdefault" />
+                <extend-project name="scoped" path="b" revision="v2" />
+            </manifest>"#,
+        )
+        .unwrap();
+        let projects = parsed.projects();
+
+        assert_eq!(projects[0].remote.as_deref(), Some("mirror"));
+        assert_eq!(projects[0].revision.as_deref(), Some("stable"));
+        assert_eq!(
+            projects[0].group_list(),
+            vec!["apps", "tools", "notdefault"]
+        );
+        assert_eq!(projects[1].revision, None);
+        assert_eq!(projects[2].revision.as_deref(), Some("v2"));
+        assert!(parsed.check_extend_projects().is_ok());
+
+        let unknown: Manifest = from_str(
+            r#"<manifest>
+                <project name="scoped" path="a" />
+                <extend-project name="scoped" path="b" revision="v2" />
+            </manifest>"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            unknown.check_extend_projects(),
+            Err(ManifestError::UnknownExtendedProject { name, path })
+                if name == "scoped" && path.as_deref() == Some("b")
+        ));
+    }
+
+    #[test]
+    fn test_superproject_pins() {
+        let parsed: Manifest = from_str(
+            r#"<manifest>
+                <remote name="aosp" fetch="https://android.googlesource.com" revision="main" />
+                <default remote="aosp" />
+                <superproject name="platform/superproject" />
+                <project name="platform/build" path="build" />
+                <project name="tools" upstream="refs/heads/stable" />
+            </manifest>"#,
+        )
+        .unwrap();
+        assert_eq!(
+            parsed
+                .superproject()
+                .map(|superproject| superproject.name()),
+            Some("platform/superproject")
+        );
+        assert_eq!(
+            parsed
+                .superproject_remote()
+                .map(|remote| remote.name.as_str()),
+            Some("aosp")
+        );
+        assert_eq!(parsed.superproject_revision().as_deref(), Some("main"));
+
+        let pins = parse_gitlinks(concat!(
+            "100644 blob 8d1bc41a52b7c1f0e2c4f1e5d0ef9c3a9c1b2f30\tREADME\n",
+            "160000 commit 04baa8a9f1a0e0c7f8b3a6d2c1e5f4b3a2c1d0e9\tbuild\n",
+            "160000 commit fc973381d2c3b4a5968778695a4b3c2d1e0f9a8b\ttools\n",
+        ));
+        assert_eq!(pins.len(), 2);
+
+        let mut projects = parsed.projects();
+        for project in &mut projects {
+            let commit = pins[project.checkout_path()].clone();
+            parsed.pin_project(project, &commit);
+        }
+        assert_eq!(
+            projects[0].revision.as_deref(),
+            Some("04baa8a9f1a0e0c7f8b3a6d2c1e5f4b3a2c1d0e9")
+        );
+        assert_eq!(projects[0].upstream(), Some("main"));
+        assert_eq!(parsed.dest_branch(&projects[0]).as_deref(), Some("main"));
+        assert_eq!(projects[1].upstream(), Some("refs/heads/stable"));
+    }
+
+    #[test]
+    fn test_submanifests() {
+        let parsed: Manifest = from_str(
+            r#"<manifest>
+                <remote name="aosp" fetch="https://android.googlesource.com" />
+                <default remote="aosp" revision="main" />
+                <submanifest name="vendor" project="vendor/manifest" revision="stable" />
+                <submanifest name="tools" path="prebuilts/tools" manifest-name="tools.xml" />
+            </manifest>"#,
+        )
+        .unwrap();
+        let submanifests = parsed.submanifests();
+        assert_eq!(submanifests.len(), 2);
+        assert_eq!(submanifests[0].path(), "vendor");
+        assert_eq!(submanifests[0].manifest_name(), "default.xml");
+        assert_eq!(submanifests[0].project(), Some("vendor/manifest"));
+        assert_eq!(
+            parsed.submanifest_revision(&submanifests[0]).as_deref(),
+            Some("stable")
+        );
+        assert_eq!(
+            parsed.submanifest_revision(&submanifests[1]).as_deref(),
+            Some("main")
+        );
+        assert_eq!(
+            parsed
+                .submanifest_remote(&submanifests[0])
+                .map(|remote| remote.name.as_str()),
+            Some("aosp")
+        );
+        assert_eq!(submanifests[1].path(), "prebuilts/tools");
+        assert_eq!(submanifests[1].manifest_name(), "tools.xml");
+        assert_eq!(submanifests[1].project(), None);
+    }
+
+    /// Loads included manifests from `files`, by name.
+    fn load_from<'a>(
+        files: &'a [(&str, &str)],
+    ) -> impl FnMut(&str) -> Result<Manifest, String> + 'a {
+        |name| match files.iter().find(|(file, _)| *file == name) {
+            Some((_, contents)) => from_str(contents).map_err(|error| error.to_string()),
+            None => Err(format!("{name} does not exist")),
+        }
+    }
+
+    #[test]
+    fn test_resolve_includes() {
+        let files = [
+            (
+                "common.xml",
+                r#"<manifest>
+                    <default remote="origin" revision="ignored" />
+                    <project name="lib" />
+                    <include name="tools.xml" />
+                </manifest>"#,
+            ),
+            (
+                "tools.xml",
+                r#"<manifest><project name="tool" /></manifest>"#,
+            ),
+        ];
+        let root: Manifest = from_str(
+            r#"<manifest>
+                <remote name="origin" fetch="https://example.com" />
+                <default remote="origin" revision="main" />
+                <project name="app" />
+                <include name="common.xml" />
+            </manifest>"#,
+        )
+        .unwrap();
+        let resolved = root
+            .resolve_includes("default.xml", &mut load_from(&files))
+            .unwrap();
+        let projects = resolved.projects();
+        let names: Vec<_> = projects.iter().map(|project| &project.name).collect();
+        assert_eq!(names, vec!["app", "lib", "tool"]);
+        assert!(resolved.includes().is_empty());
+        assert_eq!(
+            resolved.project_revision(&projects[2]).as_deref(),
+            Some("main")
+        );
+
+        let files = [
+            ("a.xml", r#"<manifest><include name="b.xml" /></manifest>"#),
+            ("b.xml", r#"<manifest><include name="a.xml" /></manifest>"#),
+        ];
+        let looping: Manifest =
+            from_str(r#"<manifest><include name="a.xml" /></manifest>"#).unwrap();
+        assert!(matches!(
+            looping.resolve_includes("default.xml", &mut load_from(&files)),
+            Err(IncludeError::Cycle { chain, .. }) if chain == "default.xml -> a.xml -> b.xml -> a.xml"
+        ));
+
+        let missing: Manifest =
+            from_str(r#"<manifest><include name="missing.xml" /></manifest>"#).unwrap();
+        assert!(matches!(
+            missing.resolve_includes("default.xml", &mut load_from(&files)),
+            Err(IncludeError::LoadError { name, parent, .. })
+                if name == "missing.xml" && parent == "default.xml"
+        ));
+    }
+
+    #[test]
+    fn test_validate() {
+        let files = [(
+            "common.xml",
+            r#"<manifest><default remote="other" /></manifest>"#,
+        )];
+        let manifest: Manifest = from_str(
+            r#"<manifest>
+                <remote name="origin" fetch="https://example.com" />
+                <remote name="origin" fetch="https://example.org" />
+                <default remote="origin" revision="main" />
+                <project name="app" />
+                <project name="lib" path="app" remote="mirror" />
+                <extend-project name="gone" />
+                <include name="common.xml" />
+            </manifest>"#,
+        )
+        .unwrap();
+        let manifest = manifest
+            .resolve_includes("default.xml", &mut load_from(&files))
+            .unwrap();
+
+        let codes: Vec<String> = manifest
+            .validate()
+            .iter()
+            .filter_map(|issue| Some(miette::Diagnostic::code(issue)?.to_string()))
+            .collect();
+        assert_eq!(
+            codes,
+            vec![
+                "repox::manifest::duplicate_remote",
+                "repox::manifest::conflicting_defaults",
+                "repox::manifest::unknown_remote",
+                "repox::manifest::duplicate_path",
+                "repox::manifest::unknown_extended_project",
+            ]
+        );
+
+        let valid: Manifest = from_str(
+            r#"<manifest>
+                <remote name="origin" fetch="https://example.com" />
+                <default remote="origin" revision="main" />
+                <project name="app" />
+            </manifest>"#,
+        )
+        .unwrap();
+        assert!(valid.validate().is_empty());
+    }
+
+    #[test]
+    fn test_apply_local_manifest() {
+        let mut manifest: Manifest = from_str(
+            r#"<manifest>
+                <remote name="origin" fetch="https://example.com" />
+                <default remote="origin" revision="main" />
+                <project name="app" />
+                <project name="lib" path="libs/lib" />
+            </manifest>"#,
+        )
+        .unwrap();
+        let local: Manifest = from_str(
+            r#"<manifest>
+                <remote name="mine" fetch="https://example.com/me" />
+                <remove-project name="app" />
+                <project name="app" remote="mine" revision="fork" />
+                <extend-project name="lib" dest-path="lib" />
+            </manifest>"#,
+        )
+        .unwrap();
+        manifest.apply_local_manifest(local);
+
+        let projects = manifest.projects();
+        let paths: Vec<_> = projects.iter().map(Project::checkout_path).collect();
+        assert_eq!(paths, vec!["lib", "app"]);
+        assert_eq!(
+            manifest
+                .project_remote(&projects[1])
+                .map(|remote| &remote.name[..]),
+            Some("mine")
+        );
+        assert!(manifest.remove_projects().is_empty());
+        assert!(manifest.check_paths().is_ok());
+    }
+
+    #[test]
+    fn test_remove_project() {
+        let parsed: Manifest = from_str(
+            r#"<manifest>
+                <remove-project name="gone" />
+                <remove-project name="scoped" path="b" />
+                <project name="gone" />
+                <project name="scoped" path="a" />
+                <project name="scoped" path="b" />
+                <project name="kept" />
+            </manifest>"#,
+        )
+        .unwrap();
+
+        let paths: Vec<_> = parsed
+            .projects()
+            .iter()
+            .map(|project| project.checkout_path().to_string())
+            .collect();
+        assert_eq!(paths, vec!["a", "kept"]);
+        assert_eq!(parsed.remove_projects()[1].path(), Some("b"));
+    }
 }
@@ -1,8 +1,15 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 /// See [Google's documentation](https://gerrit.googlesource.com/git-repo/+/master/docs/manifest-format.md#Element-manifest_server)
-#[derive(Debug, Clone, Deserialize)]
-pub(super) struct ManifestServer {
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ManifestServer {
+    /// URL of the XML RPC service.
     #[serde(rename = "@url")]
     url: String,
 }
+
+impl ManifestServer {
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+}
@@ -1,4 +1,15 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Deserialize)]
-pub(super) struct Notice {}
+/// See [Google's documentation](https://gerrit.googlesource.com/git-repo/+/master/docs/manifest-format.md#Element-notice)
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Notice {
+    /// Text displayed to the user after a sync completes.
+    #[serde(rename = "$text", default)]
+    text: String,
+}
+
+impl Notice {
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
@@ -0,0 +1,72 @@
+use std::{
+    borrow::Cow,
+    path::{Component, Path},
+};
+use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
+
+/// `path` in Unicode normalization form C and without trailing slashes, the form paths are compared in.
+///
+/// macOS stores file names decomposed (NFD) while manifests are usually written composed (NFC), so a path
+/// spelled either way names the same checkout there.
+pub fn normalize_path(path: &str) -> Cow<'_, str> {
+    let path = path.trim_end_matches('/');
+    match is_nfc_quick(path.chars()) {
+        IsNormalized::Yes => Cow::Borrowed(path),
+        IsNormalized::No | IsNormalized::Maybe => Cow::Owned(path.nfc().collect()),
+    }
+}
+
+/// Whether `a` and `b` name the same checkout once normalized, however their characters are composed.
+pub fn same_path(a: &str, b: &str) -> bool {
+    normalize_path(a) == normalize_path(b)
+}
+
+/// Whether `path` stays inside the directory it is relative to: it is neither empty nor absolute, has no
+/// `..` components, and does not reach into the `.git` or `.repo` directories git and repo keep state in.
+///
+/// This only looks at how the path is written, symlinks on the way can still lead elsewhere.
+pub fn is_contained(path: &str) -> bool {
+    !path.is_empty()
+        && Path::new(path)
+            .components()
+            .all(|component| match component {
+                Component::Normal(name) => {
+                    let name = name.to_string_lossy().to_lowercase();
+                    name != ".git" && name != ".repo"
+                }
+                Component::CurDir => true,
+                Component::ParentDir | Component::RootDir | Component::Prefix(_) => false,
+            })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_contained, normalize_path, same_path};
+    use unicode_normalization::UnicodeNormalization;
+
+    #[test]
+    fn test_normalize_path() {
+        let composed = "vendor/caf\u{e9}/";
+        let decomposed: String = composed.nfd().collect();
+        assert_ne!(composed, decomposed);
+
+        assert_eq!(normalize_path(&decomposed), "vendor/caf\u{e9}");
+        assert_eq!(normalize_path(composed), "vendor/caf\u{e9}");
+        assert!(same_path(composed, &decomposed));
+        assert!(same_path("日本語/プロジェクト", "日本語/プロジェクト/"));
+        assert!(!same_path("vendor/cafe", composed));
+    }
+
+    #[test]
+    fn test_is_contained() {
+        assert!(is_contained("build/Makefile"));
+        assert!(is_contained("./envsetup.sh"));
+        assert!(is_contained("."));
+        assert!(!is_contained(""));
+        assert!(!is_contained("/etc/passwd"));
+        assert!(!is_contained("../outside"));
+        assert!(!is_contained("tools/../../outside"));
+        assert!(!is_contained(".git/hooks/pre-commit"));
+        assert!(!is_contained("app/.REPO/manifests"));
+    }
+}
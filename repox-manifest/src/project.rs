@@ -1,43 +1,124 @@
-use serde::Deserialize;
+use crate::{
+    deserialize_bool, deserialize_count,
+    error::ManifestError,
+    groups::{split_groups, GroupFilter, GroupSet},
+    path::is_contained,
+    resolved::ResolvedProject,
+    url::ProjectUrls,
+    Manifest,
+};
+use serde::{Deserialize, Serialize};
+use std::num::NonZeroUsize;
 
 /// See [Google's documentation](https://gerrit.googlesource.com/git-repo/+/master/docs/manifest-format.md#Element-annotation)
-#[derive(Debug, Clone, Deserialize)]
-struct Annotation {
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Annotation {
     #[serde(rename = "@name")]
-    name: String,
+    pub(crate) name: String,
     #[serde(rename = "@value")]
-    value: String,
-    #[serde(rename = "@keep")]
-    keep: String,
+    pub(crate) value: String,
+    #[serde(
+        rename = "@keep",
+        default,
+        deserialize_with = "deserialize_bool",
+        skip_serializing_if = "Option::is_none"
+    )]
+    keep: Option<bool>,
+}
+
+impl Annotation {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// Whether the annotation is kept when the manifest is exported, which it is unless keep is false.
+    pub fn keep(&self) -> bool {
+        self.keep.unwrap_or(true)
+    }
 }
 
 /// See [Google's documentation](https://gerrit.googlesource.com/git-repo/+/master/docs/manifest-format.md#Element-copyfile)
-#[derive(Debug, Clone, Deserialize)]
-struct Copyfile {
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Copyfile {
     #[serde(rename = "@src")]
     src: String,
     #[serde(rename = "@dest")]
     dest: String,
 }
 
+impl Copyfile {
+    /// The file to copy, relative to the project.
+    pub fn src(&self) -> &str {
+        &self.src
+    }
+
+    /// Where to copy the file to, relative to the top of the repo client.
+    pub fn dest(&self) -> &str {
+        &self.dest
+    }
+
+    /// Checks that `src` stays inside the project and `dest` inside the repo client, as written.
+    pub fn check_paths(&self, project: &str) -> Result<(), ManifestError> {
+        check_file_path(project, "copyfile src", &self.src)?;
+        check_file_path(project, "copyfile dest", &self.dest)
+    }
+}
+
 /// See [Google's documentation](https://gerrit.googlesource.com/git-repo/+/master/docs/manifest-format.md#Element-linkfile)
-#[derive(Debug, Clone, Deserialize)]
-struct LinkFile {
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LinkFile {
     #[serde(rename = "@src")]
     src: String,
     #[serde(rename = "@dest")]
     dest: String,
 }
 
+impl LinkFile {
+    /// The path the symlink points to, relative to the project.
+    pub fn src(&self) -> &str {
+        &self.src
+    }
+
+    /// Where to create the symlink, relative to the top of the repo client.
+    pub fn dest(&self) -> &str {
+        &self.dest
+    }
+
+    /// Checks that `src` stays inside the project and `dest` inside the repo client, as written.
+    pub fn check_paths(&self, project: &str) -> Result<(), ManifestError> {
+        check_file_path(project, "linkfile src", &self.src)?;
+        check_file_path(project, "linkfile dest", &self.dest)
+    }
+}
+
+/// Rejects a copyfile or linkfile path of `project` that could write anywhere.
+fn check_file_path(project: &str, element: &'static str, path: &str) -> Result<(), ManifestError> {
+    if is_contained(path) {
+        return Ok(());
+    }
+
+    Err(ManifestError::UnsafeFilePath {
+        project: project.to_string(),
+        element,
+        path: path.to_string(),
+    })
+}
+
 /// See [Google's documentation](https://gerrit.googlesource.com/git-repo/+/master/docs/manifest-format.md#Element-project)
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Project {
     /// Zero or more annotation elements may be specified as children of a project element.
     /// Each element describes a name-value pair that will be exported into each project's environment during a ‘forall’ command, prefixed with REPO__.
     /// In addition, there is an optional attribute “keep” which accepts the case insensitive values “true” (default) or “false”.
     /// This attribute determines whether or not the annotation will be kept when exported with the manifest subcommand.
+    #[serde(skip_serializing_if = "Option::is_none")]
     annotation: Option<Vec<Annotation>>,
 
+    #[serde(skip_serializing_if = "Option::is_none")]
     project: Option<Vec<Project>>,
 
     /// Zero or more copyfile elements may be specified as children of a project element.
@@ -51,6 +132,7 @@ pub struct Project {
     /// Intermediate paths must not be symlinks either.
     ///
     /// Parent directories of “dest” will be automatically created if missing.
+    #[serde(skip_serializing_if = "Option::is_none")]
     copyfile: Option<Vec<Copyfile>>,
 
     /// It's just like copyfile and runs at the same time as copyfile but instead of copying it creates a symlink.
@@ -60,6 +142,7 @@ pub struct Project {
     /// Parent directories of “dest” will be automatically created if missing.
     ///
     /// The symlink target may be a file or directory, but it may not point outside of the repo client.
+    #[serde(skip_serializing_if = "Option::is_none")]
     linkfile: Option<Vec<LinkFile>>,
 
     /// A unique name for this project.
@@ -82,25 +165,25 @@ pub struct Project {
     /// An optional path relative to the top directory of the repo client where the Git working directory for this project should be placed.
     /// If not supplied the project name is used.
     /// If the project has a parent element, its path will be prefixed by the parent's.
-    #[serde(rename = "@path")]
+    #[serde(rename = "@path", skip_serializing_if = "Option::is_none")]
     pub path: Option<String>,
 
     /// Name of a previously defined remote element.
     /// If not supplied the remote given by the default element is used.
-    #[serde(rename = "@remote")]
+    #[serde(rename = "@remote", skip_serializing_if = "Option::is_none")]
     pub remote: Option<String>,
 
     /// Name of the Git branch the manifest wants to track for this project.
     /// Names can be relative to refs/heads (e.g. just “master”) or absolute (e.g. “refs/heads/master”).
     /// Tags and/or explicit SHA-1s should work in theory, but have not been extensively tested.
     /// If not supplied the revision given by the remote element is used if applicable, else the default element is used.
-    #[serde(rename = "@revision")]
+    #[serde(rename = "@revision", skip_serializing_if = "Option::is_none")]
     pub revision: Option<String>,
 
     /// Name of a Git branch (e.g. master).
     /// When using repo upload, changes will be submitted for code review on this branch.
     /// If unspecified both here and in the default element, revision is used instead.
-    #[serde(rename = "@dest-branch")]
+    #[serde(rename = "@dest-branch", skip_serializing_if = "Option::is_none")]
     pub dest_branch: Option<String>,
 
     /// List of groups to which this project belongs, whitespace or comma separated.
@@ -108,32 +191,363 @@ pub struct Project {
     /// E.g. for , that project definition is implicitly in the following manifest groups: default, name:monkeys, and path:barrel-of.
     /// If you place a project in the group “notdefault”, it will not be automatically downloaded by repo.
     /// If the project has a parent element, the name and path here are the prefixed ones.
-    #[serde(rename = "@groups")]
+    #[serde(rename = "@groups", skip_serializing_if = "Option::is_none")]
     pub groups: Option<String>,
 
     /// Set to true to only sync the given Git branch (specified in the revision attribute) rather than the whole ref space.
-    #[serde(rename = "@sync-c")]
-    sync_c: Option<String>,
+    #[serde(
+        rename = "@sync-c",
+        default,
+        deserialize_with = "deserialize_bool",
+        skip_serializing_if = "Option::is_none"
+    )]
+    sync_c: Option<bool>,
 
     /// Set to true to also sync sub-projects.
-    #[serde(rename = "@sync-s")]
-    sync_s: Option<String>,
+    #[serde(
+        rename = "@sync-s",
+        default,
+        deserialize_with = "deserialize_bool",
+        skip_serializing_if = "Option::is_none"
+    )]
+    sync_s: Option<bool>,
 
-    #[serde(rename = "@sync-tags")]
-    sync_tags: Option<String>,
+    #[serde(
+        rename = "@sync-tags",
+        default,
+        deserialize_with = "deserialize_bool",
+        skip_serializing_if = "Option::is_none"
+    )]
+    sync_tags: Option<bool>,
 
     /// Name of the Git ref in which a sha1 can be found.
     /// Used when syncing a revision locked manifest in -c mode to avoid having to sync the entire ref space.
-    #[serde(rename = "@upstream")]
+    #[serde(rename = "@upstream", skip_serializing_if = "Option::is_none")]
     upstream: Option<String>,
 
     /// Set the depth to use when fetching this project.
     /// If specified, this value will override any value given to repo init with the --depth option on the command line.
-    #[serde(rename = "@clone-depth")]
-    clone_depth: Option<String>,
+    #[serde(
+        rename = "@clone-depth",
+        default,
+        deserialize_with = "deserialize_count",
+        skip_serializing_if = "Option::is_none"
+    )]
+    clone_depth: Option<NonZeroUsize>,
 
     /// Set to true to force this project to create the local mirror repository according to its path attribute (if supplied) rather than the name attribute.
     /// This attribute only applies to the local mirrors syncing, it will be ignored when syncing the projects in a client working directory.
-    #[serde(rename = "@force-path")]
-    force_path: Option<String>,
+    #[serde(
+        rename = "@force-path",
+        default,
+        deserialize_with = "deserialize_bool",
+        skip_serializing_if = "Option::is_none"
+    )]
+    force_path: Option<bool>,
+
+    /// The path the project was checked out at before an extend-project element moved it with dest-path.
+    #[serde(skip)]
+    relocated_from: Option<String>,
+
+    /// The name of the project element this one was nested in, once lifted out of it.
+    #[serde(skip)]
+    parent: Option<String>,
+
+    /// The manifest file declaring the project, once includes and local manifests are resolved.
+    #[serde(skip)]
+    source: Option<String>,
+}
+
+impl Project {
+    /// A project element for the repository `name`, checked out at its name.
+    pub fn new(name: impl Into<String>) -> Self {
+        Project {
+            annotation: None,
+            project: None,
+            copyfile: None,
+            linkfile: None,
+            name: name.into(),
+            path: None,
+            remote: None,
+            revision: None,
+            dest_branch: None,
+            groups: None,
+            sync_c: None,
+            sync_s: None,
+            sync_tags: None,
+            upstream: None,
+            clone_depth: None,
+            force_path: None,
+            relocated_from: None,
+            parent: None,
+            source: None,
+        }
+    }
+
+    pub fn with_path(mut self, path: impl Into<String>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    pub fn with_remote(mut self, remote: impl Into<String>) -> Self {
+        self.remote = Some(remote.into());
+        self
+    }
+
+    pub fn with_revision(mut self, revision: impl Into<String>) -> Self {
+        self.revision = Some(revision.into());
+        self
+    }
+
+    pub fn with_dest_branch(mut self, dest_branch: impl Into<String>) -> Self {
+        self.dest_branch = Some(dest_branch.into());
+        self
+    }
+
+    /// Sets the groups attribute, a whitespace or comma separated list.
+    pub fn with_groups(mut self, groups: impl Into<String>) -> Self {
+        self.groups = Some(groups.into());
+        self
+    }
+
+    pub fn with_upstream(mut self, upstream: impl Into<String>) -> Self {
+        self.upstream = Some(upstream.into());
+        self
+    }
+
+    /// Sets clone-depth, left unset for a depth of zero.
+    pub fn with_clone_depth(mut self, depth: usize) -> Self {
+        self.clone_depth = NonZeroUsize::new(depth);
+        self
+    }
+
+    pub fn with_sync_c(mut self, sync_c: bool) -> Self {
+        self.sync_c = Some(sync_c);
+        self
+    }
+
+    pub fn with_sync_s(mut self, sync_s: bool) -> Self {
+        self.sync_s = Some(sync_s);
+        self
+    }
+
+    pub fn with_sync_tags(mut self, sync_tags: bool) -> Self {
+        self.sync_tags = Some(sync_tags);
+        self
+    }
+
+    pub fn with_force_path(mut self, force_path: bool) -> Self {
+        self.force_path = Some(force_path);
+        self
+    }
+
+    /// Adds an annotation element.
+    pub fn with_annotation(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.annotation
+            .get_or_insert_with(Vec::new)
+            .push(Annotation {
+                name: name.into(),
+                value: value.into(),
+                keep: None,
+            });
+        self
+    }
+
+    /// Adds a copyfile element copying `src` of the project to `dest`.
+    pub fn with_copyfile(mut self, src: impl Into<String>, dest: impl Into<String>) -> Self {
+        self.copyfile.get_or_insert_with(Vec::new).push(Copyfile {
+            src: src.into(),
+            dest: dest.into(),
+        });
+        self
+    }
+
+    /// Adds a linkfile element linking `dest` to `src` of the project.
+    pub fn with_linkfile(mut self, src: impl Into<String>, dest: impl Into<String>) -> Self {
+        self.linkfile.get_or_insert_with(Vec::new).push(LinkFile {
+            src: src.into(),
+            dest: dest.into(),
+        });
+        self
+    }
+
+    /// Adds a project element nested in this one.
+    pub fn with_subproject(mut self, project: Project) -> Self {
+        self.project.get_or_insert_with(Vec::new).push(project);
+        self
+    }
+
+    /// The project's annotations as name-value pairs.
+    pub fn annotations(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.annotation
+            .iter()
+            .flatten()
+            .map(|annotation| (annotation.name.as_str(), annotation.value.as_str()))
+    }
+
+    /// The project's annotation elements.
+    pub fn annotation_elements(&self) -> &[Annotation] {
+        self.annotation.as_deref().unwrap_or_default()
+    }
+
+    /// Project elements nested inside this one.
+    pub fn subprojects(&self) -> &[Project] {
+        self.project.as_deref().unwrap_or_default()
+    }
+
+    pub fn copyfiles(&self) -> &[Copyfile] {
+        self.copyfile.as_deref().unwrap_or_default()
+    }
+
+    pub fn linkfiles(&self) -> &[LinkFile] {
+        self.linkfile.as_deref().unwrap_or_default()
+    }
+
+    pub fn sync_c(&self) -> Option<bool> {
+        self.sync_c
+    }
+
+    pub fn sync_s(&self) -> Option<bool> {
+        self.sync_s
+    }
+
+    pub fn sync_tags(&self) -> Option<bool> {
+        self.sync_tags
+    }
+
+    /// The clone-depth attribute, if it is a positive number.
+    pub fn clone_depth(&self) -> Option<usize> {
+        self.clone_depth.map(NonZeroUsize::get)
+    }
+
+    /// The path of the project's working directory relative to the top of the repo client.
+    /// Falls back to the project's name when no path is supplied.
+    pub fn checkout_path(&self) -> &str {
+        self.path.as_deref().unwrap_or(&self.name)
+    }
+
+    /// The URLs repox fetches the project from and pushes it to, `None` if its remote is not defined.
+    ///
+    /// These are the manifest's URLs; a workspace's `url-rewrites` may change them further.
+    pub fn resolve_urls(&self, manifest: &Manifest) -> Option<ProjectUrls> {
+        let remote = manifest.project_remote(self)?;
+        Some(ProjectUrls::resolve(remote, &self.name, str::to_string))
+    }
+
+    /// The groups the project was explicitly placed in, split on whitespace and commas.
+    pub fn group_list(&self) -> Vec<&str> {
+        split_groups(self.groups.as_deref().unwrap_or_default()).collect()
+    }
+
+    /// The groups the project is in, the implicit ones included.
+    pub fn group_set(&self) -> GroupSet {
+        GroupSet::of(self)
+    }
+
+    /// Whether the project is selected by a comma or whitespace separated group filter such as `default,-notdefault`.
+    ///
+    /// See [`GroupFilter`] for how filters are read; parse it once to match many projects.
+    pub fn matches_groups(&self, filter: &str) -> bool {
+        GroupFilter::parse(filter).matches(&self.group_set())
+    }
+
+    /// Name of the Git ref in which a pinned revision can be found.
+    pub fn upstream(&self) -> Option<&str> {
+        self.upstream.as_deref()
+    }
+
+    /// The path the project was checked out at before extend-project's dest-path moved it, if it was moved.
+    pub fn relocated_from(&self) -> Option<&str> {
+        self.relocated_from.as_deref()
+    }
+
+    /// The name of the project this one was nested in, for projects lifted out of their parent's element.
+    pub fn parent(&self) -> Option<&str> {
+        self.parent.as_deref()
+    }
+
+    /// The manifest file declaring the project, relative to the manifest repository, or under
+    /// `local_manifests/` for a local manifest. Only known for manifests read with their includes resolved.
+    pub fn source(&self) -> Option<&str> {
+        self.source.as_deref()
+    }
+
+    /// Records `source` as the file declaring the project, unless one already is.
+    pub(crate) fn declared_in(&mut self, source: &str) {
+        self.source.get_or_insert_with(|| source.to_string());
+    }
+
+    /// Moves the project's checkout to `path`, remembering where it was.
+    pub(crate) fn relocate(&mut self, path: &str) {
+        if self.checkout_path() == path {
+            return;
+        }
+
+        self.relocated_from = Some(self.checkout_path().to_string());
+        self.path = Some(path.to_string());
+    }
+
+    /// Moves the project elements nested in this one out into `projects`, after it and recursively, with
+    /// the parent's name and path prefixed to theirs.
+    ///
+    /// Nested projects inherit the parent's remote, revision, dest-branch, upstream, groups, sync and
+    /// clone-depth attributes, unless they specify their own.
+    pub(crate) fn lift_into(mut self, projects: &mut Vec<Project>) {
+        let children = self.project.take().unwrap_or_default();
+        let parent = self.clone();
+        let path = self.checkout_path().trim_end_matches('/').to_string();
+        projects.push(self);
+
+        for mut child in children {
+            child.path = Some(format!(
+                "{path}/{}",
+                child.checkout_path().trim_end_matches('/')
+            ));
+            child.name = format!("{}/{}", parent.name, child.name);
+            child.parent = Some(parent.name.clone());
+            child.source = child.source.or_else(|| parent.source.clone());
+            child.remote = child.remote.or_else(|| parent.remote.clone());
+            child.revision = child.revision.or_else(|| parent.revision.clone());
+            child.dest_branch = child.dest_branch.or_else(|| parent.dest_branch.clone());
+            child.upstream = child.upstream.or_else(|| parent.upstream.clone());
+            child.groups = child.groups.or_else(|| parent.groups.clone());
+            child.sync_c = child.sync_c.or(parent.sync_c);
+            child.sync_s = child.sync_s.or(parent.sync_s);
+            child.sync_tags = child.sync_tags.or(parent.sync_tags);
+            child.clone_depth = child.clone_depth.or(parent.clone_depth);
+            child.lift_into(projects);
+        }
+    }
+
+    /// Sets the remote, revision, dest-branch and upstream the project inherits on it.
+    pub(crate) fn inherit(&mut self, resolved: ResolvedProject) {
+        self.remote = Some(resolved.remote.name.clone());
+        self.revision = resolved.revision;
+        self.dest_branch = resolved.dest_branch;
+        self.upstream = resolved.upstream;
+    }
+
+    /// Locks the project to `commit`, which is found by fetching `upstream`.
+    pub(crate) fn pin(&mut self, commit: &str, upstream: Option<String>) {
+        self.revision = Some(commit.to_string());
+        self.upstream = upstream;
+    }
+
+    /// Whether the project's local mirror should be laid out by its path rather than its name.
+    pub fn force_path(&self) -> bool {
+        self.force_path.unwrap_or(false)
+    }
+
+    /// The path of the project's bare repository relative to the top of a local mirror.
+    ///
+    /// Mirrors are laid out by project name unless `force-path` is set, in which case the project's path is used.
+    pub fn mirror_path(&self) -> String {
+        let path = if self.force_path() {
+            self.checkout_path()
+        } else {
+            &self.name
+        };
+
+        format!("{path}.git")
+    }
 }
@@ -1,8 +1,15 @@
-use serde::Deserialize;
+use crate::project::Annotation;
+use serde::{Deserialize, Serialize};
 
 /// See [Google's documentation](https://gerrit.googlesource.com/git-repo/+/master/docs/manifest-format.md#Element-remote)
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Remote {
+    /// Zero or more annotation elements may be specified as children of a remote element.
+    /// They are exported into the environment of every project using this remote during a ‘forall’ command,
+    /// unless the project has an annotation of the same name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    annotation: Option<Vec<Annotation>>,
+
     /// A short name unique to this manifest file.
     /// The name specified here is used as the remote name in each project's .git/config,
     ///     and is therefore automatically available to commands like git fetch, git remote, git pull and git push.
@@ -12,7 +19,7 @@ pub struct Remote {
     /// The alias, if specified, is used to override name to be set as the remote name in each project's .git/config.
     /// Its value can be duplicated while attribute name has to be unique in the manifest file.
     /// This helps each project to be able to have same remote name which actually points to different remote url.
-    #[serde(rename = "@alias")]
+    #[serde(rename = "@alias", skip_serializing_if = "Option::is_none")]
     alias: Option<String>,
 
     /// The Git URL prefix for all projects which use this remote.
@@ -23,16 +30,80 @@ pub struct Remote {
     /// The Git “push” URL prefix for all projects which use this remote.
     /// Each project's name is appended to this prefix to form the actual URL used to “git push” the project.
     /// This attribute is optional; if not specified then “git push” will use the same URL as the fetch attribute.
-    #[serde(rename = "@pushurl")]
+    #[serde(rename = "@pushurl", skip_serializing_if = "Option::is_none")]
     pushurl: Option<String>,
 
     /// Hostname of the Gerrit server where reviews are uploaded to by repo upload.
     /// This attribute is optional; if not specified then repo upload will not function.
-    #[serde(rename = "@review")]
+    #[serde(rename = "@review", skip_serializing_if = "Option::is_none")]
     review: Option<String>,
 
     /// Name of a Git branch (e.g. master or refs/heads/master).
     /// Remotes with their own revision will override the default revision.    
-    #[serde(rename = "@revision")]
+    #[serde(rename = "@revision", skip_serializing_if = "Option::is_none")]
     revision: Option<String>,
 }
+
+impl Remote {
+    /// A remote element named `name`, fetching projects from under `fetch`.
+    pub fn new(name: impl Into<String>, fetch: impl Into<String>) -> Self {
+        Remote {
+            annotation: None,
+            name: name.into(),
+            alias: None,
+            fetch: fetch.into(),
+            pushurl: None,
+            review: None,
+            revision: None,
+        }
+    }
+
+    pub fn with_alias(mut self, alias: impl Into<String>) -> Self {
+        self.alias = Some(alias.into());
+        self
+    }
+
+    pub fn with_pushurl(mut self, pushurl: impl Into<String>) -> Self {
+        self.pushurl = Some(pushurl.into());
+        self
+    }
+
+    pub fn with_review(mut self, review: impl Into<String>) -> Self {
+        self.review = Some(review.into());
+        self
+    }
+
+    pub fn with_revision(mut self, revision: impl Into<String>) -> Self {
+        self.revision = Some(revision.into());
+        self
+    }
+
+    /// The remote's annotations as name-value pairs.
+    pub fn annotations(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.annotation
+            .iter()
+            .flatten()
+            .map(|annotation| (annotation.name.as_str(), annotation.value.as_str()))
+    }
+
+    pub fn alias(&self) -> Option<&str> {
+        self.alias.as_deref()
+    }
+
+    /// The name of the remote in each project's .git/config: the alias if specified, the name otherwise.
+    pub fn git_name(&self) -> &str {
+        self.alias().unwrap_or(&self.name)
+    }
+
+    pub fn pushurl(&self) -> Option<&str> {
+        self.pushurl.as_deref()
+    }
+
+    pub fn review(&self) -> Option<&str> {
+        self.review.as_deref()
+    }
+
+    pub fn revision(&self) -> Option<&str> {
+        self.revision.as_deref()
+    }
+}
@@ -1,8 +1,33 @@
-use serde::Deserialize;
+use crate::project::Project;
+use serde::{Deserialize, Serialize};
 
 /// See [Google's documentation](https://gerrit.googlesource.com/git-repo/+/master/docs/manifest-format.md#Element-remove_project)
-#[derive(Debug, Clone, Deserialize)]
-pub(super) struct RemoveProject {
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RemoveProject {
+    /// Name of the project to remove.
     #[serde(rename = "@name")]
     name: String,
+
+    /// If specified, limit the removal to projects checked out at the specified path, rather than all projects with the given name.
+    #[serde(rename = "@path", skip_serializing_if = "Option::is_none")]
+    path: Option<String>,
+}
+
+impl RemoveProject {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+
+    /// Whether this element removes `project`.
+    pub(super) fn matches(&self, project: &Project) -> bool {
+        self.name == project.name
+            && self
+                .path
+                .as_deref()
+                .is_none_or(|path| path == project.checkout_path())
+    }
 }
@@ -1,9 +1,28 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Deserialize)]
-pub(super) struct RepoHooks {
+/// See [Google's documentation](https://gerrit.googlesource.com/git-repo/+/master/docs/manifest-format.md#Element-repo_hooks)
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RepoHooks {
+    /// The project where the repo hooks are.
+    /// The value should match the name attribute of a project element.
     #[serde(rename = "@in-project")]
     in_project: String,
+
+    /// Space separated list of hooks to enable.
     #[serde(rename = "@enabled-list")]
     enabled_list: String,
 }
+
+impl RepoHooks {
+    pub fn in_project(&self) -> &str {
+        &self.in_project
+    }
+
+    pub fn enabled_list(&self) -> impl Iterator<Item = &str> {
+        self.enabled_list.split_whitespace()
+    }
+
+    pub fn is_enabled(&self, hook: &str) -> bool {
+        self.enabled_list().any(|enabled| enabled == hook)
+    }
+}
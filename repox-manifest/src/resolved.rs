@@ -0,0 +1,28 @@
+use crate::remote::Remote;
+
+/// A project's settings with the ones it leaves out inherited, as [`Manifest::resolve_project`] computes them.
+///
+/// [`Manifest::resolve_project`]: crate::Manifest::resolve_project
+#[derive(Debug, Clone)]
+pub struct ResolvedProject<'a> {
+    /// The project's remote attribute, or the default element's.
+    pub remote: &'a Remote,
+
+    /// The project's revision attribute, its remote's, or the default element's.
+    pub revision: Option<String>,
+
+    /// The project's dest-branch attribute, the default element's, or else the revision.
+    pub dest_branch: Option<String>,
+
+    /// The project's upstream attribute, or the default element's.
+    pub upstream: Option<String>,
+
+    /// The project's sync-c attribute, or the default element's, false when neither sets it.
+    pub sync_c: bool,
+
+    /// The project's sync-s attribute, or the default element's, false when neither sets it.
+    pub sync_s: bool,
+
+    /// The project's sync-tags attribute, or the default element's, true when neither sets it.
+    pub sync_tags: bool,
+}
@@ -0,0 +1,84 @@
+use serde::{Deserialize, Serialize};
+
+/// The manifest file a submanifest reads when it names none.
+const DEFAULT_MANIFEST_NAME: &str = "default.xml";
+
+/// See [Google's documentation](https://gerrit.googlesource.com/git-repo/+/master/docs/manifest-format.md#Element-submanifest)
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Submanifest {
+    /// A unique name for this submanifest.
+    /// It acts as a default for the revision and path attributes.
+    #[serde(rename = "@name")]
+    name: String,
+
+    /// Name of a previously defined remote element.
+    /// If not supplied the remote used by the manifest project of this manifest is used.
+    #[serde(rename = "@remote", skip_serializing_if = "Option::is_none")]
+    remote: Option<String>,
+
+    /// The manifest project name.
+    /// The manifest project's name is appended onto its remote's fetch URL to generate the actual URL to configure the Git remote with.
+    /// If not supplied the manifest project of this manifest is used, reading the submanifest from the same repository.
+    #[serde(rename = "@project", skip_serializing_if = "Option::is_none")]
+    project: Option<String>,
+
+    /// The manifest filename in the manifest project.
+    /// If not supplied, `default.xml` is used.
+    #[serde(rename = "@manifest-name", skip_serializing_if = "Option::is_none")]
+    manifest_name: Option<String>,
+
+    /// Name of a Git branch (e.g. “main” or “refs/heads/main”), tag (e.g. “refs/tags/stable”), or a commit hash.
+    /// If not supplied, the revision of the manifest project of this manifest is used.
+    #[serde(rename = "@revision", skip_serializing_if = "Option::is_none")]
+    revision: Option<String>,
+
+    /// An optional path relative to the top directory of the repo client where the submanifest repo client top directory should be placed.
+    /// If not supplied, the name is used.
+    #[serde(rename = "@path", skip_serializing_if = "Option::is_none")]
+    path: Option<String>,
+
+    /// List of additional groups to which all projects in the included submanifest belong.
+    #[serde(rename = "@groups", skip_serializing_if = "Option::is_none")]
+    groups: Option<String>,
+
+    /// The list of manifest groups to sync if the submanifest's groups were not given at init time.
+    #[serde(rename = "@default-groups", skip_serializing_if = "Option::is_none")]
+    default_groups: Option<String>,
+}
+
+impl Submanifest {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn remote(&self) -> Option<&str> {
+        self.remote.as_deref()
+    }
+
+    pub fn project(&self) -> Option<&str> {
+        self.project.as_deref()
+    }
+
+    pub fn manifest_name(&self) -> &str {
+        self.manifest_name
+            .as_deref()
+            .unwrap_or(DEFAULT_MANIFEST_NAME)
+    }
+
+    pub fn revision(&self) -> Option<&str> {
+        self.revision.as_deref()
+    }
+
+    /// Where the submanifest's repo client is placed, relative to the top of this one: its path, or its name.
+    pub fn path(&self) -> &str {
+        self.path.as_deref().unwrap_or(&self.name)
+    }
+
+    pub fn groups(&self) -> Option<&str> {
+        self.groups.as_deref()
+    }
+
+    pub fn default_groups(&self) -> Option<&str> {
+        self.default_groups.as_deref()
+    }
+}
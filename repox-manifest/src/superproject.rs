@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The mode git's tree listing gives gitlinks, the entries pinning a submodule to a commit.
+const GITLINK_MODE: &str = "160000";
+
+/// See [Google's documentation](https://gerrit.googlesource.com/git-repo/+/master/docs/manifest-format.md#Element-superproject)
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Superproject {
+    /// A unique name for the superproject.
+    /// This attribute has the same meaning as project's name attribute.
+    #[serde(rename = "@name")]
+    name: String,
+
+    /// Name of a previously defined remote element.
+    /// If not supplied the remote given by the default element is used.
+    #[serde(rename = "@remote", skip_serializing_if = "Option::is_none")]
+    remote: Option<String>,
+
+    /// Name of the Git branch the superproject is tracked from.
+    /// If not supplied the remote's revision, then the default element's, is used.
+    #[serde(rename = "@revision", skip_serializing_if = "Option::is_none")]
+    revision: Option<String>,
+}
+
+impl Superproject {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn remote(&self) -> Option<&str> {
+        self.remote.as_deref()
+    }
+
+    pub fn revision(&self) -> Option<&str> {
+        self.revision.as_deref()
+    }
+}
+
+/// The commits a superproject's tree pins, by checkout path, from the output of `git ls-tree -r`.
+///
+/// The superproject holds every project as a gitlink at its checkout path, other entries are ignored.
+pub fn parse_gitlinks(ls_tree: &str) -> BTreeMap<String, String> {
+    ls_tree
+        .lines()
+        .filter_map(|line| {
+            let (entry, path) = line.split_once('\t')?;
+            let mut fields = entry.split_whitespace();
+            let (mode, _kind, commit) = (fields.next()?, fields.next()?, fields.next()?);
+            (mode == GITLINK_MODE).then(|| (path.to_string(), commit.to_string()))
+        })
+        .collect()
+}
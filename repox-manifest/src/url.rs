@@ -0,0 +1,326 @@
+use crate::remote::Remote;
+
+/// Joins a remote's fetch prefix and a project's name into the URL used to clone the project.
+///
+/// This follows the rule documented for the [project element](https://gerrit.googlesource.com/git-repo/+/master/docs/manifest-format.md#Element-project):
+///
+/// ```sh
+/// ${remote_fetch}/${project_name}.git
+/// ```
+///
+/// Trailing slashes on the fetch prefix are trimmed, duplicate slashes are collapsed,
+/// `.` and `..` path segments are resolved where possible, and the `.git` suffix is only appended when missing.
+pub fn project_url(fetch: &str, name: &str) -> String {
+    let fetch = fetch.trim_end_matches('/');
+    let name = name.trim_matches('/');
+
+    let joined = if fetch.is_empty() {
+        name.to_string()
+    } else {
+        format!("{fetch}/{name}")
+    };
+
+    let mut url = normalize(&joined);
+    if !url.ends_with(".git") {
+        url.push_str(".git");
+    }
+    url
+}
+
+/// The URLs a project is fetched from and pushed to, as repox configures its checkout.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectUrls {
+    pub fetch: String,
+
+    /// The remote's pushurl joined with the project's name, or the fetch URL when the remote has none.
+    pub push: String,
+}
+
+impl ProjectUrls {
+    /// The URLs of the project `name` on `remote`, the remote's prefixes first going through `rewrite`,
+    /// e.g. a workspace's URL rewrites.
+    pub fn resolve(remote: &Remote, name: &str, rewrite: impl Fn(&str) -> String) -> Self {
+        let fetch = project_url(&rewrite(&remote.fetch), name);
+        let push = remote
+            .pushurl()
+            .map(|pushurl| project_url(&rewrite(pushurl), name))
+            .unwrap_or_else(|| fetch.clone());
+
+        Self { fetch, push }
+    }
+}
+
+/// Maps the fetch URL schemes only Google's own tooling understands to the plain https URL they stand for,
+/// returning `None` for URLs git can already fetch from.
+///
+/// `persistent-https://` and `persistent-http://` are served by a local proxy in front of the same host,
+/// while `sso://<host>/` addresses `https://<host>.googlesource.com/` when the host is a bare name.
+pub fn map_scheme(url: &str) -> Option<String> {
+    if let Some(rest) = url.strip_prefix("persistent-https://") {
+        return Some(format!("https://{rest}"));
+    }
+    if let Some(rest) = url.strip_prefix("persistent-http://") {
+        return Some(format!("http://{rest}"));
+    }
+
+    let rest = url.strip_prefix("sso://")?;
+    let (host, path) = rest.split_once('/').unwrap_or((rest, ""));
+    if host.contains('.') {
+        Some(format!("https://{host}/{path}"))
+    } else {
+        Some(format!("https://{host}.googlesource.com/{path}"))
+    }
+}
+
+/// Resolves a remote's fetch prefix against the URL of the manifest repository, as given to `repo init`.
+///
+/// Fetch prefixes such as AOSP's `..` or `.` are relative to the manifest repository, with its last path
+/// segment standing for the repository itself: `..` with `https://android.googlesource.com/platform/manifest`
+/// is `https://android.googlesource.com/`. Prefixes with a scheme, in scp-like syntax or absolute paths are
+/// used as-is, as are all prefixes when there is no manifest URL.
+pub fn resolve_fetch_url(fetch: &str, manifest_url: &str) -> String {
+    let manifest_url = manifest_url.trim_end_matches('/');
+    let (authority, _) = split_authority(fetch);
+    if !authority.is_empty() || fetch.starts_with('/') || manifest_url.is_empty() {
+        return fetch.to_string();
+    }
+
+    let (authority, path) = split_authority(manifest_url);
+    let dir = match path.rfind('/') {
+        Some(slash) => &path[..=slash],
+        None if authority.contains("://") => "/",
+        None => "",
+    };
+    normalize(&format!("{authority}{dir}{fetch}"))
+}
+
+/// Resolves a submodule URL from a project's `.gitmodules` against the project's own URL.
+///
+/// As with git, URLs starting with `./` or `../` are relative to the project's URL, anything else is used as-is.
+pub fn submodule_url(project_url: &str, url: &str) -> String {
+    if url.starts_with("./") || url.starts_with("../") {
+        normalize(&format!("{}/{url}", project_url.trim_end_matches('/')))
+    } else {
+        url.to_string()
+    }
+}
+
+/// Splits a URL into the part that identifies the host (kept verbatim) and its path.
+fn split_authority(url: &str) -> (&str, &str) {
+    if let Some(scheme_end) = url.find("://") {
+        let after_scheme = scheme_end + 3;
+        return match url[after_scheme..].find('/') {
+            Some(path_start) => url.split_at(after_scheme + path_start),
+            None => (url, ""),
+        };
+    }
+
+    // scp-like syntax, e.g. `git@github.com:org/repo`
+    if let Some(colon) = url.find(':') {
+        if !url[..colon].contains('/') {
+            return url.split_at(colon + 1);
+        }
+    }
+
+    ("", url)
+}
+
+/// Collapses duplicate slashes and resolves `.` and `..` segments in the path portion of a URL.
+fn normalize(url: &str) -> String {
+    let (authority, path) = split_authority(url);
+    let absolute = path.starts_with('/');
+
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => match segments.last() {
+                Some(&last) if last != ".." => {
+                    segments.pop();
+                }
+                // A relative path (or a URL without an authority) keeps leading `..` segments,
+                // they are resolved later against the manifest URL.
+                _ if !absolute || authority.is_empty() => segments.push(".."),
+                _ => {}
+            },
+            segment => segments.push(segment),
+        }
+    }
+
+    let mut normalized = String::from(authority);
+    if absolute {
+        normalized.push('/');
+    }
+    normalized.push_str(&segments.join("/"));
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{map_scheme, project_url, resolve_fetch_url, submodule_url, ProjectUrls};
+    use crate::Manifest;
+
+    #[test]
+    fn test_project_url() {
+        // i.MX manifest remotes
+        assert_eq!(
+            project_url("https://git.yoctoproject.org", "meta-arm"),
+            "https://git.yoctoproject.org/meta-arm.git"
+        );
+        assert_eq!(
+            project_url("https://github.com/nxp-imx/", "meta-imx"),
+            "https://github.com/nxp-imx/meta-imx.git"
+        );
+        assert_eq!(
+            project_url("https://github.com/Freescale", "meta-freescale.git"),
+            "https://github.com/Freescale/meta-freescale.git"
+        );
+
+        // AOSP manifest remotes
+        assert_eq!(
+            project_url("https://android.googlesource.com/", "platform/build"),
+            "https://android.googlesource.com/platform/build.git"
+        );
+        assert_eq!(
+            project_url("https://android.googlesource.com//", "/platform//build/"),
+            "https://android.googlesource.com/platform/build.git"
+        );
+        assert_eq!(
+            project_url(
+                "https://android.googlesource.com/platform/manifest/..",
+                "platform/art"
+            ),
+            "https://android.googlesource.com/platform/platform/art.git"
+        );
+        assert_eq!(project_url("..", "platform/build"), "../platform/build.git");
+        assert_eq!(project_url("./", "device/google"), "device/google.git");
+
+        // scp-like syntax
+        assert_eq!(
+            project_url("git@github.com:keplersj/", "repox"),
+            "git@github.com:keplersj/repox.git"
+        );
+        assert_eq!(
+            project_url("ssh://git@example.com:29418/", "tools/repo"),
+            "ssh://git@example.com:29418/tools/repo.git"
+        );
+    }
+
+    #[test]
+    fn test_resolve_urls() {
+        let manifest: Manifest = quick_xml::de::from_str(
+            r#"<manifest>
+                <remote name="aosp" fetch="https://android.googlesource.com/" />
+                <remote name="corp" fetch="https://git.example.com" pushurl="ssh://git.example.com:29418" />
+                <default remote="aosp" />
+                <project name="platform/build" />
+                <project name="vendor/app" remote="corp" />
+                <project name="orphan" remote="missing" />
+            </manifest>"#,
+        )
+        .unwrap();
+        let projects = manifest.projects();
+
+        assert_eq!(
+            projects[0].resolve_urls(&manifest),
+            Some(ProjectUrls {
+                fetch: "https://android.googlesource.com/platform/build.git".to_string(),
+                push: "https://android.googlesource.com/platform/build.git".to_string(),
+            })
+        );
+        assert_eq!(
+            projects[1].resolve_urls(&manifest),
+            Some(ProjectUrls {
+                fetch: "https://git.example.com/vendor/app.git".to_string(),
+                push: "ssh://git.example.com:29418/vendor/app.git".to_string(),
+            })
+        );
+        assert_eq!(projects[2].resolve_urls(&manifest), None);
+
+        let remote = manifest.find_remote("aosp").unwrap();
+        assert_eq!(
+            ProjectUrls::resolve(remote, "platform/build", |url| url.replace(
+                "https://android.googlesource.com",
+                "https://mirror.local/aosp"
+            ))
+            .fetch,
+            "https://mirror.local/aosp/platform/build.git"
+        );
+    }
+
+    #[test]
+    fn test_map_scheme() {
+        assert_eq!(
+            map_scheme("persistent-https://android.googlesource.com/").as_deref(),
+            Some("https://android.googlesource.com/")
+        );
+        assert_eq!(
+            map_scheme("sso://android/platform").as_deref(),
+            Some("https://android.googlesource.com/platform")
+        );
+        assert_eq!(
+            map_scheme("sso://git.example.com").as_deref(),
+            Some("https://git.example.com/")
+        );
+        assert_eq!(map_scheme("https://android.googlesource.com/"), None);
+        assert_eq!(map_scheme("git@github.com:keplersj/"), None);
+    }
+
+    #[test]
+    fn test_resolve_fetch_url() {
+        let manifest_url = "https://android.googlesource.com/platform/manifest";
+        assert_eq!(
+            resolve_fetch_url("..", manifest_url),
+            "https://android.googlesource.com/"
+        );
+        assert_eq!(
+            resolve_fetch_url(".", &format!("{manifest_url}/")),
+            "https://android.googlesource.com/platform"
+        );
+        assert_eq!(
+            resolve_fetch_url("../vendor", "ssh://git@example.com:29418/manifest"),
+            "ssh://git@example.com:29418/vendor"
+        );
+        assert_eq!(
+            resolve_fetch_url("..", "git@example.com:platform/manifest.git"),
+            "git@example.com:"
+        );
+        assert_eq!(
+            resolve_fetch_url("..", "/srv/git/platform/manifest"),
+            "/srv/git"
+        );
+        assert_eq!(
+            resolve_fetch_url("..", "https://example.com"),
+            "https://example.com/"
+        );
+
+        assert_eq!(
+            resolve_fetch_url("https://github.com/", manifest_url),
+            "https://github.com/"
+        );
+        assert_eq!(
+            resolve_fetch_url("git@github.com:keplersj/", manifest_url),
+            "git@github.com:keplersj/"
+        );
+        assert_eq!(resolve_fetch_url("..", ""), "..");
+    }
+
+    #[test]
+    fn test_submodule_url() {
+        assert_eq!(
+            submodule_url(
+                "https://github.com/keplersj/repox.git",
+                "../repox-manifest.git"
+            ),
+            "https://github.com/keplersj/repox-manifest.git"
+        );
+        assert_eq!(
+            submodule_url("git@github.com:keplersj/repox.git", "./vendor/gix"),
+            "git@github.com:keplersj/repox.git/vendor/gix"
+        );
+        assert_eq!(
+            submodule_url("https://example.com/app.git", "https://github.com/gix.git"),
+            "https://github.com/gix.git"
+        );
+    }
+}
@@ -0,0 +1,923 @@
+use crate::git::{
+    add_linked_worktree, branch_commit, clone_depth, clone_project, count_commits, current_branch,
+    fetch_project, fetched_from, has_tracking_branch, has_upstream, head_commit, is_ancestor,
+    local_branches, mark_published, open_project, outgoing_files, override_config,
+    protected_checkout, published_commit, push, push_mirror, remote_default_branch, remote_url,
+    resolve_revision, seed_project, set_remote_url, start_branch, submodules, track_branch,
+    tracking_commit, DirtyPolicy, GitError, Refspecs, Revision, Submodule, PROTECT_WINDOWS_KEY,
+};
+use crate::{
+    auth,
+    config::WorkspaceConfig,
+    warnings::{self, WarningKind},
+};
+use gix::{remote::fetch::Shallow, ObjectId};
+use std::path::Path;
+use tracing::{info, info_span};
+
+/// What an existing project directory turned out to contain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checkout {
+    /// Whether the repository has no working tree, as in a mirror.
+    pub bare: bool,
+
+    /// Whether the working tree is a git-worktree, i.e. `.git` is a file.
+    pub linked_worktree: bool,
+
+    /// The URL of the remote the project fetches from, if it is configured.
+    pub remote_url: Option<String>,
+
+    /// The commit checked out, if `HEAD` is born.
+    pub head: Option<String>,
+
+    /// Whether the repository only has part of its history, as cloned with a depth.
+    pub shallow: bool,
+}
+
+/// The git operations commands such as sync, init, start, checkout and upload perform on projects.
+///
+/// Commands go through this trait rather than calling gix directly, so their
+/// logic can be tested against a fake without network access.
+pub trait GitBackend: Sync {
+    /// Clones `url` into `dst`, naming the remote `remote_name`. Mirrors are cloned bare.
+    ///
+    /// With a `depth`, only that many commits of history are fetched, leaving a shallow clone.
+    fn clone_project(
+        &self,
+        url: &str,
+        dst: &Path,
+        remote_name: &str,
+        mirror: bool,
+        refspecs: &Refspecs,
+        depth: Option<usize>,
+    ) -> Result<(), GitError>;
+
+    /// Fetches `remote_name` into the repository at `dst`, using `refspecs` when any are given.
+    fn fetch_project(
+        &self,
+        dst: &Path,
+        remote_name: &str,
+        refspecs: &Refspecs,
+    ) -> Result<(), GitError>;
+
+    /// Points `remote_name` of the repository at `dst` to `url`, for repositories that moved.
+    fn set_remote_url(&self, dst: &Path, remote_name: &str, url: &str) -> Result<(), GitError>;
+
+    /// Fetches the history the shallow repository at `dst` is missing from `remote_name`.
+    fn unshallow(&self, dst: &Path, remote_name: &str) -> Result<(), GitError>;
+
+    /// Moves the checkout at `dst` to `revision`, or when there is none (or it is `HEAD`) to the tip of
+    /// its current branch, falling back to the remote's default branch.
+    ///
+    /// Detached checkouts are moved to the new revision, local branches are only fast-forwarded. Shallow
+    /// checkouts are unshallowed when their history ends before a local branch meets the new revision.
+    fn update_worktree(
+        &self,
+        dst: &Path,
+        path: &str,
+        remote_name: &str,
+        revision: Option<&str>,
+        policy: DirtyPolicy,
+    ) -> Result<(), GitError>;
+
+    /// Describes the repository at `dst`, or returns `None` if it is not one.
+    fn inspect(&self, dst: &Path, remote_name: &str) -> Option<Checkout>;
+
+    /// The number of commits reachable from `to` but not from `from`, if both exist in the repository at `dst`.
+    fn count_commits(&self, dst: &Path, from: &str, to: &str) -> Option<usize>;
+
+    /// The submodules declared by the checkout at `dst`.
+    fn submodules(&self, dst: &Path) -> Vec<Submodule>;
+
+    /// Checks the existing local `branch` out in the checkout at `dst`, returning whether there is one.
+    fn checkout_branch(
+        &self,
+        dst: &Path,
+        path: &str,
+        branch: &str,
+        policy: DirtyPolicy,
+    ) -> Result<bool, GitError>;
+
+    /// Switches the checkout at `dst` to `branch`, creating it at `HEAD` when it does not exist yet and
+    /// making it track `merge` of `remote_name` when given.
+    fn start_branch(
+        &self,
+        dst: &Path,
+        path: &str,
+        branch: &str,
+        remote_name: &str,
+        merge: Option<&str>,
+    ) -> Result<(), GitError>;
+
+    /// Pushes `refspec` from the repository at `dst` to `remote_name`.
+    fn push(
+        &self,
+        dst: &Path,
+        path: &str,
+        remote_name: &str,
+        refspec: &str,
+    ) -> Result<(), GitError>;
+
+    /// Pushes every branch and tag of the mirror at `dst` to `url`, passing `options` such as `--prune` on
+    /// to git.
+    fn push_mirror(
+        &self,
+        dst: &Path,
+        path: &str,
+        url: &str,
+        remote_name: &str,
+        options: &[&str],
+    ) -> Result<(), GitError>;
+
+    /// Creates the repository at `dst` from `seed`, a git bundle or bare repository, as if it had been cloned
+    /// from `url`, leaving `HEAD` unborn on `branch` for the first sync to check out.
+    #[allow(clippy::too_many_arguments)]
+    fn seed_project(
+        &self,
+        seed: &Path,
+        dst: &Path,
+        path: &str,
+        url: &str,
+        remote_name: &str,
+        mirror: bool,
+        branch: Option<&str>,
+    ) -> Result<(), GitError>;
+
+    /// Checks `revision` of the repository at `source` out into a new git-worktree at `dst`.
+    fn add_worktree(
+        &self,
+        source: &Path,
+        dst: &Path,
+        remote_name: &str,
+        revision: &str,
+    ) -> Result<(), GitError>;
+
+    /// The commit checked out at `dst`, if `HEAD` is born.
+    fn head_commit(&self, dst: &Path) -> Option<ObjectId>;
+
+    /// The branch checked out at `dst`, or `None` when `HEAD` is detached.
+    fn current_branch(&self, dst: &Path) -> Result<Option<String>, GitError>;
+
+    /// The local branches of the repository at `dst`.
+    fn local_branches(&self, dst: &Path, path: &str) -> Result<Vec<String>, GitError>;
+
+    /// The commit the local `branch` of the repository at `dst` points to.
+    fn branch_commit(&self, dst: &Path, branch: &str) -> Option<ObjectId>;
+
+    /// The commit `branch` of `remote_name` was at when the repository at `dst` last fetched it.
+    fn tracking_commit(&self, dst: &Path, remote_name: &str, branch: &str) -> Option<ObjectId>;
+
+    /// The branch `refs/remotes/<remote_name>/HEAD` of the repository at `dst` points to.
+    fn remote_default_branch(&self, dst: &Path, remote_name: &str) -> Option<String>;
+
+    /// The commit of `branch` last uploaded from the repository at `dst`.
+    fn published_commit(&self, dst: &Path, branch: &str) -> Option<ObjectId>;
+
+    /// Records `commit` as the last upload of `branch` in the repository at `dst`.
+    fn mark_published(&self, dst: &Path, branch: &str, commit: ObjectId) -> Result<(), GitError>;
+
+    /// Whether `ancestor` is reachable from `descendant` in the repository at `dst`.
+    fn is_ancestor(&self, dst: &Path, ancestor: ObjectId, descendant: ObjectId) -> bool;
+
+    /// The files, with their sizes, that the commits of `head` not yet on `remote_name` add or change.
+    fn outgoing_files(
+        &self,
+        dst: &Path,
+        path: &str,
+        head: ObjectId,
+        remote_name: &str,
+    ) -> Result<Vec<(String, u64)>, GitError>;
+
+    /// Whether `commit` of the repository at `dst` is on a branch of `remote_name`, i.e. others can fetch it.
+    fn fetched_from(&self, dst: &Path, remote_name: &str, commit: &str) -> bool;
+}
+
+/// The [`GitBackend`] working on real repositories through gix.
+#[derive(Debug, Clone, Default)]
+pub struct GixBackend {
+    /// The workspace configuration, whose transport and auth settings apply to every fetch.
+    config: WorkspaceConfig,
+}
+
+impl GixBackend {
+    pub fn new(config: &WorkspaceConfig) -> Self {
+        Self {
+            config: config.clone(),
+        }
+    }
+
+    /// Opens the repository at `dst` with the credentials for its `remote_name`.
+    fn open_authenticated(
+        &self,
+        dst: &Path,
+        remote_name: &str,
+    ) -> Result<gix::Repository, GitError> {
+        let mut repo = open_project(dst)?;
+        let url = remote_url(&repo, remote_name).unwrap_or_default();
+        override_config(
+            &mut repo,
+            &auth::git_config(&self.config, remote_name, &url)?,
+        )?;
+
+        Ok(repo)
+    }
+
+    /// The in-memory settings every checkout is made with.
+    fn checkout_config(&self) -> Vec<String> {
+        if self.config.protect_windows {
+            vec![format!("{PROTECT_WINDOWS_KEY}=true")]
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Opens the repository at `dst` to check a revision out into.
+    fn open_for_checkout(&self, dst: &Path) -> Result<gix::Repository, GitError> {
+        let mut repo = open_project(dst)?;
+        override_config(&mut repo, &self.checkout_config())?;
+
+        Ok(repo)
+    }
+}
+
+impl GitBackend for GixBackend {
+    fn clone_project(
+        &self,
+        url: &str,
+        dst: &Path,
+        remote_name: &str,
+        mirror: bool,
+        refspecs: &Refspecs,
+        depth: Option<usize>,
+    ) -> Result<(), GitError> {
+        let mut overrides = auth::git_config(&self.config, remote_name, url)?;
+        overrides.extend(self.checkout_config());
+
+        clone_project(
+            url,
+            dst,
+            remote_name,
+            mirror,
+            refspecs,
+            clone_depth(depth),
+            &overrides,
+        )
+        .map(drop)
+    }
+
+    fn fetch_project(
+        &self,
+        dst: &Path,
+        remote_name: &str,
+        refspecs: &Refspecs,
+    ) -> Result<(), GitError> {
+        let repo = self.open_authenticated(dst, remote_name)?;
+
+        fetch_project(&repo, remote_name, refspecs, Shallow::NoChange)
+    }
+
+    fn set_remote_url(&self, dst: &Path, remote_name: &str, url: &str) -> Result<(), GitError> {
+        set_remote_url(dst, remote_name, url)
+    }
+
+    fn unshallow(&self, dst: &Path, remote_name: &str) -> Result<(), GitError> {
+        let repo = self.open_authenticated(dst, remote_name)?;
+
+        fetch_project(&repo, remote_name, &Refspecs::default(), Shallow::undo())
+    }
+
+    fn update_worktree(
+        &self,
+        dst: &Path,
+        path: &str,
+        remote_name: &str,
+        revision: Option<&str>,
+        policy: DirtyPolicy,
+    ) -> Result<(), GitError> {
+        let _update_span = info_span!("Updating worktree", path).entered();
+        let repo = self.open_for_checkout(dst)?;
+        let current = current_branch(&repo)?;
+
+        // An unset revision follows the remote's default branch. A branch that tracked a remote branch
+        // which disappeared, because the remote's default branch was renamed, is retargeted to the new one.
+        // Local topic branches, which never tracked anything, stay checked out.
+        let (revision, branch) = match (revision.filter(|&revision| revision != "HEAD"), &current) {
+            // Gerrit patchsets and pinned commits are not branches anyone works on, they are checked out detached.
+            (Some(revision), _)
+                if matches!(
+                    Revision::classify(revision),
+                    Revision::Change(_) | Revision::Commit(_)
+                ) =>
+            {
+                (revision.to_string(), None)
+            }
+            (Some(revision), Some(branch))
+                if Revision::classify(revision) == Revision::Branch(branch)
+                    && !has_tracking_branch(&repo, remote_name, branch) =>
+            {
+                match remote_default_branch(&repo, remote_name) {
+                    Some(default) if default != *branch => {
+                        info!("{path} tracked {branch}, which is gone upstream, switching to {default}");
+                        (default.clone(), Some(default))
+                    }
+                    _ => (revision.to_string(), current.clone()),
+                }
+            }
+            (Some(revision), _) => (revision.to_string(), current.clone()),
+            (None, Some(branch)) if has_tracking_branch(&repo, remote_name, branch) => {
+                (branch.clone(), current.clone())
+            }
+            (None, _) => {
+                match (remote_default_branch(&repo, remote_name), &current) {
+                    (Some(default), Some(branch)) if has_upstream(&repo, remote_name, branch) => {
+                        info!("{path} tracked {branch}, which is gone upstream, switching to {default}");
+                        track_branch(&repo, path, &default, remote_name)?;
+                        (default.clone(), Some(default))
+                    }
+                    (Some(default), None) => (default, None),
+                    (_, Some(branch)) => (branch.clone(), current.clone()),
+                    (None, None) => return Ok(()),
+                }
+            }
+        };
+
+        let target = resolve_revision(&repo, remote_name, &revision)?;
+        let head = head_commit(&repo);
+        if head == Some(target) && branch == current {
+            return Ok(());
+        }
+
+        // Local branches are only ever fast-forwarded.
+        let base = match &branch {
+            Some(branch) if Some(branch) == current.as_ref() => head,
+            Some(branch) => branch_commit(&repo, branch),
+            None => None,
+        };
+        match (branch, base) {
+            (Some(branch), Some(base)) if !is_ancestor(&repo, base, target) => {
+                // A shallow history can end before the branch and the new revision meet.
+                let repo = if repo.is_shallow() {
+                    info!("{path} is shallow, fetching its full history to compare {branch} with {revision}");
+                    self.unshallow(dst, remote_name)?;
+                    self.open_for_checkout(dst)?
+                } else {
+                    repo
+                };
+
+                if is_ancestor(&repo, base, target) {
+                    protected_checkout(&repo, path, target, Some(&branch), policy)
+                } else {
+                    warnings::warn(
+                        WarningKind::SkippedProject,
+                        format!("{path} has local commits on {branch}, not updating it"),
+                    );
+                    Ok(())
+                }
+            }
+            (branch, _) => protected_checkout(&repo, path, target, branch.as_deref(), policy),
+        }
+    }
+
+    fn inspect(&self, dst: &Path, remote_name: &str) -> Option<Checkout> {
+        let repo = open_project(dst).ok()?;
+
+        Some(Checkout {
+            bare: repo.is_bare(),
+            linked_worktree: dst.join(".git").is_file(),
+            remote_url: remote_url(&repo, remote_name),
+            head: head_commit(&repo).map(|id| id.to_string()),
+            shallow: repo.is_shallow(),
+        })
+    }
+
+    fn count_commits(&self, dst: &Path, from: &str, to: &str) -> Option<usize> {
+        // Counting in a partial history would undercount, not worth fetching all of it for.
+        if open_project(dst).ok()?.is_shallow() {
+            return None;
+        }
+
+        count_commits(dst, from, to)
+    }
+
+    fn submodules(&self, dst: &Path) -> Vec<Submodule> {
+        open_project(dst)
+            .map(|repo| submodules(&repo))
+            .unwrap_or_default()
+    }
+
+    fn checkout_branch(
+        &self,
+        dst: &Path,
+        path: &str,
+        branch: &str,
+        policy: DirtyPolicy,
+    ) -> Result<bool, GitError> {
+        let repo = self.open_for_checkout(dst)?;
+        let Some(commit) = branch_commit(&repo, branch) else {
+            return Ok(false);
+        };
+
+        protected_checkout(&repo, path, commit, Some(branch), policy)?;
+        Ok(true)
+    }
+
+    fn start_branch(
+        &self,
+        dst: &Path,
+        path: &str,
+        branch: &str,
+        remote_name: &str,
+        merge: Option<&str>,
+    ) -> Result<(), GitError> {
+        start_branch(&open_project(dst)?, path, branch, remote_name, merge)
+    }
+
+    fn push(
+        &self,
+        dst: &Path,
+        path: &str,
+        remote_name: &str,
+        refspec: &str,
+    ) -> Result<(), GitError> {
+        let repo = open_project(dst)?;
+        let url = remote_url(&repo, remote_name).unwrap_or_default();
+        let overrides = auth::git_config(&self.config, remote_name, &url)?;
+
+        push(&repo, path, remote_name, refspec, &overrides)
+    }
+
+    fn push_mirror(
+        &self,
+        dst: &Path,
+        path: &str,
+        url: &str,
+        remote_name: &str,
+        options: &[&str],
+    ) -> Result<(), GitError> {
+        let overrides = self.config.transport.git_config();
+
+        push_mirror(
+            &open_project(dst)?,
+            path,
+            url,
+            remote_name,
+            options,
+            &overrides,
+        )
+    }
+
+    fn seed_project(
+        &self,
+        seed: &Path,
+        dst: &Path,
+        path: &str,
+        url: &str,
+        remote_name: &str,
+        mirror: bool,
+        branch: Option<&str>,
+    ) -> Result<(), GitError> {
+        seed_project(seed, dst, path, url, remote_name, mirror, branch)
+    }
+
+    fn add_worktree(
+        &self,
+        source: &Path,
+        dst: &Path,
+        remote_name: &str,
+        revision: &str,
+    ) -> Result<(), GitError> {
+        add_linked_worktree(source, dst, remote_name, revision)
+    }
+
+    fn head_commit(&self, dst: &Path) -> Option<ObjectId> {
+        head_commit(&open_project(dst).ok()?)
+    }
+
+    fn current_branch(&self, dst: &Path) -> Result<Option<String>, GitError> {
+        current_branch(&open_project(dst)?)
+    }
+
+    fn local_branches(&self, dst: &Path, path: &str) -> Result<Vec<String>, GitError> {
+        local_branches(dst, path)
+    }
+
+    fn branch_commit(&self, dst: &Path, branch: &str) -> Option<ObjectId> {
+        branch_commit(&open_project(dst).ok()?, branch)
+    }
+
+    fn tracking_commit(&self, dst: &Path, remote_name: &str, branch: &str) -> Option<ObjectId> {
+        tracking_commit(&open_project(dst).ok()?, remote_name, branch)
+    }
+
+    fn remote_default_branch(&self, dst: &Path, remote_name: &str) -> Option<String> {
+        remote_default_branch(&open_project(dst).ok()?, remote_name)
+    }
+
+    fn published_commit(&self, dst: &Path, branch: &str) -> Option<ObjectId> {
+        published_commit(&open_project(dst).ok()?, branch)
+    }
+
+    fn mark_published(&self, dst: &Path, branch: &str, commit: ObjectId) -> Result<(), GitError> {
+        mark_published(&open_project(dst)?, branch, commit)
+    }
+
+    fn is_ancestor(&self, dst: &Path, ancestor: ObjectId, descendant: ObjectId) -> bool {
+        open_project(dst).is_ok_and(|repo| is_ancestor(&repo, ancestor, descendant))
+    }
+
+    fn outgoing_files(
+        &self,
+        dst: &Path,
+        path: &str,
+        head: ObjectId,
+        remote_name: &str,
+    ) -> Result<Vec<(String, u64)>, GitError> {
+        outgoing_files(&open_project(dst)?, path, head, remote_name)
+    }
+
+    fn fetched_from(&self, dst: &Path, remote_name: &str, commit: &str) -> bool {
+        fetched_from(dst, remote_name, commit)
+    }
+}
+
+/// A [`GitBackend`] that records what it is asked to do instead of touching git, for tests driving commands
+/// without network access. Outside this crate's own tests, it is behind the `test-support` feature.
+#[cfg(any(test, feature = "test-support"))]
+pub mod fake {
+    use super::{Checkout, GitBackend};
+    use crate::git::{DirtyPolicy, GitError, Refspecs, Submodule};
+    use gix::ObjectId;
+    use std::{
+        collections::HashMap,
+        fs::create_dir_all,
+        path::{Path, PathBuf},
+        sync::Mutex,
+    };
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum Action {
+        Clone {
+            url: String,
+            dst: PathBuf,
+        },
+        Seed {
+            seed: PathBuf,
+            dst: PathBuf,
+        },
+        Worktree {
+            source: PathBuf,
+            dst: PathBuf,
+            revision: String,
+        },
+        Fetch {
+            dst: PathBuf,
+        },
+        Update {
+            dst: PathBuf,
+            revision: Option<String>,
+        },
+        Checkout {
+            dst: PathBuf,
+            branch: String,
+        },
+        Start {
+            dst: PathBuf,
+            branch: String,
+        },
+        Push {
+            dst: PathBuf,
+            refspec: String,
+        },
+        PushMirror {
+            dst: PathBuf,
+            url: String,
+        },
+    }
+
+    /// Clones create an empty directory and remember the URL, so later syncs see an existing checkout.
+    /// Branches that are started or checked out are remembered as the current one.
+    #[derive(Debug, Default)]
+    pub struct FakeBackend {
+        actions: Mutex<Vec<Action>>,
+        checkouts: Mutex<HashMap<PathBuf, Checkout>>,
+        branches: Mutex<HashMap<PathBuf, String>>,
+    }
+
+    impl FakeBackend {
+        /// The actions performed since the last call, sorted so parallel syncs compare deterministically.
+        pub fn take_actions(&self) -> Vec<Action> {
+            let mut actions = std::mem::take(&mut *self.actions.lock().unwrap());
+            actions.sort_by_key(|action| format!("{action:?}"));
+            actions
+        }
+
+        /// The actions performed since the last call, in the order they were performed.
+        pub fn take_actions_in_order(&self) -> Vec<Action> {
+            std::mem::take(&mut *self.actions.lock().unwrap())
+        }
+
+        fn record(&self, action: Action) {
+            self.actions.lock().unwrap().push(action);
+        }
+
+        /// Creates `dst` and remembers it as `checkout`.
+        fn create(&self, dst: &Path, checkout: Checkout) -> Result<(), GitError> {
+            create_dir_all(dst).map_err(GitError::CreateDirectoryError)?;
+            self.checkouts
+                .lock()
+                .unwrap()
+                .insert(dst.to_path_buf(), checkout);
+
+            Ok(())
+        }
+    }
+
+    impl GitBackend for FakeBackend {
+        fn clone_project(
+            &self,
+            url: &str,
+            dst: &Path,
+            _remote_name: &str,
+            mirror: bool,
+            _refspecs: &Refspecs,
+            depth: Option<usize>,
+        ) -> Result<(), GitError> {
+            self.create(
+                dst,
+                Checkout {
+                    bare: mirror,
+                    linked_worktree: false,
+                    remote_url: Some(url.to_string()),
+                    head: None,
+                    shallow: depth.is_some(),
+                },
+            )?;
+            self.record(Action::Clone {
+                url: url.to_string(),
+                dst: dst.to_path_buf(),
+            });
+
+            Ok(())
+        }
+
+        fn fetch_project(
+            &self,
+            dst: &Path,
+            _remote_name: &str,
+            _refspecs: &Refspecs,
+        ) -> Result<(), GitError> {
+            self.record(Action::Fetch {
+                dst: dst.to_path_buf(),
+            });
+
+            Ok(())
+        }
+
+        fn set_remote_url(
+            &self,
+            dst: &Path,
+            _remote_name: &str,
+            url: &str,
+        ) -> Result<(), GitError> {
+            if let Some(checkout) = self.checkouts.lock().unwrap().get_mut(dst) {
+                checkout.remote_url = Some(url.to_string());
+            }
+
+            Ok(())
+        }
+
+        fn unshallow(&self, dst: &Path, _remote_name: &str) -> Result<(), GitError> {
+            if let Some(checkout) = self.checkouts.lock().unwrap().get_mut(dst) {
+                checkout.shallow = false;
+            }
+            self.record(Action::Fetch {
+                dst: dst.to_path_buf(),
+            });
+
+            Ok(())
+        }
+
+        fn update_worktree(
+            &self,
+            dst: &Path,
+            _path: &str,
+            _remote_name: &str,
+            revision: Option<&str>,
+            _policy: DirtyPolicy,
+        ) -> Result<(), GitError> {
+            self.record(Action::Update {
+                dst: dst.to_path_buf(),
+                revision: revision.map(str::to_string),
+            });
+
+            Ok(())
+        }
+
+        fn inspect(&self, dst: &Path, _remote_name: &str) -> Option<Checkout> {
+            self.checkouts.lock().unwrap().get(dst).cloned()
+        }
+
+        fn count_commits(&self, _dst: &Path, _from: &str, _to: &str) -> Option<usize> {
+            None
+        }
+
+        fn submodules(&self, _dst: &Path) -> Vec<Submodule> {
+            Vec::new()
+        }
+
+        fn checkout_branch(
+            &self,
+            dst: &Path,
+            _path: &str,
+            branch: &str,
+            _policy: DirtyPolicy,
+        ) -> Result<bool, GitError> {
+            self.branches
+                .lock()
+                .unwrap()
+                .insert(dst.to_path_buf(), branch.to_string());
+            self.record(Action::Checkout {
+                dst: dst.to_path_buf(),
+                branch: branch.to_string(),
+            });
+
+            Ok(true)
+        }
+
+        fn start_branch(
+            &self,
+            dst: &Path,
+            _path: &str,
+            branch: &str,
+            _remote_name: &str,
+            _merge: Option<&str>,
+        ) -> Result<(), GitError> {
+            self.branches
+                .lock()
+                .unwrap()
+                .insert(dst.to_path_buf(), branch.to_string());
+            self.record(Action::Start {
+                dst: dst.to_path_buf(),
+                branch: branch.to_string(),
+            });
+
+            Ok(())
+        }
+
+        fn push(
+            &self,
+            dst: &Path,
+            _path: &str,
+            _remote_name: &str,
+            refspec: &str,
+        ) -> Result<(), GitError> {
+            self.record(Action::Push {
+                dst: dst.to_path_buf(),
+                refspec: refspec.to_string(),
+            });
+
+            Ok(())
+        }
+
+        fn push_mirror(
+            &self,
+            dst: &Path,
+            _path: &str,
+            url: &str,
+            _remote_name: &str,
+            _options: &[&str],
+        ) -> Result<(), GitError> {
+            self.record(Action::PushMirror {
+                dst: dst.to_path_buf(),
+                url: url.to_string(),
+            });
+
+            Ok(())
+        }
+
+        fn seed_project(
+            &self,
+            seed: &Path,
+            dst: &Path,
+            _path: &str,
+            url: &str,
+            _remote_name: &str,
+            mirror: bool,
+            _branch: Option<&str>,
+        ) -> Result<(), GitError> {
+            self.create(
+                dst,
+                Checkout {
+                    bare: mirror,
+                    linked_worktree: false,
+                    remote_url: Some(url.to_string()),
+                    head: None,
+                    shallow: false,
+                },
+            )?;
+            self.record(Action::Seed {
+                seed: seed.to_path_buf(),
+                dst: dst.to_path_buf(),
+            });
+
+            Ok(())
+        }
+
+        fn add_worktree(
+            &self,
+            source: &Path,
+            dst: &Path,
+            remote_name: &str,
+            revision: &str,
+        ) -> Result<(), GitError> {
+            let remote_url = self
+                .inspect(source, remote_name)
+                .and_then(|source| source.remote_url);
+            self.create(
+                dst,
+                Checkout {
+                    bare: false,
+                    linked_worktree: true,
+                    remote_url,
+                    head: None,
+                    shallow: false,
+                },
+            )?;
+            self.record(Action::Worktree {
+                source: source.to_path_buf(),
+                dst: dst.to_path_buf(),
+                revision: revision.to_string(),
+            });
+
+            Ok(())
+        }
+
+        fn head_commit(&self, dst: &Path) -> Option<ObjectId> {
+            let head = self.checkouts.lock().unwrap().get(dst)?.head.clone()?;
+            ObjectId::from_hex(head.as_bytes()).ok()
+        }
+
+        fn current_branch(&self, dst: &Path) -> Result<Option<String>, GitError> {
+            Ok(self.branches.lock().unwrap().get(dst).cloned())
+        }
+
+        fn local_branches(&self, dst: &Path, _path: &str) -> Result<Vec<String>, GitError> {
+            Ok(self
+                .branches
+                .lock()
+                .unwrap()
+                .get(dst)
+                .cloned()
+                .into_iter()
+                .collect())
+        }
+
+        fn branch_commit(&self, _dst: &Path, _branch: &str) -> Option<ObjectId> {
+            None
+        }
+
+        fn tracking_commit(
+            &self,
+            _dst: &Path,
+            _remote_name: &str,
+            _branch: &str,
+        ) -> Option<ObjectId> {
+            None
+        }
+
+        fn remote_default_branch(&self, _dst: &Path, _remote_name: &str) -> Option<String> {
+            None
+        }
+
+        fn published_commit(&self, _dst: &Path, _branch: &str) -> Option<ObjectId> {
+            None
+        }
+
+        fn mark_published(
+            &self,
+            _dst: &Path,
+            _branch: &str,
+            _commit: ObjectId,
+        ) -> Result<(), GitError> {
+            Ok(())
+        }
+
+        fn is_ancestor(&self, _dst: &Path, _ancestor: ObjectId, _descendant: ObjectId) -> bool {
+            false
+        }
+
+        fn outgoing_files(
+            &self,
+            _dst: &Path,
+            _path: &str,
+            _head: ObjectId,
+            _remote_name: &str,
+        ) -> Result<Vec<(String, u64)>, GitError> {
+            Ok(Vec::new())
+        }
+
+        fn fetched_from(&self, _dst: &Path, _remote_name: &str, _commit: &str) -> bool {
+            true
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use crate::command::Command;
+use clap::{CommandFactory, FromArgMatches, Parser};
+use serde::{Deserialize, Serialize};
+
+/// Work-in-Progress drop-in replacement for Google's gerrit repo tool
+///
+/// The command line of the `repox` binary, for wrappers to parse or construct its invocations with. It
+/// serializes with serde, e.g. to pass invocations around as JSON.
+#[derive(Parser, Debug, Serialize, Deserialize)]
+#[clap(author, version, about, long_about = None)]
+#[serde(rename_all = "kebab-case")]
+pub struct Cli {
+    /// List commands that have not been implemented yet in --help
+    #[arg(long, global = true, default_value_t = false)]
+    pub experimental: bool,
+
+    /// Fail when the command raises warnings, e.g. about unknown manifest attributes, for use in CI
+    #[arg(long, global = true, default_value_t = false)]
+    pub strict: bool,
+
+    /// Report where the command spent its time once it is done: resolving the manifest, on the network,
+    /// checking out and in hooks, and on its slowest projects
+    #[arg(long, global = true, default_value_t = false)]
+    pub profile: bool,
+
+    /// Show more logs: -v adds debug logs, like gix's fetch negotiation, -vv adds the transfer progress of each project
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+impl Cli {
+    /// The clap command, revealing unimplemented commands in `--help` when `experimental` is set.
+    pub fn command_with(experimental: bool) -> clap::Command {
+        let mut command = Self::command();
+        if experimental {
+            let hidden: Vec<String> = command
+                .get_subcommands()
+                .filter(|subcommand| subcommand.is_hide_set())
+                .map(|subcommand| subcommand.get_name().to_string())
+                .collect();
+            for name in hidden {
+                command = command.mut_subcommand(name, |subcommand| subcommand.hide(false));
+            }
+        }
+
+        command
+    }
+
+    /// Parses `args`, the program name first, as the `repox` binary does.
+    pub fn try_parse_args<I, T>(args: I) -> Result<Self, clap::Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<std::ffi::OsString> + Clone,
+    {
+        let args: Vec<std::ffi::OsString> = args.into_iter().map(Into::into).collect();
+        let experimental = args.iter().any(|arg| arg == "--experimental");
+        let matches = Self::command_with(experimental).try_get_matches_from(args)?;
+        Self::from_arg_matches(&matches)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Cli;
+    use crate::command::Command;
+
+    #[test]
+    fn test_cli_round_trip() {
+        let cli = Cli::try_parse_args(["repox", "--strict", "forall", "-c", "git status"]).unwrap();
+        assert!(cli.strict);
+        assert!(matches!(cli.command, Command::ForAll(_)));
+
+        let json = serde_json::to_value(&cli).unwrap();
+        assert_eq!(json["strict"], true);
+        assert!(json["command"]["forall"].is_object(), "{json}");
+        let read_back: Cli = serde_json::from_value(json).unwrap();
+        assert!(matches!(read_back.command, Command::ForAll(_)));
+
+        assert!(Cli::try_parse_args(["repox", "version"]).is_ok());
+        assert!(Cli::try_parse_args(["repox", "no-such-command"]).is_err());
+    }
+}
@@ -0,0 +1,136 @@
+use crate::{
+    backend::GitBackend,
+    config::TransportConfig,
+    git::{GitError, Revision},
+    http::{Downloader, HttpError},
+    workspace::Workspace,
+};
+use miette::Diagnostic;
+use repox_manifest::project::Project;
+use std::{
+    fs::remove_file,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+use tracing::info;
+
+/// Directory in `.repo/` clone bundles are downloaded to. A download cut short resumes from here.
+const BUNDLE_DIR: &str = "clone-bundles";
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum CloneBundleError {
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    HttpError(#[from] HttpError),
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    GitError(#[from] GitError),
+}
+
+/// The URL of a project's clone bundle, expanded from a template such as
+/// `https://cdn.example.com/bundles/{project}.bundle`.
+///
+/// `{project}` is replaced by the project's name, `{path}` by its checkout path, `{remote}` by its remote's
+/// name and `{url}` by the URL it is fetched from without a trailing `.git`, so `{url}/clone.bundle` is
+/// where repo looks for one.
+pub fn bundle_url(template: &str, project: &Project, remote_name: &str, url: &str) -> String {
+    let url = url.trim_end_matches('/');
+    template
+        .replace("{project}", &project.name)
+        .replace("{path}", project.checkout_path())
+        .replace("{remote}", remote_name)
+        .replace("{url}", url.strip_suffix(".git").unwrap_or(url))
+}
+
+/// Seeds new projects from clone bundles served apart from their git servers, e.g. on a CDN, so cloning
+/// only fetches what changed since a bundle was made. Projects are seeded through `backend`.
+pub struct CloneBundles<'a> {
+    backend: &'a dyn GitBackend,
+    template: String,
+    downloader: Downloader,
+    dir: PathBuf,
+}
+
+impl<'a> CloneBundles<'a> {
+    pub fn new(
+        backend: &'a dyn GitBackend,
+        workspace: &Workspace,
+        template: &str,
+        transport: &TransportConfig,
+    ) -> Result<Self, HttpError> {
+        Ok(CloneBundles {
+            backend,
+            template: template.to_string(),
+            downloader: Downloader::new(transport)?,
+            dir: workspace.repo_dir().join(BUNDLE_DIR),
+        })
+    }
+
+    /// Creates the repository of `project` at `dst` from its clone bundle, to be fetched into afterwards.
+    ///
+    /// Returns whether there was a bundle to seed from. Projects need not have one, a missing bundle leaves
+    /// the project to be cloned as usual.
+    pub fn seed(
+        &self,
+        project: &Project,
+        path: &str,
+        dst: &Path,
+        url: &str,
+        remote_name: &str,
+        mirror: bool,
+    ) -> Result<bool, CloneBundleError> {
+        let bundle_url = bundle_url(&self.template, project, remote_name, url);
+        let bundle = self.dir.join(format!("{}.bundle", project.name));
+        match self.downloader.download(&bundle_url, &bundle, None) {
+            Ok(()) => {}
+            Err(HttpError::NotFound { .. }) => {
+                info!("No clone bundle for {path} at {bundle_url}");
+                return Ok(false);
+            }
+            Err(error) => return Err(error.into()),
+        }
+
+        info!("Seeding {path} from the clone bundle at {bundle_url}");
+        // The branch a clone of the project's revision would leave it on.
+        let branch = match project.revision.as_deref().map(Revision::classify) {
+            Some(Revision::Branch(branch)) => Some(branch),
+            _ => None,
+        };
+        let seeded =
+            self.backend
+                .seed_project(&bundle, dst, path, url, remote_name, mirror, branch);
+        let _ = remove_file(&bundle);
+        seeded?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::bundle_url;
+    use repox_manifest::project::Project;
+
+    #[test]
+    fn test_bundle_url() {
+        let project = Project::new("platform/app").with_path("app");
+        assert_eq!(
+            bundle_url(
+                "https://cdn.example.com/{remote}/{project}.bundle",
+                &project,
+                "aosp",
+                "https://android.googlesource.com/platform/app"
+            ),
+            "https://cdn.example.com/aosp/platform/app.bundle"
+        );
+        assert_eq!(
+            bundle_url(
+                "{url}/clone.bundle?path={path}",
+                &project,
+                "origin",
+                "https://example.com/platform/app.git"
+            ),
+            "https://example.com/platform/app/clone.bundle?path=app"
+        );
+    }
+}
@@ -0,0 +1,128 @@
+use super::select_projects;
+use crate::workspace::{Workspace, WorkspaceError};
+use clap::{Args, Subcommand};
+use miette::{Diagnostic, Result};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Query project annotations
+///
+/// # Description
+///
+/// Reads the metadata manifests carry in annotation elements, such as the target
+/// a project is built for, so downstream tooling can select projects on it.
+///
+/// 'get NAME' prints each project annotated with NAME and its value:
+///
+///   firmware/boot : firmware
+///
+/// 'get NAME=VALUE' prints only the projects whose NAME is VALUE, one path and
+/// project name per line as 'repo list' does. A project without the annotation
+/// inherits its remote's. NAME may be given as forall exports it, e.g.
+/// REPO__BUILD_TARGET=firmware.
+#[derive(Args, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct AnnotateArgs {
+    #[command(subcommand)]
+    command: AnnotateCommand,
+}
+
+#[derive(Subcommand, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum AnnotateCommand {
+    /// Print the projects with an annotation, or with it set to a value
+    Get {
+        /// the annotation, as NAME or NAME=VALUE
+        query: String,
+
+        projects: Option<Vec<String>>,
+
+        /// print the projects as JSON
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+}
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum AnnotateError {
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    WorkspaceError(#[from] WorkspaceError),
+
+    #[error(transparent)]
+    #[diagnostic(code(repox::command::annotate::json))]
+    JsonError(#[from] serde_json::Error),
+}
+
+/// A project as printed by `repox annotate get --json`.
+#[derive(Debug, Serialize)]
+struct AnnotatedProject {
+    name: String,
+    path: String,
+    value: String,
+}
+
+/// The annotation name and value a query asks for, without the `REPO__` prefix forall exports names with.
+fn parse_query(query: &str) -> (&str, Option<&str>) {
+    let (name, value) = match query.split_once('=') {
+        Some((name, value)) => (name, Some(value)),
+        None => (query, None),
+    };
+    (name.strip_prefix("REPO__").unwrap_or(name), value)
+}
+
+pub fn run_annotate(args: AnnotateArgs) -> Result<(), AnnotateError> {
+    let AnnotateCommand::Get {
+        query,
+        projects,
+        json,
+    } = args.command;
+    let workspace = Workspace::discover()?;
+    let manifest = workspace.cached_manifest()?;
+    let (name, value) = parse_query(&query);
+
+    let selected: Vec<String> = select_projects(&manifest, projects.as_deref())
+        .into_iter()
+        .map(|project| project.checkout_path().to_string())
+        .collect();
+    let annotated: Vec<AnnotatedProject> = manifest
+        .annotated_projects(name, value)
+        .into_iter()
+        .filter(|(project, _)| selected.iter().any(|path| path == project.checkout_path()))
+        .map(|(project, value)| AnnotatedProject {
+            path: project.checkout_path().to_string(),
+            name: project.name,
+            value,
+        })
+        .collect();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&annotated)?);
+        return Ok(());
+    }
+
+    for project in annotated {
+        if value.is_some() {
+            println!("{} : {}", project.path, project.name);
+        } else {
+            println!("{} : {}", project.path, project.value);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_query;
+
+    #[test]
+    fn test_parse_query() {
+        assert_eq!(parse_query("BUILD_TARGET"), ("BUILD_TARGET", None));
+        assert_eq!(
+            parse_query("REPO__BUILD_TARGET=firmware"),
+            ("BUILD_TARGET", Some("firmware"))
+        );
+        assert_eq!(parse_query("TAGS=a=b"), ("TAGS", Some("a=b")));
+    }
+}
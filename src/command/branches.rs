@@ -0,0 +1,176 @@
+use super::select_projects;
+use crate::{
+    backend::{GitBackend, GixBackend},
+    display::column_width,
+    git::{GitError, Revision},
+    workspace::{Workspace, WorkspaceError},
+};
+use clap::Args;
+use miette::{Diagnostic, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+/// View current topic branches
+///
+/// # Description
+///
+/// Summarizes the topic branches of the projects, one line per branch, leaving
+/// out the branch sync keeps each project on:
+///
+///   *P topic | in app, lib
+///
+/// The first column is '*' when the branch is checked out in a project. The
+/// second is 'P' when the branch was uploaded as it is from every project
+/// carrying it, and 'p' when only from some. After the bar come the projects
+/// carrying the branch, or 'in all projects' when every listed project does.
+///
+/// Naming projects, by name or path, restricts the summary to them. --current
+/// only counts branches where they are checked out, and --unpublished only where
+/// they have commits that were never uploaded, which keeps the summary short in
+/// workspaces carrying dozens of topics.
+#[derive(Args, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct BranchesArgs {
+    projects: Option<Vec<String>>,
+
+    /// only show branches checked out in their projects
+    #[arg(long, default_value_t = false)]
+    current: bool,
+
+    /// only show branches with commits not uploaded yet
+    #[arg(long, default_value_t = false)]
+    unpublished: bool,
+}
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum BranchesError {
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    WorkspaceError(#[from] WorkspaceError),
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    GitError(#[from] GitError),
+}
+
+/// A branch across the projects carrying it.
+#[derive(Debug, Default)]
+struct BranchSummary {
+    /// The paths of the projects with the branch.
+    projects: Vec<String>,
+
+    /// Whether any of the projects has the branch checked out.
+    current: bool,
+
+    /// How many of the projects uploaded the branch as it is.
+    published: usize,
+}
+
+impl BranchSummary {
+    /// The summary's line for `name`, padded to `width`, out of `project_count` listed projects.
+    fn line(&self, name: &str, width: usize, project_count: usize) -> String {
+        let current = if self.current { '*' } else { ' ' };
+        let published = match self.published {
+            0 => ' ',
+            count if count == self.projects.len() => 'P',
+            _ => 'p',
+        };
+        let projects = if project_count > 1 && self.projects.len() == project_count {
+            "in all projects".to_string()
+        } else {
+            format!("in {}", self.projects.join(", "))
+        };
+
+        format!("{current}{published} {name:width$} | {projects}")
+    }
+}
+
+pub fn run_branches(args: BranchesArgs) -> Result<(), BranchesError> {
+    let workspace = Workspace::discover()?;
+    let config = workspace.config()?;
+
+    branches(&GixBackend::new(&config), &workspace, args)
+}
+
+fn branches(
+    backend: &dyn GitBackend,
+    workspace: &Workspace,
+    args: BranchesArgs,
+) -> Result<(), BranchesError> {
+    let manifest = workspace.manifest()?;
+
+    let mut branches: BTreeMap<String, BranchSummary> = BTreeMap::new();
+    let mut project_count = 0;
+    for project in select_projects(&manifest, args.projects.as_deref()) {
+        let path = project.checkout_path();
+        let dst = workspace.project_dir(path);
+        if !dst.exists() {
+            continue;
+        }
+        project_count += 1;
+
+        let checked_out = backend.current_branch(&dst)?;
+        let remote = manifest.project_remote(&project);
+        let synced = match manifest.project_revision(&project) {
+            Some(revision) => match Revision::classify(&revision) {
+                Revision::Branch(branch) => Some(branch.to_string()),
+                _ => None,
+            },
+            None => {
+                remote.and_then(|remote| backend.remote_default_branch(&dst, remote.git_name()))
+            }
+        };
+        for branch in backend.local_branches(&dst, path)? {
+            if synced.as_deref() == Some(branch.as_str()) {
+                continue;
+            }
+            let current = checked_out.as_deref() == Some(branch.as_str());
+            let head = backend.branch_commit(&dst, &branch);
+            let published = head.is_some() && head == backend.published_commit(&dst, &branch);
+            if (args.current && !current) || (args.unpublished && published) {
+                continue;
+            }
+
+            let summary = branches.entry(branch).or_default();
+            summary.projects.push(path.to_string());
+            summary.current |= current;
+            summary.published += usize::from(published);
+        }
+    }
+
+    if branches.is_empty() {
+        println!("   (no branches)");
+        return Ok(());
+    }
+
+    let width = column_width(branches.keys().map(String::as_str), 0);
+    for (name, summary) in &branches {
+        println!("{}", summary.line(name, width, project_count));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BranchSummary;
+
+    #[test]
+    fn test_branch_line() {
+        let summary = BranchSummary {
+            projects: vec!["app".to_string(), "lib".to_string()],
+            current: true,
+            published: 1,
+        };
+        assert_eq!(summary.line("topic", 8, 3), "*p topic    | in app, lib");
+        assert_eq!(summary.line("topic", 5, 2), "*p topic | in all projects");
+
+        let summary = BranchSummary {
+            projects: vec!["app".to_string()],
+            current: false,
+            published: 1,
+        };
+        assert_eq!(summary.line("fix", 3, 1), " P fix | in app");
+    }
+}
@@ -0,0 +1,138 @@
+use super::select_projects;
+use crate::{
+    auth::{self, AuthError},
+    gerrit::{query_changes, review_url, ChangeInfo, GerritError},
+    workspace::{Workspace, WorkspaceError},
+};
+use clap::Args;
+use miette::{Diagnostic, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use thiserror::Error;
+use tracing::info;
+
+/// List open changes on the review servers
+///
+/// # Description
+///
+/// Queries the Gerrit review server of every remote in the manifest and lists
+/// the matching changes of the workspace's projects by path, across all projects
+/// at once. By default these are your own open changes; --query takes any Gerrit
+/// search, e.g. 'status:open reviewer:self'.
+///
+/// Queries about you need credentials for the review server: the token or
+/// cookie file set for the remote in .repo/repox.toml (see 'repox config').
+#[derive(Args, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ChangesArgs {
+    projects: Option<Vec<String>>,
+
+    /// Gerrit search query
+    #[arg(short = 'q', long, default_value = "owner:self status:open")]
+    query: String,
+
+    /// print the changes as JSON
+    #[arg(long, default_value_t = false)]
+    json: bool,
+}
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum ChangesError {
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    WorkspaceError(#[from] WorkspaceError),
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    AuthError(#[from] AuthError),
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    GerritError(#[from] GerritError),
+
+    #[error("None of the projects' remotes has a review server")]
+    #[diagnostic(
+        code(repox::command::changes::no_review),
+        help("the manifest's remote elements need a review attribute")
+    )]
+    NoReviewServer,
+
+    #[error(transparent)]
+    #[diagnostic(code(repox::command::changes::json))]
+    JsonError(#[from] serde_json::Error),
+}
+
+/// A change of a workspace project.
+#[derive(Debug, Serialize)]
+struct ProjectChange {
+    path: String,
+    url: String,
+    #[serde(flatten)]
+    change: ChangeInfo,
+}
+
+pub fn run_changes(args: ChangesArgs) -> Result<(), ChangesError> {
+    let workspace = Workspace::discover()?;
+    let config = workspace.config()?;
+    let manifest = workspace.manifest()?;
+
+    // Projects by the remote they are reviewed on, each remote queried once.
+    let mut by_remote: BTreeMap<String, BTreeMap<String, String>> = BTreeMap::new();
+    for project in select_projects(&manifest, args.projects.as_deref()) {
+        if let Some(remote) = manifest.project_remote(&project) {
+            by_remote
+                .entry(remote.name.clone())
+                .or_default()
+                .insert(project.name.clone(), project.checkout_path().to_string());
+        }
+    }
+
+    let mut changes = Vec::new();
+    let mut queried = false;
+    for (remote_name, projects) in &by_remote {
+        let Some((git_name, review)) = manifest.find_remote(remote_name).and_then(|remote| {
+            let review = remote.review()?;
+            Some((remote.git_name().to_string(), review.to_string()))
+        }) else {
+            info!("remote {remote_name} has no review server, skipping its projects");
+            continue;
+        };
+
+        let headers = auth::http_headers(&config, &git_name, &review_url(&review))?;
+        queried = true;
+        for change in query_changes(&review, &args.query, &headers, &config.transport)? {
+            // Changes of projects outside the workspace are not listed.
+            if let Some(path) = projects.get(&change.project) {
+                changes.push(ProjectChange {
+                    path: path.clone(),
+                    url: format!(
+                        "{}/c/{}/+/{}",
+                        review_url(&review),
+                        change.project,
+                        change.number
+                    ),
+                    change,
+                });
+            }
+        }
+    }
+    if !queried {
+        return Err(ChangesError::NoReviewServer);
+    }
+
+    changes.sort_by(|a, b| (&a.path, a.change.number).cmp(&(&b.path, b.change.number)));
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&changes)?);
+        return Ok(());
+    }
+
+    for ProjectChange { path, url, change } in &changes {
+        println!(
+            "{path}: {} {} ({})",
+            change.number, change.subject, change.branch
+        );
+        println!("  {url}");
+    }
+
+    Ok(())
+}
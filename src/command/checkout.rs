@@ -0,0 +1,97 @@
+use super::select_projects;
+use crate::{
+    backend::{GitBackend, GixBackend},
+    git::{DirtyPolicy, GitError},
+    workspace::{Workspace, WorkspaceError},
+};
+use clap::Args;
+use miette::{Diagnostic, Result};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::{info, info_span};
+
+/// Checkout a branch for development
+///
+/// # Description
+///
+/// The 'repo checkout' command checks out an existing branch that was previously
+/// created by 'repo start'.
+///
+/// The command is equivalent to:
+///
+///   repo forall [<project>...] -c git checkout <branchname>
+///
+/// Projects with uncommitted changes are left alone unless --auto-stash or
+/// --force-checkout is given.
+#[derive(Args, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct CheckoutArgs {
+    branch_name: String,
+    projects: Option<Vec<String>>,
+
+    /// stash uncommitted changes before switching branches and restore them afterwards
+    #[arg(long, default_value_t = false)]
+    auto_stash: bool,
+
+    /// switch branches even if it results in throwing away uncommitted modifications.
+    /// WARNING: this may cause loss of data
+    #[arg(long, default_value_t = false)]
+    force_checkout: bool,
+}
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum CheckoutError {
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    WorkspaceError(#[from] WorkspaceError),
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    GitError(#[from] GitError),
+
+    #[error("No project has a branch named {branch}")]
+    #[diagnostic(
+        code(repox::command::checkout::no_such_branch),
+        help("create the branch with `repox start {branch}` first")
+    )]
+    NoSuchBranch { branch: String },
+}
+
+pub fn run_checkout(args: CheckoutArgs) -> Result<(), CheckoutError> {
+    let workspace = Workspace::discover()?;
+    let config = workspace.config()?;
+
+    checkout(&GixBackend::new(&config), &workspace, args)
+}
+
+fn checkout(
+    backend: &dyn GitBackend,
+    workspace: &Workspace,
+    args: CheckoutArgs,
+) -> Result<(), CheckoutError> {
+    let policy = DirtyPolicy::from_flags(args.auto_stash, args.force_checkout);
+    let manifest = workspace.manifest()?;
+
+    let mut checked_out = 0;
+    for project in select_projects(&manifest, args.projects.as_deref()) {
+        let _project_span = info_span!("Checking out branch", name = project.name).entered();
+        let path = project.checkout_path();
+        let dst = workspace.project_dir(path);
+        if !dst.exists() {
+            continue;
+        }
+
+        if backend.checkout_branch(&dst, path, &args.branch_name, policy)? {
+            info!("Checked out {} in {path}", args.branch_name);
+            checked_out += 1;
+        }
+    }
+
+    if checked_out == 0 {
+        return Err(CheckoutError::NoSuchBranch {
+            branch: args.branch_name,
+        });
+    }
+
+    Ok(())
+}
@@ -0,0 +1,301 @@
+use crate::{
+    config::WorkspaceConfig,
+    workspace::{Workspace, WorkspaceError},
+};
+use clap::{Args, Subcommand};
+use miette::{Diagnostic, Result};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use toml::{Table, Value};
+
+/// Settings that can be read and written with `repox config`, and what they mean.
+const KEYS: [(&str, &str); 36] = [
+    ("manifest.url", "manifest repository location"),
+    ("manifest.branch", "manifest branch or revision"),
+    ("manifest.path", "location of the manifest file"),
+    ("mirror", "whether the workspace is a mirror"),
+    (
+        "worktree",
+        "whether projects are managed using git-worktree",
+    ),
+    (
+        "protect-windows",
+        "whether checkouts refuse paths Windows cannot represent",
+    ),
+    (
+        "use-superproject",
+        "whether sync pins projects to the manifest's superproject",
+    ),
+    (
+        "strict-manifest",
+        "whether unknown manifest elements and attributes are errors",
+    ),
+    ("groups", "manifest groups to restrict projects to"),
+    (
+        "clone-bundle-url",
+        "URL template new projects' clone bundles are downloaded from",
+    ),
+    ("depth", "depth of shallow clones"),
+    ("jobs", "number of projects to sync in parallel"),
+    (
+        "mirror-push-url",
+        "URL prefix `repox mirror push` replicates projects to",
+    ),
+    ("repo-url", "repository `repox selfupdate` installs from"),
+    ("repo-rev", "branch, tag or commit of repo-url"),
+    (
+        "hooks-template",
+        "directory of git hooks sync installs into every project",
+    ),
+    (
+        "transport.ssh-host-keys",
+        "SSH host key checking: strict, accept-new or off",
+    ),
+    (
+        "transport.ca-bundle",
+        "CA bundle to verify HTTPS servers against",
+    ),
+    (
+        "transport.client-cert",
+        "client certificate to present to HTTPS servers",
+    ),
+    (
+        "transport.client-key",
+        "private key of the client certificate",
+    ),
+    (
+        "transport.proxy",
+        "proxy repox's own HTTP downloads go through",
+    ),
+    (
+        "transport.max-download-rate",
+        "bytes per second repox's own HTTP downloads are held to",
+    ),
+    (
+        "fetch.negotiation-algorithm",
+        "how fetches find common history: consecutive, skipping or noop",
+    ),
+    (
+        "fetch.commit-graph",
+        "whether fetches walk history through commit-graph files",
+    ),
+    (
+        "fetch.pack-threads",
+        "threads resolving fetched packs, 0 for one per core",
+    ),
+    (
+        "branch-names.template",
+        "what `repox start` expands branch names to, e.g. {user}/{topic}",
+    ),
+    (
+        "branch-names.pattern",
+        "regular expression branch names must match",
+    ),
+    (
+        "upload.max-file-size",
+        "size in bytes above which uploaded files need confirmation",
+    ),
+    (
+        "upload.forbidden-files",
+        "globs of files whose upload needs confirmation",
+    ),
+    (
+        "upload.push-namespace",
+        "ref namespace `upload --push` pushes topic branches under",
+    ),
+    ("command-hooks.pre-sync", "program run before sync"),
+    ("command-hooks.post-sync", "program run after sync"),
+    ("command-hooks.pre-start", "program run before start"),
+    ("command-hooks.post-start", "program run after start"),
+    ("command-hooks.pre-upload", "program run before upload"),
+    ("command-hooks.post-upload", "program run after upload"),
+];
+
+/// Read and write workspace configuration
+///
+/// # Description
+///
+/// Inspects and changes the choices recorded by 'repo init' in .repo/repox.toml,
+/// so they can be changed without re-running 'repo init' with every flag.
+#[derive(Args, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ConfigArgs {
+    #[command(subcommand)]
+    command: ConfigCommand,
+}
+
+#[derive(Subcommand, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum ConfigCommand {
+    /// Print the value of a setting
+    Get { key: String },
+
+    /// Change the value of a setting
+    Set { key: String, value: String },
+
+    /// Reset a setting to its default
+    Unset { key: String },
+
+    /// Print every setting that has a value
+    List,
+}
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum ConfigCommandError {
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    WorkspaceError(#[from] WorkspaceError),
+
+    #[error("Unknown setting {key}")]
+    #[diagnostic(
+        code(repox::command::config::unknown_key),
+        help("run `repox config list` to see the known settings")
+    )]
+    UnknownKey { key: String },
+
+    #[error("Invalid value for {key}")]
+    #[diagnostic(code(repox::command::config::invalid_value))]
+    InvalidValue {
+        key: String,
+        #[source]
+        source: toml::de::Error,
+    },
+
+    #[error(transparent)]
+    #[diagnostic(code(repox::command::config::serialize))]
+    SerializationError(#[from] toml::ser::Error),
+}
+
+fn check_key(key: &str) -> Result<(), ConfigCommandError> {
+    if KEYS.iter().any(|(known, _)| *known == key) {
+        Ok(())
+    } else {
+        Err(ConfigCommandError::UnknownKey {
+            key: key.to_string(),
+        })
+    }
+}
+
+fn lookup<'a>(table: &'a Table, key: &str) -> Option<&'a Value> {
+    let mut segments = key.split('.');
+    let mut value = table.get(segments.next()?)?;
+    for segment in segments {
+        value = value.as_table()?.get(segment)?;
+    }
+    Some(value)
+}
+
+/// The table holding the last segment of `key`, created as needed, and that segment.
+fn parent_table<'a, 'k>(table: &'a mut Table, key: &'k str) -> (&'a mut Table, &'k str) {
+    let mut segments: Vec<&str> = key.split('.').collect();
+    let last = segments.pop().expect("split always yields a segment");
+
+    let mut table = table;
+    for segment in segments {
+        table = table
+            .entry(segment)
+            .or_insert_with(|| Value::Table(Table::new()))
+            .as_table_mut()
+            .expect("settings only nest tables");
+    }
+    (table, last)
+}
+
+/// Interprets a value given on the command line as TOML, falling back to a plain string.
+fn parse_value(value: &str) -> Value {
+    format!("value = {value}")
+        .parse::<Table>()
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| Value::String(value.to_string()))
+}
+
+/// The configuration `table` holds with `key` set to `value`.
+///
+/// The value is typed as TOML would read it, unless the setting takes a string: a branch named `1.0` or
+/// `true` is kept as it was given rather than becoming a number or a boolean.
+fn set_value(table: &Table, key: &str, value: &str) -> Result<WorkspaceConfig, toml::de::Error> {
+    let with = |value: Value| {
+        let mut table = table.clone();
+        let (parent, name) = parent_table(&mut table, key);
+        parent.insert(name.to_string(), value);
+        WorkspaceConfig::deserialize(table)
+    };
+
+    let typed = parse_value(value);
+    if typed.is_str() {
+        return with(typed);
+    }
+    with(typed).or_else(|error| with(Value::String(value.to_string())).map_err(|_| error))
+}
+
+fn display(value: &Value) -> String {
+    match value {
+        Value::String(value) => value.clone(),
+        value => value.to_string(),
+    }
+}
+
+pub fn run_config(args: ConfigArgs) -> Result<(), ConfigCommandError> {
+    let workspace = Workspace::discover()?;
+    let config = workspace.config()?;
+    let mut table = Table::try_from(&config)?;
+
+    match args.command {
+        ConfigCommand::Get { key } => {
+            check_key(&key)?;
+            if let Some(value) = lookup(&table, &key) {
+                println!("{}", display(value));
+            }
+        }
+        ConfigCommand::List => {
+            for (key, description) in KEYS {
+                if let Some(value) = lookup(&table, key) {
+                    println!("{key} = {}  # {description}", display(value));
+                }
+            }
+        }
+        ConfigCommand::Set { key, value } => {
+            check_key(&key)?;
+            let config = set_value(&table, &key, &value)
+                .map_err(|source| ConfigCommandError::InvalidValue { key, source })?;
+            workspace.save_config(&config)?;
+        }
+        ConfigCommand::Unset { key } => {
+            check_key(&key)?;
+            let (parent, name) = parent_table(&mut table, &key);
+            parent.remove(name);
+
+            let config = WorkspaceConfig::deserialize(table)
+                .map_err(|source| ConfigCommandError::InvalidValue { key, source })?;
+            workspace.save_config(&config)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::set_value;
+    use crate::config::WorkspaceConfig;
+    use toml::Table;
+
+    #[test]
+    fn test_set_value() {
+        let table = Table::try_from(WorkspaceConfig::default()).unwrap();
+
+        let config = set_value(&table, "manifest.branch", "1.0").unwrap();
+        assert_eq!(config.manifest.branch, "1.0");
+        let config = set_value(&table, "manifest.branch", "true").unwrap();
+        assert_eq!(config.manifest.branch, "true");
+
+        assert!(
+            set_value(&table, "protect-windows", "true")
+                .unwrap()
+                .protect_windows
+        );
+        assert_eq!(set_value(&table, "jobs", "4").unwrap().jobs, Some(4));
+        assert!(set_value(&table, "jobs", "many").is_err());
+    }
+}
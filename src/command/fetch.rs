@@ -0,0 +1,118 @@
+use super::{select_grouped_projects, sync::custom_refspecs};
+use crate::{
+    backend::{GitBackend, GixBackend},
+    git::{project_refspecs, GitError},
+    workspace::{Workspace, WorkspaceError},
+};
+use clap::Args;
+use miette::{Diagnostic, Result};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::{info, info_span};
+
+/// Download objects and refs without touching the working trees
+///
+/// # Description
+///
+/// Fetches the projects given, or every project of the workspace's groups, the
+/// way 'repox sync' does, but leaves their working trees alone.
+///
+/// Projects cloned with a depth, from the manifest's clone-depth or 'repox init
+/// --depth', only have part of their history. 'repox info' reports them, and
+/// --unshallow fetches the rest. Sync does this on its own when it needs the
+/// history to fast-forward a local branch.
+#[derive(Args, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct FetchArgs {
+    projects: Option<Vec<String>>,
+
+    /// fetch the full history of shallow projects
+    #[arg(long, default_value_t = false)]
+    unshallow: bool,
+}
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum FetchError {
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    WorkspaceError(#[from] WorkspaceError),
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    GitError(#[from] GitError),
+
+    #[error("Project {project} references an undefined remote")]
+    #[diagnostic(code(repox::command::fetch::unknown_remote))]
+    UnknownRemote { project: String },
+
+    #[error("{path} is not checked out")]
+    #[diagnostic(
+        code(repox::command::fetch::not_checked_out),
+        help("run `repox sync {path}` to clone it")
+    )]
+    NotCheckedOut { path: String },
+}
+
+pub fn run_fetch(args: FetchArgs) -> Result<(), FetchError> {
+    let workspace = Workspace::discover()?;
+    let config = workspace.config()?;
+
+    fetch(&GixBackend::new(&config), &workspace, args)
+}
+
+fn fetch(
+    backend: &dyn GitBackend,
+    workspace: &Workspace,
+    args: FetchArgs,
+) -> Result<(), FetchError> {
+    let config = workspace.config()?;
+    let manifest = workspace.manifest()?;
+
+    select_grouped_projects(
+        &manifest,
+        args.projects.as_deref(),
+        config.groups.as_deref(),
+    )
+    .into_par_iter()
+    .try_for_each(|project| {
+        let _project_span = info_span!("Fetching project", name = project.name).entered();
+        let remote =
+            manifest
+                .project_remote(&project)
+                .ok_or_else(|| FetchError::UnknownRemote {
+                    project: project.name.clone(),
+                })?;
+        let path = if config.mirror {
+            project.mirror_path()
+        } else {
+            project.checkout_path().to_string()
+        };
+
+        let dst = workspace.project_dir(&path);
+        let Some(checkout) = backend.inspect(&dst, remote.git_name()) else {
+            return Err(FetchError::NotCheckedOut { path });
+        };
+
+        if args.unshallow {
+            if !checkout.shallow {
+                info!("{path} already has its full history");
+                return Ok(());
+            }
+            info!("Fetching the full history of {path}");
+            return Ok(backend.unshallow(&dst, remote.git_name())?);
+        }
+
+        let mut refspecs = project_refspecs(
+            remote.git_name(),
+            manifest.project_revision(&project).as_deref(),
+            manifest.project_upstream(&project).as_deref(),
+            manifest.project_sync_c(&project),
+        );
+        refspecs
+            .extra
+            .extend(custom_refspecs(&config, &manifest, &project));
+
+        Ok(backend.fetch_project(&dst, remote.git_name(), &refspecs)?)
+    })
+}
@@ -1,6 +1,357 @@
+use super::select_projects;
+use crate::{
+    warnings::{self, WarningKind},
+    workspace::{Workspace, WorkspaceError},
+};
 use clap::Args;
+use miette::{Diagnostic, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{create_dir_all, File},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    time::{Duration, Instant},
+};
+use thiserror::Error;
+use tracing::info_span;
 
-#[derive(Args, Debug)]
+/// Run a shell command in each project
+///
+/// # Description
+///
+/// Executes the same shell command in each project.
+///
+/// # Failure Behavior
+///
+/// By default the command is run in every project even if it fails in some of
+/// them, and repox exits with the worst exit status seen. The -e option stops at
+/// the first project where the command fails.
+///
+/// Projects that are not checked out count as failures unless --ignore-missing is
+/// given, in which case they are skipped silently.
+///
+/// # Environment
+///
+/// The command is run from the project's working directory with the following
+/// environment:
+///
+/// REPO_PROJECT is set to the unique name of the project.
+///
+/// REPO_PATH is the path relative to the root of the client.
+///
+/// REPO_REMOTE is the name of the remote system from the manifest, or its alias
+/// when it has one.
+///
+/// REPO_RREV is the name of the revision from the manifest.
+///
+/// REPO_I is the index of the project being iterated, starting at 1, and
+/// REPO_COUNT is the total number of projects.
+///
+/// Each annotation of the project, or of its remote, is exported as REPO__<name>.
+///
+/// # Placeholders
+///
+/// %(name), %(path), %(remote) and %(revision) in the command are replaced with
+/// the project's name, path, remote and revision before it is run, e.g.
+/// `repox forall -c 'git log -1 > /tmp/%(name).log'`. %% stands for a literal %.
+///
+/// # Output Capture
+///
+/// With --output-dir, the stdout and stderr of the command in each project are
+/// written to '<dir>/<project-path>.log' instead of the terminal, and a table of
+/// each project's exit status, run time and log file is printed once every
+/// project is done.
+#[derive(Args, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub struct ForAllArgs {
     projects: Option<Vec<String>>,
+
+    /// command (and arguments) to execute
+    #[arg(short = 'c', long, required = true, num_args = 1.., allow_hyphen_values = true)]
+    command: Vec<String>,
+
+    /// abort if a command exits unsuccessfully
+    #[arg(short = 'e', long, default_value_t = false)]
+    abort_on_errors: bool,
+
+    /// silently skip projects that are not checked out
+    #[arg(long, default_value_t = false)]
+    ignore_missing: bool,
+
+    /// show project headers before output
+    #[arg(short = 'p', default_value_t = false)]
+    project_header: bool,
+
+    /// write each project's output to DIR/<project-path>.log and print a summary
+    #[arg(long, value_name = "DIR")]
+    output_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum ForAllError {
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    WorkspaceError(#[from] WorkspaceError),
+
+    #[error("Unknown placeholder %({placeholder}) in the command")]
+    #[diagnostic(
+        code(repox::command::for_all::unknown_placeholder),
+        help("the known placeholders are {}, write %% for a literal %", PLACEHOLDERS.map(|name| format!("%({name})")).join(", "))
+    )]
+    UnknownPlaceholder { placeholder: String },
+
+    #[error("Could not create the log file {path}")]
+    #[diagnostic(code(repox::command::for_all::log_file))]
+    LogFileError {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("Could not run the command in {path}")]
+    #[diagnostic(code(repox::command::for_all::spawn))]
+    SpawnError {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+/// Exit status used for projects that are not checked out, matching git-repo.
+const MISSING_PROJECT_STATUS: i32 = 1;
+
+/// Names that can appear as `%(name)` in the command.
+const PLACEHOLDERS: [&str; 4] = ["name", "path", "remote", "revision"];
+
+/// Replaces the `%(key)` placeholders in `template` with `values`, indexed like [`PLACEHOLDERS`].
+fn expand(template: &str, values: &[&str; PLACEHOLDERS.len()]) -> Result<String, ForAllError> {
+    let mut expanded = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('%') {
+        expanded.push_str(&rest[..start]);
+        rest = &rest[start + 1..];
+
+        if let Some(after) = rest.strip_prefix('%') {
+            expanded.push('%');
+            rest = after;
+        } else if let Some((placeholder, after)) = rest
+            .strip_prefix('(')
+            .and_then(|inner| inner.split_once(')'))
+        {
+            let index = PLACEHOLDERS
+                .iter()
+                .position(|name| *name == placeholder)
+                .ok_or_else(|| ForAllError::UnknownPlaceholder {
+                    placeholder: placeholder.to_string(),
+                })?;
+            expanded.push_str(values[index]);
+            rest = after;
+        } else {
+            expanded.push('%');
+        }
+    }
+    expanded.push_str(rest);
+
+    Ok(expanded)
+}
+
+fn build_command(command: &[String]) -> Command {
+    match command {
+        [script] => {
+            let mut command = Command::new("sh");
+            command.arg("-c").arg(script);
+            command
+        }
+        [program, args @ ..] => {
+            let mut command = Command::new(program);
+            command.args(args);
+            command
+        }
+        [] => unreachable!("clap requires a command"),
+    }
+}
+
+/// Opens `<output_dir>/<path>.log` for a project's output, creating the directories it is in.
+fn create_log(output_dir: &Path, path: &str) -> Result<(PathBuf, File), ForAllError> {
+    let log = output_dir.join(format!("{}.log", path.trim_end_matches('/')));
+    let log_error = |source| ForAllError::LogFileError {
+        path: log.display().to_string(),
+        source,
+    };
+
+    if let Some(parent) = log.parent() {
+        create_dir_all(parent).map_err(log_error)?;
+    }
+    let file = File::create(&log).map_err(log_error)?;
+    Ok((log, file))
+}
+
+/// How the command went in one project, for the `--output-dir` summary.
+struct Outcome {
+    path: String,
+    status: i32,
+    elapsed: Option<Duration>,
+    log: Option<PathBuf>,
+}
+
+/// Prints the `--output-dir` summary, one row per project.
+fn print_summary(outcomes: &[Outcome]) {
+    let width = outcomes
+        .iter()
+        .map(|outcome| outcome.path.len())
+        .chain(["PROJECT".len()])
+        .max()
+        .unwrap_or_default();
+
+    println!("{:<6}  {:<8}  {:<width$}  LOG", "STATUS", "TIME", "PROJECT");
+    for outcome in outcomes {
+        let elapsed = outcome
+            .elapsed
+            .map(|elapsed| format!("{:.1}s", elapsed.as_secs_f64()))
+            .unwrap_or_else(|| "-".to_string());
+        let log = match &outcome.log {
+            Some(log) => log.display().to_string(),
+            None => "not checked out".to_string(),
+        };
+        println!(
+            "{:<6}  {elapsed:<8}  {:<width$}  {log}",
+            outcome.status, outcome.path
+        );
+    }
+}
+
+/// Runs the command in each selected project, returning the worst exit status seen.
+pub fn run_for_all(args: ForAllArgs) -> Result<i32, ForAllError> {
+    let workspace = Workspace::discover()?;
+    let manifest = workspace.cached_manifest()?;
+    let projects = select_projects(&manifest, args.projects.as_deref());
+    let count = projects.len();
+
+    let mut worst = 0;
+    let mut outcomes = Vec::new();
+    for (index, project) in projects.into_iter().enumerate() {
+        let path = project.checkout_path().to_string();
+        let _project_span = info_span!("Running command", name = project.name, path).entered();
+        let dst = workspace.project_dir(&path);
+
+        let status = if dst.is_dir() {
+            if args.project_header && args.output_dir.is_none() {
+                println!("\nproject {path}/");
+            }
+
+            let remote = manifest
+                .project_remote(&project)
+                .map(|remote| remote.git_name());
+            let revision = manifest.project_revision(&project);
+            let values = [
+                project.name.as_str(),
+                path.as_str(),
+                remote.unwrap_or_default(),
+                revision.as_deref().unwrap_or_default(),
+            ];
+            let expanded = args
+                .command
+                .iter()
+                .map(|part| expand(part, &values))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let mut command = build_command(&expanded);
+            command
+                .current_dir(&dst)
+                .env("REPO_PROJECT", &project.name)
+                .env("REPO_PATH", &path)
+                .env("REPO_REMOTE", remote.unwrap_or_default())
+                .env("REPO_RREV", project.revision.as_deref().unwrap_or_default())
+                .env("REPO_I", (index + 1).to_string())
+                .env("REPO_COUNT", count.to_string());
+            for (name, value) in manifest.project_annotations(&project) {
+                command.env(format!("REPO__{name}"), value);
+            }
+
+            let mut log = None;
+            if let Some(output_dir) = &args.output_dir {
+                let (log_path, file) = create_log(output_dir, &path)?;
+                let stderr = file
+                    .try_clone()
+                    .map_err(|source| ForAllError::LogFileError {
+                        path: log_path.display().to_string(),
+                        source,
+                    })?;
+                command
+                    .stdout(Stdio::from(file))
+                    .stderr(Stdio::from(stderr));
+                log = Some(log_path);
+            }
+
+            let started = Instant::now();
+            let status = command.status().map_err(|source| ForAllError::SpawnError {
+                path: path.clone(),
+                source,
+            })?;
+
+            // Commands killed by a signal have no code, report them as a generic failure.
+            let status = status.code().unwrap_or(1);
+            outcomes.push(Outcome {
+                path: path.clone(),
+                status,
+                elapsed: Some(started.elapsed()),
+                log,
+            });
+            status
+        } else if args.ignore_missing {
+            continue;
+        } else {
+            warnings::warn(
+                WarningKind::SkippedProject,
+                format!("{path} is not checked out"),
+            );
+            outcomes.push(Outcome {
+                path: path.clone(),
+                status: MISSING_PROJECT_STATUS,
+                elapsed: None,
+                log: None,
+            });
+            MISSING_PROJECT_STATUS
+        };
+
+        worst = worst.max(status);
+        if status != 0 && args.abort_on_errors {
+            eprintln!("error: {path}: command exited with status {status}, aborting");
+            break;
+        }
+    }
+
+    if args.output_dir.is_some() {
+        print_summary(&outcomes);
+    }
+
+    Ok(worst)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{expand, ForAllError};
+
+    #[test]
+    fn test_expand() {
+        let values = ["platform/build", "build", "aosp", "main"];
+
+        assert_eq!(
+            expand("echo %(name) > /tmp/%(path).log", &values).unwrap(),
+            "echo platform/build > /tmp/build.log"
+        );
+        assert_eq!(
+            expand("git push %(remote) HEAD:%(revision)", &values).unwrap(),
+            "git push aosp HEAD:main"
+        );
+        assert_eq!(
+            expand("printf '%d %%(name)' 100%", &values).unwrap(),
+            "printf '%d %(name)' 100%"
+        );
+        assert!(matches!(
+            expand("%(branch)", &values),
+            Err(ForAllError::UnknownPlaceholder { placeholder }) if placeholder == "branch"
+        ));
+    }
 }
@@ -0,0 +1,189 @@
+use crate::{
+    backend::{GitBackend, GixBackend},
+    git::{changed_files, GitError},
+    workspace::{Workspace, WorkspaceError},
+};
+use clap::Args;
+use miette::{Diagnostic, Result};
+use quick_xml::{se::Serializer, DeError};
+use repox_manifest::{project::Project, Manifest};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fs::write};
+use thiserror::Error;
+use tracing::warn;
+
+/// Write a manifest pinning every project to its checked out commit
+///
+/// # Description
+///
+/// Records the exact state of the workspace as a manifest, so it can be handed to
+/// a colleague or attached to a bug report and recreated elsewhere with 'repox
+/// thaw' or 'repox sync --manifest-file'.
+///
+/// Every project's revision is set to the commit it has checked out, and its
+/// upstream to the revision the manifest asked for; the rest of the manifest, such
+/// as copyfile and linkfile elements, is kept as it is. Projects with uncommitted
+/// changes, or checked out at commits that were never fetched from their remote,
+/// are reported, as the frozen manifest cannot reproduce them.
+#[derive(Args, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct FreezeArgs {
+    /// file to save the frozen manifest to
+    #[arg(short = 'o', long, default_value = "-")]
+    output_file: String,
+}
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum FreezeError {
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    WorkspaceError(#[from] WorkspaceError),
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    GitError(#[from] GitError),
+
+    #[error("Could not write the frozen manifest as XML")]
+    #[diagnostic(code(repox::command::freeze::xml))]
+    XmlError(#[from] DeError),
+
+    #[error("Could not write the frozen manifest to {path}")]
+    #[diagnostic(code(repox::command::freeze::write))]
+    WriteError {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+/// Renders `manifest` as a single manifest file, as `freeze` and `manifest` write it.
+pub(crate) fn manifest_xml(manifest: &Manifest) -> Result<String, DeError> {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let mut serializer = Serializer::new(&mut xml);
+    serializer.indent(' ', 2);
+    manifest.serialize(serializer)?;
+    xml.push('\n');
+    Ok(xml)
+}
+
+/// The commit a project has checked out, warning about the state a frozen manifest cannot capture.
+fn pin(
+    backend: &dyn GitBackend,
+    workspace: &Workspace,
+    manifest: &Manifest,
+    project: &Project,
+) -> Result<Option<String>, GitError> {
+    let path = project.checkout_path();
+    let dst = workspace.project_dir(path);
+    if !dst.exists() {
+        return Ok(None);
+    }
+
+    let Some(head) = backend.head_commit(&dst) else {
+        return Ok(None);
+    };
+
+    if !changed_files(&dst, path)?.is_empty() {
+        warn!("{path} has uncommitted changes, which the frozen manifest does not include");
+    }
+    let remote = manifest
+        .project_remote(project)
+        .map(|remote| remote.name.as_str());
+    if let Some(remote) =
+        remote.filter(|remote| !backend.fetched_from(&dst, remote, &head.to_string()))
+    {
+        warn!("{path} is at {head}, which was never fetched from {remote}; push it before handing the manifest over");
+    }
+
+    Ok(Some(head.to_string()))
+}
+
+/// The workspace's manifest with every checked out project pinned to its `HEAD`, upstream recording the
+/// revision it tracked.
+pub(crate) fn pinned_manifest(
+    backend: &dyn GitBackend,
+    workspace: &Workspace,
+) -> Result<Manifest, FreezeError> {
+    let mut manifest = workspace.manifest()?;
+    let mut commits = HashMap::new();
+    for project in manifest.projects() {
+        if let Some(commit) = pin(backend, workspace, &manifest, &project)? {
+            commits.insert(project.checkout_path().to_string(), commit);
+        }
+    }
+    manifest.pin_projects(&commits);
+
+    Ok(manifest)
+}
+
+pub fn run_freeze(args: FreezeArgs) -> Result<(), FreezeError> {
+    let workspace = Workspace::discover()?;
+    let backend = GixBackend::new(&workspace.config()?);
+    let frozen = manifest_xml(&pinned_manifest(&backend, &workspace)?)?;
+
+    if args.output_file == "-" {
+        print!("{frozen}");
+        return Ok(());
+    }
+
+    write(&args.output_file, frozen).map_err(|source| FreezeError::WriteError {
+        path: args.output_file,
+        source,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::manifest_xml;
+    use repox_manifest::Manifest;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_frozen_manifest() {
+        let mut manifest: Manifest = quick_xml::de::from_str(
+            r#"<manifest>
+                <notice>Pinned for a bug report</notice>
+                <remote name="origin" fetch="https://example.com" review="https://review.example.com" />
+                <default remote="origin" revision="main" sync-j="4" sync-c="true" />
+                <superproject name="platform/superproject" />
+                <contactinfo bugurl="https://bugs.example.com" />
+                <project name="app" path="src/app" groups="pdk" sync-tags="false" force-path="true">
+                    <annotation name="owner" value="a&amp;b" />
+                    <copyfile src="Makefile" dest="Makefile" />
+                    <linkfile src="tools" dest="tools" />
+                </project>
+                <project name="tools" revision="v1.0" />
+            </manifest>"#,
+        )
+        .unwrap();
+        manifest.pin_projects(&HashMap::from([(
+            "src/app".to_string(),
+            "0123456789abcdef0123456789abcdef01234567".to_string(),
+        )]));
+
+        let frozen = manifest_xml(&manifest).unwrap();
+        assert_eq!(
+            frozen,
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest>
+  <notice>
+    Pinned for a bug report
+  </notice>
+  <remote name="origin" fetch="https://example.com" review="https://review.example.com"/>
+  <default remote="origin" revision="main" sync-j="4" sync-c="true"/>
+  <project name="app" path="src/app" revision="0123456789abcdef0123456789abcdef01234567" dest-branch="main" groups="pdk" sync-tags="false" upstream="main" force-path="true">
+    <annotation name="owner" value="a&amp;b"/>
+    <copyfile src="Makefile" dest="Makefile"/>
+    <linkfile src="tools" dest="tools"/>
+  </project>
+  <project name="tools" revision="v1.0"/>
+  <superproject name="platform/superproject"/>
+  <contactinfo bugurl="https://bugs.example.com"/>
+</manifest>
+"#
+        );
+
+        let thawed: Manifest = quick_xml::de::from_str(&frozen).unwrap();
+        assert_eq!(manifest_xml(&thawed).unwrap(), frozen);
+    }
+}
@@ -0,0 +1,109 @@
+use super::{select_projects, CheckoutState, DEFAULT_GROUPS};
+use crate::{
+    backend::{GitBackend, GixBackend},
+    display::{short_sha, Fields, RemoteBranch},
+    git::GitError,
+    workspace::{Workspace, WorkspaceError},
+};
+use clap::Args;
+use miette::{Diagnostic, Result};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Get info on the manifest branch, current branch or unmerged branches
+///
+/// # Description
+///
+/// Show the manifest branch and groups the workspace was initialized with, and
+/// for each project its mount path, manifest revision and checked out revision.
+/// Projects that are not checked out say why: excluded by the workspace's groups,
+/// or not synced yet.
+#[derive(Args, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct InfoArgs {
+    projects: Option<Vec<String>>,
+}
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum InfoError {
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    WorkspaceError(#[from] WorkspaceError),
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    GitError(#[from] GitError),
+}
+
+pub fn run_info(args: InfoArgs) -> Result<(), InfoError> {
+    let workspace = Workspace::discover()?;
+    let config = workspace.config()?;
+
+    info(&GixBackend::new(&config), &workspace, args)
+}
+
+fn info(backend: &dyn GitBackend, workspace: &Workspace, args: InfoArgs) -> Result<(), InfoError> {
+    let config = workspace.config()?;
+    let manifest = workspace.manifest()?;
+    let groups = config.groups.as_deref();
+
+    let mut header = Fields::new();
+    header.push("Manifest branch", &config.manifest.branch);
+    header.push("Manifest groups", groups.unwrap_or(DEFAULT_GROUPS));
+    if let Some(contact_info) = manifest.contact_info() {
+        header.push("Manifest bug URL", contact_info.bug_url());
+    }
+    print!("{header}");
+    println!("----------------------------");
+
+    for project in select_projects(&manifest, args.projects.as_deref()) {
+        let path = project.checkout_path();
+        let dst = workspace.project_dir(path);
+
+        let mut fields = Fields::new();
+        fields.push("Project", &project.name);
+        fields.push("Mount path", dst.display());
+        let revision = manifest.project_revision(&project).unwrap_or_default();
+        let remote = manifest.project_remote(&project);
+        match remote {
+            Some(remote) => fields.push(
+                "Manifest revision",
+                RemoteBranch {
+                    branch: &revision,
+                    remote: remote.git_name(),
+                },
+            ),
+            None => fields.push("Manifest revision", short_sha(&revision)),
+        }
+
+        match CheckoutState::of(workspace, groups, &project) {
+            CheckoutState::CheckedOut => {
+                if let Some(head) = backend.head_commit(&dst) {
+                    fields.push("Current revision", short_sha(&head.to_string()));
+                }
+                if let Some(branch) = backend.current_branch(&dst)? {
+                    fields.push("Current branch", branch);
+                }
+                let remote_name = remote.map(|remote| remote.git_name()).unwrap_or_default();
+                if backend
+                    .inspect(&dst, remote_name)
+                    .is_some_and(|checkout| checkout.shallow)
+                {
+                    fields.push(
+                        "Shallow",
+                        format!(
+                            "run `repox fetch --unshallow {}` for the full history",
+                            project.name
+                        ),
+                    );
+                }
+            }
+            state => fields.push("Current revision", state),
+        }
+
+        print!("{fields}");
+        println!("----------------------------");
+    }
+
+    Ok(())
+}
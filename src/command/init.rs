@@ -1,9 +1,25 @@
+use super::{
+    seed::{seed_branch, SeedError, Seeds},
+    select_grouped_projects,
+    self_update::explain_repo_source,
+    sync::{sync_submanifests, SyncArgs, SyncError},
+    warn_mapped_schemes,
+};
+use crate::{
+    backend::{GitBackend, GixBackend},
+    clone_bundle::{CloneBundleError, CloneBundles},
+    config::{ManifestConfig, WorkspaceConfig},
+    copyfile::{install_files, CopyfileError},
+    git::{DirtyPolicy, GitError, Refspecs},
+    git_hooks::{install_git_hooks, GitHooksError},
+    workspace::{Workspace, WorkspaceError},
+};
 use clap::Args;
 use miette::{Diagnostic, Result};
-use quick_xml::{de::from_str, DeError};
 use rayon::prelude::*;
-use repox_manifest::Manifest;
-use std::fs::read_to_string;
+use repox_manifest::error::ManifestError;
+use serde::{Deserialize, Serialize};
+use std::fs::canonicalize;
 use thiserror::Error;
 use tracing::{info, info_span};
 
@@ -41,30 +57,45 @@ use tracing::{info, info_span};
 /// borrowing from them after a first clone is made by making necessary local copies
 /// of borrowed objects.
 ///
+/// repox does not support reference mirrors or partial clones yet: --reference,
+/// --dissociate, --partial-clone and --clone-filter are accepted for compatibility
+/// with repo, and ignored.
+///
+/// The manifest URL, repo URL and repo revision default to the $REPO_MANIFEST_URL,
+/// $REPO_URL and $REPO_REV environment variables, or their $REPOX_MANIFEST_URL,
+/// $REPOX_URL and $REPOX_REV equivalents.
+///
 /// The --no-clone-bundle option disables any attempt to use $URL/clone.bundle to
 /// bootstrap a new Git repository from a resumeable bundle file on a content
 /// delivery network. This may be necessary if there are problems with the local
 /// Python HTTP client or proxy configuration, but the Git binary works.
 ///
+/// The --clone-bundle-url option fetches the bundles from elsewhere, such as a CDN
+/// apart from the Gerrit server, given as a URL template in which {project},
+/// {path}, {remote} and {url} stand for the project's name, checkout path, remote
+/// name and fetch URL. It is kept as the clone-bundle-url setting for sync.
+///
+/// The --hooks-template option installs the git hooks of a directory, such as an
+/// organization's pre-commit policy, into every project's .git/hooks. It is kept
+/// as the hooks-template setting, so sync installs them into new projects too.
+///
 /// # Switching Manifest Branches
 ///
 /// To switch to another manifest branch, `repo init -b otherbranch` may be used in
 /// an existing client. However, as this only updates the manifest, a subsequent
 /// `repo sync` (or `repo sync -d`) is necessary to update the working directory
 /// files.
-#[derive(Args, Debug)]
+#[derive(Args, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub struct InitArgs {
-    //Logging options
-    /// show all output
-    #[arg(short = 'v', long, default_value_t = false)]
-    verbose: bool,
-    /// show all output
+    //Logging options, -v/--verbose is global
+    /// only show errors
     #[arg(short = 'q', long, default_value_t = false)]
     quiet: bool,
 
     // Manifest options
     /// manifest repository location
-    #[arg(short = 'u', long)]
+    #[arg(short = 'u', long, env = "REPO_MANIFEST_URL")]
     manifest_url: String,
     /// manifest branch or revision (use HEAD for default)
     #[arg(short = 'b', long, default_value = "HEAD")]
@@ -126,6 +157,9 @@ pub struct InitArgs {
     /// create a shallow clone with given depth; see git clone
     #[arg(long)]
     depth: Option<usize>,
+    /// create projects from the bundles or bare repositories in a directory or tarball; see repox seed
+    #[arg(long)]
+    seed: Option<String>,
     /// perform partial clone (https://git-scm.com/docs/gitrepository-layout#_code_partialclone_code)
     #[arg(long)]
     partial_clone: Option<bool>,
@@ -150,19 +184,27 @@ pub struct InitArgs {
     /// disable use of /clone.bundle on HTTP/HTTPS (default if --partial-clone)
     #[arg(long)]
     no_clone_bundle: Option<bool>,
+    /// download the clone bundles of projects from TEMPLATE, e.g.
+    /// https://cdn.example.com/bundles/{project}.bundle; later syncs use it too
+    #[arg(long, value_name = "TEMPLATE")]
+    clone_bundle_url: Option<String>,
     /// enable Git LFS support
     #[arg(long)]
     git_lfs: Option<bool>,
     /// disable Git LFS support
     #[arg(long)]
     no_git_lfs: Option<bool>,
+    /// install the git hooks in DIR, relative to the workspace,
+    /// into every project; later syncs do too
+    #[arg(long, value_name = "DIR")]
+    hooks_template: Option<String>,
 
     // repo Version options
     /// repo repository location ($REPO_URL)
-    #[arg(long)]
+    #[arg(long, env = "REPO_URL")]
     repo_url: Option<String>,
     /// repo branch or revision ($REPO_REV)
-    #[arg(long)]
+    #[arg(long, env = "REPO_REV")]
     repo_rev: Option<String>,
     /// do not verify repo source code
     #[arg(long)]
@@ -191,103 +233,241 @@ pub struct InitArgs {
     all_manifests: Option<bool>,
 }
 
+/// repox-specific environment variables and the git-repo variables they stand in for.
+///
+/// The repox names take precedence when both are set.
+const ENV_ALIASES: [(&str, &str); 3] = [
+    ("REPOX_URL", "REPO_URL"),
+    ("REPOX_REV", "REPO_REV"),
+    ("REPOX_MANIFEST_URL", "REPO_MANIFEST_URL"),
+];
+
+/// Copies the `REPOX_*` environment variables onto their `REPO_*` counterparts,
+/// so clap picks either up as defaults for the init flags.
+///
+/// Must be called before arguments are parsed and before any threads are spawned.
+pub fn apply_env_aliases() {
+    for (repox_name, repo_name) in ENV_ALIASES {
+        if let Some(value) = std::env::var_os(repox_name) {
+            std::env::set_var(repo_name, value);
+        }
+    }
+}
+
 #[derive(Debug, Error, Diagnostic)]
-#[diagnostic(code(repox::command::init))]
 pub enum InitError {
     #[error("Could not read manifest file")]
+    #[diagnostic(code(repox::command::init))]
     ManifestReadError(#[source] std::io::Error),
 
     #[error("An error occurred initializing gix's interrupt handler")]
+    #[diagnostic(code(repox::command::init))]
     GixInterruptInitError(#[source] std::io::Error),
 
-    #[error("An error occurred while creating a destination directory")]
-    CreateDirectoryError(#[source] std::io::Error),
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    WorkspaceError(#[from] WorkspaceError),
 
     #[error(transparent)]
-    XmlDeserializationError(#[from] DeError),
+    #[diagnostic(code(repox::command::init))]
+    GitError(#[from] GitError),
+
+    #[error("The projects cannot be seeded")]
+    #[diagnostic(code(repox::command::init))]
+    SeedError(
+        #[from]
+        #[diagnostic_source]
+        SeedError,
+    ),
 
     #[error(transparent)]
-    GixUrlParseError(#[from] gix::url::parse::Error),
+    #[diagnostic(transparent)]
+    ManifestError(#[from] ManifestError),
 
     #[error(transparent)]
-    GixCloneError(#[from] gix::clone::Error),
+    #[diagnostic(transparent)]
+    CopyfileError(#[from] CopyfileError),
 
     #[error(transparent)]
-    GixFetchError(#[from] gix::clone::fetch::Error),
+    #[diagnostic(transparent)]
+    GitHooksError(#[from] GitHooksError),
 
     #[error(transparent)]
-    GixCheckoutError(#[from] gix::clone::checkout::main_worktree::Error),
+    #[diagnostic(transparent)]
+    CloneBundleError(#[from] CloneBundleError),
 
     #[error(transparent)]
-    GixRemoteError(#[from] gix::remote::find::existing::Error),
+    #[diagnostic(transparent)]
+    SyncError(Box<SyncError>),
+}
+
+// Boxed at the advice of clippy, syncs fail in more detail than init does.
+impl From<SyncError> for InitError {
+    fn from(error: SyncError) -> Self {
+        InitError::SyncError(Box::new(error))
+    }
+}
+
+impl InitArgs {
+    /// The workspace settings these arguments choose.
+    pub fn config(&self) -> Result<WorkspaceConfig, InitError> {
+        let manifest_path =
+            canonicalize(&self.manifest_path).map_err(InitError::ManifestReadError)?;
+
+        Ok(WorkspaceConfig {
+            manifest: ManifestConfig {
+                url: self.manifest_url.clone(),
+                branch: self.manifest_branch.clone(),
+                path: manifest_path.to_string_lossy().into_owned(),
+            },
+            mirror: self.mirror.unwrap_or(false),
+            worktree: self.worktree.unwrap_or(false),
+            use_superproject: self.use_superproject.unwrap_or(false)
+                && !self.no_use_superproject.unwrap_or(false),
+            groups: self.groups.as_ref().map(|groups| groups.join(",")),
+            clone_bundle_url: self.clone_bundle_url.clone(),
+            hooks_template: self.hooks_template.clone(),
+            depth: self.depth,
+            repo_url: self.repo_url.clone(),
+            repo_rev: self.repo_rev.clone(),
+            ..Default::default()
+        })
+    }
 }
 
 pub fn run_init(args: InitArgs) -> Result<(), InitError> {
-    let manifest_contents =
-        read_to_string(args.manifest_path).map_err(InitError::ManifestReadError)?;
+    // A workspace being re-initialized keeps its local manifests, they apply to the checkout too.
+    let workspace = Workspace::create(".")?;
+    let config = args.config()?;
+
+    init(&GixBackend::new(&config), &workspace, &config, &args)
+}
+
+/// Records `config` in `workspace` and checks its projects out through `backend`.
+pub fn init(
+    backend: &dyn GitBackend,
+    workspace: &Workspace,
+    config: &WorkspaceConfig,
+    args: &InitArgs,
+) -> Result<(), InitError> {
+    let manifest = workspace.read_manifest(&config.manifest.path)?;
+    let mirror = config.mirror;
+    let groups = config.groups.clone();
 
-    let manifest: Manifest = from_str(&manifest_contents)?;
+    workspace.save_config(config)?;
+    explain_repo_source(config);
+    warn_mapped_schemes(config, &manifest);
+    let seeds = args
+        .seed
+        .as_deref()
+        .map(|source| Seeds::open(source, &workspace.repo_dir()))
+        .transpose()?;
+    let clone_bundles = config
+        .clone_bundle_url
+        .as_deref()
+        .map(|template| CloneBundles::new(backend, workspace, template, &config.transport))
+        .transpose()
+        .map_err(CloneBundleError::from)?;
 
-    manifest
-        .projects()
-        .into_par_iter()
+    let projects = select_grouped_projects(&manifest, None, groups.as_deref());
+    projects
+        .par_iter()
         .map(|project| {
             let _project_span = info_span!("Checking out project", name = project.name).entered();
 
-            let remote = manifest
-                .remotes()
-                .into_iter()
-                .find(|remote| remote.name == project.remote.clone().unwrap())
-                .unwrap();
+            // Projects without a remote attribute of their own use the default element's.
+            let remote = manifest.resolve_project(project)?.remote;
+            info!("Project remote: {} ({})", remote.name, remote.fetch);
 
-            info!("Project remote {:#?}", remote);
-
-            let repo_url = format!("{}/{}", remote.fetch, project.name);
+            let repo_url = config.project_urls(remote, project).fetch;
             info!("Repo URL: {repo_url}");
-            let dst = project.path.unwrap();
+            let dst = if mirror {
+                project.mirror_path()
+            } else {
+                project.checkout_path().to_string()
+            };
             info!("Destination: {dst}");
 
-            std::fs::create_dir_all(&dst).map_err(InitError::CreateDirectoryError)?;
-            info!("Destination Created: {dst}");
-            let url = gix::url::parse(repo_url.as_str().into())?;
-            info!("Git URL: {:#?}", url);
-
-            info!("Url: {:?}", url.to_bstring());
-            let mut prepare_clone = gix::prepare_clone(url, &dst)?;
-
-            let clone_span = info_span!("Cloning {repo_url:?} into {dst:?}...").entered();
-            let (mut prepare_checkout, _) = prepare_clone
-                .fetch_then_checkout(gix::progress::Discard, &gix::interrupt::IS_INTERRUPTED)?;
-            clone_span.exit();
-
-            let checkout_span = info_span!(
-                "Checking out project",
-                dest = ?prepare_checkout.repo().work_dir().expect("should be there")
-            )
-            .entered();
-
-            let (repo, _) = prepare_checkout
-                .main_worktree(gix::progress::Discard, &gix::interrupt::IS_INTERRUPTED)?;
-
-            checkout_span.exit();
-
-            let remote = repo
-                .find_default_remote(gix::remote::Direction::Fetch)
-                .expect("always present after clone")?;
-
-            info!(
-                "Default remote: {} -> {}",
-                remote
-                    .name()
-                    .expect("default remote is always named")
-                    .as_bstr(),
-                remote
-                    .url(gix::remote::Direction::Fetch)
-                    .expect("should be the remote URL")
-                    .to_bstring(),
-            );
+            if let Some(seed) = seeds.as_ref().and_then(|seeds| seeds.find(&project.name)) {
+                info!("Seeding {dst} from {}", seed.display());
+                let project_dir = workspace.project_dir(&dst);
+                backend.seed_project(
+                    &seed,
+                    &project_dir,
+                    &dst,
+                    &repo_url,
+                    remote.git_name(),
+                    mirror,
+                    seed_branch(&manifest, project).as_deref(),
+                )?;
+                if !mirror {
+                    backend.update_worktree(
+                        &project_dir,
+                        &dst,
+                        remote.git_name(),
+                        project.revision.as_deref(),
+                        DirtyPolicy::from_flags(false, false),
+                    )?;
+                }
+                return Ok(());
+            }
+
+            let project_dir = workspace.project_dir(&dst);
+            if let Some(bundles) = &clone_bundles {
+                if bundles.seed(
+                    project,
+                    &dst,
+                    &project_dir,
+                    &repo_url,
+                    remote.git_name(),
+                    mirror,
+                )? {
+                    backend.fetch_project(&project_dir, remote.git_name(), &Refspecs::default())?;
+                    if !mirror {
+                        backend.update_worktree(
+                            &project_dir,
+                            &dst,
+                            remote.git_name(),
+                            project.revision.as_deref(),
+                            DirtyPolicy::from_flags(false, false),
+                        )?;
+                    }
+                    return Ok(());
+                }
+            }
+
+            backend.clone_project(
+                &repo_url,
+                &project_dir,
+                remote.git_name(),
+                mirror,
+                &Refspecs::default(),
+                project.clone_depth().or(config.depth).filter(|_| !mirror),
+            )?;
 
             Ok(())
         })
-        .collect::<Result<(), InitError>>()
+        .collect::<Result<(), InitError>>()?;
+
+    if !mirror {
+        workspace.record_checkouts(&manifest)?;
+        install_files(workspace, &manifest, &projects)?;
+        if let Some(template) = &config.hooks_template {
+            install_git_hooks(workspace, template, &projects)?;
+        }
+    }
+
+    // Submanifests are synced into their directories like any later sync does, unless asked not to.
+    let this_manifest_only =
+        args.this_manifest_only.unwrap_or(false) && !args.all_manifests.unwrap_or(false);
+    if !this_manifest_only {
+        sync_submanifests(
+            backend,
+            workspace,
+            config,
+            &manifest,
+            &SyncArgs::init_submanifests(),
+        )?;
+    }
+    Ok(())
 }
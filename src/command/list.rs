@@ -0,0 +1,135 @@
+use super::{select_projects, CheckoutState};
+use crate::{
+    backend::{GitBackend, GixBackend},
+    workspace::{Workspace, WorkspaceError},
+};
+use clap::Args;
+use miette::{Diagnostic, Result};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// List projects and their associated directories
+///
+/// # Description
+///
+/// List all projects; pass '.' to list the project for the cwd.
+///
+/// This is similar to running: repo forall -c 'echo "$REPO_PATH : $REPO_PROJECT"'.
+///
+/// The --json option prints every project's name, path, fetch URL, revision,
+/// checked out commit and groups as a JSON array, for tools that would
+/// otherwise scrape `repo manifest -r`.
+///
+/// The --missing option lists only the projects that are not checked out, along
+/// with whether the workspace's groups exclude them or they still need a sync.
+#[derive(Args, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ListArgs {
+    projects: Option<Vec<String>>,
+
+    /// display only the name of the repository
+    #[arg(short = 'n', long, default_value_t = false)]
+    name_only: bool,
+
+    /// display only the path of the repository
+    #[arg(short = 'p', long, default_value_t = false)]
+    path_only: bool,
+
+    /// display the full work tree path instead of the relative path
+    #[arg(short = 'f', long, default_value_t = false)]
+    fullpath: bool,
+
+    /// list only projects that are not checked out
+    #[arg(long, default_value_t = false)]
+    missing: bool,
+
+    /// print the projects as JSON
+    #[arg(long, default_value_t = false, conflicts_with_all = ["name_only", "path_only"])]
+    json: bool,
+}
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum ListError {
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    WorkspaceError(#[from] WorkspaceError),
+
+    #[error(transparent)]
+    #[diagnostic(code(repox::command::list::json))]
+    JsonError(#[from] serde_json::Error),
+}
+
+/// A project as printed by `repox list --json`.
+#[derive(Debug, Serialize)]
+struct ListedProject {
+    name: String,
+    path: String,
+    remote: Option<String>,
+    url: Option<String>,
+    revision: Option<String>,
+    head: Option<String>,
+    groups: Vec<String>,
+    state: CheckoutState,
+}
+
+pub fn run_list(args: ListArgs) -> Result<(), ListError> {
+    let workspace = Workspace::discover()?;
+    let config = workspace.config()?;
+
+    list(&GixBackend::new(&config), &workspace, args)
+}
+
+fn list(backend: &dyn GitBackend, workspace: &Workspace, args: ListArgs) -> Result<(), ListError> {
+    let config = workspace.config()?;
+    let manifest = workspace.cached_manifest()?;
+
+    let projects: Vec<ListedProject> = select_projects(&manifest, args.projects.as_deref())
+        .into_iter()
+        .map(|project| {
+            (
+                CheckoutState::of(workspace, config.groups.as_deref(), &project),
+                project,
+            )
+        })
+        .filter(|(state, _)| !args.missing || *state != CheckoutState::CheckedOut)
+        .map(|(state, project)| {
+            let relative_path = project.checkout_path();
+            let dst = workspace.project_dir(relative_path);
+            let path = if args.fullpath {
+                dst.to_string_lossy().into_owned()
+            } else {
+                relative_path.to_string()
+            };
+            let remote = manifest.project_remote(&project);
+
+            ListedProject {
+                name: project.name.clone(),
+                remote: remote.map(|remote| remote.name.clone()),
+                url: remote.map(|remote| config.project_urls(remote, &project).fetch),
+                revision: manifest.project_revision(&project),
+                head: backend.head_commit(&dst).map(|id| id.to_string()),
+                groups: project.group_list().into_iter().map(String::from).collect(),
+                path,
+                state,
+            }
+        })
+        .collect();
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&projects)?);
+        return Ok(());
+    }
+
+    for project in projects {
+        match (args.name_only, args.path_only) {
+            (true, false) => println!("{}", project.name),
+            (false, true) => println!("{}", project.path),
+            _ if args.missing => {
+                println!("{} : {} ({})", project.path, project.name, project.state)
+            }
+            _ => println!("{} : {}", project.path, project.name),
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,392 @@
+use crate::workspace::{Workspace, WorkspaceError};
+use clap::{ArgGroup, Args, Subcommand};
+use miette::{Diagnostic, Result};
+use quick_xml::{
+    de::from_str,
+    events::{BytesStart, Event},
+    DeError, Writer,
+};
+use repox_manifest::Manifest;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{create_dir_all, read_to_string, write},
+    path::Path,
+};
+use thiserror::Error;
+
+/// An empty local manifest, used when the file does not exist yet.
+const EMPTY_MANIFEST: &str =
+    "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<manifest>\n</manifest>\n";
+
+/// Write local_manifests entries
+///
+/// # Description
+///
+/// Adds elements to a file in .repo/local_manifests/, checking them against the
+/// manifest first, so common customizations do not need hand-written XML.
+///
+/// 'add' declares a project that is not in the manifest, such as a fork, along
+/// with its remote when --fetch is given. 'extend' overrides the remote, revision,
+/// groups or checkout path of a project in the manifest. 'remove' drops a project
+/// from the manifest.
+#[derive(Args, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct LocalManifestArgs {
+    /// file in .repo/local_manifests/ to write to
+    #[arg(short = 'f', long, default_value = "local.xml")]
+    file: String,
+
+    #[command(subcommand)]
+    command: LocalManifestCommand,
+}
+
+#[derive(Subcommand, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum LocalManifestCommand {
+    /// Add a project that is not in the manifest
+    Add {
+        name: String,
+
+        /// checkout path, relative to the top of the repo client
+        #[arg(long)]
+        path: Option<String>,
+
+        /// remote to fetch the project from
+        #[arg(long)]
+        remote: Option<String>,
+
+        /// also declare --remote, fetching from this URL prefix
+        #[arg(long, requires = "remote")]
+        fetch: Option<String>,
+
+        /// revision to check out
+        #[arg(long)]
+        revision: Option<String>,
+
+        /// groups the project belongs to
+        #[arg(long)]
+        groups: Option<String>,
+    },
+
+    /// Override attributes of a project in the manifest
+    #[command(group(
+        ArgGroup::new("overrides")
+            .required(true)
+            .multiple(true)
+            .args(["remote", "revision", "groups", "dest_path"]),
+    ))]
+    Extend {
+        name: String,
+
+        /// only override the project checked out at this path
+        #[arg(long)]
+        path: Option<String>,
+
+        /// remote to fetch the project from instead
+        #[arg(long)]
+        remote: Option<String>,
+
+        /// revision to check out instead
+        #[arg(long)]
+        revision: Option<String>,
+
+        /// additional groups the project belongs to
+        #[arg(long)]
+        groups: Option<String>,
+
+        /// path to check the project out at instead
+        #[arg(long)]
+        dest_path: Option<String>,
+    },
+
+    /// Remove a project from the manifest
+    Remove { name: String },
+}
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum LocalManifestError {
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    WorkspaceError(#[from] WorkspaceError),
+
+    #[error("No project named {name} in the manifest")]
+    #[diagnostic(
+        code(repox::command::local_manifest::unknown_project),
+        help("run `repox list` to see the manifest's projects")
+    )]
+    UnknownProject { name: String },
+
+    #[error("No remote named {name} in the manifest")]
+    #[diagnostic(
+        code(repox::command::local_manifest::unknown_remote),
+        help("pass --fetch to declare the remote along with the project")
+    )]
+    UnknownRemote { name: String },
+
+    #[error("The project is not fetched from any remote")]
+    #[diagnostic(
+        code(repox::command::local_manifest::no_remote),
+        help("the manifest has no default remote, pass --remote")
+    )]
+    NoRemote,
+
+    #[error("A remote named {name} is already declared")]
+    #[diagnostic(
+        code(repox::command::local_manifest::remote_exists),
+        help("drop --fetch to use the existing remote")
+    )]
+    RemoteExists { name: String },
+
+    #[error("{path} is already the checkout of {project}")]
+    #[diagnostic(
+        code(repox::command::local_manifest::path_taken),
+        help("pass --path to check the project out somewhere else")
+    )]
+    PathTaken { path: String, project: String },
+
+    #[error("Could not read {path}")]
+    #[diagnostic(code(repox::command::local_manifest::read))]
+    ReadError {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("Could not write {path}")]
+    #[diagnostic(code(repox::command::local_manifest::write))]
+    WriteError {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("{path} is not a manifest")]
+    #[diagnostic(
+        code(repox::command::local_manifest::invalid),
+        help("fix or remove the file, it was left untouched")
+    )]
+    InvalidManifest {
+        path: String,
+        #[source]
+        source: DeError,
+    },
+}
+
+/// Renders an empty element, escaping attribute values and leaving out unset attributes.
+fn element(name: &str, attributes: &[(&str, Option<&str>)]) -> String {
+    let start = BytesStart::new(name).with_attributes(
+        attributes
+            .iter()
+            .filter_map(|(key, value)| Some((*key, (*value)?))),
+    );
+
+    let mut writer = Writer::new(Vec::new());
+    writer
+        .write_event(Event::Empty(start))
+        .expect("writing to a Vec does not fail");
+    String::from_utf8(writer.into_inner()).expect("attributes are valid UTF-8")
+}
+
+/// Inserts `elements` before the closing tag of a local manifest's contents.
+fn insert_elements(contents: &str, elements: &[String]) -> Option<String> {
+    let end = contents.rfind("</manifest>")?;
+    let (head, tail) = contents.split_at(end);
+
+    let mut updated = head.to_string();
+    if !updated.ends_with('\n') {
+        updated.push('\n');
+    }
+    for element in elements {
+        updated.push_str("  ");
+        updated.push_str(element);
+        updated.push('\n');
+    }
+    updated.push_str(tail);
+    Some(updated)
+}
+
+/// The elements a command adds, after checking them against the manifest and the local manifest.
+fn elements(
+    command: LocalManifestCommand,
+    manifest: &Manifest,
+    local: &Manifest,
+) -> Result<Vec<String>, LocalManifestError> {
+    let projects = manifest.projects();
+    let known_project = |name: &str| {
+        if projects.iter().any(|project| project.name == name) {
+            Ok(())
+        } else {
+            Err(LocalManifestError::UnknownProject {
+                name: name.to_string(),
+            })
+        }
+    };
+    let known_remote = |name: &str| {
+        if manifest.find_remote(name).is_some() || local.find_remote(name).is_some() {
+            Ok(())
+        } else {
+            Err(LocalManifestError::UnknownRemote {
+                name: name.to_string(),
+            })
+        }
+    };
+
+    match command {
+        LocalManifestCommand::Add {
+            name,
+            path,
+            remote,
+            fetch,
+            revision,
+            groups,
+        } => {
+            let checkout_path = path.as_deref().unwrap_or(&name);
+            if let Some(taken) = projects
+                .iter()
+                .chain(local.projects().iter())
+                .find(|project| project.checkout_path() == checkout_path)
+            {
+                return Err(LocalManifestError::PathTaken {
+                    path: checkout_path.to_string(),
+                    project: taken.name.clone(),
+                });
+            }
+
+            let mut elements = Vec::new();
+            match (&remote, &fetch) {
+                (Some(remote), Some(fetch)) => {
+                    if known_remote(remote).is_ok() {
+                        return Err(LocalManifestError::RemoteExists {
+                            name: remote.clone(),
+                        });
+                    }
+                    elements.push(element(
+                        "remote",
+                        &[("name", Some(remote)), ("fetch", Some(fetch))],
+                    ));
+                }
+                (Some(remote), None) => known_remote(remote)?,
+                (None, _) => {
+                    let has_default = manifest
+                        .default()
+                        .and_then(|default| default.remote())
+                        .is_some();
+                    if !has_default {
+                        return Err(LocalManifestError::NoRemote);
+                    }
+                }
+            }
+
+            elements.push(element(
+                "project",
+                &[
+                    ("name", Some(&name)),
+                    ("path", path.as_deref()),
+                    ("remote", remote.as_deref()),
+                    ("revision", revision.as_deref()),
+                    ("groups", groups.as_deref()),
+                ],
+            ));
+            Ok(elements)
+        }
+        LocalManifestCommand::Extend {
+            name,
+            path,
+            remote,
+            revision,
+            groups,
+            dest_path,
+        } => {
+            known_project(&name)?;
+            if let Some(remote) = &remote {
+                known_remote(remote)?;
+            }
+
+            Ok(vec![element(
+                "extend-project",
+                &[
+                    ("name", Some(&name)),
+                    ("path", path.as_deref()),
+                    ("remote", remote.as_deref()),
+                    ("revision", revision.as_deref()),
+                    ("groups", groups.as_deref()),
+                    ("dest-path", dest_path.as_deref()),
+                ],
+            )])
+        }
+        LocalManifestCommand::Remove { name } => {
+            known_project(&name)?;
+
+            Ok(vec![element("remove-project", &[("name", Some(&name))])])
+        }
+    }
+}
+
+fn parse(path: &Path, contents: &str) -> Result<Manifest, LocalManifestError> {
+    from_str(contents).map_err(|source| LocalManifestError::InvalidManifest {
+        path: path.display().to_string(),
+        source,
+    })
+}
+
+pub fn run_local_manifest(args: LocalManifestArgs) -> Result<(), LocalManifestError> {
+    let workspace = Workspace::discover()?;
+    let manifest = workspace.manifest()?;
+
+    let dir = workspace.local_manifests_dir();
+    let path = dir.join(&args.file);
+    let contents = if path.exists() {
+        read_to_string(&path).map_err(|source| LocalManifestError::ReadError {
+            path: path.display().to_string(),
+            source,
+        })?
+    } else {
+        EMPTY_MANIFEST.to_string()
+    };
+    let local = parse(&path, &contents)?;
+
+    let elements = elements(args.command, &manifest, &local)?;
+    let updated = insert_elements(&contents, &elements).ok_or_else(|| {
+        LocalManifestError::InvalidManifest {
+            path: path.display().to_string(),
+            source: DeError::Custom("missing </manifest>".to_string()),
+        }
+    })?;
+    // Never leave a file behind that would break the next sync.
+    parse(&path, &updated)?;
+
+    create_dir_all(&dir)
+        .and_then(|()| write(&path, updated))
+        .map_err(|source| LocalManifestError::WriteError {
+            path: path.display().to_string(),
+            source,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{element, insert_elements, EMPTY_MANIFEST};
+
+    #[test]
+    fn test_insert_elements() {
+        let remove = element("remove-project", &[("name", Some("a&b"))]);
+        let project = element(
+            "project",
+            &[
+                ("name", Some("fork")),
+                ("path", None),
+                ("revision", Some("main")),
+            ],
+        );
+        assert_eq!(remove, r#"<remove-project name="a&amp;b"/>"#);
+        assert_eq!(project, r#"<project name="fork" revision="main"/>"#);
+
+        assert_eq!(
+            insert_elements(EMPTY_MANIFEST, &[remove, project]).unwrap(),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<manifest>\n  \
+             <remove-project name=\"a&amp;b\"/>\n  \
+             <project name=\"fork\" revision=\"main\"/>\n</manifest>\n"
+        );
+        assert_eq!(insert_elements("<manifest/>", &[]), None);
+    }
+}
@@ -0,0 +1,327 @@
+use crate::{
+    backend::GixBackend,
+    command::freeze::{manifest_xml, pinned_manifest, FreezeError},
+    git::{commit_file, GitError},
+    manifest_edit::{move_project, remove_project},
+    manifest_graph::ManifestGraph,
+    workspace::{Workspace, WorkspaceError},
+};
+use clap::{Args, Subcommand};
+use miette::{Diagnostic, Result};
+use quick_xml::{de::from_str, DeError};
+use repox_manifest::{
+    path::{normalize_path, same_path},
+    Manifest,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{read_to_string, write},
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+/// Manifest inspection utility
+///
+/// # Description
+///
+/// With the -o option, exports the current manifest for inspection. The manifest
+/// and (if present) local_manifests/ are combined together to produce a single
+/// manifest file: includes are inlined, remove-project and extend-project
+/// elements applied, and every project carries the remote and revision it
+/// inherits. This file can be stored in a Git repository for use during future
+/// 'repo init' invocations.
+///
+/// With -r, every project's revision is replaced by the commit it has checked
+/// out, and its upstream records the revision the manifest asked for, as 'repox
+/// freeze' writes it. This also applies to --json.
+///
+/// The --json option exports the fully resolved manifest as JSON instead: every
+/// remote, and every project with the remote, revision, dest-branch and upstream
+/// it effectively uses, so tools do not need their own manifest parser.
+///
+/// 'repox manifest graph' prints the include tree instead: which file declares
+/// which projects, and which projects the local manifests remove or extend, as
+/// indented text or, with --dot, as a Graphviz graph.
+///
+/// 'repox manifest validate' lints the manifest before syncing: remotes declared
+/// twice, projects fetching from undeclared remotes, differing default elements,
+/// colliding checkout paths and extend-project elements matching no project are
+/// all reported at once, and the command fails when there is any.
+///
+/// 'repox manifest mv' and 'repox manifest rm' edit the manifest repository for
+/// its maintainers: they change the path of a project, or remove it, in the file
+/// declaring it, leaving the rest of the file as it is. With --stage the change is
+/// added to git's index, with --commit it is committed. Checkouts are not moved,
+/// the next sync checks moved projects out at their new path and reports the old
+/// checkouts as orphaned.
+#[derive(Args, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ManifestArgs {
+    #[command(subcommand)]
+    command: Option<ManifestCommand>,
+
+    /// file to save the manifest to. (Filename prefix for multi-tree.)
+    #[arg(short = 'o', long, default_value = "-")]
+    output_file: String,
+
+    /// save revisions as current HEAD
+    #[arg(short = 'r', long = "revision-as-HEAD", default_value_t = false)]
+    revision_as_head: bool,
+
+    /// output manifest in JSON format
+    #[arg(long, default_value_t = false)]
+    json: bool,
+
+    /// format output for humans to read
+    #[arg(long, default_value_t = false, requires = "json")]
+    pretty: bool,
+}
+
+#[derive(Subcommand, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum ManifestCommand {
+    /// Print which file contributes which projects, following includes and local manifests
+    Graph {
+        /// output the graph in Graphviz's DOT language, e.g. for `dot -Tsvg`
+        #[arg(long, default_value_t = false)]
+        dot: bool,
+    },
+
+    /// Check the manifest for problems that would break a sync, reporting all of them
+    Validate,
+
+    /// Change the checkout path of a project in the manifest repository
+    Mv {
+        /// name of the project to move
+        name: String,
+
+        /// new checkout path, relative to the top of the repo client
+        new_path: String,
+
+        /// only move the project checked out at this path
+        #[arg(long)]
+        path: Option<String>,
+
+        #[command(flatten)]
+        edit: EditArgs,
+    },
+
+    /// Remove a project from the manifest repository
+    Rm {
+        /// name of the project to remove
+        name: String,
+
+        /// only remove the project checked out at this path
+        #[arg(long)]
+        path: Option<String>,
+
+        #[command(flatten)]
+        edit: EditArgs,
+    },
+}
+
+/// What to do with an edit of the manifest repository besides writing it.
+#[derive(Args, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct EditArgs {
+    /// stage the edited manifest file with git add
+    #[arg(long, default_value_t = false)]
+    stage: bool,
+
+    /// commit the edited manifest file
+    #[arg(long, default_value_t = false)]
+    commit: bool,
+
+    /// commit message, instead of one describing the edit
+    #[arg(short = 'm', long, requires = "commit")]
+    message: Option<String>,
+}
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum ManifestError {
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    WorkspaceError(#[from] WorkspaceError),
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    FreezeError(#[from] FreezeError),
+
+    #[error("Could not read the manifest")]
+    #[diagnostic(code(repox::command::manifest::read))]
+    ReadError(#[source] std::io::Error),
+
+    #[error("Could not write the manifest to {path}")]
+    #[diagnostic(code(repox::command::manifest::write))]
+    WriteError {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error(transparent)]
+    #[diagnostic(code(repox::command::manifest::json))]
+    JsonError(#[from] serde_json::Error),
+
+    #[error("Could not write the manifest as XML")]
+    #[diagnostic(code(repox::command::manifest::xml))]
+    XmlError(#[from] DeError),
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    GitError(#[from] GitError),
+
+    #[error("No project named {name} in the manifest repository")]
+    #[diagnostic(
+        code(repox::command::manifest::unknown_project),
+        help("run `repox manifest graph` to see which file declares which project; local manifests are edited with `repox local-manifest`")
+    )]
+    UnknownProject { name: String },
+
+    #[error("{path} is already the path of {project}")]
+    #[diagnostic(code(repox::command::manifest::path_taken))]
+    PathTaken { path: String, project: String },
+
+    #[error("Could not edit {path}: {message}")]
+    #[diagnostic(
+        code(repox::command::manifest::edit),
+        help("the file was left untouched")
+    )]
+    EditError { path: String, message: String },
+
+    #[error("The manifest has {} problem(s)", problems.len())]
+    #[diagnostic(code(repox::command::manifest::invalid))]
+    Invalid {
+        #[related]
+        problems: Vec<repox_manifest::error::ManifestError>,
+    },
+}
+
+/// Applies `edit` to the file of the manifest repository declaring `name`, then stages or commits it.
+fn edit_manifest(
+    workspace: &Workspace,
+    name: &str,
+    args: &EditArgs,
+    description: String,
+    edit: impl FnOnce(&str) -> Result<Option<String>, String>,
+) -> Result<(), ManifestError> {
+    let manifest_path = PathBuf::from(workspace.config()?.manifest.path);
+    let graph = ManifestGraph::read(&manifest_path, &workspace.local_manifests_dir());
+    let file = graph
+        .declaring_file(name)
+        .ok_or_else(|| ManifestError::UnknownProject {
+            name: name.to_string(),
+        })?;
+    let manifest_dir = manifest_path.parent().unwrap_or(Path::new("."));
+    let path = manifest_dir.join(file);
+    let edit_error = |message| ManifestError::EditError {
+        path: path.display().to_string(),
+        message,
+    };
+
+    let contents = read_to_string(&path).map_err(ManifestError::ReadError)?;
+    let edited =
+        edit(&contents)
+            .map_err(edit_error)?
+            .ok_or_else(|| ManifestError::UnknownProject {
+                name: name.to_string(),
+            })?;
+    // Never leave a file behind that would break the next sync.
+    from_str::<Manifest>(&edited).map_err(|error| edit_error(error.to_string()))?;
+    write(&path, edited).map_err(|source| ManifestError::WriteError {
+        path: path.display().to_string(),
+        source,
+    })?;
+    println!("{description} in {file}");
+
+    if args.stage || args.commit {
+        let message = args
+            .commit
+            .then(|| args.message.as_deref().unwrap_or(&description));
+        commit_file(manifest_dir, &path, message)?;
+    }
+
+    Ok(())
+}
+
+pub fn run_manifest(args: ManifestArgs) -> Result<(), ManifestError> {
+    let workspace = Workspace::discover()?;
+
+    match &args.command {
+        Some(ManifestCommand::Validate) => {
+            let problems = workspace.unchecked_manifest()?.validate();
+            if !problems.is_empty() {
+                return Err(ManifestError::Invalid { problems });
+            }
+            println!("The manifest has no problems");
+            return Ok(());
+        }
+        Some(ManifestCommand::Mv {
+            name,
+            new_path,
+            path,
+            edit,
+        }) => {
+            let new_path = normalize_path(new_path);
+            if let Some(taken) = workspace
+                .manifest()?
+                .projects()
+                .into_iter()
+                .find(|project| {
+                    project.name != *name && same_path(project.checkout_path(), &new_path)
+                })
+            {
+                return Err(ManifestError::PathTaken {
+                    path: new_path.into_owned(),
+                    project: taken.name,
+                });
+            }
+
+            let description = format!("Move {name} to {new_path}");
+            return edit_manifest(&workspace, name, edit, description, |contents| {
+                move_project(contents, name, path.as_deref(), &new_path)
+            });
+        }
+        Some(ManifestCommand::Rm { name, path, edit }) => {
+            let description = format!("Remove {name}");
+            return edit_manifest(&workspace, name, edit, description, |contents| {
+                remove_project(contents, name, path.as_deref())
+            });
+        }
+        _ => {}
+    }
+
+    let output = if let Some(ManifestCommand::Graph { dot }) = args.command {
+        let graph = ManifestGraph::read(
+            Path::new(&workspace.config()?.manifest.path),
+            &workspace.local_manifests_dir(),
+        );
+        if dot {
+            graph.to_dot()
+        } else {
+            graph.to_text()
+        }
+    } else {
+        let manifest = if args.revision_as_head {
+            let backend = GixBackend::new(&workspace.config()?);
+            pinned_manifest(&backend, &workspace)?
+        } else {
+            workspace.manifest()?
+        };
+        match (args.json, args.pretty) {
+            (true, true) => manifest.to_json_pretty()?,
+            (true, false) => manifest.to_json()?,
+            (false, _) => manifest_xml(&manifest)?,
+        }
+    };
+
+    if args.output_file == "-" {
+        println!("{output}");
+        return Ok(());
+    }
+
+    write(&args.output_file, output).map_err(|source| ManifestError::WriteError {
+        path: args.output_file,
+        source,
+    })
+}
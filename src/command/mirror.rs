@@ -0,0 +1,140 @@
+use super::select_grouped_projects;
+use crate::{
+    backend::{GitBackend, GixBackend},
+    git::GitError,
+    workspace::{Workspace, WorkspaceError},
+};
+use clap::{Args, Subcommand};
+use miette::{Diagnostic, Result};
+use rayon::prelude::*;
+use repox_manifest::url::project_url;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::info_span;
+
+/// Manage a mirror workspace
+///
+/// # Description
+///
+/// Operations on workspaces created with 'repox init --mirror'.
+///
+/// 'push' replicates every project of the mirror to a secondary host, pushing all
+/// of its branches and tags to the project's name under a URL prefix: --url, or
+/// the mirror-push-url setting. Each project must exist on the host already.
+#[derive(Args, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct MirrorArgs {
+    #[command(subcommand)]
+    command: MirrorCommand,
+}
+
+#[derive(Subcommand, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum MirrorCommand {
+    /// Push every project's branches and tags to another host
+    Push {
+        projects: Option<Vec<String>>,
+
+        /// URL prefix to push the projects to (default: the mirror-push-url setting)
+        #[arg(long)]
+        url: Option<String>,
+
+        /// also delete branches and tags the mirror no longer has
+        #[arg(long, default_value_t = false)]
+        prune: bool,
+
+        /// show what would be pushed without pushing
+        #[arg(short = 'n', long, default_value_t = false)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum MirrorError {
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    WorkspaceError(#[from] WorkspaceError),
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    GitError(#[from] GitError),
+
+    #[error("The workspace is not a mirror")]
+    #[diagnostic(
+        code(repox::command::mirror::not_a_mirror),
+        help("create a mirror with `repox init --mirror true`")
+    )]
+    NotAMirror,
+
+    #[error("No URL to push the mirror to")]
+    #[diagnostic(
+        code(repox::command::mirror::no_push_url),
+        help("pass --url, or run `repox config set mirror-push-url <url>`")
+    )]
+    NoPushUrl,
+
+    #[error("Project {project} references an undefined remote")]
+    #[diagnostic(code(repox::command::mirror::unknown_remote))]
+    UnknownRemote { project: String },
+}
+
+pub fn run_mirror(args: MirrorArgs) -> Result<(), MirrorError> {
+    let workspace = Workspace::discover()?;
+    let config = workspace.config()?;
+
+    mirror(&GixBackend::new(&config), &workspace, args)
+}
+
+fn mirror(
+    backend: &dyn GitBackend,
+    workspace: &Workspace,
+    args: MirrorArgs,
+) -> Result<(), MirrorError> {
+    let MirrorCommand::Push {
+        projects,
+        url,
+        prune,
+        dry_run,
+    } = args.command;
+
+    let config = workspace.config()?;
+    if !config.mirror {
+        return Err(MirrorError::NotAMirror);
+    }
+    let push_url = url
+        .or_else(|| config.mirror_push_url.clone())
+        .ok_or(MirrorError::NoPushUrl)?;
+    let manifest = workspace.manifest()?;
+
+    let mut options = Vec::new();
+    if prune {
+        options.push("--prune");
+    }
+    if dry_run {
+        options.push("--dry-run");
+    }
+
+    select_grouped_projects(&manifest, projects.as_deref(), config.groups.as_deref())
+        .into_par_iter()
+        .try_for_each(|project| {
+            let _project_span = info_span!("Pushing project", name = project.name).entered();
+            let remote =
+                manifest
+                    .project_remote(&project)
+                    .ok_or_else(|| MirrorError::UnknownRemote {
+                        project: project.name.clone(),
+                    })?;
+
+            let path = project.mirror_path();
+            let url = project_url(&push_url, &project.name);
+            println!("Pushing {path} to {url}");
+
+            Ok(backend.push_mirror(
+                &workspace.project_dir(&path),
+                &path,
+                &url,
+                remote.git_name(),
+                &options,
+            )?)
+        })
+}
@@ -1,20 +1,59 @@
+pub mod annotate;
+pub mod branches;
+pub mod changes;
+pub mod checkout;
+pub mod config;
 pub mod diff;
 pub mod download;
+pub mod fetch;
 pub mod for_all;
+pub mod freeze;
+pub mod info;
 pub mod init;
+pub mod list;
+pub mod local_manifest;
+pub mod manifest;
+pub mod mirror;
 pub mod prune;
+pub mod seed;
+pub mod self_update;
 pub mod start;
 pub mod status;
 pub mod sync;
+pub mod thaw;
+pub mod unimplemented;
 pub mod upload;
+pub mod worktree;
 
 use self::{
-    diff::DiffArgs, download::DownloadArgs, for_all::ForAllArgs, init::InitArgs, prune::PruneArgs,
-    start::StartArgs, status::StatusArgs, sync::SyncArgs, upload::UploadArgs,
+    annotate::AnnotateArgs, branches::BranchesArgs, changes::ChangesArgs, checkout::CheckoutArgs,
+    config::ConfigArgs, diff::DiffArgs, download::DownloadArgs, fetch::FetchArgs,
+    for_all::ForAllArgs, freeze::FreezeArgs, info::InfoArgs, init::InitArgs, list::ListArgs,
+    local_manifest::LocalManifestArgs, manifest::ManifestArgs, mirror::MirrorArgs,
+    prune::PruneArgs, seed::SeedArgs, self_update::SelfUpdateArgs, start::StartArgs,
+    status::StatusArgs, sync::SyncArgs, thaw::ThawArgs, upload::UploadArgs, worktree::WorktreeArgs,
+};
+use crate::{
+    config::WorkspaceConfig,
+    warnings::{self, WarningKind},
+    workspace::{Workspace, WorkspaceError},
 };
 use clap::Subcommand;
+use repox_manifest::{
+    groups::{GroupFilter, GroupSet},
+    path::same_path,
+    project::Project,
+    url::map_scheme,
+    Manifest,
+};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use tracing::warn;
 
-#[derive(Subcommand, Debug)]
+/// Commands that have not been implemented yet are hidden from `--help`,
+/// unless `--experimental` is passed.
+#[derive(Subcommand, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum Command {
     /// Initialize a repo client checkout in the current directory
     // Arguments boxed at the advice of clippy
@@ -23,19 +62,30 @@ pub enum Command {
     /// Update working tree to the latest revision
     Sync(SyncArgs),
 
+    /// Download objects and refs without touching the working trees
+    Fetch(FetchArgs),
+
+    /// Import projects from bundles or bare repositories before the first sync
+    Seed(SeedArgs),
+
     /// Upload changes for code review
     Upload(UploadArgs),
 
     /// Show changes between commit and working tree
+    #[command(hide = true)]
     Diff(DiffArgs),
 
     /// Download and checkout a change
+    #[command(hide = true)]
     Download(DownloadArgs),
 
     /// Run a shell command in each project
+    #[command(name = "forall", alias = "for-all")]
+    #[serde(rename = "forall")]
     ForAll(ForAllArgs),
 
     /// Prune (delete) already merged topics
+    #[command(hide = true)]
     Prune(PruneArgs),
 
     /// Start a new branch for development
@@ -45,37 +95,185 @@ pub enum Command {
     Status(StatusArgs),
 
     /// Permanently abandon a development branch
+    #[command(hide = true)]
     Abandon,
     /// View current topic branches
-    Branch,
-    /// View current topic branches
-    Branches,
+    #[command(alias = "branch")]
+    Branches(BranchesArgs),
     /// Checkout a branch for development
-    Checkout,
+    Checkout(CheckoutArgs),
+    /// List open changes on the review servers
+    Changes(ChangesArgs),
     /// Cherry-pick a change.
+    #[command(hide = true)]
     CherryPick,
     /// Manifest diff utility
+    #[command(hide = true)]
     DiffManifests,
     /// Delete a GITC Client.
+    #[command(hide = true)]
     GitcDelete,
     /// Initialize a GITC Client.
+    #[command(hide = true)]
     GitcInit,
     /// Print lines matching a pattern
+    #[command(hide = true)]
     Grep,
     /// Get info on the manifest branch, current branch or unmerged branches
-    Info,
+    Info(InfoArgs),
     /// List projects and their associated directories
-    List,
+    List(ListArgs),
+    /// Query project annotations
+    Annotate(AnnotateArgs),
     /// Manifest inspection utility
-    Manifest,
+    Manifest(ManifestArgs),
+    /// Write local_manifests entries
+    LocalManifest(LocalManifestArgs),
+    /// Manage a mirror workspace
+    Mirror(MirrorArgs),
+    /// Manage workspaces sharing this one's objects
+    Worktree(WorktreeArgs),
+    /// Write a manifest pinning every project to its checked out commit
+    Freeze(FreezeArgs),
+    /// Recreate a workspace from a frozen manifest
+    Thaw(ThawArgs),
     /// Display overview of unmerged project branches
+    #[command(hide = true)]
     Overview,
     /// Update repo to the latest version
-    SelfUpdate,
+    #[command(name = "selfupdate", alias = "self-update")]
+    #[serde(rename = "selfupdate")]
+    SelfUpdate(SelfUpdateArgs),
     /// Update working tree to the latest known good revision
+    #[command(hide = true)]
     SmartSync,
     /// Stage file(s) for commit
+    #[command(hide = true)]
     Stage,
     /// Display the version of repox
     Version,
+    /// Read and write workspace configuration
+    Config(ConfigArgs),
+}
+
+/// The projects named on the command line, or every project in the workspace's groups when none are named.
+pub(crate) fn select_grouped_projects(
+    manifest: &Manifest,
+    selected: Option<&[String]>,
+    groups: Option<&str>,
+) -> Vec<Project> {
+    let explicit = selected.is_some_and(|selected| !selected.is_empty());
+    let filter = GroupFilter::parse(groups.unwrap_or(DEFAULT_GROUPS));
+    if !explicit {
+        warn_missing_groups(manifest, &filter);
+    }
+
+    select_projects(manifest, selected)
+        .into_iter()
+        .filter(|project| explicit || filter.matches(&project.group_set()))
+        .collect()
+}
+
+/// Warns about the groups of `filter` that no project is in, which are usually misspelled.
+///
+/// `notdefault` is left out, manifests commonly exclude it without putting any project in it.
+fn warn_missing_groups(manifest: &Manifest, filter: &GroupFilter) {
+    let group_sets: Vec<GroupSet> = manifest.projects().iter().map(Project::group_set).collect();
+    for group in filter
+        .terms()
+        .iter()
+        .map(|term| term.group.as_str())
+        .filter(|group| *group != "notdefault")
+    {
+        if !group_sets.iter().any(|groups| groups.contains(group)) {
+            warnings::warn(
+                WarningKind::MissingGroup,
+                format!("no project of the manifest is in group {group}"),
+            );
+        }
+    }
+}
+
+/// Restricts the manifest's projects to the ones named on the command line, by name or path.
+/// Every project is selected when none are named.
+pub(crate) fn select_projects(manifest: &Manifest, selected: Option<&[String]>) -> Vec<Project> {
+    manifest
+        .projects()
+        .into_iter()
+        .filter(|project| match selected {
+            Some(selected) if !selected.is_empty() => selected
+                .iter()
+                .any(|s| *s == project.name || s == project.checkout_path()),
+            _ => true,
+        })
+        .collect()
+}
+
+/// Warns about remotes fetching over schemes only Google's tooling understands, which are fetched
+/// over plain https instead unless `url-rewrites` says otherwise.
+pub(crate) fn warn_mapped_schemes(config: &WorkspaceConfig, manifest: &Manifest) {
+    for remote in manifest.remotes() {
+        let rewritten = config.rewrite_url(&remote.fetch);
+        if map_scheme(&remote.fetch).as_ref() == Some(&rewritten) {
+            warn!(
+                "remote {} fetches from {}, using {rewritten} with git's credential helpers instead; \
+                 set url-rewrites in .repo/repox.toml to choose another URL",
+                remote.name, remote.fetch
+            );
+        }
+    }
+}
+
+/// The checkouts sync created that are still on disk, but that no project of `manifest` is checked out at anymore.
+pub(crate) fn orphaned_checkouts(
+    workspace: &Workspace,
+    manifest: &Manifest,
+) -> Result<Vec<String>, WorkspaceError> {
+    let projects = manifest.projects();
+    Ok(workspace
+        .project_list()?
+        .into_iter()
+        .filter(|path| {
+            !projects
+                .iter()
+                .any(|project| same_path(project.checkout_path(), path))
+                && workspace.project_dir(path).exists()
+        })
+        .collect())
+}
+
+/// The group filter used when the workspace was initialized without `--groups`.
+pub(crate) const DEFAULT_GROUPS: &str = "default";
+
+/// Whether a project's checkout is present, and if it is not, why.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum CheckoutState {
+    CheckedOut,
+    /// Not checked out because the workspace's groups exclude it.
+    Excluded,
+    /// Selected by the workspace's groups but not checked out, because it has not been synced yet or its sync failed.
+    Missing,
+}
+
+impl CheckoutState {
+    pub(crate) fn of(workspace: &Workspace, groups: Option<&str>, project: &Project) -> Self {
+        if workspace.project_dir(project.checkout_path()).exists() {
+            CheckoutState::CheckedOut
+        } else if project.matches_groups(groups.unwrap_or(DEFAULT_GROUPS)) {
+            CheckoutState::Missing
+        } else {
+            CheckoutState::Excluded
+        }
+    }
+}
+
+impl fmt::Display for CheckoutState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            CheckoutState::CheckedOut => "checked out",
+            CheckoutState::Excluded => "not checked out, excluded by the workspace's groups",
+            CheckoutState::Missing => "not checked out, not synced yet or its last sync failed",
+        })
+    }
 }
@@ -1,6 +1,8 @@
 use clap::Args;
+use serde::{Deserialize, Serialize};
 
-#[derive(Args, Debug)]
+#[derive(Args, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub struct PruneArgs {
     projects: Option<Vec<String>>,
 }
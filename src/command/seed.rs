@@ -0,0 +1,229 @@
+use super::select_grouped_projects;
+use crate::{
+    backend::{GitBackend, GixBackend},
+    git::{GitError, Revision},
+    workspace::{Workspace, WorkspaceError},
+};
+use clap::Args;
+use miette::{Diagnostic, Result};
+use rayon::prelude::*;
+use repox_manifest::{project::Project, Manifest};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{create_dir_all, read_dir, remove_dir_all},
+    path::{Path, PathBuf},
+    process::Command,
+};
+use thiserror::Error;
+use tracing::{info, info_span};
+
+/// Directory in `.repo/` that tarballs of seeds are unpacked into while seeding.
+const SEED_DIR: &str = "seed";
+
+/// Import projects from bundles or bare repositories before the first sync
+///
+/// # Description
+///
+/// Creates the workspace's projects from a directory of git bundles or bare
+/// repositories, or a tarball of one, such as a copy shipped on a drive to a site
+/// with little or no bandwidth. The next 'repox sync' then only fetches what
+/// changed upstream since the seed was made, and checks the projects out.
+///
+/// A project is seeded from '<name>.bundle', '<name>.git' or '<name>' under the
+/// source, <name> being its name in the manifest, so a 'repox init --mirror'
+/// workspace or the output of 'git bundle create <name>.bundle --all' for each
+/// project both work. Projects that are already checked out are left alone.
+#[derive(Args, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SeedArgs {
+    /// directory or tarball of bundles and bare repositories
+    source: String,
+
+    projects: Option<Vec<String>>,
+}
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum SeedError {
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    WorkspaceError(#[from] WorkspaceError),
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    GitError(#[from] GitError),
+
+    #[error("Project {project} references an undefined remote")]
+    #[diagnostic(code(repox::command::seed::unknown_remote))]
+    UnknownRemote { project: String },
+
+    #[error("Could not read the seeds in {path}")]
+    #[diagnostic(code(repox::command::seed::read))]
+    ReadError {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("Could not unpack {path}: {message}")]
+    #[diagnostic(
+        code(repox::command::seed::unpack),
+        help("tarballs are unpacked with tar, which must be installed")
+    )]
+    UnpackError { path: String, message: String },
+}
+
+/// The bundles and bare repositories projects are seeded from, unpacked first when they come as a tarball.
+pub(crate) struct Seeds {
+    root: PathBuf,
+    /// Where a tarball was unpacked, removed once seeding is done.
+    unpacked: Option<PathBuf>,
+}
+
+impl Seeds {
+    /// Opens the seeds at `source`, unpacking a tarball into `repo_dir`.
+    pub(crate) fn open(source: &str, repo_dir: &Path) -> Result<Self, SeedError> {
+        let read_error = |error| SeedError::ReadError {
+            path: source.to_string(),
+            source: error,
+        };
+
+        let path = Path::new(source);
+        if path.is_dir() {
+            return Ok(Self {
+                root: path.to_path_buf(),
+                unpacked: None,
+            });
+        }
+
+        let unpacked = repo_dir.join(SEED_DIR);
+        let _ = remove_dir_all(&unpacked);
+        create_dir_all(&unpacked).map_err(read_error)?;
+        // Dropping the seeds cleans up after a tarball that fails to unpack, too.
+        let mut seeds = Self {
+            root: unpacked.clone(),
+            unpacked: Some(unpacked.clone()),
+        };
+
+        info!("Unpacking {source}");
+        let output = Command::new("tar")
+            .arg("-xf")
+            .arg(path)
+            .arg("-C")
+            .arg(&unpacked)
+            .output()
+            .map_err(read_error)?;
+        if !output.status.success() {
+            return Err(SeedError::UnpackError {
+                path: source.to_string(),
+                message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            });
+        }
+
+        // Tarballs usually wrap their content in a single directory.
+        let entries = read_dir(&unpacked)
+            .map_err(read_error)?
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .collect::<Vec<_>>();
+        if let [only] = entries.as_slice() {
+            if only.is_dir() && !only.join("HEAD").is_file() {
+                seeds.root = only.clone();
+            }
+        }
+
+        Ok(seeds)
+    }
+
+    /// The bundle or repository to seed the project `name` from, if there is one.
+    pub(crate) fn find(&self, name: &str) -> Option<PathBuf> {
+        let bundle = self.root.join(format!("{name}.bundle"));
+        if bundle.is_file() {
+            return Some(bundle);
+        }
+
+        [self.root.join(format!("{name}.git")), self.root.join(name)]
+            .into_iter()
+            .find(|path| path.join("HEAD").is_file())
+    }
+}
+
+impl Drop for Seeds {
+    fn drop(&mut self) {
+        if let Some(unpacked) = &self.unpacked {
+            let _ = remove_dir_all(unpacked);
+        }
+    }
+}
+
+/// The branch a seeded project's `HEAD` is left on, the one a clone would check out for the manifest.
+pub(crate) fn seed_branch(manifest: &Manifest, project: &Project) -> Option<String> {
+    match Revision::classify(&manifest.project_revision(project)?) {
+        Revision::Branch(branch) => Some(branch.to_string()),
+        _ => None,
+    }
+}
+
+pub fn run_seed(args: SeedArgs) -> Result<(), SeedError> {
+    let workspace = Workspace::discover()?;
+    let config = workspace.config()?;
+
+    seed(&GixBackend::new(&config), &workspace, args)
+}
+
+fn seed(backend: &dyn GitBackend, workspace: &Workspace, args: SeedArgs) -> Result<(), SeedError> {
+    let config = workspace.config()?;
+    let manifest = workspace.manifest()?;
+    let seeds = Seeds::open(&args.source, &workspace.repo_dir())?;
+
+    let seeded = select_grouped_projects(
+        &manifest,
+        args.projects.as_deref(),
+        config.groups.as_deref(),
+    )
+    .into_par_iter()
+    .map(|project| {
+        let _project_span = info_span!("Seeding project", name = project.name).entered();
+        let path = if config.mirror {
+            project.mirror_path()
+        } else {
+            project.checkout_path().to_string()
+        };
+        let dst = workspace.project_dir(&path);
+        if dst.exists() {
+            info!("{path} already exists, not seeding it");
+            return Ok(false);
+        }
+        let Some(seed) = seeds.find(&project.name) else {
+            info!("No seed for {}", project.name);
+            return Ok(false);
+        };
+
+        let remote = manifest
+            .project_remote(&project)
+            .ok_or_else(|| SeedError::UnknownRemote {
+                project: project.name.clone(),
+            })?;
+        let url = config.project_urls(remote, &project).fetch;
+
+        println!("Seeding {path} from {}", seed.display());
+        backend.seed_project(
+            &seed,
+            &dst,
+            &path,
+            &url,
+            remote.git_name(),
+            config.mirror,
+            seed_branch(&manifest, &project).as_deref(),
+        )?;
+        Ok(true)
+    })
+    .collect::<Result<Vec<bool>, SeedError>>()?;
+
+    let count = seeded.iter().filter(|&&seeded| seeded).count();
+    println!(
+        "Seeded {count} of {} projects, run `repox sync` to bring them up to date",
+        seeded.len()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,183 @@
+use crate::{
+    config::WorkspaceConfig,
+    workspace::{Workspace, WorkspaceError},
+};
+use clap::Args;
+use miette::{Diagnostic, Result};
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+use thiserror::Error;
+use tracing::{info, warn};
+
+/// What `--repo-url` points at, which decides what repox makes of it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RepoSource {
+    /// Google's git-repo, which provisioning scripts written for repo pass.
+    GitRepo,
+    /// A repository of repox itself, a release channel `repox selfupdate` installs from.
+    Repox,
+    /// Anything else, e.g. a fork of git-repo under another name.
+    Unknown,
+}
+
+impl RepoSource {
+    pub(crate) fn classify(url: &str) -> Self {
+        let url = url.trim_end_matches('/');
+        let url = url.strip_suffix(".git").unwrap_or(url);
+        match url.rsplit(['/', ':']).next().map(str::to_lowercase) {
+            Some(name) if name == "git-repo" => RepoSource::GitRepo,
+            Some(name) if name == "repox" => RepoSource::Repox,
+            _ => RepoSource::Unknown,
+        }
+    }
+}
+
+/// Tells the user what the `--repo-url` and `--repo-rev` given to init mean to repox, which has no
+/// launcher to update, so scripts passing them keep working without surprises.
+pub(crate) fn explain_repo_source(config: &WorkspaceConfig) {
+    let Some(url) = &config.repo_url else {
+        if config.repo_rev.is_some() {
+            warn!("--repo-rev has no effect without --repo-url, it is recorded for `repox selfupdate`");
+        }
+        return;
+    };
+
+    match RepoSource::classify(url) {
+        RepoSource::GitRepo => warn!(
+            "--repo-url points at git-repo, whose source repox does not run; \
+             update repox with `cargo install repox` or your package manager instead"
+        ),
+        RepoSource::Repox => info!(
+            "repox will update itself from {url}{} with `repox selfupdate`",
+            config
+                .repo_rev
+                .as_deref()
+                .map(|rev| format!(" at {rev}"))
+                .unwrap_or_default()
+        ),
+        RepoSource::Unknown => {
+            warn!("--repo-url {url} is neither git-repo nor repox, it is recorded but not used")
+        }
+    }
+}
+
+/// Update repo to the latest version
+///
+/// # Description
+///
+/// Installs repox again from the repository and revision given to 'repo init'
+/// as --repo-url and --repo-rev, when those point at a repox repository, using
+/// cargo install. Workspaces initialized for git-repo's source, or without
+/// --repo-url, are told how to update repox instead.
+#[derive(Args, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SelfUpdateArgs {
+    /// do not verify repo source code
+    #[arg(long)]
+    no_repo_verify: bool,
+}
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum SelfUpdateError {
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    WorkspaceError(#[from] WorkspaceError),
+
+    #[error("The workspace names no repox repository to update from")]
+    #[diagnostic(
+        code(repox::command::self_update::no_channel),
+        help("update repox with `cargo install repox` or your package manager, or set repo-url to a repox repository with `repox config set repo-url <url>`")
+    )]
+    NoChannel,
+
+    #[error("{url} is not a repox repository, repox cannot update itself from it")]
+    #[diagnostic(
+        code(repox::command::self_update::not_repox),
+        help("update repox with `cargo install repox` or your package manager, or set repo-url to a repox repository with `repox config set repo-url <url>`")
+    )]
+    NotRepox { url: String },
+
+    #[error("Could not run cargo to install repox")]
+    #[diagnostic(
+        code(repox::command::self_update::cargo),
+        help("selfupdate builds repox with cargo, install a Rust toolchain first")
+    )]
+    CargoError(#[source] std::io::Error),
+
+    #[error("cargo install failed with {status}")]
+    #[diagnostic(code(repox::command::self_update::install))]
+    InstallFailed { status: std::process::ExitStatus },
+}
+
+/// The `cargo install` run that replaces repox with the build of `rev` from `url`.
+fn install_command(url: &str, rev: Option<&str>) -> Command {
+    let mut command = Command::new("cargo");
+    command.args(["install", "--locked", "--force", "--git", url]);
+    if let Some(rev) = rev {
+        command.args(["--rev", rev]);
+    }
+    command.arg("repox");
+    command
+}
+
+pub fn run_self_update(args: SelfUpdateArgs) -> Result<(), SelfUpdateError> {
+    let workspace = Workspace::discover()?;
+    let config = workspace.config()?;
+    let url = config.repo_url.ok_or(SelfUpdateError::NoChannel)?;
+    if RepoSource::classify(&url) != RepoSource::Repox {
+        return Err(SelfUpdateError::NotRepox { url });
+    }
+    if args.no_repo_verify {
+        info!("cargo builds repox from source, there are no release signatures to skip verifying");
+    }
+
+    info!("Installing repox from {url}");
+    let status = install_command(&url, config.repo_rev.as_deref())
+        .status()
+        .map_err(SelfUpdateError::CargoError)?;
+    if !status.success() {
+        return Err(SelfUpdateError::InstallFailed { status });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{install_command, RepoSource};
+
+    #[test]
+    fn test_repo_source() {
+        assert_eq!(
+            RepoSource::classify("https://gerrit.googlesource.com/git-repo"),
+            RepoSource::GitRepo
+        );
+        assert_eq!(
+            RepoSource::classify("https://github.com/keplersj/repox.git/"),
+            RepoSource::Repox
+        );
+        assert_eq!(
+            RepoSource::classify("git@github.com:keplersj/repox"),
+            RepoSource::Repox
+        );
+        assert_eq!(
+            RepoSource::classify("https://example.com/tools/repo"),
+            RepoSource::Unknown
+        );
+
+        let command = install_command("https://github.com/keplersj/repox", Some("v0.2.0"));
+        assert_eq!(
+            command.get_args().collect::<Vec<_>>(),
+            [
+                "install",
+                "--locked",
+                "--force",
+                "--git",
+                "https://github.com/keplersj/repox",
+                "--rev",
+                "v0.2.0",
+                "repox"
+            ]
+        );
+    }
+}
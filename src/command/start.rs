@@ -1,7 +1,264 @@
+use super::select_projects;
+use crate::{
+    backend::{GitBackend, GixBackend},
+    command_hooks::{run_command_hook, CommandEvent, CommandHookError},
+    config::BranchNamesConfig,
+    git::{GitError, Revision},
+    workspace::{Workspace, WorkspaceError},
+};
 use clap::Args;
+use miette::{Diagnostic, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::{info, info_span};
 
-#[derive(Args, Debug)]
+/// Start a new branch for development
+///
+/// # Description
+///
+/// 'repo start' begins a new branch of development, starting from the revision
+/// the project is checked out at, and tracking the project's destination branch
+/// in the manifest. Uncommitted changes are carried over to the new branch, and
+/// projects that already have the branch switch to it.
+///
+/// The branch-names settings enforce the team's naming policy, so branches the
+/// review server would reject are never created. With branch-names.template set,
+/// e.g. to '{user}/{topic}', '<branchname>' is the topic and expands to the full
+/// name, unless it is already in that form. With branch-names.pattern set, the
+/// full name must match that regular expression.
+#[derive(Args, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub struct StartArgs {
     branch_name: String,
     projects: Option<Vec<String>>,
 }
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum StartError {
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    WorkspaceError(#[from] WorkspaceError),
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    GitError(#[from] GitError),
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    CommandHookError(#[from] CommandHookError),
+
+    #[error("Project {project} references an undefined remote")]
+    #[diagnostic(code(repox::command::start::unknown_remote))]
+    UnknownRemote { project: String },
+
+    #[error("branch-names.pattern is not a valid regular expression")]
+    #[diagnostic(
+        code(repox::command::start::invalid_pattern),
+        help("fix it with `repox config set branch-names.pattern <regex>`")
+    )]
+    InvalidPattern(#[source] regex::Error),
+
+    #[error("Branch {branch} does not match the naming policy {pattern}")]
+    #[diagnostic(
+        code(repox::command::start::branch_name_policy),
+        help("branch names are checked against branch-names.pattern, see `repox config get branch-names.pattern`")
+    )]
+    BranchNamePolicy { branch: String, pattern: String },
+
+    #[error("branch-names.template uses {{user}}, but the user's login is unknown")]
+    #[diagnostic(
+        code(repox::command::start::unknown_user),
+        help("set the USER environment variable, or pass the full branch name")
+    )]
+    UnknownUser,
+
+    #[error("No project to start {branch} in is checked out")]
+    #[diagnostic(
+        code(repox::command::start::no_projects),
+        help("run `repox sync` to check the projects out first")
+    )]
+    NoProjects { branch: String },
+}
+
+/// The full name of the branch `name` asks for under the naming policy, checked against its pattern.
+fn branch_name(policy: &BranchNamesConfig, name: &str) -> Result<String, StartError> {
+    let branch = match &policy.template {
+        Some(template) => {
+            let template = if template.contains("{user}") {
+                let user = std::env::var("USER")
+                    .or_else(|_| std::env::var("USERNAME"))
+                    .map_err(|_| StartError::UnknownUser)?;
+                template.replace("{user}", &user)
+            } else {
+                template.clone()
+            };
+
+            // A name already in the template's form is not wrapped again.
+            match template.split_once("{topic}") {
+                Some((prefix, suffix))
+                    if name.len() > prefix.len() + suffix.len()
+                        && name.starts_with(prefix)
+                        && name.ends_with(suffix) =>
+                {
+                    name.to_string()
+                }
+                _ => template.replace("{topic}", name),
+            }
+        }
+        None => name.to_string(),
+    };
+
+    if let Some(pattern) = &policy.pattern {
+        let regex = Regex::new(&format!("^(?:{pattern})$")).map_err(StartError::InvalidPattern)?;
+        if !regex.is_match(&branch) {
+            return Err(StartError::BranchNamePolicy {
+                branch,
+                pattern: pattern.clone(),
+            });
+        }
+    }
+
+    Ok(branch)
+}
+
+pub fn run_start(args: StartArgs) -> Result<(), StartError> {
+    let workspace = Workspace::discover()?;
+    let config = workspace.config()?;
+
+    start(&GixBackend::new(&config), &workspace, args)
+}
+
+fn start(
+    backend: &dyn GitBackend,
+    workspace: &Workspace,
+    args: StartArgs,
+) -> Result<(), StartError> {
+    let config = workspace.config()?;
+    let manifest = workspace.manifest()?;
+
+    let branch = branch_name(&config.branch_names, &args.branch_name)?;
+    if branch != args.branch_name {
+        println!("Starting {branch}, as branch-names.template names it");
+    }
+
+    let projects: Vec<_> = select_projects(&manifest, args.projects.as_deref())
+        .into_iter()
+        .filter(|project| workspace.project_dir(project.checkout_path()).exists())
+        .collect();
+    if projects.is_empty() {
+        return Err(StartError::NoProjects { branch });
+    }
+    let paths: Vec<String> = projects
+        .iter()
+        .map(|project| project.checkout_path().to_string())
+        .collect();
+    run_command_hook(workspace, &config, CommandEvent::PreStart, &paths, None)?;
+
+    let result = projects.iter().try_for_each(|project| {
+        let _project_span = info_span!("Starting branch", name = project.name).entered();
+        let path = project.checkout_path();
+        let remote = manifest
+            .project_remote(project)
+            .ok_or_else(|| StartError::UnknownRemote {
+                project: project.name.clone(),
+            })?;
+        let dest_branch = manifest.dest_branch(project);
+        let merge = match dest_branch.as_deref().map(Revision::classify) {
+            Some(Revision::Branch(merge)) => Some(merge),
+            _ => None,
+        };
+
+        info!("Starting {branch} in {path}");
+        Ok(backend.start_branch(
+            &workspace.project_dir(path),
+            path,
+            &branch,
+            remote.git_name(),
+            merge,
+        )?)
+    });
+
+    run_command_hook(
+        workspace,
+        &config,
+        CommandEvent::PostStart,
+        &paths,
+        Some(result.is_ok()),
+    )?;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{branch_name, start, StartArgs, StartError};
+    use crate::{
+        backend::fake::{Action, FakeBackend},
+        config::{BranchNamesConfig, ManifestConfig, WorkspaceConfig},
+        workspace::Workspace,
+    };
+    use std::fs::{create_dir_all, write};
+
+    #[test]
+    fn test_branch_name() {
+        let policy = BranchNamesConfig {
+            template: Some("dev/{topic}".to_string()),
+            pattern: Some(r"dev/[a-z0-9-]+".to_string()),
+        };
+
+        assert_eq!(branch_name(&policy, "fix-build").unwrap(), "dev/fix-build");
+        assert_eq!(
+            branch_name(&policy, "dev/fix-build").unwrap(),
+            "dev/fix-build"
+        );
+        assert!(matches!(
+            branch_name(&policy, "Fix_Build"),
+            Err(StartError::BranchNamePolicy { branch, .. }) if branch == "dev/Fix_Build"
+        ));
+        assert_eq!(
+            branch_name(&BranchNamesConfig::default(), "anything").unwrap(),
+            "anything"
+        );
+    }
+
+    #[test]
+    fn test_start_in_checked_out_projects() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest_path = dir.path().join("manifest.xml");
+        write(
+            &manifest_path,
+            r#"<manifest>
+                <remote name="origin" fetch="https://example.com" />
+                <project name="app" remote="origin" revision="main" />
+                <project name="lib" remote="origin" />
+            </manifest>"#,
+        )
+        .unwrap();
+        let workspace = Workspace::create(dir.path()).unwrap();
+        workspace
+            .save_config(&WorkspaceConfig {
+                manifest: ManifestConfig {
+                    path: manifest_path.to_string_lossy().into_owned(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .unwrap();
+        let app = workspace.project_dir("app");
+        create_dir_all(&app).unwrap();
+
+        let backend = FakeBackend::default();
+        let args = StartArgs {
+            branch_name: "topic".to_string(),
+            projects: None,
+        };
+        start(&backend, &workspace, args).unwrap();
+        assert_eq!(
+            backend.take_actions(),
+            vec![Action::Start {
+                dst: app,
+                branch: "topic".to_string(),
+            }]
+        );
+    }
+}
@@ -1,6 +1,240 @@
+use super::{
+    orphaned_checkouts, select_projects,
+    sync::{prune_orphans, run_sync, SyncArgs, SyncError},
+    CheckoutState,
+};
+use crate::{
+    backend::{GitBackend, GixBackend},
+    display::{column_width, MIN_COLUMN_WIDTH},
+    git::{changed_files, GitError},
+    workspace::{Workspace, WorkspaceError},
+};
 use clap::Args;
+use miette::{Diagnostic, Result};
+use rayon::{prelude::*, ThreadPoolBuilder};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
-#[derive(Args, Debug)]
+/// Show the working tree status
+///
+/// # Description
+///
+/// 'repo status' compares the working tree to the staging area (aka index), and
+/// the most recent commit on this branch (HEAD), in each project specified. A
+/// summary is displayed, one line per file where there is a difference between
+/// these three states.
+///
+/// Projects that are not checked out are reported with the reason: either the
+/// workspace's groups exclude them, or they have not been synced yet or their
+/// last sync failed. Projects excluded by groups are only shown with --missing,
+/// which lists nothing but the projects that are not checked out.
+///
+/// With -q, only the paths of the modified projects are printed, one per line,
+/// e.g. for scripts refusing to build from a modified tree. Combined with
+/// --missing, the paths of the projects that are not checked out are printed.
+///
+/// Checkouts of projects the manifest no longer lists are reported as orphaned.
+/// Missing and orphaned projects come with the commands fixing them, and --fix
+/// runs them: it syncs the missing projects, and deletes the orphaned checkouts
+/// that have no uncommitted changes, as 'repo sync --prune' does.
+#[derive(Args, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub struct StatusArgs {
     projects: Option<Vec<String>>,
+
+    /// only list projects that are not checked out, and why
+    #[arg(long, default_value_t = false)]
+    missing: bool,
+
+    /// only print the paths of the listed projects
+    #[arg(short, long, default_value_t = false)]
+    quiet: bool,
+
+    /// number of projects to check simultaneously (default: the jobs setting)
+    #[arg(short = 'j', long)]
+    jobs: Option<usize>,
+
+    /// sync the missing projects and delete the clean orphaned checkouts
+    #[arg(long, default_value_t = false)]
+    fix: bool,
+}
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum StatusError {
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    WorkspaceError(#[from] WorkspaceError),
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    GitError(#[from] GitError),
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    SyncError(Box<SyncError>),
+
+    #[error("Could not start the worker threads")]
+    #[diagnostic(code(repox::command::status::thread_pool))]
+    ThreadPoolError(#[source] rayon::ThreadPoolBuildError),
+}
+
+// Boxed at the advice of clippy, syncs fail in more detail than status does.
+impl From<SyncError> for StatusError {
+    fn from(error: SyncError) -> Self {
+        StatusError::SyncError(Box::new(error))
+    }
+}
+
+/// What `repo status` reports about a single project.
+enum ProjectStatus {
+    Modified {
+        branch: Option<String>,
+        changes: Vec<String>,
+    },
+    NotCheckedOut(CheckoutState),
+    /// Checked out by an earlier sync, but no longer in the manifest.
+    Orphaned,
+}
+
+pub fn run_status(args: StatusArgs) -> Result<(), StatusError> {
+    let workspace = Workspace::discover()?;
+    let config = workspace.config()?;
+
+    status(&GixBackend::new(&config), &workspace, args)
+}
+
+fn status(
+    backend: &dyn GitBackend,
+    workspace: &Workspace,
+    args: StatusArgs,
+) -> Result<(), StatusError> {
+    let config = workspace.config()?;
+    let manifest = workspace.cached_manifest()?;
+
+    let pool = ThreadPoolBuilder::new()
+        .num_threads(args.jobs.or(config.jobs).unwrap_or_default())
+        .build()
+        .map_err(StatusError::ThreadPoolError)?;
+
+    let projects = select_projects(&manifest, args.projects.as_deref());
+    let statuses = pool.install(|| {
+        projects
+            .par_iter()
+            .map(|project| {
+                let path = project.checkout_path();
+                let status = match CheckoutState::of(workspace, config.groups.as_deref(), project) {
+                    CheckoutState::CheckedOut if !args.missing => {
+                        let dst = workspace.project_dir(path);
+                        let changes = changed_files(&dst, path)?;
+                        if changes.is_empty() {
+                            None
+                        } else {
+                            let branch = backend.current_branch(&dst)?;
+                            Some(ProjectStatus::Modified { branch, changes })
+                        }
+                    }
+                    CheckoutState::CheckedOut | CheckoutState::Excluded if !args.missing => None,
+                    CheckoutState::CheckedOut => None,
+                    state => Some(ProjectStatus::NotCheckedOut(state)),
+                };
+
+                Ok(status.map(|status| (path, status)))
+            })
+            .collect::<Result<Vec<_>, StatusError>>()
+    })?;
+
+    // Orphans are not among the selected projects, naming projects leaves them out.
+    let named = args
+        .projects
+        .as_deref()
+        .is_some_and(|selected| !selected.is_empty());
+    let orphans = if named || args.missing {
+        Vec::new()
+    } else {
+        orphaned_checkouts(workspace, &manifest)?
+    };
+    let missing: Vec<&str> = statuses
+        .iter()
+        .flatten()
+        .filter_map(|(path, status)| {
+            matches!(status, ProjectStatus::NotCheckedOut(CheckoutState::Missing)).then_some(*path)
+        })
+        .collect();
+
+    if args.fix {
+        if !missing.is_empty() {
+            run_sync(SyncArgs::missing(
+                missing.iter().map(|path| path.to_string()).collect(),
+            ))?;
+        }
+        if !named {
+            prune_orphans(workspace, &manifest)?;
+        }
+        return Ok(());
+    }
+
+    let statuses = statuses
+        .iter()
+        .flatten()
+        .map(|(path, status)| (*path, status));
+    let orphaned = orphans
+        .iter()
+        .map(|path| (path.as_str(), &ProjectStatus::Orphaned));
+
+    let listed: Vec<(&str, &ProjectStatus)> = statuses.chain(orphaned).collect();
+    // Paths longer than repo's column push the others along, so the statuses still line up.
+    let width = column_width(listed.iter().map(|(path, _)| *path), MIN_COLUMN_WIDTH);
+
+    let mut clean = true;
+    for (path, status) in listed {
+        clean = false;
+        if args.quiet {
+            // Without --missing, quiet output is just the modified projects.
+            let listed = match status {
+                ProjectStatus::Modified { .. } => !args.missing,
+                ProjectStatus::NotCheckedOut(_) => args.missing,
+                ProjectStatus::Orphaned => false,
+            };
+            if listed {
+                println!("{path}");
+            }
+            continue;
+        }
+
+        match status {
+            ProjectStatus::Modified { branch, changes } => {
+                match branch {
+                    Some(branch) => println!("project {path:<width$} branch {branch}"),
+                    None => println!("project {path:<width$} (*** NO BRANCH ***)"),
+                }
+                for change in changes {
+                    println!(" {change}");
+                }
+            }
+            ProjectStatus::NotCheckedOut(state) => println!("project {path:<width$} {state}"),
+            ProjectStatus::Orphaned => {
+                println!("project {path:<width$} orphaned, no longer in the manifest")
+            }
+        }
+    }
+
+    if clean && !args.missing && !args.quiet {
+        println!("nothing to commit (working directory clean)");
+    }
+
+    if !args.quiet && (!missing.is_empty() || !orphans.is_empty()) {
+        println!();
+        if !missing.is_empty() {
+            println!(
+                "hint: run `repox sync {}` to check out the missing projects",
+                missing.join(" ")
+            );
+        }
+        if !orphans.is_empty() {
+            println!("hint: run `repox sync --prune` to delete the orphaned checkouts");
+        }
+        println!("hint: `repox status --fix` runs these for you");
+    }
+
+    Ok(())
 }
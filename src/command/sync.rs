@@ -1,16 +1,1616 @@
+use super::{
+    orphaned_checkouts, select_grouped_projects, select_projects, warn_mapped_schemes,
+    DEFAULT_GROUPS,
+};
+use crate::{
+    backend::{GitBackend, GixBackend},
+    clone_bundle::{CloneBundleError, CloneBundles},
+    command_hooks::{run_command_hook, CommandEvent, CommandHookError},
+    config::WorkspaceConfig,
+    conflict::{Conflict, ConflictReport},
+    copyfile::{install_files, CopyfileError},
+    display::short_sha,
+    git::{changed_files, project_refspecs, DirtyPolicy, GitError, Refspecs},
+    git_hooks::{install_git_hooks, GitHooksError},
+    hooks::{run_hook, HookArgs, HookError, HookType},
+    picker::pick_projects,
+    redirect,
+    submanifest::{innermost, submanifest_workspaces, SubmanifestError},
+    superproject::{pinned_revisions, SuperprojectError},
+    warnings::{self, WarningKind},
+    workspace::{Workspace, WorkspaceError},
+};
 use clap::Args;
 use miette::{Diagnostic, Result};
+use rayon::{prelude::*, ThreadPoolBuilder};
+use repox_manifest::{
+    groups::GroupFilter, path::normalize_path, project::Project, remote::Remote,
+    url::submodule_url, Manifest,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    fmt,
+    fs::{canonicalize, create_dir_all, remove_dir_all, rename, write},
+    io::IsTerminal,
+    path::Path,
+    sync::Mutex,
+};
 use thiserror::Error;
+use tracing::{info, info_span, warn};
 
-#[derive(Args, Debug)]
+/// Annotation listing whitespace-separated refspecs to fetch for a project, in addition to the remote's.
+pub const REFSPECS_ANNOTATION: &str = "repox-fetch-refspecs";
 
+#[derive(Args, Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub struct SyncArgs {
     projects: Option<Vec<String>>,
+
+    /// overwrite an existing git directory if it needs to point to a different
+    /// object directory. WARNING: this may cause loss of data
+    #[arg(long, default_value_t = false)]
+    force_sync: bool,
+
+    /// stash uncommitted changes before moving a project to a new revision and restore them afterwards
+    #[arg(long, default_value_t = false)]
+    auto_stash: bool,
+
+    /// force checkout even if it results in throwing away uncommitted modifications.
+    /// WARNING: this may cause loss of data
+    #[arg(long, default_value_t = false)]
+    force_checkout: bool,
+
+    /// projects to fetch simultaneously (default: the jobs setting, or the manifest's sync-j)
+    #[arg(short = 'j', long)]
+    jobs: Option<usize>,
+
+    /// fetch only current manifest branch from server
+    #[arg(short = 'c', long, default_value_t = false)]
+    current_branch: bool,
+
+    /// only update working tree from already fetched objects, never touch the network
+    #[arg(short = 'l', long, alias = "local-only", default_value_t = false)]
+    offline: bool,
+
+    /// use the existing manifest as-is. The manifest is never updated by sync
+    /// yet, so this is accepted for compatibility only
+    #[arg(long, alias = "nmu", default_value_t = false)]
+    no_manifest_update: bool,
+
+    /// check out REF in PROJECT (a name or path) instead of its manifest revision,
+    /// for this sync only. May be given several times
+    #[arg(long = "revision", value_name = "PROJECT=REF", value_parser = parse_revision_override)]
+    revisions: Vec<(String, String)>,
+
+    /// sync the projects of this manifest, e.g. one written by `repox freeze`,
+    /// instead of the workspace's manifest, for this sync only
+    #[arg(long, value_name = "FILE", conflicts_with = "manifest_name")]
+    manifest_file: Option<String>,
+
+    /// switch the workspace to NAME, another manifest file of the manifest
+    /// repository such as `release.xml`, without running init again
+    #[arg(short = 'm', long, value_name = "NAME")]
+    manifest_name: Option<String>,
+
+    /// sync only the projects whose path is PREFIX or under it, e.g. `kernel/`,
+    /// whatever their groups. May be given several times
+    #[arg(long = "path-prefix", value_name = "PREFIX")]
+    path_prefixes: Vec<String>,
+
+    /// choose the projects to sync from a searchable list showing their paths
+    /// and groups, starting from those the workspace's groups select
+    #[arg(long, default_value_t = false)]
+    interactive: bool,
+
+    /// list the projects whose checked out commit changed, with commit counts,
+    /// in FILE or on stdout when no FILE is given
+    #[arg(long, value_name = "FILE", num_args = 0..=1, default_missing_value = "-")]
+    log_changes: Option<String>,
+
+    /// download the clone bundles of projects cloned for the first time from
+    /// TEMPLATE, e.g. https://cdn.example.com/bundles/{project}.bundle,
+    /// instead of the clone-bundle-url setting
+    #[arg(long, value_name = "TEMPLATE")]
+    clone_bundle_url: Option<String>,
+
+    /// when a project's server redirects to where its repository moved, fetch
+    /// from there, and keep doing so by recording it in .repo/repox.toml
+    #[arg(long, default_value_t = false)]
+    follow_redirects: bool,
+
+    /// print the report of projects left with conflicts as JSON
+    #[arg(long, default_value_t = false)]
+    json: bool,
+
+    /// pin the projects to the commits the manifest's superproject records;
+    /// implies -c
+    #[arg(long, default_value_t = false, conflicts_with = "no_use_superproject")]
+    use_superproject: bool,
+
+    /// sync to the manifest's revisions, even if the workspace uses the
+    /// superproject
+    #[arg(long, default_value_t = false)]
+    no_use_superproject: bool,
+
+    /// delete the checkouts of projects the manifest no longer lists, unless
+    /// they have uncommitted changes
+    #[arg(long, default_value_t = false)]
+    prune: bool,
+
+    /// operate starting at the outermost manifest, wherever in the
+    /// workspace sync runs (default)
+    #[arg(long, default_value_t = false, conflicts_with = "no_outer_manifest")]
+    outer_manifest: bool,
+
+    /// operate starting at the innermost submanifest checked out around
+    /// the current directory
+    #[arg(long, default_value_t = false)]
+    no_outer_manifest: bool,
+
+    /// only sync the projects of this manifest, not those of its
+    /// submanifests
+    #[arg(long, default_value_t = false, conflicts_with = "all_manifests")]
+    this_manifest_only: bool,
+
+    /// sync the projects of this manifest and of its submanifests (default)
+    #[arg(long, default_value_t = false)]
+    all_manifests: bool,
+
+    #[command(flatten)]
+    hooks: HookArgs,
+}
+
+impl SyncArgs {
+    /// A `repox sync` of `projects`, or of every project for `None`, passing no other option.
+    fn new(projects: Option<Vec<String>>, hooks: HookArgs) -> Self {
+        Self {
+            projects,
+            force_sync: false,
+            auto_stash: false,
+            force_checkout: false,
+            jobs: None,
+            current_branch: false,
+            offline: false,
+            no_manifest_update: false,
+            revisions: Vec::new(),
+            manifest_file: None,
+            manifest_name: None,
+            path_prefixes: Vec::new(),
+            interactive: false,
+            log_changes: None,
+            clone_bundle_url: None,
+            follow_redirects: false,
+            json: false,
+            use_superproject: false,
+            no_use_superproject: false,
+            prune: false,
+            outer_manifest: false,
+            no_outer_manifest: false,
+            this_manifest_only: false,
+            all_manifests: false,
+            hooks,
+        }
+    }
+
+    /// Checks out every project of the workspace's submanifests, as `repox init` does.
+    pub(crate) fn init_submanifests() -> Self {
+        Self::new(None, HookArgs::skipped())
+    }
+
+    /// The options a submanifest is synced with: these, less those naming the outer manifest or its projects.
+    fn for_submanifest(&self) -> Self {
+        Self {
+            revisions: Vec::new(),
+            manifest_file: None,
+            manifest_name: None,
+            interactive: false,
+            // Each manifest would overwrite the file with its own changes.
+            log_changes: self
+                .log_changes
+                .clone()
+                .filter(|destination| destination == "-"),
+            ..self.clone()
+        }
+    }
+
+    /// Syncs every project of `manifest_file`, as `repox thaw` does.
+    pub(crate) fn thaw(
+        manifest_file: String,
+        jobs: Option<usize>,
+        force_checkout: bool,
+        hooks: HookArgs,
+    ) -> Self {
+        Self {
+            force_checkout,
+            jobs,
+            manifest_file: Some(manifest_file),
+            ..Self::new(None, hooks)
+        }
+    }
+
+    /// Checks out the missing `projects`, as `repox status --fix` does.
+    pub(crate) fn missing(projects: Vec<String>) -> Self {
+        Self::new(Some(projects), HookArgs::default())
+    }
+}
+
+fn parse_revision_override(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((project, revision)) if !project.is_empty() && !revision.is_empty() => {
+            Ok((project.to_string(), revision.to_string()))
+        }
+        _ => Err(format!("expected PROJECT=REF, got {value}")),
+    }
+}
+
+/// A project checkout on disk that no longer matches what the manifest describes.
+#[derive(Debug, Error, Diagnostic)]
+pub enum ProjectMismatch {
+    #[error("The remote URL of {path} changed from {recorded} to {expected}")]
+    #[diagnostic(
+        code(repox::command::sync::remote_url_changed),
+        help("run `repox sync --force-sync {path}` to replace the checkout with a fresh clone of the new URL")
+    )]
+    RemoteUrlChanged {
+        path: String,
+        recorded: String,
+        expected: String,
+    },
+
+    #[error("{path} exists but is not a git repository")]
+    #[diagnostic(
+        code(repox::command::sync::not_a_repository),
+        help("move {path} out of the way, or run `repox sync --force-sync {path}` to replace it")
+    )]
+    NotARepository { path: String },
+
+    #[error("{path} was checked out as a {found} but the workspace expects a {expected}")]
+    #[diagnostic(
+        code(repox::command::sync::layout_changed),
+        help("re-run `repox init` with the options the workspace was created with, or run `repox sync --force-sync {path}` to re-create the checkout")
+    )]
+    LayoutChanged {
+        path: String,
+        found: &'static str,
+        expected: &'static str,
+    },
 }
 
 #[derive(Debug, Error, Diagnostic)]
-pub enum SyncError {}
+pub enum SyncError {
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    WorkspaceError(#[from] WorkspaceError),
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    GitError(#[from] GitError),
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    HookError(#[from] HookError),
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    CommandHookError(#[from] CommandHookError),
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    SuperprojectError(#[from] SuperprojectError),
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    SubmanifestError(#[from] SubmanifestError),
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    CopyfileError(#[from] CopyfileError),
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    GitHooksError(#[from] GitHooksError),
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    CloneBundleError(#[from] CloneBundleError),
+
+    #[error("Project {project} references an undefined remote")]
+    #[diagnostic(code(repox::command::sync::unknown_remote))]
+    UnknownRemote { project: String },
+
+    #[error("--revision names {project}, which is not a project in the manifest")]
+    #[diagnostic(
+        code(repox::command::sync::unknown_project),
+        help("run `repox list` to see the manifest's projects")
+    )]
+    UnknownProject { project: String },
+
+    #[error("sync-after names {project}, which is not a project in the manifest")]
+    #[diagnostic(
+        code(repox::command::sync::unknown_ordered_project),
+        help("run `repox list` to see the manifest's projects, and fix sync-after in .repo/repox.toml")
+    )]
+    UnknownOrderedProject { project: String },
+
+    #[error("sync-after orders {chain} in a cycle")]
+    #[diagnostic(
+        code(repox::command::sync::order_cycle),
+        help("remove one of the sync-after entries along the cycle from .repo/repox.toml")
+    )]
+    OrderCycle { chain: String },
+
+    #[error("No project is under {prefixes}")]
+    #[diagnostic(
+        code(repox::command::sync::no_project_under_prefix),
+        help("run `repox list --path-only` to see the projects' paths")
+    )]
+    NoProjectUnderPrefix { prefixes: String },
+
+    #[error("--interactive needs a terminal to choose the projects on")]
+    #[diagnostic(
+        code(repox::command::sync::not_a_terminal),
+        help("name the projects, or pass --path-prefix, on the command line instead")
+    )]
+    NotATerminal,
+
+    #[error("No manifest named {name} in {dir}")]
+    #[diagnostic(
+        code(repox::command::sync::unknown_manifest),
+        help("-m names a file of the manifest repository, relative to its root")
+    )]
+    UnknownManifest { name: String, dir: String },
+
+    #[error("Could not read the projects to sync")]
+    #[diagnostic(code(repox::command::sync::picker))]
+    PickerError(#[source] std::io::Error),
+
+    #[error("Could not remove {path} while force syncing")]
+    #[diagnostic(code(repox::command::sync::force_sync))]
+    ForceSyncRemoveError {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("Could not remove {path}, which the manifest no longer lists")]
+    #[diagnostic(
+        code(repox::command::sync::prune),
+        help("remove {path} by hand, it is not part of the workspace anymore")
+    )]
+    PruneError {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("Could not move {from} to {to}")]
+    #[diagnostic(
+        code(repox::command::sync::relocate),
+        help("move {from} to {to} by hand and re-run `repox sync`")
+    )]
+    RelocateError {
+        from: String,
+        to: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("Could not start the sync worker threads")]
+    #[diagnostic(code(repox::command::sync::thread_pool))]
+    ThreadPoolError(#[source] rayon::ThreadPoolBuildError),
+
+    #[error("{path} is not checked out and cannot be cloned while offline")]
+    #[diagnostic(
+        code(repox::command::sync::offline_not_checked_out),
+        help("run `repox sync {path}` with network access first")
+    )]
+    OfflineNotCheckedOut { path: String },
+
+    #[error("{path} has not fetched revision {revision} and cannot fetch it while offline")]
+    #[diagnostic(
+        code(repox::command::sync::offline_revision_missing),
+        help("run `repox sync {path}` with network access first")
+    )]
+    OfflineRevisionMissing { path: String, revision: String },
+
+    #[error("Could not write the changes to {path}")]
+    #[diagnostic(code(repox::command::sync::log_changes))]
+    LogChangesError {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("{count} project(s) were left with conflicts")]
+    #[diagnostic(
+        code(repox::command::sync::conflicts),
+        help("resolve the conflicts as reported above, then re-run `repox sync`")
+    )]
+    Conflicts { count: usize },
+
+    #[error(transparent)]
+    #[diagnostic(code(repox::command::sync::json))]
+    JsonError(#[from] serde_json::Error),
+
+    #[error("The manifest changed in a way that requires --force-sync or a re-init")]
+    #[diagnostic(code(repox::command::sync::manifest_changed))]
+    ManifestChanged {
+        #[related]
+        mismatches: Vec<ProjectMismatch>,
+    },
+}
+
+/// A project ready to sync: the project, its remote, the URL to fetch, its checkout path and the refspecs to fetch.
+type PlannedProject = (Project, Remote, String, String, Refspecs);
+
+/// A project whose checked out commit moved during a sync, as listed by `--log-changes`.
+#[derive(Debug)]
+struct RevisionChange {
+    path: String,
+
+    /// The commit checked out before, `None` for new checkouts.
+    from: Option<String>,
+    to: String,
+
+    /// Commits gained and lost, when they could be counted.
+    counts: Option<(usize, usize)>,
+}
+
+impl fmt::Display for RevisionChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = &self.path;
+        let to = short_sha(&self.to);
+        match (self.from.as_deref().map(short_sha), self.counts) {
+            (None, _) => write!(f, "{path}: checked out {to}"),
+            (Some(from), Some((added, removed))) => {
+                write!(f, "{path}: {from}..{to} (+{added} -{removed})")
+            }
+            (Some(from), None) => write!(f, "{path}: {from}..{to}"),
+        }
+    }
+}
+
+/// Writes the `--log-changes` digest to `destination`, or stdout for `-`.
+fn log_changes(destination: &str, changes: &[RevisionChange]) -> Result<(), SyncError> {
+    let log: String = changes.iter().map(|change| format!("{change}\n")).collect();
+    if destination == "-" {
+        print!("{log}");
+        return Ok(());
+    }
+
+    write(destination, log).map_err(|source| SyncError::LogChangesError {
+        path: destination.to_string(),
+        source,
+    })
+}
+
+/// The layout a project checkout is expected to have in this workspace.
+fn expected_layout(mirror: bool, worktree: bool) -> &'static str {
+    match (mirror, worktree) {
+        (true, _) => "bare mirror repository",
+        (false, true) => "git-worktree checkout",
+        (false, false) => "regular checkout",
+    }
+}
+
+/// Compares an existing checkout against the manifest without touching it.
+fn check_project(
+    backend: &dyn GitBackend,
+    dst: &Path,
+    path: &str,
+    remote_name: &str,
+    expected_url: &str,
+    mirror: bool,
+    worktree: bool,
+) -> Option<ProjectMismatch> {
+    let Some(checkout) = backend.inspect(dst, remote_name) else {
+        return Some(ProjectMismatch::NotARepository {
+            path: path.to_string(),
+        });
+    };
+
+    let found = expected_layout(checkout.bare, checkout.linked_worktree);
+    let expected = expected_layout(mirror, worktree);
+    if found != expected {
+        return Some(ProjectMismatch::LayoutChanged {
+            path: path.to_string(),
+            found,
+            expected,
+        });
+    }
+
+    match checkout.remote_url {
+        Some(recorded) if recorded != expected_url => Some(ProjectMismatch::RemoteUrlChanged {
+            path: path.to_string(),
+            recorded,
+            expected: expected_url.to_string(),
+        }),
+        _ => None,
+    }
+}
+
+/// Moves a checkout an extend-project dest-path relocated to its new path, keeping its local branches and changes.
+fn relocate_checkout(workspace: &Workspace, from: &str, to: &str) -> Result<(), SyncError> {
+    let src = workspace.project_dir(from);
+    let dst = workspace.project_dir(to);
+    if dst.exists() || !src.is_dir() {
+        return Ok(());
+    }
+
+    info!("Moving {from} to {to}");
+    let relocate_error = |source| SyncError::RelocateError {
+        from: from.to_string(),
+        to: to.to_string(),
+        source,
+    };
+    if let Some(parent) = dst.parent() {
+        create_dir_all(parent).map_err(relocate_error)?;
+    }
+    rename(&src, &dst).map_err(relocate_error)
+}
+
+/// Deletes the orphaned checkouts, those of projects the manifest no longer lists, and drops them from the project list.
+///
+/// Checkouts with uncommitted changes are kept, with a warning, since deleting them would lose work.
+pub(crate) fn prune_orphans(workspace: &Workspace, manifest: &Manifest) -> Result<(), SyncError> {
+    let orphans = orphaned_checkouts(workspace, manifest)?;
+    if orphans.is_empty() {
+        return Ok(());
+    }
+
+    let mut listed: BTreeSet<String> = workspace.project_list()?.into_iter().collect();
+    let result = orphans.into_iter().try_for_each(|path| {
+        let dst = workspace.project_dir(&path);
+        let changes = changed_files(&dst, &path)?;
+        if !changes.is_empty() {
+            warn!(
+                "{path} is no longer in the manifest, but has {} uncommitted change(s), keeping it",
+                changes.len()
+            );
+            return Ok(());
+        }
+
+        info!("Removing {path}, which is no longer in the manifest");
+        remove_dir_all(&dst).map_err(|source| SyncError::PruneError {
+            path: path.clone(),
+            source,
+        })?;
+        listed.remove(&path);
+        Ok(())
+    });
+
+    workspace.save_project_list(&listed)?;
+    result
+}
+
+/// The phase each of `projects` is synced in, as the workspace's `sync-after` setting orders them.
+///
+/// A project is synced in the phase after the last of the projects it must follow, so projects without
+/// constraints are all in the first phase. Projects that are not being synced impose no order.
+fn sync_phases(
+    projects: &[&Project],
+    sync_after: &BTreeMap<String, Vec<String>>,
+) -> Result<Vec<usize>, SyncError> {
+    let is =
+        |project: &Project, name: &str| project.name == name || project.checkout_path() == name;
+    let follows: Vec<Vec<usize>> = projects
+        .iter()
+        .map(|project| {
+            sync_after
+                .iter()
+                .filter(|(name, _)| is(project, name))
+                .flat_map(|(_, after)| after)
+                .flat_map(|name| {
+                    (0..projects.len()).filter(move |&other| is(projects[other], name))
+                })
+                .collect()
+        })
+        .collect();
+
+    let mut phases = vec![None; projects.len()];
+    (0..projects.len())
+        .map(|index| {
+            phase_of(index, &follows, &mut phases, &mut Vec::new()).map_err(|cycle| {
+                let names: Vec<_> = cycle.iter().map(|&i| projects[i].name.as_str()).collect();
+                SyncError::OrderCycle {
+                    chain: names.join(" -> "),
+                }
+            })
+        })
+        .collect()
+}
+
+/// The phase of project `index` among the projects `follows` orders, `chain` holding the projects waiting on it.
+///
+/// Fails with the projects along the cycle when `index` is among them.
+fn phase_of(
+    index: usize,
+    follows: &[Vec<usize>],
+    phases: &mut [Option<usize>],
+    chain: &mut Vec<usize>,
+) -> Result<usize, Vec<usize>> {
+    if let Some(phase) = phases[index] {
+        return Ok(phase);
+    }
+    if let Some(start) = chain.iter().position(|&waiting| waiting == index) {
+        let mut cycle = chain[start..].to_vec();
+        cycle.push(index);
+        return Err(cycle);
+    }
+
+    chain.push(index);
+    let mut phase = 0;
+    for &other in &follows[index] {
+        phase = phase.max(phase_of(other, follows, phases, chain)? + 1);
+    }
+    chain.pop();
+    phases[index] = Some(phase);
+    Ok(phase)
+}
+
+/// Whether the checkout `path` is `prefix` or inside it, comparing whole path components.
+fn is_under_prefix(path: &str, prefix: &str) -> bool {
+    let prefix = prefix.trim_end_matches('/');
+    let path = path.trim_end_matches('/');
+    prefix.is_empty()
+        || path
+            .strip_prefix(prefix)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// The projects under any of `prefixes`, for `--path-prefix`. Groups are ignored, the paths are the filter.
+fn select_under_prefixes(
+    manifest: &Manifest,
+    selected: Option<&[String]>,
+    prefixes: &[String],
+) -> Result<Vec<Project>, SyncError> {
+    let projects: Vec<_> = select_projects(manifest, selected)
+        .into_iter()
+        .filter(|project| {
+            prefixes
+                .iter()
+                .any(|prefix| is_under_prefix(project.checkout_path(), prefix))
+        })
+        .collect();
+    if projects.is_empty() {
+        return Err(SyncError::NoProjectUnderPrefix {
+            prefixes: prefixes.join(", "),
+        });
+    }
+    Ok(projects)
+}
+
+/// Lets the user choose among `candidates` on the terminal, for `--interactive`. `None` if they quit.
+fn pick_interactively(
+    candidates: Vec<Project>,
+    groups: Option<&str>,
+) -> Result<Option<Vec<Project>>, SyncError> {
+    let stdin = std::io::stdin();
+    if !stdin.is_terminal() {
+        return Err(SyncError::NotATerminal);
+    }
+
+    let filter = GroupFilter::parse(groups.unwrap_or(DEFAULT_GROUPS));
+    pick_projects(
+        candidates,
+        |project| filter.matches(&project.group_set()),
+        &mut stdin.lock(),
+        &mut std::io::stdout(),
+    )
+    .map_err(SyncError::PickerError)
+}
+
+fn find_remote(manifest: &Manifest, project: &Project) -> Result<Remote, SyncError> {
+    manifest
+        .project_remote(project)
+        .cloned()
+        .ok_or_else(|| SyncError::UnknownRemote {
+            project: project.name.clone(),
+        })
+}
+
+/// The refspecs a project asks for beyond the remote's, e.g. `refs/meta/config`, from its
+/// annotations and then the workspace configuration.
+pub(crate) fn custom_refspecs(
+    config: &WorkspaceConfig,
+    manifest: &Manifest,
+    project: &Project,
+) -> Vec<String> {
+    let annotated = manifest
+        .project_annotations(project)
+        .into_iter()
+        .filter(|(name, _)| *name == REFSPECS_ANNOTATION)
+        .flat_map(|(_, value)| value.split_whitespace())
+        .map(str::to_string);
+    let configured = config
+        .refspecs
+        .get(&project.name)
+        .into_iter()
+        .flatten()
+        .cloned();
+
+    annotated.chain(configured).collect()
+}
+
+/// The checkout paths of every project the manifest declares, including those nested in another project
+/// element, which take precedence over submodules at the same path.
+fn claimed_paths(manifest: &Manifest) -> HashSet<String> {
+    manifest
+        .projects()
+        .iter()
+        .map(|project| normalize_path(project.checkout_path()).into_owned())
+        .collect()
+}
+
+/// Syncs the git submodules of the checkout at `dst` as implicit sub-projects, recursively.
+///
+/// Submodules are fetched from the parent's remote, with its credentials, and checked out detached at the
+/// commit the parent pins. Those at a path the manifest declares a project for are left to that project.
+fn sync_submodules(
+    backend: &dyn GitBackend,
+    dst: &Path,
+    path: &str,
+    url: &str,
+    remote_name: &str,
+    claimed: &HashSet<String>,
+    args: &SyncArgs,
+) -> Result<Option<Conflict>, SyncError> {
+    let policy = DirtyPolicy::from_flags(args.auto_stash, args.force_checkout);
+
+    for submodule in backend.submodules(dst) {
+        let sub_path = format!("{path}/{}", submodule.path);
+        if claimed.contains(normalize_path(&sub_path).as_ref()) {
+            info!("{sub_path} is declared in the manifest, not syncing it as a submodule");
+            continue;
+        }
+
+        let _submodule_span = info_span!("Syncing submodule", path = sub_path).entered();
+        let sub_dst = dst.join(&submodule.path);
+        let sub_url = submodule_url(url, &submodule.url);
+
+        // A fresh checkout of the parent leaves an empty directory where the submodule goes.
+        if backend.inspect(&sub_dst, remote_name).is_none() {
+            if args.offline {
+                warnings::warn(
+                    WarningKind::SkippedProject,
+                    format!("{sub_path} is not checked out and cannot be cloned while offline"),
+                );
+                continue;
+            }
+            info!("Cloning {sub_url} into {sub_path}");
+            backend.clone_project(
+                &sub_url,
+                &sub_dst,
+                remote_name,
+                false,
+                &Refspecs::default(),
+                None,
+            )?;
+        } else if !args.offline {
+            backend.fetch_project(&sub_dst, remote_name, &Refspecs::default())?;
+        }
+
+        match backend.update_worktree(
+            &sub_dst,
+            &sub_path,
+            remote_name,
+            Some(&submodule.commit),
+            policy,
+        ) {
+            Ok(()) => {}
+            Err(GitError::Conflict(conflict)) => return Ok(Some(conflict)),
+            Err(error) => return Err(error.into()),
+        }
+
+        if let Some(conflict) = sync_submodules(
+            backend,
+            &sub_dst,
+            &sub_path,
+            &sub_url,
+            remote_name,
+            claimed,
+            args,
+        )? {
+            return Ok(Some(conflict));
+        }
+    }
+
+    Ok(None)
+}
 
 pub fn run_sync(args: SyncArgs) -> Result<(), SyncError> {
+    let mut workspace = Workspace::discover()?;
+    workspace.check_writable()?;
+    if args.no_outer_manifest {
+        let current_dir = std::env::current_dir().map_err(WorkspaceError::CurrentDirError)?;
+        workspace = innermost(&workspace, &current_dir)?;
+    }
+    let config = workspace.config()?;
+    if config.transport.has_certificates() {
+        warn!("HTTPS certificate settings apply to git itself, gix's built-in HTTPS client only trusts its bundled roots");
+    }
+
+    sync(&GixBackend::new(&config), &workspace, args)
+}
+
+/// Makes `name`, a file of the manifest repository, the workspace's manifest once it reads fine, and returns it.
+fn switch_manifest(
+    workspace: &Workspace,
+    config: &mut WorkspaceConfig,
+    name: &str,
+) -> Result<Manifest, SyncError> {
+    let manifest_dir = Path::new(&config.manifest.path)
+        .parent()
+        .unwrap_or(Path::new("."));
+    let path = canonicalize(manifest_dir.join(name)).map_err(|_| SyncError::UnknownManifest {
+        name: name.to_string(),
+        dir: manifest_dir.display().to_string(),
+    })?;
+    let manifest = workspace.read_manifest(&path)?;
+
+    if path != Path::new(&config.manifest.path) {
+        info!("Switching the workspace's manifest to {name}");
+        config.manifest.path = path.display().to_string();
+        workspace.save_config(config)?;
+    }
+    Ok(manifest)
+}
+
+/// Syncs the projects of each of `manifest`'s submanifests into its own directory, after one another.
+///
+/// A submanifest's own submanifests are synced along with it, the same way.
+pub(crate) fn sync_submanifests(
+    backend: &dyn GitBackend,
+    workspace: &Workspace,
+    config: &WorkspaceConfig,
+    manifest: &Manifest,
+    args: &SyncArgs,
+) -> Result<(), SyncError> {
+    for inner in submanifest_workspaces(backend, workspace, config, manifest, args.offline)? {
+        let _submanifest_span =
+            info_span!("Syncing submanifest", path = %inner.root().display()).entered();
+        sync(backend, &inner, args.for_submanifest())?;
+    }
+
     Ok(())
 }
+
+/// Syncs `workspace` with its manifest, fetching and checking projects out through `backend`.
+pub fn sync(
+    backend: &dyn GitBackend,
+    workspace: &Workspace,
+    args: SyncArgs,
+) -> Result<(), SyncError> {
+    let policy = DirtyPolicy::from_flags(args.auto_stash, args.force_checkout);
+    let mut config = workspace.config()?;
+    let manifest = match (&args.manifest_file, &args.manifest_name) {
+        (Some(path), _) => workspace.read_manifest(path)?,
+        (None, Some(name)) => switch_manifest(workspace, &mut config, name)?,
+        (None, None) => workspace.manifest()?,
+    };
+    warn_mapped_schemes(&config, &manifest);
+
+    let all_projects = manifest.projects();
+    if let Some((project, _)) = args.revisions.iter().find(|(project, _)| {
+        !all_projects
+            .iter()
+            .any(|p| p.name == *project || p.checkout_path() == project)
+    }) {
+        return Err(SyncError::UnknownProject {
+            project: project.clone(),
+        });
+    }
+
+    if let Some(project) = config
+        .sync_after
+        .iter()
+        .flat_map(|(project, after)| std::iter::once(project).chain(after))
+        .find(|name| {
+            !all_projects
+                .iter()
+                .any(|p| p.name == **name || p.checkout_path() == *name)
+        })
+    {
+        return Err(SyncError::UnknownOrderedProject {
+            project: project.clone(),
+        });
+    }
+
+    let mut projects = if args.interactive {
+        let candidates = if args.path_prefixes.is_empty() {
+            select_projects(&manifest, args.projects.as_deref())
+        } else {
+            select_under_prefixes(&manifest, args.projects.as_deref(), &args.path_prefixes)?
+        };
+        let Some(picked) = pick_interactively(candidates, config.groups.as_deref())? else {
+            println!("Nothing synced");
+            return Ok(());
+        };
+        picked
+    } else if args.path_prefixes.is_empty() {
+        select_grouped_projects(
+            &manifest,
+            args.projects.as_deref(),
+            config.groups.as_deref(),
+        )
+    } else {
+        select_under_prefixes(&manifest, args.projects.as_deref(), &args.path_prefixes)?
+    };
+    let use_superproject =
+        !args.no_use_superproject && (args.use_superproject || config.use_superproject);
+    if use_superproject && args.offline {
+        warn!("The superproject cannot be fetched offline, syncing to the manifest's revisions");
+    } else if use_superproject {
+        match pinned_revisions(workspace, &config, &manifest)? {
+            Some(pins) => {
+                for project in &mut projects {
+                    if let Some(commit) = pins.get(project.checkout_path()) {
+                        manifest.pin_project(project, commit);
+                    }
+                }
+            }
+            None => warn!("The manifest has no superproject, syncing to its revisions"),
+        }
+    }
+
+    // The last override of a project wins, over the superproject's pins too.
+    for (name, revision) in &args.revisions {
+        projects
+            .iter_mut()
+            .filter(|project| project.name == *name || project.checkout_path() == name)
+            .for_each(|project| project.revision = Some(revision.clone()));
+    }
+
+    // The command line wins over the workspace setting, which wins over the manifest's hint.
+    let jobs = args.jobs.or(config.jobs).or_else(|| manifest.sync_j());
+    let pool = ThreadPoolBuilder::new()
+        .num_threads(jobs.unwrap_or_default())
+        .build()
+        .map_err(SyncError::ThreadPoolError)?;
+    let clone_bundles = match args
+        .clone_bundle_url
+        .as_deref()
+        .or(config.clone_bundle_url.as_deref())
+    {
+        Some(template) if !args.offline => Some(
+            CloneBundles::new(backend, workspace, template, &config.transport)
+                .map_err(CloneBundleError::from)?,
+        ),
+        _ => None,
+    };
+
+    let planned = projects
+        .into_iter()
+        .map(|project| {
+            let remote = find_remote(&manifest, &project)?;
+            let url = config.project_urls(&remote, &project).fetch;
+            let path = if config.mirror {
+                project.mirror_path()
+            } else {
+                project.checkout_path().to_string()
+            };
+
+            // Pinned revisions are fetched through their upstream branch instead of every head.
+            let mut refspecs = project_refspecs(
+                remote.git_name(),
+                manifest.project_revision(&project).as_deref(),
+                manifest.project_upstream(&project).as_deref(),
+                args.current_branch || use_superproject || manifest.project_sync_c(&project),
+            );
+            refspecs
+                .extra
+                .extend(custom_refspecs(&config, &manifest, &project));
+
+            Ok((project, remote, url, path, refspecs))
+        })
+        .collect::<Result<Vec<_>, SyncError>>()?;
+
+    let phase_of = sync_phases(
+        &planned
+            .iter()
+            .map(|(project, ..)| project)
+            .collect::<Vec<_>>(),
+        &config.sync_after,
+    )?;
+    if phase_of.iter().any(|phase| *phase > 0) {
+        info!(
+            "Syncing in {} phases, as sync-after orders them",
+            phase_of.iter().max().unwrap_or(&0) + 1
+        );
+    }
+
+    if !config.mirror {
+        for (project, _, _, path, _) in &planned {
+            if let Some(from) = project.relocated_from() {
+                relocate_checkout(workspace, from, path)?;
+            }
+        }
+    }
+
+    // Check every existing checkout up front so a changed manifest is reported before anything is modified.
+    let mut mismatches: Vec<(String, ProjectMismatch)> = planned
+        .par_iter()
+        .filter_map(|(_, remote, url, path, _)| {
+            let dst = workspace.project_dir(path);
+            if !dst.exists() {
+                return None;
+            }
+
+            check_project(
+                backend,
+                &dst,
+                path,
+                remote.git_name(),
+                url,
+                config.mirror,
+                config.worktree,
+            )
+            .map(|mismatch| (path.clone(), mismatch))
+        })
+        .collect();
+
+    if !mismatches.is_empty() {
+        if !args.force_sync {
+            return Err(SyncError::ManifestChanged {
+                mismatches: mismatches.into_iter().map(|(_, m)| m).collect(),
+            });
+        }
+
+        for (path, mismatch) in mismatches.drain(..) {
+            warn!("{mismatch}, removing {path} to re-create it");
+            remove_dir_all(workspace.project_dir(&path))
+                .map_err(|source| SyncError::ForceSyncRemoveError { path, source })?;
+        }
+    }
+
+    if args.offline {
+        if let Some((.., path, _)) = planned
+            .iter()
+            .find(|(.., path, _)| !workspace.project_dir(path).exists())
+        {
+            return Err(SyncError::OfflineNotCheckedOut { path: path.clone() });
+        }
+    }
+
+    let claimed = claimed_paths(&manifest);
+    let synced: Vec<Project> = planned
+        .iter()
+        .map(|(project, ..)| project.clone())
+        .collect();
+    let paths: Vec<String> = planned
+        .iter()
+        .map(|(_, _, _, path, _)| path.clone())
+        .collect();
+
+    run_command_hook(workspace, &config, CommandEvent::PreSync, &paths, None)?;
+
+    let mut phases: Vec<Vec<_>> = Vec::new();
+    for (planned, phase) in planned.into_iter().zip(phase_of) {
+        if phases.len() <= phase {
+            phases.resize_with(phase + 1, Vec::new);
+        }
+        phases[phase].push(planned);
+    }
+
+    let followed = Mutex::new(Vec::new());
+    let result = (|| {
+        let sync_project =
+            |(project, remote, url, path, refspecs): PlannedProject|
+             -> Result<(Option<Conflict>, Option<RevisionChange>), SyncError> {
+                let _project_span = info_span!("Syncing project", name = project.name).entered();
+                let dst = workspace.project_dir(&path);
+                let head = || {
+                    args.log_changes
+                        .as_ref()
+                        .and_then(|_| backend.inspect(&dst, remote.git_name())?.head)
+                };
+                let before = head();
+
+                // A failed clone can leave an empty directory behind, which a retry clones into.
+                let mut cloning = !dst.exists();
+                // A project seeded from its clone bundle only fetches what changed since.
+                if let Some(bundles) = clone_bundles.as_ref().filter(|_| cloning) {
+                    cloning = !bundles.seed(&project, &path, &dst, &url, remote.git_name(), config.mirror)?;
+                }
+                let fetch = |url: &str| {
+                    if cloning {
+                        info!("Cloning {url} into {path}");
+                        // Mirrors serve clones of their own, they always carry the full history.
+                        let depth = project
+                            .clone_depth()
+                            .or(config.depth)
+                            .filter(|_| !config.mirror);
+                        backend.clone_project(
+                            url,
+                            &dst,
+                            remote.git_name(),
+                            config.mirror,
+                            &refspecs,
+                            depth,
+                        )
+                    } else if !args.offline {
+                        backend.fetch_project(&dst, remote.git_name(), &refspecs)
+                    } else {
+                        Ok(())
+                    }
+                };
+                let url = match fetch(&url) {
+                    Ok(()) => url,
+                    Err(error) => {
+                        let Some(moved) = redirect::moved_to(&url, &config.transport) else {
+                            return Err(error.into());
+                        };
+                        if !args.follow_redirects {
+                            warnings::warn(
+                                    WarningKind::MovedRepository,
+                                    format!("{path}: {url} moved to {moved}, sync with --follow-redirects to fetch from there"),
+                                );
+                            return Err(error.into());
+                        }
+
+                        warnings::warn(
+                            WarningKind::MovedRepository,
+                            format!(
+                                "{path}: {url} moved to {moved}, fetching from there from now on"
+                            ),
+                        );
+                        if !cloning {
+                            backend.set_remote_url(&dst, remote.git_name(), &moved)?;
+                        }
+                        followed
+                            .lock()
+                            .expect("not poisoned")
+                            .push((url, moved.clone()));
+                        fetch(&moved)?;
+                        moved
+                    }
+                };
+
+                // A fresh clone checks out the remote's HEAD, which need not be the manifest's revision.
+                let conflict = if !config.mirror {
+                    backend
+                        .update_worktree(
+                            &dst,
+                            &path,
+                            remote.git_name(),
+                            project.revision.as_deref(),
+                            policy,
+                        )
+                        .map(|()| None)
+                        .or_else(|error| match error {
+                            // Reported together once every project is synced.
+                            GitError::Conflict(conflict) => Ok(Some(conflict)),
+                            GitError::RevisionNotFound { revision } if args.offline => {
+                                Err(SyncError::OfflineRevisionMissing {
+                                    path: path.clone(),
+                                    revision,
+                                })
+                            }
+                            error => Err(error.into()),
+                        })?
+                } else {
+                    None
+                };
+
+                let conflict = match conflict {
+                    None if !config.mirror && manifest.project_sync_s(&project) => {
+                        sync_submodules(backend, &dst, &path, &url, remote.git_name(), &claimed, &args)?
+                    }
+                    conflict => conflict,
+                };
+
+                let change = head()
+                    .filter(|after| before.as_ref() != Some(after))
+                    .map(|to| RevisionChange {
+                        counts: before.as_ref().and_then(|from| {
+                            let added = backend.count_commits(&dst, from, &to)?;
+                            let removed = backend.count_commits(&dst, &to, from)?;
+                            Some((added, removed))
+                        }),
+                        path: path.clone(),
+                        from: before.clone(),
+                        to,
+                    });
+
+                Ok((conflict, change))
+            };
+
+        // A phase starts once the previous one is done, its own projects sync in parallel.
+        let mut outcomes = Vec::new();
+        for phase in phases {
+            outcomes.extend(pool.install(|| {
+                phase
+                    .into_par_iter()
+                    .map(&sync_project)
+                    .collect::<Result<Vec<_>, SyncError>>()
+            })?);
+        }
+        let (conflicts, changes): (Vec<_>, Vec<_>) = outcomes.into_iter().unzip();
+
+        if let Some(destination) = &args.log_changes {
+            let changes: Vec<RevisionChange> = changes.into_iter().flatten().collect();
+            log_changes(destination, &changes)?;
+        }
+
+        let report = ConflictReport::new(conflicts.into_iter().flatten().collect());
+        if !report.is_empty() {
+            report.print(args.json)?;
+            return Err(SyncError::Conflicts {
+                count: report.len(),
+            });
+        }
+
+        if args.prune && !config.mirror {
+            prune_orphans(workspace, &manifest)?;
+        }
+
+        if !config.mirror {
+            install_files(workspace, &manifest, &synced)?;
+            if let Some(template) = &config.hooks_template {
+                install_git_hooks(workspace, template, &synced)?;
+            }
+        }
+
+        run_hook(
+            workspace,
+            &manifest,
+            HookType::PostSync,
+            &paths,
+            &args.hooks,
+        )?;
+
+        if !args.this_manifest_only {
+            sync_submanifests(backend, workspace, &config, &manifest, &args)?;
+        }
+        Ok(())
+    })();
+
+    // Checkouts made before another project failed are recorded too, so they are known once dropped.
+    if !config.mirror {
+        workspace.record_checkouts(&manifest)?;
+    }
+
+    // Checkouts already point to where their repository moved, even when another project failed.
+    let followed = followed.into_inner().expect("not poisoned");
+    if !followed.is_empty() {
+        let mut config = workspace.config()?;
+        config.redirects.extend(followed);
+        workspace.save_config(&config)?;
+    }
+
+    run_command_hook(
+        workspace,
+        &config,
+        CommandEvent::PostSync,
+        &paths,
+        Some(result.is_ok()),
+    )?;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{custom_refspecs, is_under_prefix, sync, sync_phases, SyncArgs, SyncError};
+    use crate::{
+        backend::fake::{Action, FakeBackend},
+        config::{ManifestConfig, WorkspaceConfig},
+        workspace::Workspace,
+    };
+    use clap::Parser;
+    use repox_manifest::Manifest;
+    use std::fs::write;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        args: SyncArgs,
+    }
+
+    fn sync_args(args: &[&str]) -> SyncArgs {
+        Cli::parse_from(std::iter::once("sync").chain(args.iter().copied())).args
+    }
+
+    fn workspace_with_manifest(dir: &std::path::Path, manifest: &str) -> Workspace {
+        let manifest_path = dir.join("manifest.xml");
+        write(&manifest_path, manifest).unwrap();
+
+        let workspace = Workspace::create(dir).unwrap();
+        workspace
+            .save_config(&WorkspaceConfig {
+                manifest: ManifestConfig {
+                    path: manifest_path.to_string_lossy().into_owned(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .unwrap();
+        workspace
+    }
+
+    #[test]
+    fn test_sync_clones_then_updates() {
+        let dir = tempfile::tempdir().unwrap();
+        let workspace = workspace_with_manifest(
+            dir.path(),
+            r#"<manifest>
+                <remote name="origin" fetch="https://example.com" />
+                <default remote="origin" />
+                <project name="app" path="src/app" revision="main" />
+                <project name="tools" groups="notdefault" />
+            </manifest>"#,
+        );
+        let backend = FakeBackend::default();
+        let app = workspace.project_dir("src/app");
+
+        sync(&backend, &workspace, sync_args(&[])).unwrap();
+        assert_eq!(
+            backend.take_actions(),
+            vec![
+                Action::Clone {
+                    url: "https://example.com/app.git".to_string(),
+                    dst: app.clone(),
+                },
+                Action::Update {
+                    dst: app.clone(),
+                    revision: Some("main".to_string()),
+                },
+            ]
+        );
+
+        sync(&backend, &workspace, sync_args(&[])).unwrap();
+        assert_eq!(
+            backend.take_actions(),
+            vec![
+                Action::Fetch { dst: app.clone() },
+                Action::Update {
+                    dst: app,
+                    revision: Some("main".to_string()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sync_offline() {
+        let dir = tempfile::tempdir().unwrap();
+        let workspace = workspace_with_manifest(
+            dir.path(),
+            r#"<manifest>
+                <remote name="origin" fetch="https://example.com" />
+                <project name="app" remote="origin" />
+            </manifest>"#,
+        );
+        let backend = FakeBackend::default();
+
+        assert!(matches!(
+            sync(&backend, &workspace, sync_args(&["--offline"])),
+            Err(SyncError::OfflineNotCheckedOut { path }) if path == "app"
+        ));
+        assert!(backend.take_actions().is_empty());
+
+        sync(&backend, &workspace, sync_args(&[])).unwrap();
+        backend.take_actions();
+
+        sync(&backend, &workspace, sync_args(&["--offline"])).unwrap();
+        assert_eq!(
+            backend.take_actions(),
+            vec![Action::Update {
+                dst: workspace.project_dir("app"),
+                revision: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_sync_reports_changed_remote_url() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = |fetch: &str| {
+            format!(
+                r#"<manifest>
+                    <remote name="origin" fetch="{fetch}" />
+                    <project name="app" remote="origin" />
+                </manifest>"#
+            )
+        };
+        let workspace = workspace_with_manifest(dir.path(), &manifest("https://example.com"));
+        let backend = FakeBackend::default();
+
+        sync(&backend, &workspace, sync_args(&[])).unwrap();
+        backend.take_actions();
+
+        workspace_with_manifest(dir.path(), &manifest("https://mirror.example.com"));
+        assert!(matches!(
+            sync(&backend, &workspace, sync_args(&[])),
+            Err(SyncError::ManifestChanged { mismatches }) if mismatches.len() == 1
+        ));
+        assert!(backend.take_actions().is_empty());
+
+        sync(&backend, &workspace, sync_args(&["--force-sync"])).unwrap();
+        assert_eq!(
+            backend.take_actions(),
+            vec![
+                Action::Clone {
+                    url: "https://mirror.example.com/app.git".to_string(),
+                    dst: workspace.project_dir("app"),
+                },
+                Action::Update {
+                    dst: workspace.project_dir("app"),
+                    revision: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sync_revision_overrides() {
+        let dir = tempfile::tempdir().unwrap();
+        let workspace = workspace_with_manifest(
+            dir.path(),
+            r#"<manifest>
+                <remote name="origin" fetch="https://example.com" />
+                <default remote="origin" revision="main" />
+                <project name="app" path="src/app" />
+                <project name="lib" />
+            </manifest>"#,
+        );
+        let backend = FakeBackend::default();
+
+        assert!(matches!(
+            sync(&backend, &workspace, sync_args(&["--revision", "tools=main"])),
+            Err(SyncError::UnknownProject { project }) if project == "tools"
+        ));
+
+        sync(
+            &backend,
+            &workspace,
+            sync_args(&["--revision", "src/app=feature", "--revision", "lib=v2"]),
+        )
+        .unwrap();
+        let updates: Vec<_> = backend
+            .take_actions()
+            .into_iter()
+            .filter_map(|action| match action {
+                Action::Update { revision, .. } => revision,
+                _ => None,
+            })
+            .collect();
+        assert_eq!(updates, vec!["v2", "feature"]);
+    }
+
+    #[test]
+    fn test_sync_path_prefixes() {
+        let dir = tempfile::tempdir().unwrap();
+        let workspace = workspace_with_manifest(
+            dir.path(),
+            r#"<manifest>
+                <remote name="origin" fetch="https://example.com" />
+                <default remote="origin" />
+                <project name="kernel/common" />
+                <project name="kernel2" />
+                <project name="nxp-bsp" path="vendor/nxp/bsp" groups="notdefault" />
+                <project name="app" />
+            </manifest>"#,
+        );
+        let backend = FakeBackend::default();
+
+        assert!(matches!(
+            sync(&backend, &workspace, sync_args(&["--path-prefix", "device/"])),
+            Err(SyncError::NoProjectUnderPrefix { prefixes }) if prefixes == "device/"
+        ));
+
+        sync(
+            &backend,
+            &workspace,
+            sync_args(&["--path-prefix", "kernel", "--path-prefix", "vendor/nxp/"]),
+        )
+        .unwrap();
+        let clones: Vec<_> = backend
+            .take_actions()
+            .into_iter()
+            .filter_map(|action| match action {
+                Action::Clone { url, .. } => Some(url),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            clones,
+            vec![
+                "https://example.com/kernel/common.git",
+                "https://example.com/nxp-bsp.git"
+            ]
+        );
+
+        assert!(is_under_prefix("kernel", "kernel/"));
+        assert!(!is_under_prefix("kernel2", "kernel"));
+    }
+
+    #[test]
+    fn test_sync_after_orders_phases() {
+        let dir = tempfile::tempdir().unwrap();
+        let workspace = workspace_with_manifest(
+            dir.path(),
+            r#"<manifest>
+                <remote name="origin" fetch="https://example.com" />
+                <default remote="origin" />
+                <project name="app" />
+                <project name="tools" path="build/tools" />
+                <project name="hooks" />
+            </manifest>"#,
+        );
+        let mut config = workspace.config().unwrap();
+        config.sync_after = [
+            ("app".to_string(), vec!["build/tools".to_string()]),
+            ("tools".to_string(), vec!["hooks".to_string()]),
+        ]
+        .into();
+        workspace.save_config(&config).unwrap();
+        let backend = FakeBackend::default();
+
+        sync(&backend, &workspace, sync_args(&[])).unwrap();
+        let clones: Vec<_> = backend
+            .take_actions_in_order()
+            .into_iter()
+            .filter_map(|action| match action {
+                Action::Clone { url, .. } => Some(url),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            clones,
+            vec![
+                "https://example.com/hooks.git",
+                "https://example.com/tools.git",
+                "https://example.com/app.git"
+            ]
+        );
+
+        let manifest = workspace.manifest().unwrap();
+        let projects = manifest.projects();
+        let projects: Vec<_> = projects.iter().collect();
+        config
+            .sync_after
+            .insert("hooks".to_string(), vec!["app".to_string()]);
+        assert!(matches!(
+            sync_phases(&projects, &config.sync_after),
+            Err(SyncError::OrderCycle { chain }) if chain == "app -> tools -> hooks -> app"
+        ));
+    }
+
+    #[test]
+    fn test_custom_refspecs() {
+        let manifest: Manifest = quick_xml::de::from_str(
+            r#"<manifest>
+                <remote name="origin" fetch="https://example.com">
+                    <annotation name="repox-fetch-refspecs" value="+refs/meta/config:refs/meta/config" />
+                </remote>
+                <project name="app" remote="origin">
+                    <annotation name="repox-fetch-refspecs" value="refs/notes/*:refs/notes/*  refs/meta/*:refs/meta/*" />
+                </project>
+                <project name="lib" remote="origin" />
+            </manifest>"#,
+        )
+        .unwrap();
+        let config = WorkspaceConfig {
+            refspecs: [(
+                "lib".to_string(),
+                vec!["refs/notes/*:refs/notes/*".to_string()],
+            )]
+            .into(),
+            ..Default::default()
+        };
+        let projects = manifest.projects();
+
+        assert_eq!(
+            custom_refspecs(&config, &manifest, &projects[0]),
+            vec!["refs/notes/*:refs/notes/*", "refs/meta/*:refs/meta/*"]
+        );
+        assert_eq!(
+            custom_refspecs(&config, &manifest, &projects[1]),
+            vec![
+                "+refs/meta/config:refs/meta/config",
+                "refs/notes/*:refs/notes/*"
+            ]
+        );
+    }
+}
@@ -0,0 +1,82 @@
+use super::sync::{run_sync, SyncArgs, SyncError};
+use crate::{
+    config::{ManifestConfig, WorkspaceConfig},
+    hooks::HookArgs,
+    workspace::{read_manifest, Workspace, WorkspaceError},
+};
+use clap::Args;
+use miette::Result;
+use serde::{Deserialize, Serialize};
+use std::fs::{canonicalize, copy};
+use tracing::info;
+
+/// Name of the copy of the frozen manifest kept in `.repo/` by workspaces created by thaw.
+const FROZEN_MANIFEST: &str = "frozen.xml";
+
+/// Recreate a workspace from a frozen manifest
+///
+/// # Description
+///
+/// Checks out every project of a manifest written by 'repox freeze' at the commit
+/// it pins, reproducing the workspace it was frozen from.
+///
+/// Inside an existing workspace this is 'repox sync --manifest-file', and later
+/// syncs go back to the workspace's own manifest. Anywhere else, a new workspace
+/// is created in the current directory with a copy of the frozen manifest as its
+/// manifest.
+#[derive(Args, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ThawArgs {
+    /// manifest written by `repox freeze`
+    manifest_file: String,
+
+    /// projects to fetch simultaneously
+    #[arg(short = 'j', long)]
+    jobs: Option<usize>,
+
+    /// force checkout even if it results in throwing away uncommitted modifications.
+    /// WARNING: this may cause loss of data
+    #[arg(long, default_value_t = false)]
+    force_checkout: bool,
+
+    #[command(flatten)]
+    hooks: HookArgs,
+}
+
+/// Thawing is a sync once the workspace exists, so it fails the way a sync does.
+pub fn run_thaw(args: ThawArgs) -> Result<(), SyncError> {
+    let frozen = canonicalize(&args.manifest_file).map_err(WorkspaceError::ManifestReadError)?;
+
+    let manifest_file = match Workspace::discover() {
+        Ok(_) => frozen,
+        Err(WorkspaceError::NotFound) => {
+            // Check the manifest before leaving a workspace behind.
+            read_manifest(&frozen)?;
+
+            let workspace = Workspace::create(".")?;
+            let path = workspace.repo_dir().join(FROZEN_MANIFEST);
+            info!("Creating a workspace from {}", frozen.display());
+            let path = copy(&frozen, &path)
+                .and_then(|_| canonicalize(&path))
+                .map_err(WorkspaceError::CreateError)?;
+
+            workspace.save_config(&WorkspaceConfig {
+                manifest: ManifestConfig {
+                    url: frozen.display().to_string(),
+                    branch: "HEAD".to_string(),
+                    path: path.display().to_string(),
+                },
+                ..Default::default()
+            })?;
+            path
+        }
+        Err(error) => return Err(error.into()),
+    };
+
+    run_sync(SyncArgs::thaw(
+        manifest_file.display().to_string(),
+        args.jobs,
+        args.force_checkout,
+        args.hooks,
+    ))
+}
@@ -0,0 +1,83 @@
+use miette::Diagnostic;
+use thiserror::Error;
+
+/// Why a command has not been implemented yet, and what to do instead.
+#[derive(Debug, Error, Diagnostic)]
+pub enum Unimplemented {
+    #[error("`repox diff` has not been implemented yet")]
+    #[diagnostic(
+        code(repox::unimplemented::diff),
+        help("run `repox forall -p -c git diff` to see the changes in every project")
+    )]
+    Diff,
+
+    #[error("`repox download` has not been implemented yet")]
+    #[diagnostic(
+        code(repox::unimplemented::download),
+        help("fetch the change with `git fetch <remote> refs/changes/...` inside the project, then `git checkout FETCH_HEAD`")
+    )]
+    Download,
+
+    #[error("`repox prune` has not been implemented yet")]
+    #[diagnostic(
+        code(repox::unimplemented::prune),
+        help("run `repox forall -c git branch --merged` to find merged topic branches, and delete them with `git branch -d`")
+    )]
+    Prune,
+
+    #[error("`repox abandon` has not been implemented yet")]
+    #[diagnostic(
+        code(repox::unimplemented::abandon),
+        help("run `repox forall -c git branch -D <branch>` to delete a branch everywhere")
+    )]
+    Abandon,
+
+    #[error("`repox cherry-pick` has not been implemented yet")]
+    #[diagnostic(
+        code(repox::unimplemented::cherry_pick),
+        help("run `git cherry-pick -x <sha>` inside the project")
+    )]
+    CherryPick,
+
+    #[error("`repox diff-manifests` has not been implemented yet")]
+    #[diagnostic(
+        code(repox::unimplemented::diff_manifests),
+        help("compare the manifest files with `diff` or `git diff --no-index`")
+    )]
+    DiffManifests,
+
+    #[error("`repox {0}` will not be implemented")]
+    #[diagnostic(
+        code(repox::unimplemented::gitc),
+        help("GITC clients have been removed from git-repo, use a regular checkout with `repox init` instead")
+    )]
+    Gitc(&'static str),
+
+    #[error("`repox grep` has not been implemented yet")]
+    #[diagnostic(
+        code(repox::unimplemented::grep),
+        help("run `repox forall -p -c git grep <pattern>` to search every project")
+    )]
+    Grep,
+
+    #[error("`repox overview` has not been implemented yet")]
+    #[diagnostic(
+        code(repox::unimplemented::overview),
+        help("run `repox forall -p -c git log --oneline @{{upstream}}..` to see unmerged commits")
+    )]
+    Overview,
+
+    #[error("`repox smart-sync` has not been implemented yet")]
+    #[diagnostic(
+        code(repox::unimplemented::smart_sync),
+        help("run `repox sync` to sync to the revisions in the manifest")
+    )]
+    SmartSync,
+
+    #[error("`repox stage` has not been implemented yet")]
+    #[diagnostic(
+        code(repox::unimplemented::stage),
+        help("run `git add --interactive` inside the project")
+    )]
+    Stage,
+}
@@ -1,6 +1,345 @@
+use super::select_projects;
+use crate::{
+    backend::{GitBackend, GixBackend},
+    command_hooks::{run_command_hook, CommandEvent, CommandHookError},
+    config::UploadConfig,
+    display::RemoteBranch,
+    git::GitError,
+    hooks::{run_hook, HookArgs, HookError, HookType},
+    workspace::{Workspace, WorkspaceError},
+};
 use clap::Args;
+use gix::glob::{wildmatch, wildmatch::Mode};
+use miette::{Diagnostic, Result};
+use serde::{Deserialize, Serialize};
+use std::io::{IsTerminal, Write};
+use thiserror::Error;
+use tracing::{info, info_span};
 
-#[derive(Args, Debug)]
+/// Upload changes for code review
+///
+/// # Description
+///
+/// The 'repo upload' command is used to send changes to the Gerrit Code Review
+/// system. It searches for topic branches in local projects that have not yet
+/// been published for review. If multiple topic branches are found, 'repo upload'
+/// uploads each of them.
+///
+/// # Destination Branch
+///
+/// Changes are submitted for review on the project's dest-branch. Projects
+/// without one use the default element's dest-branch, then the project's
+/// revision and finally the default element's revision. The -D option overrides
+/// the destination branch of every selected project.
+///
+/// # Skipped Branches
+///
+/// Branches with nothing new for review are not pushed, so re-running upload
+/// does not fail with Gerrit's "no new changes": branches already merged into the
+/// fetched destination branch, and branches whose commit was uploaded before, as
+/// recorded in refs/published/.
+///
+/// # Upload Safety
+///
+/// Before pushing, the files the outgoing commits add or change are checked for
+/// build outputs and secrets: files larger than upload.max-file-size (5 MiB by
+/// default), and files matching upload.forbidden-files (object files, archives,
+/// keys and the like by default). Such files are listed, and the upload only goes
+/// ahead once confirmed, or with --yes.
+///
+/// # Remotes Without Review
+///
+/// Projects whose remote has no review host are hosted on plain git servers,
+/// which have no refs/for/ to upload to. Uploading them fails unless --push is
+/// given, in which case their topic branches are pushed to the remote directly,
+/// as branches of the same name under upload.push-namespace (refs/heads/ by
+/// default).
+#[derive(Args, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub struct UploadArgs {
     projects: Option<Vec<String>>,
+
+    /// submit for review on this target branch
+    #[arg(short = 'D', long)]
+    dest_branch: Option<String>,
+
+    /// do everything except actually upload the CL
+    #[arg(short = 'n', long, default_value_t = false)]
+    dry_run: bool,
+
+    /// upload large or forbidden files without asking for confirmation
+    #[arg(short = 'y', long, default_value_t = false)]
+    yes: bool,
+
+    /// push topic branches directly to remotes without a review host
+    #[arg(long, default_value_t = false)]
+    push: bool,
+
+    #[command(flatten)]
+    hooks: HookArgs,
+}
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum UploadError {
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    WorkspaceError(#[from] WorkspaceError),
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    GitError(#[from] GitError),
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    HookError(#[from] HookError),
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    CommandHookError(#[from] CommandHookError),
+
+    #[error("Project {project} references an undefined remote")]
+    #[diagnostic(code(repox::command::upload::unknown_remote))]
+    UnknownRemote { project: String },
+
+    #[error("Could not determine which branch to upload {project} to")]
+    #[diagnostic(
+        code(repox::command::upload::no_dest_branch),
+        help("set dest-branch or revision in the manifest, or pass --dest-branch")
+    )]
+    NoDestBranch { project: String },
+
+    #[error("Could not upload {project} for review, its remote {remote} has no review host")]
+    #[diagnostic(
+        code(repox::command::upload::no_review),
+        help("pass --push to push the topic branch to the remote directly")
+    )]
+    NoReview { project: String, remote: String },
+
+    #[error("{count} file(s) look like build outputs or secrets, not uploading")]
+    #[diagnostic(
+        code(repox::command::upload::unsafe_files),
+        help("remove the files from the commits, change the upload settings with `repox config`, or pass --yes to upload them anyway")
+    )]
+    UnsafeFiles { count: usize },
+}
+
+/// Why an outgoing file needs confirmation before it is uploaded, if it does.
+fn unsafe_file(config: &UploadConfig, file: &str, size: u64) -> Option<String> {
+    let name = file.rsplit('/').next().unwrap_or(file);
+    if let Some(glob) = config.forbidden_files().into_iter().find(|glob| {
+        let value = if glob.contains('/') { file } else { name };
+        wildmatch(
+            glob.as_str().into(),
+            value.into(),
+            Mode::NO_MATCH_SLASH_LITERAL,
+        )
+    }) {
+        return Some(format!("matches {glob}"));
+    }
+
+    let max = config.max_file_size();
+    (size > max).then(|| format!("is {size} bytes, more than {max}"))
+}
+
+/// Asks the user whether the files listed above may be uploaded anyway.
+fn confirm_unsafe_files() -> bool {
+    let stdin = std::io::stdin();
+    if !stdin.is_terminal() {
+        return false;
+    }
+
+    print!("Upload them anyway? [y/N] ");
+    let _ = std::io::stdout().flush();
+
+    let mut answer = String::new();
+    stdin.read_line(&mut answer).is_ok() && matches!(answer.trim(), "y" | "Y" | "yes")
+}
+
+pub fn run_upload(args: UploadArgs) -> Result<(), UploadError> {
+    let workspace = Workspace::discover()?;
+    let config = workspace.config()?;
+
+    upload(&GixBackend::new(&config), &workspace, args)
+}
+
+fn upload(
+    backend: &dyn GitBackend,
+    workspace: &Workspace,
+    args: UploadArgs,
+) -> Result<(), UploadError> {
+    let manifest = workspace.manifest()?;
+    let config = workspace.config()?;
+
+    let mut uploads = Vec::new();
+    let mut unsafe_files = Vec::new();
+    for project in select_projects(&manifest, args.projects.as_deref()) {
+        let path = project.checkout_path().to_string();
+        let dst = workspace.project_dir(&path);
+        if !dst.exists() {
+            continue;
+        }
+
+        let Some(branch) = backend.current_branch(&dst)? else {
+            info!("{path} is not on a topic branch, skipping");
+            continue;
+        };
+
+        let remote =
+            manifest
+                .project_remote(&project)
+                .ok_or_else(|| UploadError::UnknownRemote {
+                    project: project.name.clone(),
+                })?;
+
+        let dest_branch = args
+            .dest_branch
+            .clone()
+            .or_else(|| manifest.dest_branch(&project))
+            .ok_or_else(|| UploadError::NoDestBranch {
+                project: project.name.clone(),
+            })?;
+        let dest_branch = dest_branch
+            .strip_prefix("refs/heads/")
+            .unwrap_or(&dest_branch)
+            .to_string();
+
+        let head = backend.branch_commit(&dst, &branch);
+        let merged = head
+            .zip(backend.tracking_commit(&dst, remote.git_name(), &dest_branch))
+            .is_some_and(|(head, dest)| backend.is_ancestor(&dst, head, dest));
+        if merged {
+            println!("Skipping {path} ({branch}): already merged into {dest_branch}");
+            continue;
+        }
+        if head.is_some() && head == backend.published_commit(&dst, &branch) {
+            println!("Skipping {path} ({branch}): no new changes since the last upload");
+            continue;
+        }
+
+        if let Some(head) = head {
+            for (file, size) in backend.outgoing_files(&dst, &path, head, remote.git_name())? {
+                if let Some(reason) = unsafe_file(&config.upload, &file, size) {
+                    unsafe_files.push(format!("{path}/{file} {reason}"));
+                }
+            }
+        }
+
+        let direct = remote.review().is_none();
+        if direct && !args.push {
+            return Err(UploadError::NoReview {
+                project: project.name.clone(),
+                remote: remote.name.clone(),
+            });
+        }
+        let (refspec, target) = if direct {
+            let target = config.upload.push_ref(&branch);
+            (format!("refs/heads/{branch}:{target}"), target)
+        } else {
+            (
+                format!("refs/heads/{branch}:refs/for/{dest_branch}"),
+                dest_branch,
+            )
+        };
+
+        uploads.push((
+            path,
+            dst,
+            branch,
+            head,
+            remote.git_name().to_string(),
+            direct,
+            refspec,
+            target,
+        ));
+    }
+
+    if !unsafe_files.is_empty() && !args.yes {
+        println!("The changes to upload add files that look like build outputs or secrets:");
+        for file in &unsafe_files {
+            println!("  {file}");
+        }
+        if !confirm_unsafe_files() {
+            return Err(UploadError::UnsafeFiles {
+                count: unsafe_files.len(),
+            });
+        }
+    }
+
+    let paths: Vec<String> = uploads.iter().map(|(path, ..)| path.clone()).collect();
+    run_hook(
+        workspace,
+        &manifest,
+        HookType::PreUpload,
+        &paths,
+        &args.hooks,
+    )?;
+
+    run_command_hook(workspace, &config, CommandEvent::PreUpload, &paths, None)?;
+
+    let result = uploads.into_iter().try_for_each(
+        |(path, dst, branch, head, remote_name, direct, refspec, target)| {
+            let _project_span = info_span!("Uploading project", path).entered();
+            let target = RemoteBranch {
+                branch: &target,
+                remote: &remote_name,
+            };
+            if direct {
+                println!("Pushing {path} ({branch}) to {target}");
+            } else {
+                println!("Uploading {path} ({branch}) for review on {target}");
+            }
+
+            if !args.dry_run {
+                backend.push(&dst, &path, &remote_name, &refspec)?;
+                if let Some(head) = head {
+                    backend.mark_published(&dst, &branch, head)?;
+                }
+            }
+            Ok(())
+        },
+    );
+
+    run_command_hook(
+        workspace,
+        &config,
+        CommandEvent::PostUpload,
+        &paths,
+        Some(result.is_ok()),
+    )?;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::unsafe_file;
+    use crate::config::UploadConfig;
+
+    #[test]
+    fn test_unsafe_file() {
+        let config = UploadConfig::default();
+        assert_eq!(
+            unsafe_file(&config, "out/libapp.so", 10),
+            Some("matches *.so".to_string())
+        );
+        assert_eq!(
+            unsafe_file(&config, "keys/id_rsa", 10),
+            Some("matches id_rsa*".to_string())
+        );
+        assert_eq!(unsafe_file(&config, "src/main.c", 10), None);
+
+        let config = UploadConfig {
+            max_file_size: Some(100),
+            forbidden_files: Some(vec!["build/*".to_string()]),
+            ..UploadConfig::default()
+        };
+        assert_eq!(
+            unsafe_file(&config, "build/app", 10),
+            Some("matches build/*".to_string())
+        );
+        assert_eq!(unsafe_file(&config, "lib/build/app", 10), None);
+        assert_eq!(
+            unsafe_file(&config, "data.bin", 200),
+            Some("is 200 bytes, more than 100".to_string())
+        );
+    }
 }
@@ -0,0 +1,198 @@
+use super::select_grouped_projects;
+use crate::{
+    backend::{GitBackend, GixBackend},
+    config::{ManifestConfig, WorkspaceConfig},
+    git::{toplevel, GitError, Refspecs},
+    workspace::{Workspace, WorkspaceError},
+};
+use clap::{Args, Subcommand};
+use miette::{Diagnostic, Result};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{canonicalize, read_dir},
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+use tracing::{info, info_span};
+
+/// Directory inside a linked workspace's `.repo/` holding bare clones of the projects missing from the
+/// workspace it was created from.
+const PROJECT_OBJECTS_DIR: &str = "project-objects";
+
+/// Manage workspaces sharing this one's objects
+///
+/// # Description
+///
+/// 'add' creates a second workspace in DIR for another branch of the manifest
+/// repository, e.g. a release branch next to main, for working on both at once.
+/// The manifest repository and every project checked out here become linked git
+/// worktrees of their checkouts in this workspace, so nothing is downloaded twice.
+/// Projects this workspace lacks are cloned into the new workspace's
+/// .repo/project-objects/ and linked from there.
+///
+/// The new workspace is synced and used like any other, its projects are detached
+/// at the branch's revisions to begin with.
+#[derive(Args, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct WorktreeArgs {
+    #[command(subcommand)]
+    command: WorktreeCommand,
+}
+
+#[derive(Subcommand, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum WorktreeCommand {
+    /// Create a workspace for another manifest branch in DIR
+    Add {
+        /// manifest branch the new workspace checks out
+        branch: String,
+
+        /// directory to create the workspace in
+        dir: PathBuf,
+    },
+}
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum WorktreeError {
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    WorkspaceError(#[from] WorkspaceError),
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    GitError(#[from] GitError),
+
+    #[error("The manifest {path} is not in a git repository, it has no other branches")]
+    #[diagnostic(
+        code(repox::command::worktree::no_manifest_repository),
+        help("initialize the workspace from a manifest file inside a clone of the manifest repository")
+    )]
+    NoManifestRepository { path: String },
+
+    #[error("{dir} already exists and is not empty")]
+    #[diagnostic(
+        code(repox::command::worktree::exists),
+        help("choose another directory, or remove {dir}")
+    )]
+    DirectoryNotEmpty { dir: String },
+
+    #[error("Project {project} references an undefined remote")]
+    #[diagnostic(code(repox::command::worktree::unknown_remote))]
+    UnknownRemote { project: String },
+}
+
+pub fn run_worktree(args: WorktreeArgs) -> Result<(), WorktreeError> {
+    let workspace = Workspace::discover()?;
+    let config = workspace.config()?;
+
+    worktree(&GixBackend::new(&config), &workspace, args)
+}
+
+fn worktree(
+    backend: &dyn GitBackend,
+    workspace: &Workspace,
+    args: WorktreeArgs,
+) -> Result<(), WorktreeError> {
+    let WorktreeCommand::Add { branch, dir } = args.command;
+    let config = workspace.config()?;
+
+    let no_repository = || WorktreeError::NoManifestRepository {
+        path: config.manifest.path.clone(),
+    };
+    let manifest_path = canonicalize(&config.manifest.path).map_err(|_| no_repository())?;
+    let manifest_repo = manifest_path
+        .parent()
+        .and_then(toplevel)
+        .ok_or_else(no_repository)?;
+    let manifest_file = manifest_path
+        .strip_prefix(&manifest_repo)
+        .map_err(|_| no_repository())?;
+
+    if read_dir(&dir).is_ok_and(|mut entries| entries.next().is_some()) {
+        return Err(WorktreeError::DirectoryNotEmpty {
+            dir: dir.display().to_string(),
+        });
+    }
+    let linked = Workspace::create(&dir)?;
+
+    info!("Checking out {branch} of the manifest repository");
+    let manifests_dir = linked.repo_dir().join("manifests");
+    backend.add_worktree(&manifest_repo, &manifests_dir, "origin", &branch)?;
+    let linked_config = WorkspaceConfig {
+        manifest: ManifestConfig {
+            url: config.manifest.url.clone(),
+            branch: branch.clone(),
+            path: manifests_dir.join(manifest_file).display().to_string(),
+        },
+        worktree: true,
+        ..config.clone()
+    };
+    linked.save_config(&linked_config)?;
+    let manifest = linked.manifest()?;
+
+    select_grouped_projects(&manifest, None, linked_config.groups.as_deref())
+        .into_par_iter()
+        .map(|project| {
+            let _project_span = info_span!("Linking project", name = project.name).entered();
+            let remote =
+                manifest
+                    .project_remote(&project)
+                    .ok_or_else(|| WorktreeError::UnknownRemote {
+                        project: project.name.clone(),
+                    })?;
+            let path = project.checkout_path();
+            let revision = manifest
+                .project_revision(&project)
+                .unwrap_or_else(|| "HEAD".to_string());
+
+            let source = object_source(workspace, &linked, path, &project.mirror_path());
+            if source.exists() {
+                // This workspace may not have fetched the branch's commits yet.
+                backend.fetch_project(&source, remote.git_name(), &Refspecs::default())?;
+            } else {
+                let url = linked_config.project_urls(remote, &project).fetch;
+                info!("Cloning {url}, which this workspace has not checked out");
+                backend.clone_project(
+                    &url,
+                    &source,
+                    remote.git_name(),
+                    true,
+                    &Refspecs::default(),
+                    None,
+                )?;
+            }
+
+            backend.add_worktree(
+                &source,
+                &linked.project_dir(path),
+                remote.git_name(),
+                &revision,
+            )?;
+            Ok(())
+        })
+        .collect::<Result<(), WorktreeError>>()?;
+
+    linked.record_checkouts(&manifest)?;
+    println!("Created a workspace for {branch} in {}", dir.display());
+    Ok(())
+}
+
+/// The repository a project of `linked` shares its objects with: its checkout in `workspace`, or else
+/// a bare clone kept in `linked`'s `.repo/project-objects/`.
+fn object_source(
+    workspace: &Workspace,
+    linked: &Workspace,
+    path: &str,
+    mirror_path: &str,
+) -> PathBuf {
+    let checkout = workspace.project_dir(path);
+    if Path::new(&checkout).join(".git").exists() {
+        checkout
+    } else {
+        linked
+            .repo_dir()
+            .join(PROJECT_OBJECTS_DIR)
+            .join(mirror_path)
+    }
+}
@@ -0,0 +1,150 @@
+use crate::{
+    config::WorkspaceConfig,
+    warnings::{self, WarningKind},
+    workspace::Workspace,
+};
+use miette::Diagnostic;
+use serde::Serialize;
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+use thiserror::Error;
+use tracing::info_span;
+
+/// The points in a command at which the programs configured under `command-hooks` run.
+///
+/// Unlike the manifest's repo-hooks, these belong to the user, e.g. to refresh an IDE, warm a build
+/// cache or notify a chat bot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandEvent {
+    PreSync,
+    PostSync,
+    PreStart,
+    PostStart,
+    PreUpload,
+    PostUpload,
+}
+
+impl CommandEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CommandEvent::PreSync => "pre-sync",
+            CommandEvent::PostSync => "post-sync",
+            CommandEvent::PreStart => "pre-start",
+            CommandEvent::PostStart => "post-start",
+            CommandEvent::PreUpload => "pre-upload",
+            CommandEvent::PostUpload => "post-upload",
+        }
+    }
+
+    /// Whether the hook runs before the command does anything, and can stop it.
+    fn is_pre(&self) -> bool {
+        matches!(
+            self,
+            CommandEvent::PreSync | CommandEvent::PreStart | CommandEvent::PreUpload
+        )
+    }
+}
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum CommandHookError {
+    #[error("Could not run the {event} command hook `{command}`")]
+    #[diagnostic(code(repox::command_hooks::spawn))]
+    SpawnError {
+        event: &'static str,
+        command: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("The {event} command hook `{command}` failed with {status}")]
+    #[diagnostic(
+        code(repox::command_hooks::failed),
+        help("fix the hook, or remove it with `repox config unset command-hooks.{event}`")
+    )]
+    Failed {
+        event: &'static str,
+        command: String,
+        status: String,
+    },
+}
+
+/// What a command hook is told about the command on its stdin, as JSON.
+#[derive(Debug, Serialize)]
+struct Payload<'a> {
+    event: &'static str,
+    /// The top of the repo client.
+    workspace: String,
+    /// The paths of the projects the command works on.
+    projects: &'a [String],
+    /// Whether the command succeeded, for the hooks running after it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    success: Option<bool>,
+}
+
+/// Runs the program configured for `event`, if any, with `sh -c` at the top of the workspace.
+///
+/// A pre- hook failing stops the command. Post- hooks are told whether the command `succeeded`, and their
+/// failures are only warned about, as the command already did its work.
+pub fn run_command_hook(
+    workspace: &Workspace,
+    config: &WorkspaceConfig,
+    event: CommandEvent,
+    projects: &[String],
+    succeeded: Option<bool>,
+) -> Result<(), CommandHookError> {
+    let Some(command) = config.command_hooks.get(event.name()) else {
+        return Ok(());
+    };
+    let _hook_span = info_span!("Running command hook", event = event.name()).entered();
+
+    let payload = Payload {
+        event: event.name(),
+        workspace: workspace.root().display().to_string(),
+        projects,
+        success: succeeded,
+    };
+    let result = run(command, workspace, &payload);
+    match result {
+        Err(error) if !event.is_pre() => {
+            warnings::warn(WarningKind::FailedHook, error.to_string());
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+fn run(command: &str, workspace: &Workspace, payload: &Payload) -> Result<(), CommandHookError> {
+    let spawn_error = |source| CommandHookError::SpawnError {
+        event: payload.event,
+        command: command.to_string(),
+        source,
+    };
+
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .current_dir(workspace.root())
+        .env("REPOX_EVENT", payload.event)
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(spawn_error)?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        let json = serde_json::to_vec(payload).expect("the payload serializes to JSON");
+        // A hook that does not read its stdin closes it early, which is not an error.
+        let _ = stdin.write_all(&json);
+    }
+
+    let status = child.wait().map_err(spawn_error)?;
+    if status.success() {
+        return Ok(());
+    }
+
+    Err(CommandHookError::Failed {
+        event: payload.event,
+        command: command.to_string(),
+        status: status.to_string(),
+    })
+}
@@ -0,0 +1,479 @@
+use miette::Diagnostic;
+use repox_manifest::{
+    project::Project,
+    remote::Remote,
+    url::{map_scheme, resolve_fetch_url, ProjectUrls},
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, path::Path};
+use thiserror::Error;
+
+/// Settings chosen at `repox init` time and persisted in `.repo/repox.toml`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct WorkspaceConfig {
+    pub manifest: ManifestConfig,
+
+    /// Whether the workspace is a replica of the remote repositories rather than a client working directory.
+    pub mirror: bool,
+
+    /// Whether projects are managed using git-worktree.
+    pub worktree: bool,
+
+    /// Whether checkouts refuse paths Windows cannot represent: reserved device names, characters it does not
+    /// allow, trailing dots and spaces, and paths that differ only in case.
+    pub protect_windows: bool,
+
+    /// Whether sync checks projects out at the commits the manifest's superproject pins them to.
+    pub use_superproject: bool,
+
+    /// Whether manifests with elements or attributes repox does not read fail to parse, rather than being
+    /// warned about, so typos like `revison=` cannot slip through.
+    pub strict_manifest: bool,
+
+    /// Manifest groups to restrict projects to, e.g. `default,-notdefault`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub groups: Option<String>,
+
+    /// Where new projects' clone bundles are downloaded from, a URL template such as
+    /// `https://cdn.example.com/bundles/{project}.bundle`. See [`crate::clone_bundle::bundle_url`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clone_bundle_url: Option<String>,
+
+    /// Depth of shallow clones.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub depth: Option<usize>,
+
+    /// Number of projects to sync in parallel.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jobs: Option<usize>,
+
+    /// URL prefix `repox mirror push` replicates every project of a mirror to, e.g. an internal mirror host.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mirror_push_url: Option<String>,
+
+    /// Where repo's own source comes from, as given to `repox init --repo-url`. `repox selfupdate` installs
+    /// from it when it is a repox repository.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repo_url: Option<String>,
+
+    /// The branch, tag or commit of `repo-url`, as given to `repox init --repo-rev`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repo_rev: Option<String>,
+
+    /// Refspecs fetched in addition to the remote's, keyed by project name, e.g. `refs/notes/*:refs/notes/*`.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub refspecs: BTreeMap<String, Vec<String>>,
+
+    /// URL prefixes to fetch from instead, keyed by the prefix they replace, like git's `url.<base>.insteadOf`.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub url_rewrites: BTreeMap<String, String>,
+
+    /// URLs of repositories that moved, keyed by the URL they were fetched from before, as recorded by
+    /// `repox sync --follow-redirects`. The manifest is left as it is.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub redirects: BTreeMap<String, String>,
+
+    /// Projects that must be checked out before others, e.g. the hooks project, or a tooling project other
+    /// projects' copyfile elements read from. Each project, by name or path, is synced once every project
+    /// it lists is.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub sync_after: BTreeMap<String, Vec<String>>,
+
+    /// Directory of git hooks, such as `pre-commit`, that sync installs into every project's `.git/hooks`,
+    /// replacing hooks of the same name. Relative paths are from the workspace root, so a project can carry
+    /// the hooks, e.g. `tools/git-hooks`. See [`crate::git_hooks::install_git_hooks`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hooks_template: Option<String>,
+
+    pub hooks: HooksConfig,
+
+    pub branch_names: BranchNamesConfig,
+
+    pub upload: UploadConfig,
+
+    /// Programs run with `sh -c` around repox commands, keyed by event, e.g. `post-sync`. See [`crate::command_hooks`].
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub command_hooks: BTreeMap<String, String>,
+
+    pub transport: TransportConfig,
+
+    pub fetch: FetchConfig,
+
+    /// How to authenticate to each remote, keyed by remote name.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub auth: BTreeMap<String, RemoteAuth>,
+}
+
+/// Trust decisions for the manifest's repo-hooks.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct HooksConfig {
+    /// The hooks project revision the user approved, keyed by hook name.
+    pub approved: BTreeMap<String, String>,
+}
+
+/// The team's naming policy for topic branches, enforced by `repox start`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct BranchNamesConfig {
+    /// What the name given to `repox start` expands to, with `{topic}` standing for that name and `{user}`
+    /// for the user's login, e.g. `{user}/{topic}`. Names already in that form are left as they are.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+
+    /// Regular expression the whole branch name must match, after expansion.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+}
+
+/// What `repox upload` asks for confirmation before pushing, to keep build outputs and secrets out of review.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct UploadConfig {
+    /// Size in bytes above which an uploaded file needs confirmation, [`DEFAULT_MAX_FILE_SIZE`] when unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_file_size: Option<u64>,
+
+    /// Globs of files that need confirmation, matched against the file name, or the whole path when they
+    /// contain a `/`. [`DEFAULT_FORBIDDEN_FILES`] when unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub forbidden_files: Option<Vec<String>>,
+
+    /// Ref namespace `upload --push` pushes topic branches under on remotes without a review host,
+    /// [`DEFAULT_PUSH_NAMESPACE`] when unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub push_namespace: Option<String>,
+}
+
+/// Files larger than this are rarely meant for code review.
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 5 * 1024 * 1024;
+
+/// Topic branches pushed without review become branches of the remote.
+pub const DEFAULT_PUSH_NAMESPACE: &str = "refs/heads/";
+
+/// Build outputs and names that usually hold secrets.
+pub const DEFAULT_FORBIDDEN_FILES: &[&str] = &[
+    "*.o",
+    "*.a",
+    "*.so",
+    "*.dll",
+    "*.exe",
+    "*.class",
+    "*.jar",
+    "*.pyc",
+    "*.apk",
+    ".env",
+    "*.pem",
+    "*.key",
+    "*.p12",
+    "*.keystore",
+    "id_rsa*",
+    "id_ed25519*",
+];
+
+impl UploadConfig {
+    pub fn max_file_size(&self) -> u64 {
+        self.max_file_size.unwrap_or(DEFAULT_MAX_FILE_SIZE)
+    }
+
+    pub fn forbidden_files(&self) -> Vec<String> {
+        match &self.forbidden_files {
+            Some(globs) => globs.clone(),
+            None => DEFAULT_FORBIDDEN_FILES
+                .iter()
+                .map(|glob| glob.to_string())
+                .collect(),
+        }
+    }
+
+    /// The ref `upload --push` pushes the topic `branch` to.
+    pub fn push_ref(&self, branch: &str) -> String {
+        let namespace = self
+            .push_namespace
+            .as_deref()
+            .unwrap_or(DEFAULT_PUSH_NAMESPACE)
+            .trim_end_matches('/');
+        format!("{namespace}/{branch}")
+    }
+}
+
+/// How connections to remotes are secured, for every project fetch and push.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct TransportConfig {
+    /// How the keys of SSH hosts are checked against the known hosts.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ssh_host_keys: Option<HostKeyPolicy>,
+
+    /// CA bundle to verify HTTPS servers against, e.g. a private CA.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ca_bundle: Option<String>,
+
+    /// Client certificate to present to HTTPS servers.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_cert: Option<String>,
+
+    /// Private key of the client certificate.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_key: Option<String>,
+
+    /// Proxy repox's own HTTP downloads go through, e.g. `http://proxy.example.com:3128`.
+    /// Without it, the `https_proxy` and `http_proxy` environment variables are honored.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+
+    /// Bytes per second repox's own HTTP downloads are held to, so they leave bandwidth for others.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_download_rate: Option<u64>,
+}
+
+/// Tuning of how project fetches negotiate and index packs, for experimenting on large manifests.
+///
+/// Unset settings keep gix's defaults, which suit most manifests: consecutive negotiation, the commit-graph
+/// when the repository has one, and a pack thread per core.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct FetchConfig {
+    /// How common history is found with the remote, as git's `fetch.negotiationAlgorithm`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub negotiation_algorithm: Option<NegotiationAlgorithm>,
+
+    /// Whether commit-graph files speed up walking local history, as git's `core.commitGraph`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit_graph: Option<bool>,
+
+    /// Threads resolving received packs, 0 for one per core, as git's `pack.threads`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pack_threads: Option<usize>,
+}
+
+/// How a fetch finds the commits it has in common with the remote.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum NegotiationAlgorithm {
+    /// Walk back from every local ref one commit at a time.
+    Consecutive,
+
+    /// Skip ever larger stretches of history, fewer rounds at the cost of fetching more than needed.
+    Skipping,
+
+    /// Send no local commits, fetching everything the wanted refs reach.
+    Noop,
+}
+
+impl NegotiationAlgorithm {
+    fn as_str(self) -> &'static str {
+        match self {
+            NegotiationAlgorithm::Consecutive => "consecutive",
+            NegotiationAlgorithm::Skipping => "skipping",
+            NegotiationAlgorithm::Noop => "noop",
+        }
+    }
+}
+
+impl FetchConfig {
+    /// The settings as `key=value` git configuration, applied in memory to gix and passed to git with `-c`.
+    pub fn git_config(&self) -> Vec<String> {
+        [
+            (
+                "fetch.negotiationAlgorithm",
+                self.negotiation_algorithm
+                    .map(|algorithm| algorithm.as_str().to_string()),
+            ),
+            (
+                "core.commitGraph",
+                self.commit_graph.map(|enabled| enabled.to_string()),
+            ),
+            (
+                "pack.threads",
+                self.pack_threads.map(|threads| threads.to_string()),
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| Some(format!("{key}={}", value?)))
+        .collect()
+    }
+}
+
+/// Credentials for one remote, from an `[auth.<remote>]` table. Remotes with an alias are keyed by the
+/// alias, the name projects know them by.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct RemoteAuth {
+    /// SSH private key to connect with.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ssh_key: Option<String>,
+
+    /// Environment variable holding an HTTPS access token, sent with basic authentication.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_env: Option<String>,
+
+    /// User name sent along with the token, `git` by default.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+
+    /// Netscape-format cookie file, like Gerrit's `.gitcookies`, whose cookies for the remote's host are sent.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cookie_file: Option<String>,
+}
+
+/// What to do when connecting to an SSH host whose key is unknown or changed, as ssh's `StrictHostKeyChecking`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HostKeyPolicy {
+    /// Refuse hosts that are not in the known hosts.
+    Strict,
+
+    /// Add unknown hosts to the known hosts, refuse changed keys.
+    AcceptNew,
+
+    /// Accept any key, without recording it.
+    Off,
+}
+
+impl TransportConfig {
+    /// The settings as `key=value` git configuration, applied in memory to gix and passed to git with `-c`.
+    pub fn git_config(&self) -> Vec<String> {
+        [
+            ("core.sshCommand", self.ssh_command()),
+            ("http.sslCAInfo", self.ca_bundle.as_deref()),
+            ("http.sslCert", self.client_cert.as_deref()),
+            ("http.sslKey", self.client_key.as_deref()),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| Some(format!("{key}={}", value?)))
+        .collect()
+    }
+
+    /// The ssh command enforcing the host key policy, if one is set.
+    pub fn ssh_command(&self) -> Option<&'static str> {
+        self.ssh_host_keys.map(|policy| match policy {
+            HostKeyPolicy::Strict => "ssh -o StrictHostKeyChecking=yes",
+            HostKeyPolicy::AcceptNew => "ssh -o StrictHostKeyChecking=accept-new",
+            HostKeyPolicy::Off => "ssh -o StrictHostKeyChecking=no -o UserKnownHostsFile=/dev/null",
+        })
+    }
+
+    /// Whether any HTTPS certificate setting is made.
+    pub fn has_certificates(&self) -> bool {
+        self.ca_bundle.is_some() || self.client_cert.is_some() || self.client_key.is_some()
+    }
+}
+
+/// Where the workspace's manifest comes from.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct ManifestConfig {
+    /// Manifest repository location.
+    pub url: String,
+
+    /// Manifest branch or revision.
+    pub branch: String,
+
+    /// Location of the manifest file on disk.
+    pub path: String,
+}
+
+#[derive(Debug, Error, Diagnostic)]
+#[diagnostic(code(repox::config))]
+pub enum ConfigError {
+    #[error("Could not read the workspace configuration")]
+    ReadError(#[source] std::io::Error),
+
+    #[error("Could not write the workspace configuration")]
+    WriteError(#[source] std::io::Error),
+
+    #[error(transparent)]
+    TomlDeserializationError(#[from] toml::de::Error),
+
+    #[error(transparent)]
+    TomlSerializationError(#[from] toml::ser::Error),
+}
+
+impl WorkspaceConfig {
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let contents = fs::read_to_string(path).map_err(ConfigError::ReadError)?;
+
+        Ok(toml::from_str(&contents)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), ConfigError> {
+        let contents = toml::to_string_pretty(self)?;
+
+        fs::write(path, contents).map_err(ConfigError::WriteError)
+    }
+
+    /// The URL to fetch from instead of `url`: a relative URL such as `..` is resolved against the manifest
+    /// URL, the longest matching `url-rewrites` prefix is replaced, then schemes git cannot fetch from are
+    /// mapped to https.
+    pub fn rewrite_url(&self, url: &str) -> String {
+        let url = resolve_fetch_url(url, &self.manifest.url);
+        let rewritten = self
+            .url_rewrites
+            .iter()
+            .filter(|(prefix, _)| url.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(prefix, replacement)| format!("{replacement}{}", &url[prefix.len()..]))
+            .unwrap_or_else(|| url.to_string());
+
+        map_scheme(&rewritten).unwrap_or(rewritten)
+    }
+
+    /// The URLs this workspace fetches `project` from and pushes it to over `remote`, URL rewrites
+    /// and followed redirects applied.
+    pub fn project_urls(&self, remote: &Remote, project: &Project) -> ProjectUrls {
+        let mut urls = ProjectUrls::resolve(remote, &project.name, |url| self.rewrite_url(url));
+        if let Some(moved) = self.redirects.get(&urls.fetch) {
+            if urls.push == urls.fetch {
+                urls.push = moved.clone();
+            }
+            urls.fetch = moved.clone();
+        }
+        urls
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FetchConfig, HostKeyPolicy, NegotiationAlgorithm, TransportConfig};
+
+    #[test]
+    fn test_transport_git_config() {
+        assert!(TransportConfig::default().git_config().is_empty());
+
+        let transport: TransportConfig =
+            toml::from_str("ssh-host-keys = \"accept-new\"\nca-bundle = \"/etc/ssl/corp.pem\"")
+                .unwrap();
+        assert_eq!(transport.ssh_host_keys, Some(HostKeyPolicy::AcceptNew));
+        assert_eq!(
+            transport.git_config(),
+            vec![
+                "core.sshCommand=ssh -o StrictHostKeyChecking=accept-new",
+                "http.sslCAInfo=/etc/ssl/corp.pem",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fetch_git_config() {
+        assert!(FetchConfig::default().git_config().is_empty());
+
+        let fetch: FetchConfig = toml::from_str(
+            "negotiation-algorithm = \"skipping\"\ncommit-graph = false\npack-threads = 4",
+        )
+        .unwrap();
+        assert_eq!(
+            fetch.negotiation_algorithm,
+            Some(NegotiationAlgorithm::Skipping)
+        );
+        assert_eq!(
+            fetch.git_config(),
+            vec![
+                "fetch.negotiationAlgorithm=skipping",
+                "core.commitGraph=false",
+                "pack.threads=4",
+            ]
+        );
+    }
+}
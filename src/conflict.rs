@@ -0,0 +1,81 @@
+use serde::Serialize;
+use std::fmt;
+
+/// A project an operation stopped in, leaving conflicting files for the user to resolve.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Conflict {
+    /// The project's path, relative to the top of the repo client.
+    pub path: String,
+
+    /// What conflicted, e.g. `restore stashed changes`.
+    pub operation: String,
+
+    /// The conflicting files, relative to the project.
+    pub files: Vec<String>,
+
+    /// How to resolve the conflict.
+    pub hint: String,
+
+    /// The command finishing the operation once the files are resolved, run from the project.
+    pub continue_with: String,
+}
+
+/// The conflicts of an operation spanning several projects, reported together once every project was handled
+/// instead of interleaved with the operation's output.
+#[derive(Debug, Default, Serialize)]
+pub struct ConflictReport {
+    conflicts: Vec<Conflict>,
+}
+
+impl ConflictReport {
+    pub fn new(mut conflicts: Vec<Conflict>) -> Self {
+        conflicts.sort_by(|a, b| a.path.cmp(&b.path));
+        Self { conflicts }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.conflicts.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.conflicts.len()
+    }
+
+    pub fn conflicts(&self) -> &[Conflict] {
+        &self.conflicts
+    }
+
+    /// Prints the report to stdout, as JSON for tooling or as text for humans.
+    pub fn print(&self, json: bool) -> Result<(), serde_json::Error> {
+        if json {
+            println!("{}", serde_json::to_string_pretty(self)?);
+        } else {
+            print!("{self}");
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for ConflictReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for conflict in &self.conflicts {
+            writeln!(
+                f,
+                "project {}/: conflicts while {}",
+                conflict.path, conflict.operation
+            )?;
+            for file in &conflict.files {
+                writeln!(f, "  conflict: {file}")?;
+            }
+            writeln!(f, "  hint: {}", conflict.hint)?;
+            writeln!(
+                f,
+                "  then: (cd {} && {})",
+                conflict.path, conflict.continue_with
+            )?;
+        }
+
+        Ok(())
+    }
+}
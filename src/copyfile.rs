@@ -0,0 +1,327 @@
+use crate::{
+    git::{add_excludes, toplevel, GitError},
+    warnings::{warn, WarningKind},
+    workspace::Workspace,
+};
+use miette::Diagnostic;
+use repox_manifest::{error::ManifestError, project::Project, Manifest};
+use std::{
+    collections::BTreeMap,
+    fs::{self, create_dir_all},
+    io,
+    path::{Component, Path, PathBuf},
+};
+use thiserror::Error;
+use tracing::debug;
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum CopyfileError {
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    ManifestError(#[from] ManifestError),
+
+    #[error("Project {project} {element} path {path} goes through the symlink {symlink}")]
+    #[diagnostic(
+        code(repox::copyfile::symlinked_path),
+        help("symlinks can lead out of the workspace, copyfile and linkfile paths must not go through them")
+    )]
+    SymlinkedPath {
+        project: String,
+        element: &'static str,
+        path: String,
+        symlink: String,
+    },
+
+    #[error("Could not copy {src} to {dest}")]
+    #[diagnostic(code(repox::copyfile::copy))]
+    CopyError {
+        src: String,
+        dest: String,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("Could not link {dest} to {src}")]
+    #[diagnostic(code(repox::copyfile::link))]
+    LinkError {
+        src: String,
+        dest: String,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    GitError(#[from] GitError),
+}
+
+/// Copies and links the files the copyfile and linkfile elements of `projects` name into the workspace.
+///
+/// Each file is then excluded from the repository it lands in, the checkout of another project or a
+/// repository the workspace itself is in, so it is not reported as untracked. Where the filesystem has no
+/// symlinks, as on some network shares, linked files are copied instead.
+pub fn install_files(
+    workspace: &Workspace,
+    manifest: &Manifest,
+    projects: &[Project],
+) -> Result<(), CopyfileError> {
+    let mut installed = Vec::new();
+    let mut symlinks = None;
+    for project in projects {
+        let path = project.checkout_path();
+        if !workspace.project_dir(path).exists() {
+            continue;
+        }
+
+        let project_dir = workspace.project_dir(path);
+        for copyfile in project.copyfiles() {
+            copyfile.check_paths(&project.name)?;
+            // Copying from a symlink would copy whatever it points to.
+            check_symlinks(&project_dir, project, "copyfile src", copyfile.src(), true)?;
+            check_symlinks(
+                workspace.root(),
+                project,
+                "copyfile dest",
+                copyfile.dest(),
+                false,
+            )?;
+            copy_file(workspace, path, copyfile.src(), copyfile.dest())?;
+            installed.push(copyfile.dest());
+        }
+        for linkfile in project.linkfiles() {
+            linkfile.check_paths(&project.name)?;
+            check_symlinks(&project_dir, project, "linkfile src", linkfile.src(), false)?;
+            check_symlinks(
+                workspace.root(),
+                project,
+                "linkfile dest",
+                linkfile.dest(),
+                false,
+            )?;
+            if *symlinks.get_or_insert_with(|| workspace.filesystem().symlinks) {
+                link_file(workspace, path, linkfile.src(), linkfile.dest())?;
+            } else {
+                warn(
+                    WarningKind::LimitedFilesystem,
+                    format!(
+                        "The filesystem of {} does not support symlinks, linkfile elements are copied instead and only updated by sync",
+                        workspace.root().display()
+                    ),
+                );
+                copy_linked(workspace, path, linkfile.src(), linkfile.dest())?;
+            }
+            installed.push(linkfile.dest());
+        }
+    }
+
+    let mut excludes: BTreeMap<PathBuf, Vec<String>> = BTreeMap::new();
+    for dest in installed {
+        if let Some((repo, relative)) = enclosing_repository(workspace, manifest, dest) {
+            excludes
+                .entry(repo)
+                .or_default()
+                .push(exclude_pattern(&relative));
+        }
+    }
+    for (repo, patterns) in excludes {
+        debug!("Excluding {patterns:?} in {}", repo.display());
+        add_excludes(&repo, &patterns)?;
+    }
+
+    Ok(())
+}
+
+/// Rejects `path`, relative to `base`, when one of the directories on its way is a symlink, which could
+/// lead out of the workspace however the path is written. The last component is only checked with `last`,
+/// as the files installed at a destination replace whatever is there.
+fn check_symlinks(
+    base: &Path,
+    project: &Project,
+    element: &'static str,
+    path: &str,
+    last: bool,
+) -> Result<(), CopyfileError> {
+    let components: Vec<_> = Path::new(path).components().collect();
+    let checked = if last {
+        components.len()
+    } else {
+        components.len().saturating_sub(1)
+    };
+
+    let mut current = PathBuf::new();
+    for component in &components[..checked] {
+        current.push(component);
+        if base.join(&current).is_symlink() {
+            return Err(CopyfileError::SymlinkedPath {
+                project: project.name.clone(),
+                element,
+                path: path.to_string(),
+                symlink: current.display().to_string(),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Copies `src` of the project at `path` to `dest`, leaving a copy with the same contents alone.
+fn copy_file(
+    workspace: &Workspace,
+    path: &str,
+    src: &str,
+    dest: &str,
+) -> Result<(), CopyfileError> {
+    let from = workspace.project_dir(path).join(src);
+    let to = workspace.project_dir(dest);
+    let copy_error = |source| CopyfileError::CopyError {
+        src: format!("{path}/{src}"),
+        dest: dest.to_string(),
+        source,
+    };
+
+    let contents = fs::read(&from).map_err(copy_error)?;
+    if !to.is_symlink() && fs::read(&to).is_ok_and(|existing| existing == contents) {
+        return Ok(());
+    }
+
+    if let Some(parent) = to.parent() {
+        create_dir_all(parent).map_err(copy_error)?;
+    }
+    // The previous copy may be read-only, or a symlink into a project that fs::copy would write through.
+    if to.symlink_metadata().is_ok() {
+        fs::remove_file(&to).map_err(copy_error)?;
+    }
+    fs::copy(&from, &to).map(|_| ()).map_err(copy_error)
+}
+
+/// Copies `src` of the project at `path`, a file or a directory, to `dest` in place of a link to it.
+fn copy_linked(
+    workspace: &Workspace,
+    path: &str,
+    src: &str,
+    dest: &str,
+) -> Result<(), CopyfileError> {
+    let from = workspace.project_dir(path).join(src);
+    if !from.is_dir() {
+        return copy_file(workspace, path, src, dest);
+    }
+
+    let to = workspace.project_dir(dest);
+    if to.is_symlink() {
+        fs::remove_file(&to)
+    } else {
+        Ok(())
+    }
+    .and_then(|()| copy_dir(&from, &to))
+    .map_err(|source| CopyfileError::CopyError {
+        src: format!("{path}/{src}"),
+        dest: dest.to_string(),
+        source,
+    })
+}
+
+/// Copies the directory `from` to `to` with everything in it, merging it into what `to` already holds.
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), target)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Points `dest` at `src` of the project at `path`, with a relative link so the workspace can be moved.
+fn link_file(
+    workspace: &Workspace,
+    path: &str,
+    src: &str,
+    dest: &str,
+) -> Result<(), CopyfileError> {
+    let to = workspace.project_dir(dest);
+    let link_error = |source| CopyfileError::LinkError {
+        src: format!("{path}/{src}"),
+        dest: dest.to_string(),
+        source,
+    };
+
+    let depth = Path::new(dest)
+        .components()
+        .filter(|component| matches!(component, Component::Normal(_)))
+        .count()
+        .saturating_sub(1);
+    let mut target: PathBuf = std::iter::repeat_n("..", depth).collect();
+    target.push(path);
+    target.push(src);
+
+    if fs::read_link(&to).is_ok_and(|existing| existing == target) {
+        return Ok(());
+    }
+
+    if let Some(parent) = to.parent() {
+        create_dir_all(parent).map_err(link_error)?;
+    }
+    if to.symlink_metadata().is_ok() {
+        fs::remove_file(&to).map_err(link_error)?;
+    }
+    symlink(&target, &to, workspace.project_dir(path).join(src).is_dir()).map_err(link_error)
+}
+
+#[cfg(unix)]
+fn symlink(target: &Path, link: &Path, _dir: bool) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+fn symlink(target: &Path, link: &Path, dir: bool) -> io::Result<()> {
+    if dir {
+        std::os::windows::fs::symlink_dir(target, link)
+    } else {
+        std::os::windows::fs::symlink_file(target, link)
+    }
+}
+
+/// The repository `dest` lands in, with `dest` relative to its top: the checkout of the innermost project
+/// containing it, or else the repository the workspace is in, if any.
+fn enclosing_repository(
+    workspace: &Workspace,
+    manifest: &Manifest,
+    dest: &str,
+) -> Option<(PathBuf, PathBuf)> {
+    let dest = Path::new(dest);
+    let projects = manifest.projects();
+    let project = projects
+        .iter()
+        .map(|project| Path::new(project.checkout_path()))
+        .filter(|path| dest.starts_with(path) && dest != *path)
+        .filter(|path| workspace.project_dir(&path.to_string_lossy()).exists())
+        .max_by_key(|path| path.components().count());
+    if let Some(path) = project {
+        let relative = dest.strip_prefix(path).ok()?.to_path_buf();
+        return Some((workspace.project_dir(&path.to_string_lossy()), relative));
+    }
+
+    let root = workspace.root().canonicalize().ok()?;
+    let top = toplevel(&root)?;
+    let relative = root.join(dest).strip_prefix(&top).ok()?.to_path_buf();
+    Some((top, relative))
+}
+
+/// An exclude pattern matching exactly `path`, relative to the top of its repository.
+fn exclude_pattern(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut pattern = String::from("/");
+    for character in path.chars() {
+        if matches!(character, '*' | '?' | '[' | '\\') {
+            pattern.push('\\');
+        }
+        pattern.push(character);
+    }
+
+    pattern
+}
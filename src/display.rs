@@ -0,0 +1,109 @@
+use std::fmt;
+
+/// How many hex digits of a commit id human output shows, as git abbreviates them by default.
+///
+/// Output meant to be parsed, like `--json` reports and frozen manifests, keeps full commit ids instead.
+pub const SHORT_SHA_LEN: usize = 7;
+
+/// The narrowest the first column of a listing is padded to, as repo lays out `repo status`.
+pub const MIN_COLUMN_WIDTH: usize = 40;
+
+/// Abbreviates `revision` when it is a full commit id, leaving branch names, tags and short ids as they are.
+pub fn short_sha(revision: &str) -> &str {
+    let full =
+        matches!(revision.len(), 40 | 64) && revision.bytes().all(|byte| byte.is_ascii_hexdigit());
+    if full {
+        &revision[..SHORT_SHA_LEN]
+    } else {
+        revision
+    }
+}
+
+/// A branch of a remote, shown as `branch@remote`, the way repo names the branch a project tracks.
+#[derive(Debug, Clone, Copy)]
+pub struct RemoteBranch<'a> {
+    pub branch: &'a str,
+    pub remote: &'a str,
+}
+
+impl fmt::Display for RemoteBranch<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let branch = self
+            .branch
+            .strip_prefix("refs/heads/")
+            .unwrap_or(self.branch);
+        write!(f, "{}@{}", short_sha(branch), self.remote)
+    }
+}
+
+/// The width lining up a column holding `cells`: the widest of them, and at least `min`.
+pub fn column_width<'a>(cells: impl IntoIterator<Item = &'a str>, min: usize) -> usize {
+    cells
+        .into_iter()
+        .map(|cell| cell.chars().count())
+        .max()
+        .unwrap_or_default()
+        .max(min)
+}
+
+/// Label and value pairs, printed with the values lined up after the longest label.
+#[derive(Debug, Default)]
+pub struct Fields {
+    rows: Vec<(String, String)>,
+}
+
+impl Fields {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, label: impl Into<String>, value: impl fmt::Display) {
+        self.rows.push((label.into(), value.to_string()));
+    }
+}
+
+impl fmt::Display for Fields {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = column_width(self.rows.iter().map(|(label, _)| label.as_str()), 0) + 1;
+        for (label, value) in &self.rows {
+            writeln!(f, "{:<width$} {value}", format!("{label}:"))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{column_width, short_sha, Fields, RemoteBranch};
+
+    #[test]
+    fn test_display_helpers() {
+        let sha = "04baa8a9f1a0e0c7f8b3a6d2c1e5f4b3a2c1d0e9";
+        assert_eq!(short_sha(sha), "04baa8a");
+        assert_eq!(short_sha("main"), "main");
+        assert_eq!(short_sha("deadbeef"), "deadbeef");
+
+        let tracked = RemoteBranch {
+            branch: "refs/heads/main",
+            remote: "origin",
+        };
+        assert_eq!(tracked.to_string(), "main@origin");
+        let pinned = RemoteBranch {
+            branch: sha,
+            remote: "aosp",
+        };
+        assert_eq!(pinned.to_string(), "04baa8a@aosp");
+
+        assert_eq!(column_width(["app", "platform/build"], 4), 14);
+        assert_eq!(column_width(["app"], 40), 40);
+
+        let mut fields = Fields::new();
+        fields.push("Project", "app");
+        fields.push("Mount path", "/src/app");
+        assert_eq!(
+            fields.to_string(),
+            "Project:    app\nMount path: /src/app\n"
+        );
+    }
+}
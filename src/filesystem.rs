@@ -0,0 +1,112 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process,
+};
+
+/// What the filesystem of a directory supports, among what network filesystems such as NFS and SMB mounts
+/// often do not.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FilesystemSupport {
+    /// Whether symlinks can be created.
+    pub symlinks: bool,
+
+    /// Whether a file can be renamed over another, replacing it in one step.
+    pub rename_over: bool,
+}
+
+/// A file of `dir` only the running process uses, to try things out with.
+fn probe_path(dir: &Path, name: &str) -> PathBuf {
+    dir.join(format!(".repox-probe-{}-{name}", process::id()))
+}
+
+/// Whether files can be written in `dir`, with the error of writing one when they cannot, e.g. because the
+/// filesystem is a read-only snapshot.
+pub fn check_writable(dir: &Path) -> io::Result<()> {
+    let probe = probe_path(dir, "write");
+    fs::write(&probe, "")?;
+    fs::remove_file(&probe)
+}
+
+/// Tries out what the filesystem of `dir` supports, in files of its own that are removed afterwards.
+pub fn probe(dir: &Path) -> FilesystemSupport {
+    let file = probe_path(dir, "file");
+    let other = probe_path(dir, "other");
+    let link = probe_path(dir, "link");
+    if fs::write(&file, "file").is_err() {
+        return FilesystemSupport {
+            symlinks: false,
+            rename_over: false,
+        };
+    }
+
+    let symlinks = symlink_file(Path::new(file.file_name().unwrap_or_default()), &link).is_ok()
+        && fs::read(&link).is_ok_and(|contents| contents == b"file");
+    let rename_over = fs::write(&other, "other").is_ok()
+        && fs::rename(&other, &file).is_ok()
+        && fs::read(&file).is_ok_and(|contents| contents == b"other");
+
+    for path in [&file, &other, &link] {
+        let _ = fs::remove_file(path);
+    }
+    FilesystemSupport {
+        symlinks,
+        rename_over,
+    }
+}
+
+#[cfg(unix)]
+fn symlink_file(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+fn symlink_file(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::windows::fs::symlink_file(target, link)
+}
+
+/// Moves the file `from` to `to`, replacing whatever is there.
+///
+/// A rename replaces `to` in one step where the filesystem can, but some network filesystems refuse to
+/// rename over an existing file, or to rename at all, in which case `to` is removed first or `from` is
+/// copied over.
+pub fn replace_file(from: &Path, to: &Path) -> io::Result<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    if to.symlink_metadata().is_ok() {
+        fs::remove_file(to)?;
+        if fs::rename(from, to).is_ok() {
+            return Ok(());
+        }
+    }
+
+    fs::copy(from, to)?;
+    fs::remove_file(from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_writable, probe, replace_file};
+    use std::fs::{read_dir, read_to_string, write};
+
+    #[test]
+    fn test_probe() {
+        let dir = tempfile::tempdir().unwrap();
+        check_writable(dir.path()).unwrap();
+        let support = probe(dir.path());
+        assert!(support.rename_over);
+        assert!(support.symlinks || cfg!(windows));
+        assert_eq!(read_dir(dir.path()).unwrap().count(), 0);
+
+        assert!(check_writable(&dir.path().join("missing")).is_err());
+        assert!(!probe(&dir.path().join("missing")).symlinks);
+
+        let (from, to) = (dir.path().join("from"), dir.path().join("to"));
+        write(&from, "new").unwrap();
+        write(&to, "old").unwrap();
+        replace_file(&from, &to).unwrap();
+        assert_eq!(read_to_string(&to).unwrap(), "new");
+        assert!(!from.exists());
+    }
+}
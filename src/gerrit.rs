@@ -0,0 +1,134 @@
+use crate::{
+    config::TransportConfig,
+    http::{client, HttpError},
+};
+use miette::Diagnostic;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Gerrit prefixes its JSON responses with this line to defeat cross-site script inclusion.
+const XSSI_PREFIX: &str = ")]}'";
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum GerritError {
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    HttpError(#[from] HttpError),
+
+    #[error("Could not query {url}")]
+    #[diagnostic(code(repox::gerrit::request))]
+    RequestError {
+        url: String,
+        #[source]
+        source: reqwest::Error,
+    },
+
+    #[error("{url} answered {status}")]
+    #[diagnostic(
+        code(repox::gerrit::status),
+        help("queries like owner:self need credentials, set auth.<remote>.token-env or cookie-file in .repo/repox.toml")
+    )]
+    StatusError { url: String, status: u16 },
+
+    #[error("{url} did not answer with a list of changes")]
+    #[diagnostic(code(repox::gerrit::response))]
+    ResponseError {
+        url: String,
+        #[source]
+        source: serde_json::Error,
+    },
+}
+
+/// A change as listed by Gerrit's `/changes/` endpoint, with the fields repox uses.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ChangeInfo {
+    pub project: String,
+    pub branch: String,
+    #[serde(rename = "_number")]
+    pub number: u64,
+    pub subject: String,
+    pub status: String,
+    #[serde(default)]
+    pub topic: Option<String>,
+}
+
+/// The base URL of a review server as written in a remote's `review` attribute, which may leave out the scheme.
+pub fn review_url(review: &str) -> String {
+    let review = review.trim_end_matches('/');
+    if review.contains("://") {
+        review.to_string()
+    } else {
+        format!("https://{review}")
+    }
+}
+
+/// Parses a `/changes/` response body.
+fn parse_changes(body: &str) -> Result<Vec<ChangeInfo>, serde_json::Error> {
+    serde_json::from_str(body.trim_start().trim_start_matches(XSSI_PREFIX))
+}
+
+/// Runs `query` against the review server at `review`, sending `headers` (`Name: value` lines) along, through
+/// the proxy and certificates of `transport`.
+///
+/// Authenticated requests go through Gerrit's `/a/` prefix, which is where queries about the
+/// current user are answered.
+pub fn query_changes(
+    review: &str,
+    query: &str,
+    headers: &[String],
+    transport: &TransportConfig,
+) -> Result<Vec<ChangeInfo>, GerritError> {
+    let prefix = if headers.is_empty() { "" } else { "/a" };
+    let url = format!("{}{prefix}/changes/", review_url(review));
+    let request_error = |source| GerritError::RequestError {
+        url: url.clone(),
+        source,
+    };
+
+    let request = headers
+        .iter()
+        .filter_map(|header| header.split_once(':'))
+        .fold(
+            client(transport)?.get(&url).query(&[("q", query)]),
+            |request, (name, value)| request.header(name.trim(), value.trim()),
+        );
+    let response = request.send().map_err(request_error)?;
+    if !response.status().is_success() {
+        return Err(GerritError::StatusError {
+            url,
+            status: response.status().as_u16(),
+        });
+    }
+
+    let body = response.text().map_err(request_error)?;
+    parse_changes(&body).map_err(|source| GerritError::ResponseError { url, source })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_changes, review_url, ChangeInfo};
+
+    #[test]
+    fn test_parse_changes() {
+        let body = ")]}'\n[{\"id\":\"app~main~I8473b95934b5732ac55d26311a706c9c2bde9940\",\
+                    \"project\":\"platform/app\",\"branch\":\"main\",\"_number\":4247,\
+                    \"subject\":\"Fix the build\",\"status\":\"NEW\",\"topic\":\"build\"}]\n";
+
+        assert_eq!(
+            parse_changes(body).unwrap(),
+            vec![ChangeInfo {
+                project: "platform/app".to_string(),
+                branch: "main".to_string(),
+                number: 4247,
+                subject: "Fix the build".to_string(),
+                status: "NEW".to_string(),
+                topic: Some("build".to_string()),
+            }]
+        );
+        assert_eq!(
+            review_url("review.example.com/"),
+            "https://review.example.com"
+        );
+        assert_eq!(review_url("http://localhost:8080"), "http://localhost:8080");
+    }
+}
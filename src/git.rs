@@ -0,0 +1,1659 @@
+use crate::{auth::AuthError, conflict::Conflict, progress::TracingProgress};
+use gix::{
+    bstr::BStr,
+    refs::{
+        transaction::{Change, LogChange, PreviousValue, RefEdit, RefLog},
+        FullName, Target,
+    },
+    remote::fetch::Shallow,
+    ObjectId,
+};
+use miette::Diagnostic;
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::OsStr,
+    num::NonZeroU32,
+    path::{Path, PathBuf},
+    process::Command,
+};
+use thiserror::Error;
+use tracing::{info, info_span, warn};
+
+#[derive(Debug, Error, Diagnostic)]
+#[diagnostic(code(repox::git))]
+pub enum GitError {
+    #[error("An error occurred while creating a destination directory")]
+    CreateDirectoryError(#[source] std::io::Error),
+
+    #[error(transparent)]
+    GixUrlParseError(Box<gix::url::parse::Error>),
+
+    #[error(transparent)]
+    GixOpenError(Box<gix::open::Error>),
+
+    #[error(transparent)]
+    GixCloneError(Box<gix::clone::Error>),
+
+    #[error(transparent)]
+    GixRemoteNameError(Box<gix::remote::name::Error>),
+
+    #[error(transparent)]
+    GixCloneFetchError(Box<gix::clone::fetch::Error>),
+
+    #[error(transparent)]
+    GixCheckoutError(Box<gix::clone::checkout::main_worktree::Error>),
+
+    #[error(transparent)]
+    GixRemoteError(Box<gix::remote::find::existing::Error>),
+
+    #[error(transparent)]
+    GixConnectError(Box<gix::remote::connect::Error>),
+
+    #[error(transparent)]
+    GixPrepareFetchError(Box<gix::remote::fetch::prepare::Error>),
+
+    #[error(transparent)]
+    GixFetchError(Box<gix::remote::fetch::Error>),
+
+    #[error("Could not resolve revision {revision}")]
+    RevisionNotFound { revision: String },
+
+    #[error("{path} has uncommitted changes that would be overwritten")]
+    #[diagnostic(help("commit or stash the changes, pass --auto-stash to stash and restore them automatically, or pass --force-checkout to discard them")
+    )]
+    DirtyWorktree { path: String },
+
+    #[error("Could not {action} uncommitted changes in {path}: {message}")]
+    StashError {
+        action: &'static str,
+        path: String,
+        message: String,
+    },
+
+    #[error("{} left conflicts in {}", .0.operation, .0.path)]
+    Conflict(Conflict),
+
+    #[error("Could not push {refspec} from {path}: {message}")]
+    PushError {
+        path: String,
+        refspec: String,
+        message: String,
+    },
+
+    #[error("Could not start {branch} in {path}: {message}")]
+    StartError {
+        path: String,
+        branch: String,
+        message: String,
+    },
+
+    #[error("Could not make {branch} in {path} track {remote_name}: {message}")]
+    TrackError {
+        path: String,
+        branch: String,
+        remote_name: String,
+        message: String,
+    },
+
+    #[error("Could not point the remote of {path} to {url}: {message}")]
+    RemoteUrlError {
+        path: String,
+        url: String,
+        message: String,
+    },
+
+    #[error("Could not fetch the superproject from {url}: {message}")]
+    SuperprojectError { url: String, message: String },
+
+    #[error("Could not list the files {path} would upload: {message}")]
+    OutgoingFilesError { path: String, message: String },
+
+    #[error("Could not seed {path} from {seed}: {message}")]
+    SeedError {
+        path: String,
+        seed: String,
+        message: String,
+    },
+
+    #[error("Refusing to check out {entry} in {path}: it {reason}")]
+    #[diagnostic(help(
+        "see git's core.protectNTFS and core.protectHFS settings, and repox's protect-windows"
+    ))]
+    ProtectedPath {
+        path: String,
+        entry: String,
+        reason: &'static str,
+    },
+
+    #[error("Could not read the status of {path}: {message}")]
+    StatusError { path: String, message: String },
+
+    #[error("Could not commit {path}: {message}")]
+    CommitError { path: String, message: String },
+
+    #[error("Could not list the branches of {path}: {message}")]
+    BranchesError { path: String, message: String },
+
+    #[error("Could not add a worktree of {path} at {dst}: {message}")]
+    WorktreeAddError {
+        path: String,
+        dst: String,
+        message: String,
+    },
+
+    #[error("Could not update the excludes of {path}: {message}")]
+    ExcludeError { path: String, message: String },
+
+    #[error("{path} is a bare repository and has no working tree")]
+    BareRepository { path: String },
+
+    #[error("An error occurred while updating the working tree")]
+    WorktreeIoError(#[source] std::io::Error),
+
+    #[error(transparent)]
+    GixFindObjectError(Box<gix::object::find::existing::Error>),
+
+    #[error(transparent)]
+    GixPeelError(Box<gix::object::peel::to_kind::Error>),
+
+    #[error(transparent)]
+    GixIndexFromTreeError(Box<gix::traverse::tree::breadthfirst::Error>),
+
+    #[error(transparent)]
+    GixOpenIndexError(Box<gix::worktree::open_index::Error>),
+
+    #[error(transparent)]
+    GixWorktreeCheckoutError(Box<gix::worktree::state::checkout::Error>),
+
+    #[error(transparent)]
+    GixIndexWriteError(Box<gix::index::file::write::Error>),
+
+    #[error(transparent)]
+    GixReferenceNameError(Box<gix::refs::name::Error>),
+
+    #[error(transparent)]
+    GixReferenceEditError(Box<gix::reference::edit::Error>),
+
+    #[error(transparent)]
+    GixReferenceFindError(Box<gix::reference::find::existing::Error>),
+
+    #[error(transparent)]
+    GixIsDirtyError(Box<gix::status::is_dirty::Error>),
+
+    #[error("Could not set up the credentials of the remote")]
+    AuthError(
+        #[from]
+        #[diagnostic_source]
+        AuthError,
+    ),
+
+    #[error(transparent)]
+    GixConfigOverrideError(Box<gix::config::overrides::Error>),
+
+    #[error(transparent)]
+    GixConfigError(Box<gix::config::Error>),
+
+    #[error(transparent)]
+    GixRefspecError(Box<gix::refspec::parse::Error>),
+}
+
+/// Implements `From<$source>` for [`GitError`] by boxing the (often large) gix error.
+macro_rules! boxed_from {
+    ($($source:ty => $variant:ident),* $(,)?) => {
+        $(
+            impl From<$source> for GitError {
+                fn from(error: $source) -> Self {
+                    GitError::$variant(Box::new(error))
+                }
+            }
+        )*
+    };
+}
+
+boxed_from! {
+    gix::url::parse::Error => GixUrlParseError,
+    gix::open::Error => GixOpenError,
+    gix::clone::Error => GixCloneError,
+    gix::remote::name::Error => GixRemoteNameError,
+    gix::clone::fetch::Error => GixCloneFetchError,
+    gix::clone::checkout::main_worktree::Error => GixCheckoutError,
+    gix::remote::find::existing::Error => GixRemoteError,
+    gix::remote::connect::Error => GixConnectError,
+    gix::remote::fetch::prepare::Error => GixPrepareFetchError,
+    gix::remote::fetch::Error => GixFetchError,
+    gix::object::find::existing::Error => GixFindObjectError,
+    gix::object::peel::to_kind::Error => GixPeelError,
+    gix::traverse::tree::breadthfirst::Error => GixIndexFromTreeError,
+    gix::worktree::open_index::Error => GixOpenIndexError,
+    gix::worktree::state::checkout::Error => GixWorktreeCheckoutError,
+    gix::index::file::write::Error => GixIndexWriteError,
+    gix::refs::name::Error => GixReferenceNameError,
+    gix::reference::edit::Error => GixReferenceEditError,
+    gix::reference::find::existing::Error => GixReferenceFindError,
+    gix::status::is_dirty::Error => GixIsDirtyError,
+    gix::refspec::parse::Error => GixRefspecError,
+    gix::config::overrides::Error => GixConfigOverrideError,
+    gix::config::Error => GixConfigError,
+}
+
+/// Clones `url` into `dst`, naming the remote `remote_name`.
+///
+/// Mirrors are cloned bare and never checked out. `config_overrides` are `key=value` settings applied in memory.
+pub fn clone_project(
+    url: &str,
+    dst: &Path,
+    remote_name: &str,
+    mirror: bool,
+    refspecs: &Refspecs,
+    shallow: Shallow,
+    config_overrides: &[String],
+) -> Result<gix::Repository, GitError> {
+    std::fs::create_dir_all(dst).map_err(GitError::CreateDirectoryError)?;
+    info!("Destination Created: {}", dst.display());
+
+    let url = gix::url::parse(url.into())?;
+    info!("Url: {}", url.to_bstring());
+
+    if mirror {
+        let _mirror_span =
+            info_span!("Mirroring", url = %url.to_bstring(), dst = %dst.display()).entered();
+        let (repo, _) = with_refspecs(gix::prepare_clone_bare(url, dst)?, refspecs)?
+            .with_shallow(shallow)
+            .with_in_memory_config_overrides(config_overrides.iter().map(String::as_str))
+            .with_remote_name(remote_name)?
+            .fetch_only(
+                TracingProgress::new("mirror"),
+                &gix::interrupt::IS_INTERRUPTED,
+            )?;
+
+        return Ok(repo);
+    }
+
+    let mut prepare_clone = with_refspecs(gix::prepare_clone(url, dst)?, refspecs)?
+        .with_shallow(shallow)
+        .with_in_memory_config_overrides(config_overrides.iter().map(String::as_str))
+        .with_remote_name(remote_name)?;
+
+    let clone_span = info_span!("Cloning", dst = %dst.display()).entered();
+    let (mut prepare_checkout, _) = prepare_clone.fetch_then_checkout(
+        TracingProgress::new("clone"),
+        &gix::interrupt::IS_INTERRUPTED,
+    )?;
+    clone_span.exit();
+
+    let repo = prepare_checkout.repo();
+    if let Some(head) = head_commit(repo) {
+        let tree = repo.find_object(head)?.peel_to_tree()?.id;
+        let index = repo.index_from_tree(&tree)?;
+        PathProtections::of(repo).check(&index, &dst.display().to_string())?;
+    }
+
+    let checkout_span = info_span!(
+        "Checking out worktree",
+        dest = ?prepare_checkout.repo().work_dir().expect("should be there")
+    )
+    .entered();
+
+    let (repo, _) = prepare_checkout.main_worktree(
+        TracingProgress::new("checkout"),
+        &gix::interrupt::IS_INTERRUPTED,
+    )?;
+
+    checkout_span.exit();
+
+    Ok(repo)
+}
+
+/// Configures a clone to fetch `refspecs`.
+fn with_refspecs(
+    prepare_clone: gix::clone::PrepareFetch,
+    refspecs: &Refspecs,
+) -> Result<gix::clone::PrepareFetch, GitError> {
+    let prepare_clone = prepare_clone.with_fetch_options(gix::remote::ref_map::Options {
+        extra_refspecs: parse_refspecs(&refspecs.extra)?,
+        ..Default::default()
+    });
+    if refspecs.configured.is_empty() {
+        return Ok(prepare_clone);
+    }
+
+    let configured = refspecs.configured.clone();
+    Ok(prepare_clone.configure_remote(move |mut remote| {
+        remote.replace_refspecs(
+            configured.iter().map(String::as_str),
+            gix::remote::Direction::Fetch,
+        )?;
+        Ok(remote)
+    }))
+}
+
+fn parse_refspecs(refspecs: &[String]) -> Result<Vec<gix::refspec::RefSpec>, GitError> {
+    refspecs
+        .iter()
+        .map(|refspec| {
+            Ok(gix::refspec::parse(
+                refspec.as_str().into(),
+                gix::refspec::parse::Operation::Fetch,
+            )?
+            .to_owned())
+        })
+        .collect()
+}
+
+/// Applies `key=value` settings to an open repository, in memory only.
+pub fn override_config(repo: &mut gix::Repository, overrides: &[String]) -> Result<(), GitError> {
+    if overrides.is_empty() {
+        return Ok(());
+    }
+
+    let mut config = repo.config_snapshot_mut();
+    config.append_config(
+        overrides.iter().map(String::as_str),
+        gix::config::Source::Api,
+    )?;
+    config.commit()?;
+
+    Ok(())
+}
+
+/// In-memory git setting carrying the workspace's `protect-windows` setting to checkouts.
+pub const PROTECT_WINDOWS_KEY: &str = "repox.protectWindows";
+
+/// Windows device names, which cannot be used as file names with any extension.
+const WINDOWS_DEVICES: [&str; 6] = ["CON", "PRN", "AUX", "NUL", "CONIN$", "CONOUT$"];
+
+/// The paths a checkout refuses to write, after git's `core.protectNTFS` and `core.protectHFS`.
+///
+/// Components git would read as the repository's own `.git` directory are always refused.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PathProtections {
+    /// `.git` as NTFS reads it: with trailing dots or spaces, as the `git~1` short name or with a stream suffix.
+    pub ntfs: bool,
+    /// `.git` as HFS+ reads it, with code points it ignores.
+    pub hfs: bool,
+    /// Names Windows reserves or cannot store, and paths that differ only in case.
+    pub windows: bool,
+}
+
+impl PathProtections {
+    /// The protections configured for `repo`, with git's defaults: NTFS protections everywhere, HFS+ ones on
+    /// macOS. Windows protections imply the NTFS ones.
+    pub fn of(repo: &gix::Repository) -> Self {
+        let config = repo.config_snapshot();
+        let windows = config.boolean(PROTECT_WINDOWS_KEY).unwrap_or(false);
+
+        Self {
+            ntfs: windows || config.boolean("core.protectNTFS").unwrap_or(true),
+            hfs: config
+                .boolean("core.protectHFS")
+                .unwrap_or(cfg!(target_os = "macos")),
+            windows,
+        }
+    }
+
+    /// Why the path component `name` must not be written, if it must not.
+    fn violation(&self, name: &str) -> Option<&'static str> {
+        if name.is_empty() || name == "." || name == ".." {
+            return Some("is not a valid path");
+        }
+        if name.eq_ignore_ascii_case(".git") {
+            return Some("would write into the .git directory");
+        }
+
+        if self.ntfs {
+            let trimmed = name.trim_end_matches(['.', ' ']);
+            let lowercase = name.to_ascii_lowercase();
+            if trimmed.eq_ignore_ascii_case(".git")
+                || lowercase == "git~1"
+                || lowercase.starts_with(".git:")
+            {
+                return Some("is the .git directory on NTFS");
+            }
+            if name.contains('\\') {
+                return Some("contains a backslash, a path separator on NTFS");
+            }
+        }
+
+        if self.hfs {
+            // HFS+ ignores zero-width and directional formatting code points in file names.
+            let visible: String = name
+                .chars()
+                .filter(|c| {
+                    !matches!(c, '\u{200c}'..='\u{200f}' | '\u{202a}'..='\u{202e}' | '\u{206a}'..='\u{206f}' | '\u{feff}')
+                })
+                .collect();
+            if visible.eq_ignore_ascii_case(".git") {
+                return Some("is the .git directory on HFS+");
+            }
+        }
+
+        if self.windows {
+            if name
+                .chars()
+                .any(|c| c.is_control() || matches!(c, '<' | '>' | ':' | '"' | '|' | '?' | '*'))
+            {
+                return Some("contains a character Windows does not allow in file names");
+            }
+            if name.ends_with(['.', ' ']) {
+                return Some("ends with a dot or space, which Windows drops");
+            }
+
+            let stem = name.split('.').next().unwrap_or(name).trim_end();
+            let numbered = stem.len() == 4
+                && stem.get(..3).is_some_and(|device| {
+                    device.eq_ignore_ascii_case("COM") || device.eq_ignore_ascii_case("LPT")
+                })
+                && matches!(stem.as_bytes()[3], b'1'..=b'9');
+            if numbered
+                || WINDOWS_DEVICES
+                    .iter()
+                    .any(|device| stem.eq_ignore_ascii_case(device))
+            {
+                return Some("is a device name reserved by Windows");
+            }
+        }
+
+        None
+    }
+
+    /// Checks every path of `index`, about to be checked out into the project at `path`.
+    pub fn check(&self, index: &gix::index::State, path: &str) -> Result<(), GitError> {
+        let refuse = |entry: &str, reason| GitError::ProtectedPath {
+            path: path.to_string(),
+            entry: entry.to_string(),
+            reason,
+        };
+
+        // Every file and directory seen so far, by the name a case-insensitive filesystem knows it as.
+        let mut seen: HashMap<String, String> = HashMap::new();
+        for entry in index.entries() {
+            let entry = entry.path(index).to_string();
+            let mut prefix = 0;
+            for name in entry.split('/') {
+                if let Some(reason) = self.violation(name) {
+                    return Err(refuse(&entry, reason));
+                }
+
+                prefix += name.len();
+                let known = &entry[..prefix];
+                prefix += 1;
+                if self.windows || self.hfs {
+                    let existing = seen
+                        .entry(known.to_lowercase())
+                        .or_insert_with(|| known.to_string());
+                    if existing != known {
+                        return Err(refuse(&entry, "differs only in case from another path"));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Opens the repository of an already checked out project.
+pub fn open_project(dst: &Path) -> Result<gix::Repository, GitError> {
+    Ok(gix::open(dst)?)
+}
+
+/// The fetch URL currently configured for `remote_name`, if the remote exists.
+pub fn remote_url(repo: &gix::Repository, remote_name: &str) -> Option<String> {
+    let remote = repo.find_remote(remote_name).ok()?;
+    let url = remote.url(gix::remote::Direction::Fetch)?;
+
+    Some(url.to_bstring().to_string())
+}
+
+/// How much history a clone asks for: everything, or only the last `depth` commits of each ref when a
+/// depth is given.
+pub fn clone_depth(depth: Option<usize>) -> Shallow {
+    depth
+        .and_then(|depth| u32::try_from(depth).ok())
+        .and_then(NonZeroU32::new)
+        .map_or(Shallow::NoChange, Shallow::DepthAtRemote)
+}
+
+/// What a manifest revision names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Revision<'a> {
+    /// A branch, relative to `refs/heads/` or not.
+    Branch(&'a str),
+    /// A full `refs/tags/...` name.
+    Tag(&'a str),
+    /// A Gerrit patchset, `refs/changes/XX/NNNN/P`.
+    Change(&'a str),
+    /// A full commit id.
+    Commit(&'a str),
+}
+
+impl<'a> Revision<'a> {
+    pub fn classify(revision: &'a str) -> Self {
+        if revision.len() == 40 && revision.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            Revision::Commit(revision)
+        } else if revision.starts_with("refs/tags/") {
+            Revision::Tag(revision)
+        } else if revision.starts_with("refs/changes/") {
+            Revision::Change(revision)
+        } else {
+            Revision::Branch(revision.strip_prefix("refs/heads/").unwrap_or(revision))
+        }
+    }
+
+    /// The refspec fetching exactly this revision, if it is a ref.
+    fn refspec(self, remote_name: &str) -> Option<String> {
+        match self {
+            Revision::Branch(branch) => Some(format!(
+                "+refs/heads/{branch}:refs/remotes/{remote_name}/{branch}"
+            )),
+            Revision::Tag(reference) | Revision::Change(reference) => {
+                Some(format!("+{reference}:{reference}"))
+            }
+            Revision::Commit(_) => None,
+        }
+    }
+}
+
+/// Which refs a fetch asks the remote for.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Refspecs {
+    /// Replaces the remote's configured fetch refspecs, and is saved as such when cloning.
+    pub configured: Vec<String>,
+
+    /// Fetched in addition to the configured refspecs, without being saved.
+    pub extra: Vec<String>,
+}
+
+/// The refspecs needed to sync a project to `revision`.
+///
+/// With `current_branch_only`, as `repo sync -c` does, only the tracked ref is fetched. Gerrit
+/// patchsets are never covered by the remote's refspecs, so they are always fetched explicitly.
+pub fn project_refspecs(
+    remote_name: &str,
+    revision: Option<&str>,
+    upstream: Option<&str>,
+    current_branch_only: bool,
+) -> Refspecs {
+    let configured = if current_branch_only {
+        current_branch_refspecs(remote_name, revision, upstream)
+    } else {
+        Vec::new()
+    };
+    let extra = match revision.map(Revision::classify) {
+        Some(change @ Revision::Change(_)) if configured.is_empty() => {
+            change.refspec(remote_name).into_iter().collect()
+        }
+        _ => Vec::new(),
+    };
+
+    Refspecs { configured, extra }
+}
+
+/// The refspecs fetching only the ref a project tracks, as `repo sync -c` does.
+///
+/// A revision locked to a commit id is fetched through its upstream ref, when the manifest names one.
+/// An empty list means the remote's configured refspecs, i.e. all heads, should be fetched.
+pub fn current_branch_refspecs(
+    remote_name: &str,
+    revision: Option<&str>,
+    upstream: Option<&str>,
+) -> Vec<String> {
+    let tracked = match revision.map(Revision::classify) {
+        Some(Revision::Commit(_)) => upstream.map(Revision::classify),
+        revision => revision,
+    };
+
+    tracked
+        .and_then(|tracked| tracked.refspec(remote_name))
+        .into_iter()
+        .collect()
+}
+
+/// Fetches `remote_name` into an existing repository, using `refspecs` when any are given.
+pub fn fetch_project(
+    repo: &gix::Repository,
+    remote_name: &str,
+    refspecs: &Refspecs,
+    shallow: Shallow,
+) -> Result<(), GitError> {
+    let _fetch_span = info_span!("Fetching", remote = remote_name).entered();
+
+    let mut remote = repo.find_remote(remote_name)?;
+    if !refspecs.configured.is_empty() {
+        remote.replace_refspecs(
+            refspecs.configured.iter().map(String::as_str),
+            gix::remote::Direction::Fetch,
+        )?;
+    }
+
+    // Also list HEAD, to learn which branch the remote currently considers its default.
+    let mut extra_refspecs = parse_refspecs(&refspecs.extra)?;
+    extra_refspecs.extend(parse_refspecs(&["HEAD".to_string()])?);
+
+    let prepare = remote
+        .connect(gix::remote::Direction::Fetch)?
+        .prepare_fetch(
+            TracingProgress::new("negotiate"),
+            gix::remote::ref_map::Options {
+                extra_refspecs,
+                ..Default::default()
+            },
+        )?;
+
+    let remote_refs = &prepare.ref_map().remote_refs;
+    let advertised_default = remote_refs.iter().find_map(|remote_ref| match remote_ref {
+        gix::protocol::handshake::Ref::Symbolic {
+            full_ref_name,
+            target,
+            ..
+        } if full_ref_name == "HEAD" => target
+            .to_string()
+            .strip_prefix("refs/heads/")
+            .map(str::to_string),
+        _ => None,
+    });
+    let old_default = remote_default_branch(repo, remote_name);
+    let old_default_advertised = old_default.as_ref().is_some_and(|old| {
+        remote_refs
+            .iter()
+            .any(|remote_ref| remote_ref.unpack().0 == format!("refs/heads/{old}").as_str())
+    });
+
+    prepare.with_shallow(shallow).receive(
+        TracingProgress::new("fetch"),
+        &gix::interrupt::IS_INTERRUPTED,
+    )?;
+
+    match (advertised_default, old_default) {
+        (Some(new), old) if old.as_ref() != Some(&new) => {
+            if let Some(old) = old {
+                info!("The default branch of {remote_name} changed from {old} to {new}");
+
+                // The old default branch is gone upstream, drop its tracking ref so checkouts of it are retargeted.
+                if !old_default_advertised {
+                    if let Ok(stale) =
+                        repo.find_reference(format!("refs/remotes/{remote_name}/{old}").as_str())
+                    {
+                        stale.delete()?;
+                    }
+                }
+            }
+
+            update_reference(
+                repo,
+                FullName::try_from(format!("refs/remotes/{remote_name}/HEAD"))?,
+                Target::Symbolic(FullName::try_from(format!(
+                    "refs/remotes/{remote_name}/{new}"
+                ))?),
+                "repox: remote HEAD changed",
+            )
+        }
+        _ => Ok(()),
+    }
+}
+
+/// The branch `refs/remotes/<remote_name>/HEAD` points to, i.e. the remote's default branch as of the last fetch.
+pub fn remote_default_branch(repo: &gix::Repository, remote_name: &str) -> Option<String> {
+    let head = repo
+        .find_reference(format!("refs/remotes/{remote_name}/HEAD").as_str())
+        .ok()?;
+    let target = head.target().try_name()?.as_bstr().to_string();
+
+    target
+        .strip_prefix(&format!("refs/remotes/{remote_name}/"))
+        .map(str::to_string)
+}
+
+/// Whether `refs/remotes/<remote_name>/<branch>` exists.
+pub fn has_tracking_branch(repo: &gix::Repository, remote_name: &str, branch: &str) -> bool {
+    repo.find_reference(format!("refs/remotes/{remote_name}/{branch}").as_str())
+        .is_ok()
+}
+
+/// Whether `branch` is configured to track a branch of `remote_name`, as clones and `repox start` set up.
+pub fn has_upstream(repo: &gix::Repository, remote_name: &str, branch: &str) -> bool {
+    let config = repo.config_snapshot();
+    let remote = config.string(format!("branch.{branch}.remote").as_str());
+
+    remote.is_some_and(|remote| remote.as_ref() == remote_name)
+        && config
+            .string(format!("branch.{branch}.merge").as_str())
+            .is_some()
+}
+
+/// Makes `branch` track the branch `merge` of `remote_name`.
+fn set_upstream(
+    work_dir: &Path,
+    branch: &str,
+    remote_name: &str,
+    merge: &str,
+) -> Result<(), String> {
+    let remote_key = format!("branch.{branch}.remote");
+    let merge_key = format!("branch.{branch}.merge");
+    let merge_ref = format!("refs/heads/{merge}");
+    run_git(work_dir, &["config", &remote_key, remote_name])?;
+    run_git(work_dir, &["config", &merge_key, &merge_ref])?;
+    Ok(())
+}
+
+/// Makes the branch `branch` of the checkout at `path` track the branch of the same name on `remote_name`.
+pub fn track_branch(
+    repo: &gix::Repository,
+    path: &str,
+    branch: &str,
+    remote_name: &str,
+) -> Result<(), GitError> {
+    set_upstream(repo.git_dir(), branch, remote_name, branch).map_err(|message| {
+        GitError::TrackError {
+            path: path.to_string(),
+            branch: branch.to_string(),
+            remote_name: remote_name.to_string(),
+            message,
+        }
+    })
+}
+
+/// Resolves a manifest revision to a commit, preferring the remote-tracking branch of `remote_name`.
+///
+/// Branch names may be given relative to `refs/heads/` or absolute; tags and SHA-1s are resolved as-is.
+pub fn resolve_revision(
+    repo: &gix::Repository,
+    remote_name: &str,
+    revision: &str,
+) -> Result<ObjectId, GitError> {
+    let branch = revision.strip_prefix("refs/heads/").unwrap_or(revision);
+    let candidates = [
+        format!("refs/remotes/{remote_name}/{branch}"),
+        revision.to_string(),
+    ];
+
+    for candidate in candidates {
+        if let Ok(id) = repo.rev_parse_single(candidate.as_str()) {
+            return Ok(id.object()?.peel_to_kind(gix::object::Kind::Commit)?.id);
+        }
+    }
+
+    Err(GitError::RevisionNotFound {
+        revision: revision.to_string(),
+    })
+}
+
+/// What to do with uncommitted changes when an operation needs to rewrite a project's working tree.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DirtyPolicy {
+    /// Refuse to touch the project.
+    #[default]
+    Refuse,
+
+    /// Stash the changes, rewrite the working tree, then restore them.
+    AutoStash,
+
+    /// Rewrite the working tree, discarding the changes.
+    Discard,
+}
+
+impl DirtyPolicy {
+    pub fn from_flags(auto_stash: bool, force_checkout: bool) -> Self {
+        match (auto_stash, force_checkout) {
+            (_, true) => DirtyPolicy::Discard,
+            (true, false) => DirtyPolicy::AutoStash,
+            (false, false) => DirtyPolicy::Refuse,
+        }
+    }
+}
+
+/// Whether the project has modifications to tracked files.
+pub fn is_dirty(repo: &gix::Repository) -> Result<bool, GitError> {
+    Ok(repo.is_dirty()?)
+}
+
+/// Runs the git CLI in `work_dir`, for operations gix does not implement yet.
+/// Points `remote_name` of the repository at `dst` to `url`, e.g. once the repository moved.
+pub fn set_remote_url(dst: &Path, remote_name: &str, url: &str) -> Result<(), GitError> {
+    run_git(dst, &["remote", "set-url", remote_name, url])
+        .map(drop)
+        .map_err(|message| GitError::RemoteUrlError {
+            path: dst.display().to_string(),
+            url: url.to_string(),
+            message,
+        })
+}
+
+/// Fetches `revision` of the superproject at `url` into the bare repository `git_dir`, and lists its tree.
+///
+/// Only the tip commit is fetched, the superproject's history is of no use for pinning the projects.
+pub fn fetch_superproject_tree(
+    git_dir: &Path,
+    url: &str,
+    revision: &str,
+) -> Result<String, GitError> {
+    let superproject_error = |message| GitError::SuperprojectError {
+        url: url.to_string(),
+        message,
+    };
+    std::fs::create_dir_all(git_dir).map_err(GitError::CreateDirectoryError)?;
+    if !git_dir.join("HEAD").exists() {
+        run_git(git_dir, &["init", "--bare", "--quiet"]).map_err(superproject_error)?;
+    }
+
+    run_git(
+        git_dir,
+        &["fetch", "--quiet", "--depth=1", "--no-tags", url, revision],
+    )
+    .map_err(superproject_error)?;
+    run_git(git_dir, &["ls-tree", "-r", "FETCH_HEAD"]).map_err(superproject_error)
+}
+
+/// The top of the git working tree containing `dir`, or `None` when it is not in one.
+pub fn toplevel(dir: &Path) -> Option<PathBuf> {
+    let toplevel = run_git_bytes(dir, &["rev-parse", "--show-toplevel"]).ok()?;
+    Some(gix::path::from_byte_slice(toplevel.trim_ascii_end()).to_path_buf())
+}
+
+/// Checks `revision` of the repository at `source` out at `dst` as a linked worktree, sharing its objects.
+///
+/// The worktree is left detached, as a fresh sync leaves a checkout. `revision` is looked up as a branch of
+/// `remote_name` first, then as a local branch, then as any commit-ish, so it works for checkouts and mirrors.
+pub fn add_linked_worktree(
+    source: &Path,
+    dst: &Path,
+    remote_name: &str,
+    revision: &str,
+) -> Result<(), GitError> {
+    let branch = revision.strip_prefix("refs/heads/").unwrap_or(revision);
+    let commit = [
+        format!("refs/remotes/{remote_name}/{branch}"),
+        format!("refs/heads/{branch}"),
+        revision.to_string(),
+    ]
+    .iter()
+    .find_map(|candidate| {
+        run_git(
+            source,
+            &[
+                "rev-parse",
+                "--verify",
+                "--quiet",
+                &format!("{candidate}^{{commit}}"),
+            ],
+        )
+        .ok()
+    })
+    .ok_or_else(|| GitError::RevisionNotFound {
+        revision: revision.to_string(),
+    })?;
+
+    run_git(
+        source,
+        &[
+            OsStr::new("worktree"),
+            OsStr::new("add"),
+            OsStr::new("--quiet"),
+            OsStr::new("--detach"),
+            dst.as_os_str(),
+            OsStr::new(commit.trim()),
+        ],
+    )
+    .map(|_| ())
+    .map_err(|message| GitError::WorktreeAddError {
+        path: source.display().to_string(),
+        dst: dst.display().to_string(),
+        message,
+    })
+}
+
+/// Adds `patterns` missing from the `info/exclude` file of the repository at `repo`, so `git status` stops
+/// listing the files they match as untracked.
+///
+/// Linked worktrees share the exclude file of the repository they belong to.
+pub fn add_excludes(repo: &Path, patterns: &[String]) -> Result<(), GitError> {
+    let exclude_error = |message: String| GitError::ExcludeError {
+        path: repo.display().to_string(),
+        message,
+    };
+    let exclude = repo.join(gix::path::from_byte_slice(
+        run_git_bytes(repo, &["rev-parse", "--git-path", "info/exclude"])
+            .map_err(exclude_error)?
+            .trim_ascii_end(),
+    ));
+
+    let existing = std::fs::read_to_string(&exclude).unwrap_or_default();
+    let missing: Vec<&String> = patterns
+        .iter()
+        .filter(|pattern| !existing.lines().any(|line| line == pattern.as_str()))
+        .collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    let mut contents = existing;
+    if !contents.is_empty() && !contents.ends_with('\n') {
+        contents.push('\n');
+    }
+    for pattern in missing {
+        contents.push_str(pattern);
+        contents.push('\n');
+    }
+    if let Some(dir) = exclude.parent() {
+        std::fs::create_dir_all(dir).map_err(|error| exclude_error(error.to_string()))?;
+    }
+    std::fs::write(&exclude, contents).map_err(|error| exclude_error(error.to_string()))
+}
+
+fn run_git<S: AsRef<OsStr>>(work_dir: &Path, args: &[S]) -> Result<String, String> {
+    run_git_bytes(work_dir, args).map(|stdout| String::from_utf8_lossy(&stdout).into_owned())
+}
+
+/// Runs git like [`run_git`], keeping its output as bytes, for paths that need not be UTF-8.
+fn run_git_bytes<S: AsRef<OsStr>>(work_dir: &Path, args: &[S]) -> Result<Vec<u8>, String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(work_dir)
+        .output()
+        .map_err(|error| error.to_string())?;
+
+    if output.status.success() {
+        Ok(output.stdout)
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let message = if stderr.trim().is_empty() {
+            stdout
+        } else {
+            stderr
+        };
+
+        Err(message.trim().to_string())
+    }
+}
+
+/// The number of commits reachable from `to` but not from `from`.
+///
+/// gix cannot exclude commits from a walk yet, so this shells out to git.
+pub fn count_commits(work_dir: &Path, from: &str, to: &str) -> Option<usize> {
+    run_git(work_dir, &["rev-list", "--count", &format!("{from}..{to}")])
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// The files added or changed by the commits of `head` that `remote_name` does not have, with the size
+/// of their last outgoing version.
+///
+/// gix cannot diff commits against a set of excluded refs yet, so this shells out to git.
+pub fn outgoing_files(
+    repo: &gix::Repository,
+    path: &str,
+    head: ObjectId,
+    remote_name: &str,
+) -> Result<Vec<(String, u64)>, GitError> {
+    let work_dir = repo.work_dir().unwrap_or_else(|| repo.git_dir());
+    let head = head.to_string();
+    let remotes = format!("--remotes={remote_name}");
+    let log = run_git(
+        work_dir,
+        &[
+            "log",
+            "--raw",
+            "--no-abbrev",
+            "--no-renames",
+            "--diff-filter=AM",
+            "--format=",
+            &head,
+            "--not",
+            &remotes,
+        ],
+    )
+    .map_err(|message| GitError::OutgoingFilesError {
+        path: path.to_string(),
+        message,
+    })?;
+
+    // Newest commits come first, so the first version of a file seen is the one that is uploaded.
+    let mut seen = HashSet::new();
+    let mut files = Vec::new();
+    for line in log.lines() {
+        let Some((status, file)) = line.split_once('\t') else {
+            continue;
+        };
+        let Some(id) = status
+            .split_whitespace()
+            .nth(3)
+            .and_then(|id| ObjectId::from_hex(id.as_bytes()).ok())
+        else {
+            continue;
+        };
+        if seen.insert(file.to_string()) {
+            let size = repo.find_header(id)?.size();
+            files.push((file.to_string(), size));
+        }
+    }
+    Ok(files)
+}
+
+/// Whether `commit` is reachable from a ref fetched from `remote_name`: its remote-tracking branches,
+/// tags or Gerrit changes. Commits that are not can only be found in this checkout.
+pub fn fetched_from(work_dir: &Path, remote_name: &str, commit: &str) -> bool {
+    run_git(
+        work_dir,
+        &[
+            "for-each-ref",
+            "--count=1",
+            "--contains",
+            commit,
+            &format!("refs/remotes/{remote_name}/"),
+            "refs/tags/",
+            "refs/changes/",
+        ],
+    )
+    .is_ok_and(|refs| !refs.trim().is_empty())
+}
+
+/// The working tree changes of a project, one `git status --short` line per changed file.
+pub fn changed_files(work_dir: &Path, path: &str) -> Result<Vec<String>, GitError> {
+    // Print non-ASCII file names as they are rather than octal-escaped, composed on macOS as elsewhere.
+    let output = run_git(
+        work_dir,
+        &[
+            "-c",
+            "core.quotePath=false",
+            "-c",
+            "core.precomposeUnicode=true",
+            "status",
+            "--porcelain",
+        ],
+    )
+    .map_err(|message| GitError::StatusError {
+        path: path.to_string(),
+        message,
+    })?;
+
+    Ok(output.lines().map(str::to_string).collect())
+}
+
+/// The names of a project's local branches, relative to `refs/heads/`.
+pub fn local_branches(work_dir: &Path, path: &str) -> Result<Vec<String>, GitError> {
+    let output = run_git(
+        work_dir,
+        &[
+            "for-each-ref",
+            "--format=%(refname:lstrip=2)",
+            "refs/heads/",
+        ],
+    )
+    .map_err(|message| GitError::BranchesError {
+        path: path.to_string(),
+        message,
+    })?;
+
+    Ok(output.lines().map(str::to_string).collect())
+}
+
+/// Stages `file`, in the repository checked out at `work_dir`, and commits it with `message` when one
+/// is given, leaving other staged changes out of the commit.
+pub fn commit_file(work_dir: &Path, file: &Path, message: Option<&str>) -> Result<(), GitError> {
+    let error = |message| GitError::CommitError {
+        path: file.display().to_string(),
+        message,
+    };
+    run_git(
+        work_dir,
+        &[OsStr::new("add"), OsStr::new("--"), file.as_os_str()],
+    )
+    .map_err(error)?;
+    if let Some(message) = message {
+        run_git(
+            work_dir,
+            &[
+                OsStr::new("commit"),
+                OsStr::new("--quiet"),
+                OsStr::new("--message"),
+                OsStr::new(message),
+                OsStr::new("--"),
+                file.as_os_str(),
+            ],
+        )
+        .map_err(error)?;
+    }
+
+    Ok(())
+}
+
+fn stash(work_dir: &Path, path: &str, args: &[&str], action: &'static str) -> Result<(), GitError> {
+    run_git(work_dir, args)
+        .map(|_| ())
+        .map_err(|message| GitError::StashError {
+            action,
+            path: path.to_string(),
+            message,
+        })
+}
+
+/// Switches the checkout to the topic branch `branch`, creating it at `HEAD` if it does not exist yet.
+///
+/// A new branch tracks `merge` on `remote_name`, so `git pull` and `repox upload` know where it goes.
+/// Uncommitted changes are carried over, as `git switch` does, which gix cannot do yet.
+pub fn start_branch(
+    repo: &gix::Repository,
+    path: &str,
+    branch: &str,
+    remote_name: &str,
+    merge: Option<&str>,
+) -> Result<(), GitError> {
+    let work_dir = repo.work_dir().ok_or_else(|| GitError::BareRepository {
+        path: path.to_string(),
+    })?;
+    let start_error = |message| GitError::StartError {
+        path: path.to_string(),
+        branch: branch.to_string(),
+        message,
+    };
+
+    if branch_commit(repo, branch).is_some() {
+        return run_git(work_dir, &["switch", "--quiet", branch])
+            .map(|_| ())
+            .map_err(start_error);
+    }
+
+    run_git(work_dir, &["switch", "--quiet", "--create", branch]).map_err(start_error)?;
+    if let Some(merge) = merge {
+        set_upstream(work_dir, branch, remote_name, merge).map_err(start_error)?;
+    }
+    Ok(())
+}
+
+/// Pushes `refspec` to `remote_name`, with the `key=value` settings of `config_overrides`.
+///
+/// gix cannot push yet, so this shells out to git.
+pub fn push(
+    repo: &gix::Repository,
+    path: &str,
+    remote_name: &str,
+    refspec: &str,
+    config_overrides: &[String],
+) -> Result<(), GitError> {
+    let _push_span = info_span!("Pushing", remote = remote_name, refspec).entered();
+    let work_dir = repo.work_dir().unwrap_or_else(|| repo.git_dir());
+
+    let mut args: Vec<&str> = config_overrides
+        .iter()
+        .flat_map(|value| ["-c", value.as_str()])
+        .collect();
+    args.extend(["push", remote_name, refspec]);
+    run_git(work_dir, &args)
+        .map(|_| ())
+        .map_err(|message| GitError::PushError {
+            path: path.to_string(),
+            refspec: refspec.to_string(),
+            message,
+        })
+}
+
+/// Pushes every branch and tag a mirror fetched from `remote_name` to `url`, with the `key=value`
+/// settings of `config_overrides` and further `git push` options, e.g. `--prune`.
+///
+/// Branches are pushed from the remote-tracking refs they are fetched into, as plain branches.
+pub fn push_mirror(
+    repo: &gix::Repository,
+    path: &str,
+    url: &str,
+    remote_name: &str,
+    options: &[&str],
+    config_overrides: &[String],
+) -> Result<(), GitError> {
+    let _push_span = info_span!("Pushing", url).entered();
+    let branches = format!("+refs/remotes/{remote_name}/*:refs/heads/*");
+    let remote_head = format!("^refs/remotes/{remote_name}/HEAD");
+    let refspecs = [branches.as_str(), &remote_head, "+refs/tags/*:refs/tags/*"];
+
+    let mut args: Vec<&str> = config_overrides
+        .iter()
+        .flat_map(|value| ["-c", value.as_str()])
+        .collect();
+    args.push("push");
+    args.extend(options);
+    args.push(url);
+    args.extend(refspecs);
+    run_git(repo.git_dir(), &args)
+        .map(|_| ())
+        .map_err(|message| GitError::PushError {
+            path: path.to_string(),
+            refspec: refspecs.join(" "),
+            message,
+        })
+}
+
+/// Creates the repository at `dst` from `seed`, a git bundle or bare repository, as if it had been cloned
+/// from `url`: the seed's branches become the remote-tracking branches of `remote_name` and its tags are
+/// copied. Nothing is checked out; `HEAD` is left unborn on `branch`, for the first sync to check out.
+///
+/// Seeds laid out as mirrors, with their branches under `refs/remotes/<remote_name>/`, are read as such.
+pub fn seed_project(
+    seed: &Path,
+    dst: &Path,
+    path: &str,
+    url: &str,
+    remote_name: &str,
+    mirror: bool,
+    branch: Option<&str>,
+) -> Result<(), GitError> {
+    std::fs::create_dir_all(dst).map_err(GitError::CreateDirectoryError)?;
+    let seed_error = |message| GitError::SeedError {
+        path: path.to_string(),
+        seed: seed.display().to_string(),
+        message,
+    };
+    // git runs in `dst`, where a relative path would not lead to the seed.
+    let seed = std::path::absolute(seed).unwrap_or_else(|_| seed.to_path_buf());
+    let seed = seed.as_os_str();
+    let tracking = format!("refs/remotes/{remote_name}/");
+    let init: &[&str] = if mirror {
+        &["init", "--quiet", "--bare"]
+    } else {
+        &["init", "--quiet"]
+    };
+
+    let seeded = (|| {
+        run_git(dst, init)?;
+        run_git(dst, &["remote", "add", remote_name, url])?;
+
+        let branches = if run_git(dst, &[OsStr::new("ls-remote"), seed])?.contains(&tracking) {
+            format!("+{tracking}*:{tracking}*")
+        } else {
+            format!("+refs/heads/*:{tracking}*")
+        };
+        let remote_head = format!("^{tracking}HEAD");
+        run_git(
+            dst,
+            &[
+                OsStr::new("fetch"),
+                OsStr::new("--quiet"),
+                OsStr::new("--no-tags"),
+                seed,
+                OsStr::new(&branches),
+                OsStr::new(&remote_head),
+                OsStr::new("+refs/tags/*:refs/tags/*"),
+            ],
+        )?;
+
+        if let Some(branch) = branch.filter(|_| !mirror) {
+            run_git(
+                dst,
+                &["symbolic-ref", "HEAD", &format!("refs/heads/{branch}")],
+            )?;
+            if run_git(
+                dst,
+                &[
+                    "rev-parse",
+                    "--verify",
+                    "--quiet",
+                    &format!("{tracking}{branch}"),
+                ],
+            )
+            .is_ok()
+            {
+                run_git(
+                    dst,
+                    &[
+                        "symbolic-ref",
+                        &format!("{tracking}HEAD"),
+                        &format!("{tracking}{branch}"),
+                    ],
+                )?;
+                set_upstream(dst, branch, remote_name, branch)?;
+            }
+        }
+        Ok(())
+    })();
+
+    // A half-seeded repository would be fetched into rather than cloned by the next sync.
+    seeded.map_err(|message| {
+        let _ = std::fs::remove_dir_all(dst);
+        seed_error(message)
+    })
+}
+
+/// Checks out `commit` into the project's working tree, protecting uncommitted changes according to `policy`.
+///
+/// When `branch` is given, `HEAD` is attached to it, otherwise `HEAD` is detached at `commit`.
+pub fn protected_checkout(
+    repo: &gix::Repository,
+    path: &str,
+    commit: ObjectId,
+    branch: Option<&str>,
+    policy: DirtyPolicy,
+) -> Result<(), GitError> {
+    let work_dir = repo.work_dir().ok_or_else(|| GitError::BareRepository {
+        path: path.to_string(),
+    })?;
+
+    // A checkout with nothing committed yet, as seeding leaves it, has nothing to protect.
+    let dirty = head_commit(repo).is_some() && is_dirty(repo)?;
+    let stashed = match (dirty, policy) {
+        (false, _) => false,
+        (true, DirtyPolicy::Refuse) => {
+            return Err(GitError::DirtyWorktree {
+                path: path.to_string(),
+            })
+        }
+        (true, DirtyPolicy::AutoStash) => {
+            info!("Stashing uncommitted changes in {path}");
+            stash(
+                work_dir,
+                path,
+                &["stash", "push", "--message", "repox auto-stash"],
+                "stash",
+            )?;
+            true
+        }
+        (true, DirtyPolicy::Discard) => {
+            warn!("Discarding uncommitted changes in {path}");
+            false
+        }
+    };
+
+    checkout(repo, path, commit, branch)?;
+
+    if stashed {
+        info!("Restoring uncommitted changes in {path}");
+        if let Err(error) = stash(work_dir, path, &["stash", "pop"], "restore") {
+            let files = conflicted_files(work_dir);
+            if files.is_empty() {
+                return Err(error);
+            }
+
+            return Err(GitError::Conflict(Conflict {
+                path: path.to_string(),
+                operation: "restoring stashed changes".to_string(),
+                files,
+                hint: "resolve the conflicts and stage the files, the changes stay in the stash until dropped"
+                    .to_string(),
+                continue_with: "git stash drop".to_string(),
+            }));
+        }
+    }
+
+    Ok(())
+}
+
+/// The files with unresolved conflicts in a working tree.
+fn conflicted_files(work_dir: &Path) -> Vec<String> {
+    run_git(work_dir, &["diff", "--name-only", "--diff-filter=U"])
+        .map(|output| output.lines().map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+/// Rewrites the working tree and index to match `commit` and points `HEAD` at it.
+fn checkout(
+    repo: &gix::Repository,
+    path: &str,
+    commit: ObjectId,
+    branch: Option<&str>,
+) -> Result<(), GitError> {
+    let work_dir = repo.work_dir().expect("checked by the caller");
+    let tree = repo.find_object(commit)?.peel_to_tree()?.id;
+
+    let previous_index = repo.index_or_empty()?;
+    let mut index = repo.index_from_tree(&tree)?;
+    PathProtections::of(repo).check(&index, path)?;
+
+    // gix only writes the entries of the new index, and does not truncate the files it overwrites, so
+    // files that changed or are no longer tracked have to be removed by hand. Submodules are left alone.
+    let retained: HashSet<(&BStr, ObjectId)> = index
+        .entries()
+        .iter()
+        .map(|e| (e.path(&index), e.id))
+        .collect();
+    for entry in previous_index.entries() {
+        let entry_path = entry.path(&previous_index);
+        if entry.mode != gix::index::entry::Mode::COMMIT
+            && !retained.contains(&(entry_path, entry.id))
+        {
+            let file = work_dir.join(gix::path::from_bstr(entry_path));
+            match std::fs::remove_file(&file) {
+                Err(error) if error.kind() != std::io::ErrorKind::NotFound => {
+                    return Err(GitError::WorktreeIoError(error))
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let options = gix::worktree::state::checkout::Options {
+        fs: gix::fs::Capabilities::probe(work_dir),
+        overwrite_existing: true,
+        ..Default::default()
+    };
+    gix::worktree::state::checkout(
+        &mut index,
+        work_dir,
+        repo.objects
+            .clone()
+            .into_arc()
+            .map_err(GitError::WorktreeIoError)?,
+        &TracingProgress::new("checkout files"),
+        &TracingProgress::new("checkout bytes"),
+        &gix::interrupt::IS_INTERRUPTED,
+        options,
+    )?;
+    index.write(Default::default())?;
+
+    let head = match branch {
+        Some(branch) => {
+            let branch = FullName::try_from(format!("refs/heads/{branch}"))?;
+            update_reference(
+                repo,
+                branch.clone(),
+                Target::Peeled(commit),
+                "repox: checkout",
+            )?;
+            Target::Symbolic(branch)
+        }
+        None => Target::Peeled(commit),
+    };
+    update_reference(repo, FullName::try_from("HEAD")?, head, "repox: checkout")?;
+
+    Ok(())
+}
+
+fn update_reference(
+    repo: &gix::Repository,
+    name: FullName,
+    new: Target,
+    message: &str,
+) -> Result<(), GitError> {
+    repo.edit_reference(RefEdit {
+        change: Change::Update {
+            log: LogChange {
+                mode: RefLog::AndReference,
+                force_create_reflog: false,
+                message: message.into(),
+            },
+            expected: PreviousValue::Any,
+            new,
+        },
+        name,
+        deref: false,
+    })?;
+
+    Ok(())
+}
+
+/// The local branch `HEAD` is attached to, if any.
+pub fn current_branch(repo: &gix::Repository) -> Result<Option<String>, GitError> {
+    Ok(repo.head_name()?.and_then(|name| {
+        name.as_bstr()
+            .to_string()
+            .strip_prefix("refs/heads/")
+            .map(str::to_string)
+    }))
+}
+
+/// The commit a local branch points to, if the branch exists.
+pub fn branch_commit(repo: &gix::Repository, branch: &str) -> Option<ObjectId> {
+    reference_commit(repo, &format!("refs/heads/{branch}"))
+}
+
+/// The commit the remote-tracking branch of `branch` points to, if it was fetched.
+pub fn tracking_commit(
+    repo: &gix::Repository,
+    remote_name: &str,
+    branch: &str,
+) -> Option<ObjectId> {
+    reference_commit(repo, &format!("refs/remotes/{remote_name}/{branch}"))
+}
+
+/// The commit last uploaded for review from `branch`, recorded in `refs/published/` as repo does.
+pub fn published_commit(repo: &gix::Repository, branch: &str) -> Option<ObjectId> {
+    reference_commit(repo, &format!("refs/published/{branch}"))
+}
+
+/// Records `commit` as uploaded for review from `branch`.
+pub fn mark_published(
+    repo: &gix::Repository,
+    branch: &str,
+    commit: ObjectId,
+) -> Result<(), GitError> {
+    update_reference(
+        repo,
+        FullName::try_from(format!("refs/published/{branch}"))?,
+        Target::Peeled(commit),
+        "repox: upload",
+    )
+}
+
+fn reference_commit(repo: &gix::Repository, name: &str) -> Option<ObjectId> {
+    repo.find_reference(name)
+        .ok()?
+        .peel_to_id_in_place()
+        .ok()
+        .map(|id| id.detach())
+}
+
+/// A submodule a project's `.gitmodules` declares, at the commit its `HEAD` records for it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Submodule {
+    /// The submodule's path, relative to the project.
+    pub path: String,
+
+    /// The URL as written in `.gitmodules`, possibly relative to the project's own URL.
+    pub url: String,
+
+    /// The commit the project pins the submodule to.
+    pub commit: String,
+}
+
+/// The submodules of the project checked out in `repo`, skipping entries without a path, URL or gitlink.
+pub fn submodules(repo: &gix::Repository) -> Vec<Submodule> {
+    let Ok(Some(submodules)) = repo.submodules() else {
+        return Vec::new();
+    };
+
+    submodules
+        .filter_map(|submodule| {
+            Some(Submodule {
+                path: submodule.path().ok()?.to_string(),
+                url: submodule.url().ok()?.to_bstring().to_string(),
+                commit: submodule.head_id().ok()??.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// The commit `HEAD` points to, if it is born.
+pub fn head_commit(repo: &gix::Repository) -> Option<ObjectId> {
+    repo.head_id().ok().map(|id| id.detach())
+}
+
+/// Whether `ancestor` is reachable from `descendant`.
+pub fn is_ancestor(repo: &gix::Repository, ancestor: ObjectId, descendant: ObjectId) -> bool {
+    repo.rev_walk([descendant])
+        .all()
+        .map(|walk| walk.filter_map(Result::ok).any(|info| info.id == ancestor))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{current_branch_refspecs, project_refspecs, PathProtections, Refspecs};
+
+    #[test]
+    fn test_path_protections() {
+        let default = PathProtections::default();
+        assert!(default.violation(".GIT").is_some());
+        assert!(default.violation("..").is_some());
+        assert_eq!(default.violation(".git "), None);
+        assert_eq!(default.violation("nul.txt"), None);
+
+        let ntfs = PathProtections {
+            ntfs: true,
+            ..Default::default()
+        };
+        assert!(ntfs.violation(".git. ").is_some());
+        assert!(ntfs.violation("GIT~1").is_some());
+        assert!(ntfs.violation(".git::$INDEX_ALLOCATION").is_some());
+        assert_eq!(ntfs.violation(".gitignore"), None);
+
+        let hfs = PathProtections {
+            hfs: true,
+            ..Default::default()
+        };
+        assert!(hfs.violation(".g\u{200c}it").is_some());
+
+        let windows = PathProtections {
+            ntfs: true,
+            windows: true,
+            ..Default::default()
+        };
+        assert!(windows.violation("nul.txt").is_some());
+        assert!(windows.violation("Com1").is_some());
+        assert!(windows.violation("a:b").is_some());
+        assert!(windows.violation("trailing.").is_some());
+        assert_eq!(windows.violation("com10"), None);
+        assert_eq!(windows.violation("console.rs"), None);
+    }
+
+    #[test]
+    fn test_current_branch_refspecs() {
+        let sha = "0123456789abcdef0123456789abcdef01234567";
+
+        assert_eq!(
+            current_branch_refspecs("origin", Some(sha), Some("refs/heads/main")),
+            vec!["+refs/heads/main:refs/remotes/origin/main"]
+        );
+        assert!(current_branch_refspecs("origin", Some(sha), None).is_empty());
+        assert_eq!(
+            current_branch_refspecs("origin", Some("dev"), Some("main")),
+            vec!["+refs/heads/dev:refs/remotes/origin/dev"]
+        );
+        assert_eq!(
+            current_branch_refspecs("origin", Some("refs/tags/v1.0"), None),
+            vec!["+refs/tags/v1.0:refs/tags/v1.0"]
+        );
+    }
+
+    #[test]
+    fn test_change_refspecs() {
+        let change = "refs/changes/34/1234/5";
+
+        assert_eq!(
+            project_refspecs("origin", Some(change), None, false),
+            Refspecs {
+                configured: Vec::new(),
+                extra: vec![format!("+{change}:{change}")],
+            }
+        );
+        assert_eq!(
+            project_refspecs("origin", Some(change), None, true),
+            Refspecs {
+                configured: vec![format!("+{change}:{change}")],
+                extra: Vec::new(),
+            }
+        );
+        assert_eq!(
+            project_refspecs("origin", Some("main"), None, false),
+            Refspecs::default()
+        );
+    }
+}
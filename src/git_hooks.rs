@@ -0,0 +1,127 @@
+use crate::{
+    git::{open_project, GitError},
+    workspace::Workspace,
+};
+use miette::Diagnostic;
+use repox_manifest::project::Project;
+use std::{
+    fs::{self, create_dir_all},
+    io,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+use tracing::debug;
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum GitHooksError {
+    #[error("Could not read the hooks template directory {dir}")]
+    #[diagnostic(
+        code(repox::git_hooks::template),
+        help(
+            "check hooks-template in .repo/repox.toml, relative paths are from the workspace root"
+        )
+    )]
+    TemplateError {
+        dir: String,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("Could not install the {hook} hook into {path}")]
+    #[diagnostic(code(repox::git_hooks::install))]
+    InstallError {
+        hook: String,
+        path: String,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    GitError(#[from] GitError),
+}
+
+/// The hooks in the `template` directory, by file name. Subdirectories are left out, git only runs the
+/// hooks directly in its hooks directory.
+fn template_hooks(template: &Path) -> Result<Vec<(String, PathBuf)>, GitHooksError> {
+    let template_error = |source| GitHooksError::TemplateError {
+        dir: template.display().to_string(),
+        source,
+    };
+
+    let mut hooks = Vec::new();
+    for entry in fs::read_dir(template).map_err(template_error)? {
+        let path = entry.map_err(template_error)?.path();
+        if !path.is_file() {
+            continue;
+        }
+        if let Some(name) = path.file_name().and_then(|name| name.to_str()) {
+            hooks.push((name.to_string(), path.clone()));
+        }
+    }
+    hooks.sort();
+
+    Ok(hooks)
+}
+
+/// Copies `src` to `dest` as an executable hook, leaving a copy with the same contents alone.
+fn install_hook(src: &Path, dest: &Path) -> io::Result<()> {
+    let contents = fs::read(src)?;
+    if fs::read(dest).is_ok_and(|existing| existing == contents) {
+        return Ok(());
+    }
+
+    if let Some(parent) = dest.parent() {
+        create_dir_all(parent)?;
+    }
+    // The previous copy may be read-only, or a symlink that writing would go through.
+    if dest.symlink_metadata().is_ok() {
+        fs::remove_file(dest)?;
+    }
+    fs::write(dest, contents)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(dest, fs::Permissions::from_mode(0o755))?;
+    }
+
+    Ok(())
+}
+
+/// Installs the hooks of the `template` directory into the hooks directory of each of `projects` that is
+/// checked out, so policies such as a `pre-commit` hook apply across every repository of the workspace.
+///
+/// Hooks of the same name are replaced, others are left as they are. Projects checked out with
+/// git-worktree share the hooks of the repository they are a worktree of.
+pub fn install_git_hooks(
+    workspace: &Workspace,
+    template: &str,
+    projects: &[Project],
+) -> Result<(), GitHooksError> {
+    let hooks = template_hooks(&workspace.root().join(template))?;
+    if hooks.is_empty() {
+        return Ok(());
+    }
+
+    for project in projects {
+        let path = project.checkout_path();
+        let dst = workspace.project_dir(path);
+        if !dst.exists() {
+            continue;
+        }
+
+        let hooks_dir = open_project(&dst)?.common_dir().join("hooks");
+        for (name, src) in &hooks {
+            debug!("Installing the {name} hook into {path}");
+            install_hook(src, &hooks_dir.join(name)).map_err(|source| {
+                GitHooksError::InstallError {
+                    hook: name.clone(),
+                    path: path.to_string(),
+                    source,
+                }
+            })?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,312 @@
+use crate::{
+    git::{head_commit, open_project, GitError},
+    workspace::{Workspace, WorkspaceError},
+};
+use clap::Args;
+use miette::Diagnostic;
+use repox_manifest::Manifest;
+use serde::{Deserialize, Serialize};
+use std::{
+    io::{IsTerminal, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    thread::{sleep, spawn},
+    time::{Duration, Instant},
+};
+use thiserror::Error;
+use tracing::{info, info_span, warn};
+
+/// Hooks repox knows how to run from the manifest's repo-hooks project.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookType {
+    /// Runs before `repo upload` pushes anything, and can veto the upload.
+    PreUpload,
+
+    /// Runs after a successful `repo sync`.
+    PostSync,
+}
+
+impl HookType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            HookType::PreUpload => "pre-upload",
+            HookType::PostSync => "post-sync",
+        }
+    }
+}
+
+/// Seconds a repo-hook may run before it is killed, unless `--hook-timeout` says otherwise.
+const DEFAULT_HOOK_TIMEOUT: u64 = 300;
+
+/// Variables of repox's own environment a repo-hook still sees, so it can find its interpreter and the
+/// user's git configuration. Windows cannot start most programs without its system directories.
+const HOOK_ENV_PASSTHROUGH: &[&str] = &[
+    "PATH",
+    "HOME",
+    #[cfg(windows)]
+    "SYSTEMROOT",
+    #[cfg(windows)]
+    "USERPROFILE",
+];
+
+/// Command line options controlling how repo-hooks are run.
+#[derive(Args, Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct HookArgs {
+    /// Do not run the repo-hooks
+    #[arg(long, default_value_t = false)]
+    no_verify: bool,
+
+    /// Run the repo-hooks without prompting for approval
+    #[arg(long, default_value_t = false)]
+    verify: bool,
+
+    /// Carry on even if a repo-hook fails
+    #[arg(long, default_value_t = false)]
+    ignore_hooks: bool,
+
+    /// Seconds a repo-hook may run before it is killed
+    #[arg(long, default_value_t = DEFAULT_HOOK_TIMEOUT)]
+    hook_timeout: u64,
+}
+
+/// The options of a command line passing none, for commands that sync on the user's behalf.
+impl Default for HookArgs {
+    fn default() -> Self {
+        Self {
+            no_verify: false,
+            verify: false,
+            ignore_hooks: false,
+            hook_timeout: DEFAULT_HOOK_TIMEOUT,
+        }
+    }
+}
+
+impl HookArgs {
+    /// The options of `--no-verify`, for commands checking projects out without running any hook.
+    pub(crate) fn skipped() -> Self {
+        Self {
+            no_verify: true,
+            ..Self::default()
+        }
+    }
+}
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum HookError {
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    WorkspaceError(#[from] WorkspaceError),
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    GitError(#[from] GitError),
+
+    #[error("The repo-hooks project {project} is not part of the manifest")]
+    #[diagnostic(code(repox::hooks::missing_project))]
+    MissingProject { project: String },
+
+    #[error("The {hook} hook at revision {revision} has not been approved")]
+    #[diagnostic(
+        code(repox::hooks::not_approved),
+        help("re-run interactively to review and approve it, pass --verify to run it anyway, or --no-verify to skip it")
+    )]
+    NotApproved { hook: String, revision: String },
+
+    #[error("Could not run the {hook} hook")]
+    #[diagnostic(code(repox::hooks::spawn))]
+    SpawnError {
+        hook: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("The {hook} hook did not finish within {seconds} seconds")]
+    #[diagnostic(
+        code(repox::hooks::timeout),
+        help("pass --hook-timeout to allow it more time")
+    )]
+    Timeout { hook: String, seconds: u64 },
+
+    #[error("The {hook} hook failed with {status}")]
+    #[diagnostic(
+        code(repox::hooks::failed),
+        help("fix the reported problems, or pass --ignore-hooks to carry on regardless")
+    )]
+    Failed { hook: String, status: String },
+}
+
+/// Finds the executable for `hook` in the hooks project, if it provides one.
+fn find_hook(hooks_dir: &Path, hook: &str) -> Option<PathBuf> {
+    [hook.to_string(), format!("{hook}.py"), format!("{hook}.sh")]
+        .into_iter()
+        .map(|name| hooks_dir.join(name))
+        .find(|path| path.is_file())
+}
+
+fn hook_command(script: &Path) -> Command {
+    match script.extension().and_then(|ext| ext.to_str()) {
+        Some("py") => {
+            let mut command = Command::new("python3");
+            command.arg(script);
+            command
+        }
+        Some("sh") => {
+            let mut command = Command::new("sh");
+            command.arg(script);
+            command
+        }
+        _ => Command::new(script),
+    }
+}
+
+/// Asks the user whether a hooks revision they have not seen before may run.
+fn prompt_approval(hook: &str, script: &Path, revision: &str) -> bool {
+    let stdin = std::io::stdin();
+    if !stdin.is_terminal() {
+        return false;
+    }
+
+    print!(
+        "Repo hook {hook} ({}) at revision {revision} has not been run before.\n\
+         Allow it to run now and whenever it is unchanged? [y/N] ",
+        script.display()
+    );
+    let _ = std::io::stdout().flush();
+
+    let mut answer = String::new();
+    stdin.read_line(&mut answer).is_ok() && matches!(answer.trim(), "y" | "Y" | "yes")
+}
+
+/// Runs one of the manifest's repo-hooks for `projects` (paths relative to the top of the repo client).
+///
+/// The hook runs from the top of the repo client and receives the project paths as arguments and one per
+/// line on stdin. Its environment is cleared, so credentials and other secrets in repox's environment do not
+/// leak into hooks fetched from the manifest; it only sees `PATH`, `HOME`, the `REPO_*` variables repox was
+/// run with, and:
+///
+/// - `REPO_HOOK`: the name of the hook being run
+/// - `REPO_TOPDIR`: the top of the repo client
+/// - `REPO_HOOKS_PROJECT`: the path of the hooks project
+/// - `REPO_PROJECTS`: the project paths, separated by spaces
+///
+/// The first time a hooks revision is seen the user is asked to approve it, and the approval is recorded
+/// in the workspace configuration.
+pub fn run_hook(
+    workspace: &Workspace,
+    manifest: &Manifest,
+    hook_type: HookType,
+    projects: &[String],
+    args: &HookArgs,
+) -> Result<(), HookError> {
+    let hook = hook_type.name();
+    let Some(repo_hooks) = manifest.repo_hooks() else {
+        return Ok(());
+    };
+    if args.no_verify || !repo_hooks.is_enabled(hook) {
+        return Ok(());
+    }
+
+    let _hook_span = info_span!("Running repo hook", hook).entered();
+
+    let hooks_project = manifest
+        .projects()
+        .into_iter()
+        .find(|project| project.name == repo_hooks.in_project())
+        .ok_or_else(|| HookError::MissingProject {
+            project: repo_hooks.in_project().to_string(),
+        })?;
+    let hooks_path = hooks_project.checkout_path().to_string();
+    let hooks_dir = workspace.project_dir(&hooks_path);
+
+    let Some(script) = find_hook(&hooks_dir, hook) else {
+        info!("{hooks_path} does not provide a {hook} hook");
+        return Ok(());
+    };
+
+    let revision = head_commit(&open_project(&hooks_dir)?)
+        .map(|id| id.to_string())
+        .unwrap_or_default();
+
+    let mut config = workspace.config()?;
+    let approved = config.hooks.approved.get(hook) == Some(&revision);
+    if !approved && !args.verify {
+        if !prompt_approval(hook, &script, &revision) {
+            return Err(HookError::NotApproved {
+                hook: hook.to_string(),
+                revision,
+            });
+        }
+
+        config
+            .hooks
+            .approved
+            .insert(hook.to_string(), revision.clone());
+        workspace.save_config(&config)?;
+    }
+
+    let spawn_error = |source| HookError::SpawnError {
+        hook: hook.to_string(),
+        source,
+    };
+
+    let passthrough = std::env::vars().filter(|(name, _)| {
+        name.starts_with("REPO_") || HOOK_ENV_PASSTHROUGH.contains(&name.as_str())
+    });
+    let mut child = hook_command(&script)
+        .args(projects)
+        .current_dir(workspace.root())
+        .env_clear()
+        .envs(passthrough)
+        .env("REPO_HOOK", hook)
+        .env("REPO_TOPDIR", workspace.root())
+        .env("REPO_HOOKS_PROJECT", &hooks_path)
+        .env("REPO_PROJECTS", projects.join(" "))
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(spawn_error)?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // A list larger than the pipe buffer blocks until the hook reads it, which a hook never has to, so it is
+        // written from its own thread while the timeout runs. A hook that does not read its stdin closes it
+        // early, which is not an error.
+        let input = projects.join("\n");
+        spawn(move || {
+            let _ = stdin.write_all(input.as_bytes());
+        });
+    }
+
+    let timeout = Duration::from_secs(args.hook_timeout);
+    let started = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait().map_err(spawn_error)? {
+            break status;
+        }
+
+        if started.elapsed() > timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(HookError::Timeout {
+                hook: hook.to_string(),
+                seconds: args.hook_timeout,
+            });
+        }
+
+        sleep(Duration::from_millis(50));
+    };
+
+    if status.success() {
+        return Ok(());
+    }
+
+    let error = HookError::Failed {
+        hook: hook.to_string(),
+        status: status.to_string(),
+    };
+    if args.ignore_hooks {
+        warn!("{error}, ignoring");
+        return Ok(());
+    }
+
+    Err(error)
+}
@@ -0,0 +1,463 @@
+use crate::{config::TransportConfig, filesystem::replace_file, progress::TracingProgress};
+use gix::progress::{Count, Progress};
+use miette::Diagnostic;
+use reqwest::{
+    blocking::{Client, ClientBuilder, Response},
+    header::{CONTENT_RANGE, RANGE},
+    Certificate, Identity, Proxy, StatusCode,
+};
+use sha1_smol::Sha1;
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    thread::sleep,
+    time::{Duration, Instant},
+};
+use thiserror::Error;
+use tracing::{info, info_span, warn};
+
+/// Times a download is tried before giving up, the first included.
+const ATTEMPTS: u32 = 4;
+
+/// Delay before the first retry, doubling with every further one.
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// How long connecting may take. Large files may take any time to transfer, so there is no overall timeout.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum HttpError {
+    #[error("Could not set up the HTTP client")]
+    #[diagnostic(
+        code(repox::http::client),
+        help("check transport.proxy, transport.client-cert and transport.client-key in .repo/repox.toml")
+    )]
+    ClientError(#[source] reqwest::Error),
+
+    #[error("Could not read {path}")]
+    #[diagnostic(
+        code(repox::http::certificate),
+        help("transport.ca-bundle, transport.client-cert and transport.client-key name PEM files")
+    )]
+    CertificateError {
+        path: String,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("Could not download {url}")]
+    #[diagnostic(code(repox::http::request))]
+    RequestError {
+        url: String,
+        #[source]
+        source: reqwest::Error,
+    },
+
+    #[error("The connection to {url} broke off")]
+    #[diagnostic(code(repox::http::read))]
+    ReadError {
+        url: String,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("{url} does not exist")]
+    #[diagnostic(code(repox::http::not_found))]
+    NotFound { url: String },
+
+    #[error("{url} answered {status}")]
+    #[diagnostic(code(repox::http::status))]
+    StatusError { url: String, status: u16 },
+
+    #[error("{url} answered another range than the rest of the partial download")]
+    #[diagnostic(code(repox::http::range))]
+    RangeMismatch { url: String },
+
+    #[error("Could not write {path}")]
+    #[diagnostic(code(repox::http::write))]
+    WriteError {
+        path: String,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("{url} has SHA-1 {actual}, but {expected} was expected")]
+    #[diagnostic(
+        code(repox::http::checksum),
+        help("the file changed on the server or was corrupted on the way, the partial download was removed")
+    )]
+    ChecksumMismatch {
+        url: String,
+        expected: String,
+        actual: String,
+    },
+}
+
+impl HttpError {
+    /// Whether trying again may succeed: the connection failed, or the server is struggling.
+    fn is_transient(&self) -> bool {
+        match self {
+            HttpError::RequestError { .. }
+            | HttpError::ReadError { .. }
+            | HttpError::RangeMismatch { .. } => true,
+            HttpError::StatusError { status, .. } => {
+                *status >= 500 || *status == StatusCode::TOO_MANY_REQUESTS.as_u16()
+            }
+            _ => false,
+        }
+    }
+}
+
+/// An HTTP client honoring the workspace's proxy and certificates.
+pub fn client(transport: &TransportConfig) -> Result<Client, HttpError> {
+    client_builder(transport)?
+        .build()
+        .map_err(HttpError::ClientError)
+}
+
+/// A builder for an HTTP client honoring the workspace's proxy and certificates, for callers that need
+/// to adjust the client further, such as its redirect policy.
+pub fn client_builder(transport: &TransportConfig) -> Result<ClientBuilder, HttpError> {
+    let read = |path: &str| {
+        fs::read(path).map_err(|source| HttpError::CertificateError {
+            path: path.to_string(),
+            source,
+        })
+    };
+
+    let mut builder = Client::builder().connect_timeout(CONNECT_TIMEOUT);
+    if let Some(proxy) = &transport.proxy {
+        builder = builder.proxy(Proxy::all(proxy).map_err(HttpError::ClientError)?);
+    }
+    if let Some(ca_bundle) = &transport.ca_bundle {
+        for certificate in
+            Certificate::from_pem_bundle(&read(ca_bundle)?).map_err(HttpError::ClientError)?
+        {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+    if let (Some(cert), Some(key)) = (&transport.client_cert, &transport.client_key) {
+        let mut pem = read(cert)?;
+        pem.extend(read(key)?);
+        builder = builder.identity(Identity::from_pem(&pem).map_err(HttpError::ClientError)?);
+    }
+
+    Ok(builder)
+}
+
+/// Downloads files over HTTP(S), for the features fetching anything but git repositories, such as clone
+/// bundles, standalone manifests and project archives.
+///
+/// A download is written to `<file>.part` first and only renamed into place once complete and verified.
+/// When the connection breaks off, whether within the retries of one download or between runs, the download
+/// resumes from the partial file with a Range request.
+pub struct Downloader {
+    client: Client,
+    max_rate: Option<u64>,
+}
+
+impl Downloader {
+    /// A downloader honoring the workspace's proxy, certificates and download rate.
+    pub fn new(transport: &TransportConfig) -> Result<Self, HttpError> {
+        Ok(Downloader {
+            client: client(transport)?,
+            max_rate: transport.max_download_rate.filter(|rate| *rate > 0),
+        })
+    }
+
+    /// Downloads `url` to `dst`, checking it against the hex `sha1` when given.
+    ///
+    /// Transient failures are retried with a growing delay. A missing file fails with [`HttpError::NotFound`]
+    /// right away, so optional files like clone bundles can be told apart from failed downloads.
+    pub fn download(&self, url: &str, dst: &Path, sha1: Option<&str>) -> Result<(), HttpError> {
+        let _download_span = info_span!("Downloading", url).entered();
+        let part = part_path(dst);
+        if let Some(parent) = dst.parent() {
+            fs::create_dir_all(parent).map_err(|source| HttpError::WriteError {
+                path: parent.display().to_string(),
+                source,
+            })?;
+        }
+
+        let mut attempt = 1;
+        loop {
+            match self.fetch(url, &part) {
+                Ok(()) => break,
+                Err(error) if error.is_transient() && attempt < ATTEMPTS => {
+                    let delay = RETRY_DELAY * 2u32.pow(attempt - 1);
+                    warn!("{error}, retrying in {}s", delay.as_secs());
+                    sleep(delay);
+                    attempt += 1;
+                }
+                Err(error) => return Err(error),
+            }
+        }
+
+        let write_error = |source| HttpError::WriteError {
+            path: part.display().to_string(),
+            source,
+        };
+        if let Some(expected) = sha1 {
+            let actual = file_sha1(&part).map_err(write_error)?;
+            if !actual.eq_ignore_ascii_case(expected) {
+                fs::remove_file(&part).map_err(write_error)?;
+                return Err(HttpError::ChecksumMismatch {
+                    url: url.to_string(),
+                    expected: expected.to_string(),
+                    actual,
+                });
+            }
+        }
+        replace_file(&part, dst).map_err(write_error)
+    }
+
+    /// Downloads the rest of `url` into `part`, starting over when the server cannot resume.
+    fn fetch(&self, url: &str, part: &Path) -> Result<(), HttpError> {
+        let offset = fs::metadata(part)
+            .map(|metadata| metadata.len())
+            .unwrap_or(0);
+        let mut request = self.client.get(url);
+        if offset > 0 {
+            request = request.header(RANGE, format!("bytes={offset}-"));
+        }
+        let response = request.send().map_err(|source| HttpError::RequestError {
+            url: url.to_string(),
+            source,
+        })?;
+
+        let write_error = |source| HttpError::WriteError {
+            path: part.display().to_string(),
+            source,
+        };
+        let range_mismatch = || {
+            fs::remove_file(part).map_err(write_error)?;
+            Err(HttpError::RangeMismatch {
+                url: url.to_string(),
+            })
+        };
+        let range = response
+            .headers()
+            .get(CONTENT_RANGE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse_content_range);
+
+        let offset = match response.status() {
+            StatusCode::PARTIAL_CONTENT => match range {
+                Some((Some(start), _)) if start == offset => offset,
+                _ => return range_mismatch(),
+            },
+            // The partial file already holds everything.
+            StatusCode::RANGE_NOT_SATISFIABLE if offset > 0 => {
+                return match range {
+                    Some((None, Some(total))) if total == offset => Ok(()),
+                    _ => range_mismatch(),
+                };
+            }
+            // The server ignored the range, or there was none to ask for.
+            StatusCode::OK => 0,
+            StatusCode::NOT_FOUND | StatusCode::GONE => {
+                return Err(HttpError::NotFound {
+                    url: url.to_string(),
+                })
+            }
+            status => {
+                return Err(HttpError::StatusError {
+                    url: url.to_string(),
+                    status: status.as_u16(),
+                })
+            }
+        };
+
+        if offset > 0 {
+            info!("Resuming {url} from byte {offset}");
+        }
+        let file = if offset > 0 {
+            OpenOptions::new().append(true).open(part)
+        } else {
+            File::create(part)
+        }
+        .map_err(write_error)?;
+        self.copy(url, response, file, offset)
+            .map_err(|error| match error {
+                CopyError::Read(source) => HttpError::ReadError {
+                    url: url.to_string(),
+                    source,
+                },
+                CopyError::Write(source) => write_error(source),
+            })
+    }
+
+    /// Copies the body of `response` to `file`, reporting progress and holding to the download rate.
+    fn copy(
+        &self,
+        url: &str,
+        mut response: Response,
+        mut file: File,
+        offset: u64,
+    ) -> Result<(), CopyError> {
+        let name = url.rsplit('/').next().unwrap_or(url);
+        let mut progress = TracingProgress::new(format!("download {name}"));
+        progress.init(
+            response
+                .content_length()
+                .map(|length| (offset + length) as usize),
+            gix::progress::bytes(),
+        );
+        progress.set(offset as usize);
+
+        let started = Instant::now();
+        let mut received = 0u64;
+        let mut buffer = vec![0; CHUNK_SIZE];
+        loop {
+            let read = response.read(&mut buffer).map_err(CopyError::Read)?;
+            if read == 0 {
+                break;
+            }
+            file.write_all(&buffer[..read]).map_err(CopyError::Write)?;
+            received += read as u64;
+            progress.inc_by(read);
+
+            if let Some(max_rate) = self.max_rate {
+                let due = Duration::from_secs_f64(received as f64 / max_rate as f64);
+                if let Some(ahead) = due.checked_sub(started.elapsed()) {
+                    sleep(ahead);
+                }
+            }
+        }
+
+        file.flush().map_err(CopyError::Write)
+    }
+}
+
+/// Which side of a copy failed: reading is the connection's fault, writing the disk's.
+enum CopyError {
+    Read(io::Error),
+    Write(io::Error),
+}
+
+/// Where a download to `dst` is kept until it is complete.
+fn part_path(dst: &Path) -> PathBuf {
+    let mut part = dst.as_os_str().to_owned();
+    part.push(".part");
+    PathBuf::from(part)
+}
+
+/// The start of the range and the total length from a `Content-Range` header, e.g. `bytes 5-10/11`,
+/// or `bytes */11` as answered when the range asked for is past the end.
+fn parse_content_range(value: &str) -> Option<(Option<u64>, Option<u64>)> {
+    let (range, total) = value.strip_prefix("bytes ")?.split_once('/')?;
+    let start = match range {
+        "*" => None,
+        range => Some(range.split_once('-')?.0.parse().ok()?),
+    };
+    Some((start, total.parse().ok()))
+}
+
+fn file_sha1(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut sha1 = Sha1::new();
+    let mut buffer = vec![0; CHUNK_SIZE];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            return Ok(sha1.digest().to_string());
+        }
+        sha1.update(&buffer[..read]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_content_range, Downloader, HttpError};
+    use crate::config::TransportConfig;
+    use std::{
+        fs::{read_to_string, write},
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    /// SHA-1 of `hello world`.
+    const HELLO_SHA1: &str = "2aae6c35c94fcfb415dbe95f408b9ce91ee846ed";
+
+    /// Serves `hello world`, honoring Range requests, except `/missing`. Returns the server's base URL.
+    fn serve(requests: usize) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            for stream in listener.incoming().take(requests) {
+                let mut stream = stream.unwrap();
+                let mut request = Vec::new();
+                for line in BufReader::new(&stream).lines() {
+                    let line = line.unwrap();
+                    if line.is_empty() {
+                        break;
+                    }
+                    request.push(line.to_lowercase());
+                }
+
+                let body = "hello world";
+                let start = request
+                    .iter()
+                    .find_map(|line| line.strip_prefix("range: bytes="))
+                    .and_then(|range| range.trim_end_matches('-').parse::<usize>().ok());
+                let response = if request[0].starts_with("get /missing ") {
+                    "HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\n\r\n".to_string()
+                } else if let Some(start) = start {
+                    format!(
+                        "HTTP/1.1 206 Partial Content\r\ncontent-range: bytes {start}-{}/{}\r\ncontent-length: {}\r\n\r\n{}",
+                        body.len() - 1,
+                        body.len(),
+                        body.len() - start,
+                        &body[start..]
+                    )
+                } else {
+                    format!(
+                        "HTTP/1.1 200 OK\r\ncontent-length: {}\r\n\r\n{body}",
+                        body.len()
+                    )
+                };
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+        url
+    }
+
+    #[test]
+    fn test_download_resumes_and_verifies() {
+        let dir = tempfile::tempdir().unwrap();
+        let url = serve(3);
+        let downloader = Downloader::new(&TransportConfig::default()).unwrap();
+
+        // A previous run left the first half behind.
+        let dst = dir.path().join("bundles/app.bundle");
+        std::fs::create_dir_all(dst.parent().unwrap()).unwrap();
+        write(dir.path().join("bundles/app.bundle.part"), "hello").unwrap();
+        downloader
+            .download(&format!("{url}/app.bundle"), &dst, Some(HELLO_SHA1))
+            .unwrap();
+        assert_eq!(read_to_string(&dst).unwrap(), "hello world");
+        assert!(!dir.path().join("bundles/app.bundle.part").exists());
+
+        let other = dir.path().join("other");
+        assert!(matches!(
+            downloader.download(&format!("{url}/other"), &other, Some(&"0".repeat(40))),
+            Err(HttpError::ChecksumMismatch { actual, .. }) if actual == HELLO_SHA1
+        ));
+        assert!(!other.exists());
+
+        assert!(matches!(
+            downloader.download(&format!("{url}/missing"), &other, None),
+            Err(HttpError::NotFound { .. })
+        ));
+
+        assert_eq!(
+            parse_content_range("bytes 5-10/11"),
+            Some((Some(5), Some(11)))
+        );
+        assert_eq!(parse_content_range("bytes */11"), Some((None, Some(11))));
+    }
+}
@@ -1 +1,28 @@
+pub mod auth;
+pub mod backend;
+pub mod cli;
+pub mod clone_bundle;
 pub mod command;
+pub mod command_hooks;
+pub mod config;
+pub mod conflict;
+pub mod copyfile;
+pub mod display;
+pub mod filesystem;
+pub mod gerrit;
+pub mod git;
+pub mod git_hooks;
+pub mod hooks;
+pub mod http;
+pub mod manifest_edit;
+pub mod manifest_graph;
+pub mod manifest_server;
+pub mod picker;
+pub mod profile;
+pub mod progress;
+pub mod redact;
+pub mod redirect;
+pub mod submanifest;
+pub mod superproject;
+pub mod warnings;
+pub mod workspace;
@@ -1,52 +1,364 @@
-use clap::{CommandFactory, Parser};
-use miette::{Diagnostic, Result};
+use clap::CommandFactory;
+use miette::{Diagnostic, MietteHandlerOpts, Result};
 use repox::command::{
-    init::{self, run_init},
+    annotate::{self, run_annotate},
+    branches::{self, run_branches},
+    changes::{self, run_changes},
+    checkout::{self, run_checkout},
+    config::{self, run_config},
+    fetch::{self, run_fetch},
+    for_all::{self, run_for_all},
+    freeze::{self, run_freeze},
+    info::{self, run_info},
+    init::{self, apply_env_aliases, run_init},
+    list::{self, run_list},
+    local_manifest::{self, run_local_manifest},
+    manifest::{self, run_manifest},
+    mirror::{self, run_mirror},
+    seed::{self, run_seed},
+    self_update::{self, run_self_update},
+    start::{self, run_start},
+    status::{self, run_status},
     sync::{self, run_sync},
+    thaw::run_thaw,
+    unimplemented::Unimplemented,
+    upload::{self, run_upload},
+    worktree::{self, run_worktree},
     Command,
 };
+use repox::{
+    cli::Cli,
+    profile,
+    redact::{RedactingMakeWriter, RedactingReportHandler},
+    warnings,
+    workspace::Workspace,
+};
 use thiserror::Error;
-
-/// Work-in-Progress drop-in replacement for Google's gerrit repo tool
-#[derive(Parser, Debug)]
-#[clap(author, version, about)]
-struct Args {
-    #[command(subcommand)]
-    command: Command,
-}
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, Layer};
 
 #[derive(Debug, Error, Diagnostic)]
 enum CLIError {
     #[error("An error occurred while running the init command")]
     #[diagnostic(code(repox::main::init))]
-    InitError(#[from] init::InitError),
+    InitError(
+        #[from]
+        #[diagnostic_source]
+        init::InitError,
+    ),
 
     #[error("An error occurred while running the sync command")]
     #[diagnostic(code(repox::main::sync))]
-    SyncError(#[from] sync::SyncError),
+    SyncError(
+        #[from]
+        #[diagnostic_source]
+        sync::SyncError,
+    ),
+
+    #[error("An error occurred while running the start command")]
+    #[diagnostic(code(repox::main::start))]
+    StartError(
+        #[from]
+        #[diagnostic_source]
+        start::StartError,
+    ),
+
+    #[error("An error occurred while running the checkout command")]
+    #[diagnostic(code(repox::main::checkout))]
+    CheckoutError(
+        #[from]
+        #[diagnostic_source]
+        checkout::CheckoutError,
+    ),
+
+    #[error("An error occurred while running the changes command")]
+    #[diagnostic(code(repox::main::changes))]
+    ChangesError(
+        #[from]
+        #[diagnostic_source]
+        changes::ChangesError,
+    ),
+
+    #[error("An error occurred while running the upload command")]
+    #[diagnostic(code(repox::main::upload))]
+    UploadError(
+        #[from]
+        #[diagnostic_source]
+        upload::UploadError,
+    ),
+
+    #[error("An error occurred while running the forall command")]
+    #[diagnostic(code(repox::main::for_all))]
+    ForAllError(
+        #[from]
+        #[diagnostic_source]
+        for_all::ForAllError,
+    ),
+
+    #[error("An error occurred while running the config command")]
+    #[diagnostic(code(repox::main::config))]
+    ConfigError(
+        #[from]
+        #[diagnostic_source]
+        config::ConfigCommandError,
+    ),
+
+    #[error("An error occurred while running the annotate command")]
+    #[diagnostic(code(repox::main::annotate))]
+    AnnotateError(
+        #[from]
+        #[diagnostic_source]
+        annotate::AnnotateError,
+    ),
+
+    #[error("An error occurred while running the list command")]
+    #[diagnostic(code(repox::main::list))]
+    ListError(
+        #[from]
+        #[diagnostic_source]
+        list::ListError,
+    ),
+
+    #[error("An error occurred while running the manifest command")]
+    #[diagnostic(code(repox::main::manifest))]
+    ManifestError(
+        #[from]
+        #[diagnostic_source]
+        manifest::ManifestError,
+    ),
+
+    #[error("An error occurred while running the local-manifest command")]
+    #[diagnostic(code(repox::main::local_manifest))]
+    LocalManifestError(
+        #[from]
+        #[diagnostic_source]
+        local_manifest::LocalManifestError,
+    ),
+
+    #[error("An error occurred while running the fetch command")]
+    #[diagnostic(code(repox::main::fetch))]
+    FetchError(
+        #[from]
+        #[diagnostic_source]
+        fetch::FetchError,
+    ),
+
+    #[error("An error occurred while running the seed command")]
+    #[diagnostic(code(repox::main::seed))]
+    SeedError(
+        #[from]
+        #[diagnostic_source]
+        seed::SeedError,
+    ),
+
+    #[error("An error occurred while running the selfupdate command")]
+    #[diagnostic(code(repox::main::self_update))]
+    SelfUpdateError(
+        #[from]
+        #[diagnostic_source]
+        self_update::SelfUpdateError,
+    ),
+
+    #[error("An error occurred while running the mirror command")]
+    #[diagnostic(code(repox::main::mirror))]
+    MirrorError(
+        #[from]
+        #[diagnostic_source]
+        mirror::MirrorError,
+    ),
+
+    #[error("An error occurred while running the worktree command")]
+    #[diagnostic(code(repox::main::worktree))]
+    WorktreeError(
+        #[from]
+        #[diagnostic_source]
+        worktree::WorktreeError,
+    ),
+
+    #[error("An error occurred while running the freeze command")]
+    #[diagnostic(code(repox::main::freeze))]
+    FreezeError(
+        #[from]
+        #[diagnostic_source]
+        freeze::FreezeError,
+    ),
+
+    #[error("An error occurred while running the thaw command")]
+    #[diagnostic(code(repox::main::thaw))]
+    ThawError(#[diagnostic_source] sync::SyncError),
+
+    #[error("An error occurred while running the status command")]
+    #[diagnostic(code(repox::main::status))]
+    StatusError(
+        #[from]
+        #[diagnostic_source]
+        status::StatusError,
+    ),
+
+    #[error("An error occurred while running the branches command")]
+    #[diagnostic(code(repox::main::branches))]
+    BranchesError(
+        #[from]
+        #[diagnostic_source]
+        branches::BranchesError,
+    ),
 
-    #[error("The executed command has not been implemented: {0:#?}")]
+    #[error("An error occurred while running the info command")]
+    #[diagnostic(code(repox::main::info))]
+    InfoError(
+        #[from]
+        #[diagnostic_source]
+        info::InfoError,
+    ),
+
+    #[error("The executed command has not been implemented")]
     #[diagnostic(code(repox::main::command_unimplemented))]
-    // Command Boxed at the advice of clippy
-    UnimplementedCommand(Box<Command>),
+    UnimplementedCommand(#[diagnostic_source] Unimplemented),
+
+    #[error("The command raised {count} warning(s)")]
+    #[diagnostic(
+        code(repox::main::strict),
+        help("--strict turns warnings into errors, fix them or run without it")
+    )]
+    StrictWarnings { count: usize },
+}
+
+/// Prints the warnings the command raised, failing when there are any and `strict` is set.
+fn report_warnings(strict: bool) -> Result<()> {
+    let warnings = warnings::take();
+    for warning in &warnings {
+        eprintln!("{warning}");
+    }
+
+    if strict && !warnings.is_empty() {
+        return Err(CLIError::StrictWarnings {
+            count: warnings.len(),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// Prints where the command spent its time, when it was profiled.
+fn report_profile() {
+    if let Some(report) = profile::report() {
+        eprint!("{report}");
+    }
+}
+
+/// Points at where the manifest's authors take bug reports, when its contactinfo element says.
+fn report_contact_info() {
+    let bug_url = Workspace::discover()
+        .and_then(|workspace| workspace.manifest())
+        .ok()
+        .and_then(|manifest| Some(manifest.contact_info()?.bug_url().to_string()));
+    if let Some(bug_url) = bug_url {
+        eprintln!("If this looks like a problem with the manifest, report it at {bug_url}");
+    }
 }
 
 fn run_version() -> Result<()> {
-    let version = Args::command().render_long_version();
+    let version = Cli::command().render_long_version();
     println!("{version}");
 
     Ok(())
 }
 
+/// Parses the command line, revealing unimplemented commands in `--help` when `--experimental` is passed.
+fn parse_args() -> Cli {
+    Cli::try_parse_args(std::env::args_os()).unwrap_or_else(|error| error.exit())
+}
+
 fn main() -> Result<()> {
-    tracing_subscriber::fmt::init();
+    apply_env_aliases();
+    let args = parse_args();
+
+    // Logs and error reports may end up in shared CI logs, the credentials in URLs are kept out of both.
+    // Reports are left unwrapped, a URL split across lines would slip past redaction.
+    miette::set_hook(Box::new(|_| {
+        Box::new(RedactingReportHandler::new(Box::new(
+            MietteHandlerOpts::new().wrap_lines(false).build(),
+        )))
+    }))?;
 
-    let args = Args::parse();
+    // Logs go to stderr, keeping stdout for output meant to be parsed, like --json reports.
+    // The profile is gathered from the same spans as the logs.
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_writer(RedactingMakeWriter(std::io::stderr))
+                .with_filter(match args.verbose {
+                    0 => LevelFilter::INFO,
+                    1 => LevelFilter::DEBUG,
+                    _ => LevelFilter::TRACE,
+                }),
+        )
+        .with(
+            args.profile
+                .then(|| profile::layer().with_filter(LevelFilter::INFO)),
+        )
+        .init();
+
+    // Warnings are reported even when the command fails, they may explain why.
+    let result = run(args.command, args.strict);
+    let strict = report_warnings(args.strict);
+    report_profile();
+    if result.is_err() {
+        report_contact_info();
+    }
+    result?;
+    strict
+}
+
+fn unimplemented(command: Unimplemented) -> Result<()> {
+    Err(CLIError::UnimplementedCommand(command).into())
+}
 
-    match args.command {
+fn run(command: Command, strict: bool) -> Result<()> {
+    match command {
         Command::Init(args) => Ok(run_init(*args).map_err(CLIError::InitError)?),
         Command::Sync(args) => Ok(run_sync(args).map_err(CLIError::SyncError)?),
+        Command::Start(args) => Ok(run_start(args).map_err(CLIError::StartError)?),
+        Command::Checkout(args) => Ok(run_checkout(args).map_err(CLIError::CheckoutError)?),
+        Command::Changes(args) => Ok(run_changes(args).map_err(CLIError::ChangesError)?),
+        Command::Upload(args) => Ok(run_upload(args).map_err(CLIError::UploadError)?),
+        Command::ForAll(args) => match run_for_all(args).map_err(CLIError::ForAllError)? {
+            0 => Ok(()),
+            status => {
+                report_warnings(strict)?;
+                report_profile();
+                std::process::exit(status)
+            }
+        },
+        Command::Config(args) => Ok(run_config(args).map_err(CLIError::ConfigError)?),
+        Command::List(args) => Ok(run_list(args).map_err(CLIError::ListError)?),
+        Command::Annotate(args) => Ok(run_annotate(args).map_err(CLIError::AnnotateError)?),
+        Command::Manifest(args) => Ok(run_manifest(args).map_err(CLIError::ManifestError)?),
+        Command::LocalManifest(args) => {
+            Ok(run_local_manifest(args).map_err(CLIError::LocalManifestError)?)
+        }
+        Command::Fetch(args) => Ok(run_fetch(args).map_err(CLIError::FetchError)?),
+        Command::Seed(args) => Ok(run_seed(args).map_err(CLIError::SeedError)?),
+        Command::SelfUpdate(args) => Ok(run_self_update(args).map_err(CLIError::SelfUpdateError)?),
+        Command::Mirror(args) => Ok(run_mirror(args).map_err(CLIError::MirrorError)?),
+        Command::Worktree(args) => Ok(run_worktree(args).map_err(CLIError::WorktreeError)?),
+        Command::Freeze(args) => Ok(run_freeze(args).map_err(CLIError::FreezeError)?),
+        Command::Thaw(args) => Ok(run_thaw(args).map_err(CLIError::ThawError)?),
+        Command::Status(args) => Ok(run_status(args).map_err(CLIError::StatusError)?),
+        Command::Info(args) => Ok(run_info(args).map_err(CLIError::InfoError)?),
+        Command::Branches(args) => Ok(run_branches(args).map_err(CLIError::BranchesError)?),
         Command::Version => run_version(),
-        command => Err(CLIError::UnimplementedCommand(Box::from(command)).into()),
+        Command::Diff(_) => unimplemented(Unimplemented::Diff),
+        Command::Download(_) => unimplemented(Unimplemented::Download),
+        Command::Prune(_) => unimplemented(Unimplemented::Prune),
+        Command::Abandon => unimplemented(Unimplemented::Abandon),
+        Command::CherryPick => unimplemented(Unimplemented::CherryPick),
+        Command::DiffManifests => unimplemented(Unimplemented::DiffManifests),
+        Command::GitcDelete => unimplemented(Unimplemented::Gitc("gitc-delete")),
+        Command::GitcInit => unimplemented(Unimplemented::Gitc("gitc-init")),
+        Command::Grep => unimplemented(Unimplemented::Grep),
+        Command::Overview => unimplemented(Unimplemented::Overview),
+        Command::SmartSync => unimplemented(Unimplemented::SmartSync),
+        Command::Stage => unimplemented(Unimplemented::Stage),
     }
 }
@@ -0,0 +1,256 @@
+use quick_xml::{
+    events::{BytesStart, Event},
+    Reader, Writer,
+};
+
+/// Where a project element is in a manifest file's contents, as byte offsets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ProjectSpan {
+    /// The start of the opening tag.
+    start: usize,
+    /// The end of the opening tag.
+    tag_end: usize,
+    /// The end of the closing tag, the same as `tag_end` for empty elements.
+    end: usize,
+    /// Whether the element is empty, i.e. `<project ... />`.
+    empty: bool,
+}
+
+/// Whether the tag is a project element for `name`, checked out at `path` when one is given.
+fn is_project(tag: &BytesStart, name: &str, path: Option<&str>) -> Result<bool, quick_xml::Error> {
+    if tag.name().as_ref() != b"project" {
+        return Ok(false);
+    }
+    let attribute = |key: &str| -> Result<Option<String>, quick_xml::Error> {
+        match tag.try_get_attribute(key)? {
+            Some(attribute) => Ok(Some(attribute.unescape_value()?.into_owned())),
+            None => Ok(None),
+        }
+    };
+
+    if attribute("name")?.as_deref() != Some(name) {
+        return Ok(false);
+    }
+    Ok(match path {
+        Some(path) => attribute("path")?.as_deref().unwrap_or(name) == path,
+        None => true,
+    })
+}
+
+/// The project elements for `name` in `contents`, nested ones included.
+fn find_projects(
+    contents: &str,
+    name: &str,
+    path: Option<&str>,
+) -> Result<Vec<ProjectSpan>, quick_xml::Error> {
+    let mut reader = Reader::from_str(contents);
+    let mut spans = Vec::new();
+    // The opening tags of the project elements being read, if they match.
+    let mut open: Vec<Option<(usize, usize)>> = Vec::new();
+    loop {
+        let start = reader.buffer_position();
+        match reader.read_event()? {
+            Event::Eof => break,
+            Event::Empty(tag) if is_project(&tag, name, path)? => {
+                let end = reader.buffer_position();
+                spans.push(ProjectSpan {
+                    start,
+                    tag_end: end,
+                    end,
+                    empty: true,
+                });
+            }
+            Event::Start(tag) if tag.name().as_ref() == b"project" => {
+                let matched = is_project(&tag, name, path)?;
+                open.push(matched.then_some((start, reader.buffer_position())));
+            }
+            Event::End(tag) if tag.name().as_ref() == b"project" => {
+                if let Some(Some((start, tag_end))) = open.pop() {
+                    spans.push(ProjectSpan {
+                        start,
+                        tag_end,
+                        end: reader.buffer_position(),
+                        empty: false,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(spans)
+}
+
+/// The single project element for `name`, `None` if there is none and an error message if there are several.
+fn find_project(
+    contents: &str,
+    name: &str,
+    path: Option<&str>,
+) -> Result<Option<ProjectSpan>, String> {
+    let spans = find_projects(contents, name, path).map_err(|error| error.to_string())?;
+    match spans.as_slice() {
+        [] => Ok(None),
+        [span] => Ok(Some(*span)),
+        _ => Err(format!(
+            "{} project elements are named {name}, pass the path of the one to edit",
+            spans.len()
+        )),
+    }
+}
+
+/// `contents` with the path attribute of the project element for `name` set to `new_path`, other
+/// attributes and the rest of the file left as they are. `None` when no such element exists.
+pub fn move_project(
+    contents: &str,
+    name: &str,
+    path: Option<&str>,
+    new_path: &str,
+) -> Result<Option<String>, String> {
+    let Some(span) = find_project(contents, name, path)? else {
+        return Ok(None);
+    };
+
+    let mut reader = Reader::from_str(&contents[span.start..span.tag_end]);
+    let tag = match reader.read_event().map_err(|error| error.to_string())? {
+        Event::Empty(tag) | Event::Start(tag) => tag,
+        event => unreachable!("the span starts with a project tag, not {event:?}"),
+    };
+    let mut attributes = Vec::new();
+    for attribute in tag.attributes() {
+        let attribute = attribute.map_err(|error| error.to_string())?;
+        let key = String::from_utf8_lossy(attribute.key.as_ref()).into_owned();
+        let value = attribute
+            .unescape_value()
+            .map_err(|error| error.to_string())?
+            .into_owned();
+        attributes.push((key, value));
+    }
+    match attributes.iter_mut().find(|(key, _)| key == "path") {
+        Some((_, value)) => *value = new_path.to_string(),
+        None => {
+            let after_name = attributes
+                .iter()
+                .position(|(key, _)| key == "name")
+                .map_or(attributes.len(), |index| index + 1);
+            attributes.insert(after_name, ("path".to_string(), new_path.to_string()));
+        }
+    }
+
+    let moved = BytesStart::new("project").with_attributes(
+        attributes
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str())),
+    );
+    let mut writer = Writer::new(Vec::new());
+    let event = if span.empty {
+        Event::Empty(moved)
+    } else {
+        Event::Start(moved)
+    };
+    writer
+        .write_event(event)
+        .expect("writing to a Vec does not fail");
+    let moved = String::from_utf8(writer.into_inner()).expect("attributes are valid UTF-8");
+
+    Ok(Some(format!(
+        "{}{moved}{}",
+        &contents[..span.start],
+        &contents[span.tag_end..]
+    )))
+}
+
+/// `contents` without the project element for `name`, and without the line it was on if nothing else
+/// was. `None` when no such element exists.
+pub fn remove_project(
+    contents: &str,
+    name: &str,
+    path: Option<&str>,
+) -> Result<Option<String>, String> {
+    let Some(span) = find_project(contents, name, path)? else {
+        return Ok(None);
+    };
+
+    let (mut start, mut end) = (span.start, span.end);
+    let line_start = contents[..start].rfind('\n').map_or(0, |index| index + 1);
+    let line_end = contents[end..]
+        .find('\n')
+        .map_or(contents.len(), |index| end + index + 1);
+    if contents[line_start..start].trim().is_empty() && contents[end..line_end].trim().is_empty() {
+        start = line_start;
+        end = line_end;
+    }
+
+    Ok(Some(format!("{}{}", &contents[..start], &contents[end..])))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{move_project, remove_project};
+
+    const MANIFEST: &str = r#"<manifest>
+  <!-- Applications -->
+  <project name="app" groups="apps" />
+  <project name="lib" path="src/lib">
+    <project name="plugin" />
+  </project>
+  <project name="tools" path="a" /><project name="tools" path="b" />
+</manifest>
+"#;
+
+    #[test]
+    fn test_move_project() {
+        let moved = move_project(MANIFEST, "app", None, "apps/app")
+            .unwrap()
+            .unwrap();
+        assert!(
+            moved.contains(
+                r#"  <!-- Applications -->
+  <project name="app" path="apps/app" groups="apps"/>
+  <project name="lib" path="src/lib">"#
+            ),
+            "{moved}"
+        );
+
+        let moved = move_project(MANIFEST, "lib", None, "lib").unwrap().unwrap();
+        assert!(
+            moved
+                .contains("  <project name=\"lib\" path=\"lib\">\n    <project name=\"plugin\" />"),
+            "{moved}"
+        );
+
+        assert_eq!(move_project(MANIFEST, "gone", None, "x"), Ok(None));
+        assert!(move_project(MANIFEST, "tools", None, "c").is_err());
+        let moved = move_project(MANIFEST, "tools", Some("b"), "c&d")
+            .unwrap()
+            .unwrap();
+        assert!(
+            moved.contains(
+                r#"<project name="tools" path="a" /><project name="tools" path="c&amp;d"/>"#
+            ),
+            "{moved}"
+        );
+    }
+
+    #[test]
+    fn test_remove_project() {
+        let removed = remove_project(MANIFEST, "lib", None).unwrap().unwrap();
+        assert_eq!(
+            removed,
+            r#"<manifest>
+  <!-- Applications -->
+  <project name="app" groups="apps" />
+  <project name="tools" path="a" /><project name="tools" path="b" />
+</manifest>
+"#
+        );
+
+        let removed = remove_project(MANIFEST, "tools", Some("a"))
+            .unwrap()
+            .unwrap();
+        assert!(
+            removed.contains("\n  <project name=\"tools\" path=\"b\" />\n"),
+            "{removed}"
+        );
+        assert_eq!(remove_project(MANIFEST, "gone", None), Ok(None));
+    }
+}
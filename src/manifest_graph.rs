@@ -0,0 +1,269 @@
+use crate::workspace::local_manifest_paths;
+use quick_xml::de::from_str;
+use repox_manifest::{extend_project::ExtendProject, Manifest};
+use std::{collections::HashMap, fs::read_to_string, path::Path};
+
+/// A manifest file and what it contributes: its projects, removals, extensions and included files.
+#[derive(Debug, Default)]
+pub struct ManifestNode {
+    /// The file, relative to the manifest repository, or `local_manifests/` for local manifests.
+    pub file: String,
+    /// The names and checkout paths of the projects the file declares.
+    pub projects: Vec<(String, String)>,
+    pub removes: Vec<String>,
+    /// The extended project names, with the overrides they apply.
+    pub extends: Vec<(String, String)>,
+    pub includes: Vec<ManifestNode>,
+    /// Why the file could not be read, e.g. a missing include or an include cycle.
+    pub error: Option<String>,
+}
+
+impl ManifestNode {
+    /// Reads `file`, relative to `manifest_dir`, and the files it includes, which `parents` must not be among.
+    fn read(manifest_dir: &Path, file: &str, path: &Path, parents: &mut Vec<String>) -> Self {
+        let mut node = ManifestNode {
+            file: file.to_string(),
+            ..Default::default()
+        };
+        if parents.iter().any(|parent| parent == file) {
+            node.error = Some(format!("include cycle through {}", parents.join(" -> ")));
+            return node;
+        }
+
+        let manifest: Manifest = match read_to_string(path)
+            .map_err(|error| error.to_string())
+            .and_then(|contents| from_str(&contents).map_err(|error| error.to_string()))
+        {
+            Ok(manifest) => manifest,
+            Err(error) => {
+                node.error = Some(error);
+                return node;
+            }
+        };
+
+        node.projects = manifest
+            .projects()
+            .iter()
+            .map(|project| (project.name.clone(), project.checkout_path().to_string()))
+            .collect();
+        node.removes = manifest
+            .remove_projects()
+            .iter()
+            .map(|remove| remove.name().to_string())
+            .collect();
+        node.extends = manifest
+            .extend_projects()
+            .iter()
+            .map(|extend| (extend.name().to_string(), describe_overrides(extend)))
+            .collect();
+
+        parents.push(file.to_string());
+        node.includes = manifest
+            .includes()
+            .iter()
+            .map(|include| {
+                let path = manifest_dir.join(include.name());
+                ManifestNode::read(manifest_dir, include.name(), &path, parents)
+            })
+            .collect();
+        parents.pop();
+
+        node
+    }
+
+    /// This file and the files it includes, depth first.
+    fn files(&self) -> Vec<&ManifestNode> {
+        let mut files = vec![self];
+        files.extend(self.includes.iter().flat_map(ManifestNode::files));
+        files
+    }
+}
+
+/// The attributes an extend-project element overrides, e.g. `revision=stable, groups=+tools`.
+fn describe_overrides(extend: &ExtendProject) -> String {
+    [
+        ("path", extend.path()),
+        ("remote", extend.remote()),
+        ("revision", extend.revision()),
+        ("groups", extend.groups().map(|groups| groups.trim())),
+        ("dest-path", extend.dest_path()),
+    ]
+    .into_iter()
+    .filter_map(|(attribute, value)| Some(format!("{attribute}={}", value?)))
+    .collect::<Vec<_>>()
+    .join(", ")
+}
+
+/// The include tree of a workspace's manifest, followed by its local manifests.
+#[derive(Debug)]
+pub struct ManifestGraph {
+    pub manifest: ManifestNode,
+    pub local_manifests: Vec<ManifestNode>,
+}
+
+impl ManifestGraph {
+    /// Reads the manifest at `manifest_path`, the files it includes, and the `*.xml` files in `local_manifests_dir`.
+    ///
+    /// Includes are resolved relative to the manifest's directory, for local manifests too. Files that cannot
+    /// be read are kept in the graph with the reason, since a broken include is what the graph is for.
+    pub fn read(manifest_path: &Path, local_manifests_dir: &Path) -> Self {
+        let manifest_dir = manifest_path.parent().unwrap_or(Path::new("."));
+        let file = manifest_path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let manifest = ManifestNode::read(manifest_dir, &file, manifest_path, &mut Vec::new());
+
+        let local_manifests = local_manifest_paths(local_manifests_dir)
+            .iter()
+            .map(|path| {
+                let file = format!(
+                    "local_manifests/{}",
+                    path.file_name().unwrap_or_default().to_string_lossy()
+                );
+                ManifestNode::read(manifest_dir, &file, path, &mut Vec::new())
+            })
+            .collect();
+
+        ManifestGraph {
+            manifest,
+            local_manifests,
+        }
+    }
+
+    /// Which file declares each project name, the first declaration winning, local manifests coming last.
+    fn declared_in(&self) -> HashMap<&str, &str> {
+        let mut declared_in = HashMap::new();
+        let local_files = self.local_manifests.iter().flat_map(ManifestNode::files);
+        for node in self.manifest.files().into_iter().chain(local_files) {
+            for (name, _) in &node.projects {
+                declared_in
+                    .entry(name.as_str())
+                    .or_insert(node.file.as_str());
+            }
+        }
+        declared_in
+    }
+
+    /// The file of the manifest repository declaring the project `name`, leaving out local manifests.
+    pub fn declaring_file(&self, name: &str) -> Option<&str> {
+        self.manifest
+            .files()
+            .into_iter()
+            .find(|node| node.projects.iter().any(|(project, _)| project == name))
+            .map(|node| node.file.as_str())
+    }
+
+    /// Renders the graph as an indented tree, noting which file each local manifest override applies to.
+    pub fn to_text(&self) -> String {
+        let declared_in = self.declared_in();
+        let mut text = String::new();
+        write_text(&mut text, &self.manifest, 0, &declared_in);
+        for local in &self.local_manifests {
+            write_text(&mut text, local, 0, &declared_in);
+        }
+        text
+    }
+
+    /// Renders the graph in Graphviz's DOT language, with files as boxes and projects as ellipses.
+    pub fn to_dot(&self) -> String {
+        let declared_in = self.declared_in();
+        let mut dot = String::from("digraph manifest {\n    rankdir=LR;\n");
+        write_dot(&mut dot, &self.manifest, &declared_in);
+        for local in &self.local_manifests {
+            write_dot(&mut dot, local, &declared_in);
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// Where a local manifest's removal or extension of `name` lands.
+fn origin(declared_in: &HashMap<&str, &str>, name: &str) -> String {
+    match declared_in.get(name) {
+        Some(file) => format!("from {file}"),
+        None => "not in the manifest".to_string(),
+    }
+}
+
+fn write_text(
+    text: &mut String,
+    node: &ManifestNode,
+    depth: usize,
+    declared_in: &HashMap<&str, &str>,
+) {
+    let indent = "  ".repeat(depth);
+    if let Some(error) = &node.error {
+        text.push_str(&format!("{indent}{} (unreadable: {error})\n", node.file));
+        return;
+    }
+    let count = node.projects.len();
+    let plural = if count == 1 { "" } else { "s" };
+    text.push_str(&format!("{indent}{}: {count} project{plural}\n", node.file));
+
+    for (name, path) in &node.projects {
+        if name == path {
+            text.push_str(&format!("{indent}  project {name}\n"));
+        } else {
+            text.push_str(&format!("{indent}  project {name} at {path}\n"));
+        }
+    }
+    for name in &node.removes {
+        let origin = origin(declared_in, name);
+        text.push_str(&format!("{indent}  remove-project {name}, {origin}\n"));
+    }
+    for (name, overrides) in &node.extends {
+        let origin = origin(declared_in, name);
+        text.push_str(&format!(
+            "{indent}  extend-project {name} ({overrides}), {origin}\n"
+        ));
+    }
+    for include in &node.includes {
+        write_text(text, include, depth + 1, declared_in);
+    }
+}
+
+/// Quotes `id` as a DOT identifier.
+fn quote(id: &str) -> String {
+    format!("\"{}\"", id.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn write_dot(dot: &mut String, node: &ManifestNode, declared_in: &HashMap<&str, &str>) {
+    let file = quote(&node.file);
+    match &node.error {
+        Some(error) => {
+            let label = quote(&format!("{}\n{error}", node.file));
+            dot.push_str(&format!(
+                "    {file} [shape=box, color=red, label={label}];\n"
+            ));
+        }
+        None => dot.push_str(&format!("    {file} [shape=box];\n")),
+    }
+
+    for (name, _) in &node.projects {
+        dot.push_str(&format!("    {file} -> {};\n", quote(name)));
+    }
+    for name in &node.removes {
+        if !declared_in.contains_key(name.as_str()) {
+            dot.push_str(&format!("    {} [color=red];\n", quote(name)));
+        }
+        dot.push_str(&format!(
+            "    {file} -> {} [style=dashed, color=red, label=\"remove-project\"];\n",
+            quote(name)
+        ));
+    }
+    for (name, overrides) in &node.extends {
+        let label = quote(&format!("extend-project\n{overrides}"));
+        dot.push_str(&format!(
+            "    {file} -> {} [style=dashed, label={label}];\n",
+            quote(name)
+        ));
+    }
+    for include in &node.includes {
+        dot.push_str(&format!(
+            "    {file} -> {} [label=\"include\"];\n",
+            quote(&include.file)
+        ));
+        write_dot(dot, include, declared_in);
+    }
+}
@@ -0,0 +1,508 @@
+use crate::{
+    config::TransportConfig,
+    http::{client, HttpError},
+};
+use miette::Diagnostic;
+use quick_xml::{escape::escape, events::Event, Reader};
+use reqwest::{blocking::Client, header::CONTENT_TYPE};
+use std::env;
+use thiserror::Error;
+use tracing::info_span;
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum ManifestServerError {
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    HttpError(#[from] HttpError),
+
+    #[error("Could not call {method} on the manifest server {url}")]
+    #[diagnostic(code(repox::manifest_server::request))]
+    RequestError {
+        url: String,
+        method: &'static str,
+        #[source]
+        source: reqwest::Error,
+    },
+
+    #[error("The manifest server {url} answered {status}")]
+    #[diagnostic(code(repox::manifest_server::status))]
+    StatusError { url: String, status: u16 },
+
+    #[error("The manifest server {url} answered with something other than XML-RPC: {reason}")]
+    #[diagnostic(code(repox::manifest_server::response))]
+    ResponseError { url: String, reason: String },
+
+    #[error("{method} failed on the manifest server {url}: {message} (fault {code})")]
+    #[diagnostic(code(repox::manifest_server::fault))]
+    Fault {
+        url: String,
+        method: &'static str,
+        code: i64,
+        message: String,
+    },
+
+    #[error("The manifest server {url} has no manifest for {request}: {message}")]
+    #[diagnostic(
+        code(repox::manifest_server::rejected),
+        help("check the branch, target or tag asked for, and the manifest-server element of the manifest")
+    )]
+    Rejected {
+        url: String,
+        request: String,
+        message: String,
+    },
+}
+
+/// An XML-RPC value, as far as manifest servers use them.
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Bool(bool),
+    Int(i64),
+    Double(f64),
+    String(String),
+    Array(Vec<Value>),
+    Struct(Vec<(String, Value)>),
+    Nil,
+}
+
+impl Value {
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    fn member(&self, name: &str) -> Option<&Value> {
+        match self {
+            Value::Struct(members) => members
+                .iter()
+                .find(|(member, _)| member == name)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+}
+
+/// An element of an XML-RPC document, with the text directly inside it.
+#[derive(Debug, Default)]
+struct Element {
+    name: String,
+    text: String,
+    children: Vec<Element>,
+}
+
+impl Element {
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    /// The child elements, the whitespace between them left out.
+    fn only_child(&self) -> Option<&Element> {
+        match self.children.as_slice() {
+            [child] => Some(child),
+            _ => None,
+        }
+    }
+}
+
+/// Parses an XML document into its root element.
+fn parse_document(xml: &str) -> Result<Element, String> {
+    let mut reader = Reader::from_str(xml);
+    let mut open: Vec<Element> = vec![Element::default()];
+    loop {
+        match reader.read_event().map_err(|error| error.to_string())? {
+            Event::Start(tag) => open.push(Element {
+                name: String::from_utf8_lossy(tag.name().as_ref()).into_owned(),
+                ..Element::default()
+            }),
+            Event::Empty(tag) => {
+                let element = Element {
+                    name: String::from_utf8_lossy(tag.name().as_ref()).into_owned(),
+                    ..Element::default()
+                };
+                open.last_mut()
+                    .expect("the document is open")
+                    .children
+                    .push(element);
+            }
+            Event::End(_) => {
+                let element = open.pop().expect("the reader checks tags are balanced");
+                open.last_mut()
+                    .ok_or("unbalanced tags")?
+                    .children
+                    .push(element);
+            }
+            Event::Text(text) => {
+                let text = text.unescape().map_err(|error| error.to_string())?;
+                open.last_mut()
+                    .expect("the document is open")
+                    .text
+                    .push_str(&text);
+            }
+            Event::CData(text) => {
+                let text = String::from_utf8_lossy(&text.into_inner()).into_owned();
+                open.last_mut()
+                    .expect("the document is open")
+                    .text
+                    .push_str(&text);
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    let document = open
+        .pop()
+        .filter(|_| open.is_empty())
+        .ok_or("unclosed tags")?;
+    document
+        .children
+        .into_iter()
+        .next()
+        .ok_or_else(|| "the document is empty".to_string())
+}
+
+/// The value a `<value>` element holds. Values without a type element are strings.
+fn parse_value(element: &Element) -> Result<Value, String> {
+    let Some(typed) = element.only_child() else {
+        return if element.children.is_empty() {
+            Ok(Value::String(element.text.clone()))
+        } else {
+            Err("a value holds several elements".to_string())
+        };
+    };
+
+    let invalid = |kind: &str| format!("{} is not a valid {kind}", typed.text.trim());
+    Ok(match typed.name.as_str() {
+        "string" | "base64" | "dateTime.iso8601" => Value::String(typed.text.clone()),
+        "boolean" => match typed.text.trim() {
+            "1" => Value::Bool(true),
+            "0" => Value::Bool(false),
+            _ => return Err(invalid("boolean")),
+        },
+        "int" | "i4" | "i8" => Value::Int(typed.text.trim().parse().map_err(|_| invalid("int"))?),
+        "double" => Value::Double(typed.text.trim().parse().map_err(|_| invalid("double"))?),
+        "nil" => Value::Nil,
+        "array" => Value::Array(
+            typed
+                .child("data")
+                .map(|data| data.children.iter().map(parse_value).collect())
+                .transpose()?
+                .unwrap_or_default(),
+        ),
+        "struct" => Value::Struct(
+            typed
+                .children
+                .iter()
+                .map(|member| {
+                    let name = member.child("name").ok_or("a struct member has no name")?;
+                    let value = member
+                        .child("value")
+                        .ok_or("a struct member has no value")?;
+                    Ok((name.text.clone(), parse_value(value)?))
+                })
+                .collect::<Result<_, String>>()?,
+        ),
+        other => return Err(format!("{other} is not an XML-RPC type")),
+    })
+}
+
+/// A method response's value, or the code and message of its fault.
+fn parse_response(xml: &str) -> Result<Result<Value, (i64, String)>, String> {
+    let response = parse_document(xml)?;
+    if response.name != "methodResponse" {
+        return Err(format!("the document is a {}", response.name));
+    }
+
+    if let Some(fault) = response.child("fault") {
+        let fault = parse_value(fault.child("value").ok_or("the fault has no value")?)?;
+        let code = match fault.member("faultCode") {
+            Some(Value::Int(code)) => *code,
+            _ => 0,
+        };
+        let message = fault
+            .member("faultString")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string();
+        return Ok(Err((code, message)));
+    }
+
+    let value = response
+        .child("params")
+        .and_then(|params| params.child("param"))
+        .and_then(|param| param.child("value"))
+        .ok_or("the response has no value")?;
+    Ok(Ok(parse_value(value)?))
+}
+
+/// The body of a call to `method` with string `params`.
+fn method_call(method: &str, params: &[&str]) -> String {
+    let params: String = params
+        .iter()
+        .map(|param| {
+            format!(
+                "<param><value><string>{}</string></value></param>",
+                escape(param)
+            )
+        })
+        .collect();
+    format!(
+        "<?xml version=\"1.0\"?>\n<methodCall><methodName>{method}</methodName><params>{params}</params></methodCall>\n"
+    )
+}
+
+/// The build target to ask the manifest server about, from the environment as repo reads it: `SYNC_TARGET`
+/// when set, or else the Android build's `TARGET_PRODUCT`, `TARGET_RELEASE` and `TARGET_BUILD_VARIANT`.
+pub fn sync_target() -> Option<String> {
+    target_from(|name| env::var(name).ok().filter(|value| !value.is_empty()))
+}
+
+fn target_from(var: impl Fn(&str) -> Option<String>) -> Option<String> {
+    if let Some(target) = var("SYNC_TARGET") {
+        return Some(target);
+    }
+
+    let product = var("TARGET_PRODUCT")?;
+    let variant = var("TARGET_BUILD_VARIANT")?;
+    Some(match var("TARGET_RELEASE") {
+        Some(release) => format!("{product}-{release}-{variant}"),
+        None => format!("{product}-{variant}"),
+    })
+}
+
+/// A client of the XML-RPC service a manifest's manifest-server element names, which hands out manifests
+/// pinning every project to a known good revision, for `sync --smart-sync` and `--smart-tag`.
+pub struct ManifestServerClient {
+    client: Client,
+    url: String,
+}
+
+impl ManifestServerClient {
+    /// A client of the service at `url`, honoring the workspace's proxy and certificates.
+    pub fn new(url: &str, transport: &TransportConfig) -> Result<Self, ManifestServerError> {
+        Ok(ManifestServerClient {
+            client: client(transport)?,
+            url: url.to_string(),
+        })
+    }
+
+    /// The latest approved manifest for the manifest `branch`, built for `target` when one is given, as
+    /// `GetApprovedManifest` returns it. See [`sync_target`] for the target repo asks for.
+    pub fn approved_manifest(
+        &self,
+        branch: &str,
+        target: Option<&str>,
+    ) -> Result<String, ManifestServerError> {
+        let branch = branch.strip_prefix("refs/heads/").unwrap_or(branch);
+        let (params, request) = match target {
+            Some(target) => (vec![branch, target], format!("{branch} and {target}")),
+            None => (vec![branch], branch.to_string()),
+        };
+        self.manifest_call("GetApprovedManifest", &params, request)
+    }
+
+    /// The manifest tagged `tag`, as `GetManifest` returns it.
+    pub fn manifest(&self, tag: &str) -> Result<String, ManifestServerError> {
+        self.manifest_call("GetManifest", &[tag], tag.to_string())
+    }
+
+    /// Calls a method answering `[success, manifest or error message]`, as both of the service's do.
+    fn manifest_call(
+        &self,
+        method: &'static str,
+        params: &[&str],
+        request: String,
+    ) -> Result<String, ManifestServerError> {
+        let response_error = |reason: &str| ManifestServerError::ResponseError {
+            url: self.url.clone(),
+            reason: reason.to_string(),
+        };
+
+        match self.call(method, params)? {
+            Value::Array(values) => match values.as_slice() {
+                [Value::Bool(true), Value::String(manifest)] => Ok(manifest.clone()),
+                [Value::Bool(false), message] => Err(ManifestServerError::Rejected {
+                    url: self.url.clone(),
+                    request,
+                    message: message.as_str().unwrap_or_default().to_string(),
+                }),
+                _ => Err(response_error(
+                    "the result is not a success flag and a string",
+                )),
+            },
+            _ => Err(response_error("the result is not an array")),
+        }
+    }
+
+    fn call(&self, method: &'static str, params: &[&str]) -> Result<Value, ManifestServerError> {
+        let _call_span = info_span!("Calling manifest server", method).entered();
+        let request_error = |source| ManifestServerError::RequestError {
+            url: self.url.clone(),
+            method,
+            source,
+        };
+
+        let response = self
+            .client
+            .post(&self.url)
+            .header(CONTENT_TYPE, "text/xml")
+            .body(method_call(method, params))
+            .send()
+            .map_err(request_error)?;
+        if !response.status().is_success() {
+            return Err(ManifestServerError::StatusError {
+                url: self.url.clone(),
+                status: response.status().as_u16(),
+            });
+        }
+
+        let body = response.text().map_err(request_error)?;
+        match parse_response(&body) {
+            Ok(Ok(value)) => Ok(value),
+            Ok(Err((code, message))) => Err(ManifestServerError::Fault {
+                url: self.url.clone(),
+                method,
+                code,
+                message,
+            }),
+            Err(reason) => Err(ManifestServerError::ResponseError {
+                url: self.url.clone(),
+                reason,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{method_call, parse_response, target_from, ManifestServerClient, Value};
+    use crate::config::TransportConfig;
+    use std::{
+        collections::HashMap,
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    #[test]
+    fn test_parse_response() {
+        assert_eq!(
+            method_call("GetManifest", &["v1.0 <rc>"]),
+            "<?xml version=\"1.0\"?>\n<methodCall><methodName>GetManifest</methodName><params>\
+             <param><value><string>v1.0 &lt;rc&gt;</string></value></param></params></methodCall>\n"
+        );
+
+        let response = r#"<?xml version="1.0"?>
+            <methodResponse><params><param><value><array><data>
+              <value><boolean>1</boolean></value>
+              <value><string>&lt;manifest /&gt;</string></value>
+              <value>untyped</value>
+              <value><int>7</int></value>
+            </data></array></value></param></params></methodResponse>"#;
+        assert_eq!(
+            parse_response(response),
+            Ok(Ok(Value::Array(vec![
+                Value::Bool(true),
+                Value::String("<manifest />".to_string()),
+                Value::String("untyped".to_string()),
+                Value::Int(7),
+            ])))
+        );
+
+        let fault = r#"<methodResponse><fault><value><struct>
+              <member><name>faultCode</name><value><int>4</int></value></member>
+              <member><name>faultString</name><value><string>Too many parameters</string></value></member>
+            </struct></value></fault></methodResponse>"#;
+        assert_eq!(
+            parse_response(fault),
+            Ok(Err((4, "Too many parameters".to_string())))
+        );
+        assert!(parse_response("<html><body>Not Found</body></html>").is_err());
+        assert!(parse_response("<methodResponse><params>").is_err());
+    }
+
+    #[test]
+    fn test_sync_target() {
+        let target = |vars: &[(&str, &str)]| {
+            let vars: HashMap<_, _> = vars.iter().copied().collect();
+            target_from(|name| vars.get(name).map(|value| value.to_string()))
+        };
+        assert_eq!(target(&[]), None);
+        assert_eq!(target(&[("TARGET_PRODUCT", "aosp_arm64")]), None);
+        assert_eq!(
+            target(&[
+                ("TARGET_PRODUCT", "aosp_arm64"),
+                ("TARGET_BUILD_VARIANT", "userdebug")
+            ])
+            .as_deref(),
+            Some("aosp_arm64-userdebug")
+        );
+        assert_eq!(
+            target(&[
+                ("TARGET_PRODUCT", "aosp_arm64"),
+                ("TARGET_RELEASE", "trunk_staging"),
+                ("TARGET_BUILD_VARIANT", "userdebug"),
+                ("SYNC_TARGET", "custom")
+            ])
+            .as_deref(),
+            Some("custom")
+        );
+    }
+
+    #[test]
+    fn test_approved_manifest() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            let mut bodies = Vec::new();
+            for (success, stream) in ["1", "0"].into_iter().zip(listener.incoming()) {
+                let mut reader = BufReader::new(stream.unwrap());
+                let mut length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if let Some(value) = line.to_lowercase().strip_prefix("content-length:") {
+                        length = value.trim().parse().unwrap();
+                    }
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                bodies.push(String::from_utf8(body).unwrap());
+
+                let response = format!(
+                    "<methodResponse><params><param><value><array><data>\
+                     <value><boolean>{success}</boolean></value><value><string>manifest</string></value>\
+                     </data></array></value></param></params></methodResponse>"
+                );
+                write!(
+                    reader.get_mut(),
+                    "HTTP/1.1 200 OK\r\ncontent-length: {}\r\n\r\n{response}",
+                    response.len()
+                )
+                .unwrap();
+            }
+            bodies
+        });
+
+        let client = ManifestServerClient::new(&url, &TransportConfig::default()).unwrap();
+        assert_eq!(
+            client
+                .approved_manifest("refs/heads/main", Some("aosp_arm64-userdebug"))
+                .unwrap(),
+            "manifest"
+        );
+        assert!(client.manifest("v1.0").is_err());
+
+        let bodies = server.join().unwrap();
+        assert!(bodies[0].contains(
+            "<methodName>GetApprovedManifest</methodName><params><param><value><string>main</string>"
+        ));
+        assert!(bodies[0].contains("<string>aosp_arm64-userdebug</string>"));
+        assert!(bodies[1].contains("<methodName>GetManifest</methodName>"));
+    }
+}
@@ -0,0 +1,179 @@
+use repox_manifest::project::Project;
+use std::io::{self, BufRead, Write};
+
+/// How many matching projects are listed at once; narrowing the search shows the rest.
+const PAGE_SIZE: usize = 30;
+
+/// Whether every character of `query` appears in `text` in order, ignoring case, so `kcom` finds `kernel/common`.
+fn fuzzy_matches(text: &str, query: &str) -> bool {
+    let mut text = text.chars().flat_map(char::to_lowercase);
+    query
+        .chars()
+        .flat_map(char::to_lowercase)
+        .filter(|c| !c.is_whitespace())
+        .all(|wanted| text.any(|c| c == wanted))
+}
+
+/// What a project is searched by and listed with: its path, name and groups.
+fn describe(project: &Project) -> String {
+    let mut line = project.checkout_path().to_string();
+    if project.name != project.checkout_path() {
+        line.push_str(&format!(" ({})", project.name));
+    }
+    let groups = project.group_list();
+    if !groups.is_empty() {
+        line.push_str(&format!(" [{}]", groups.join(", ")));
+    }
+    line
+}
+
+/// Parses a selection such as `1 3-5,8` into indices into the listed projects.
+fn parse_selection(input: &str, listed: usize) -> Option<Vec<usize>> {
+    let mut indices = Vec::new();
+    for item in input
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|item| !item.is_empty())
+    {
+        let (start, end): (usize, usize) = match item.split_once('-') {
+            Some((start, end)) => (start.parse().ok()?, end.parse().ok()?),
+            None => {
+                let number = item.parse().ok()?;
+                (number, number)
+            }
+        };
+        if start == 0 || start > end || end > listed {
+            return None;
+        }
+        indices.extend(start - 1..end);
+    }
+    (!indices.is_empty()).then_some(indices)
+}
+
+/// Lets the user choose projects among `candidates`, reading commands from `input`.
+///
+/// `preselected` decides which projects start out chosen. Typing text searches the projects' paths,
+/// names and groups and `/` clears the search, numbers and ranges toggle listed projects, `a` toggles
+/// everything listed, and an empty line accepts the choice. Returns `None` if the user quits with `q` or closes the input.
+pub fn pick_projects(
+    candidates: Vec<Project>,
+    preselected: impl Fn(&Project) -> bool,
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+) -> io::Result<Option<Vec<Project>>> {
+    let descriptions: Vec<_> = candidates.iter().map(describe).collect();
+    let mut chosen: Vec<bool> = candidates.iter().map(preselected).collect();
+    let mut query = String::new();
+
+    loop {
+        let listed: Vec<usize> = (0..candidates.len())
+            .filter(|&index| fuzzy_matches(&descriptions[index], &query))
+            .collect();
+
+        writeln!(output)?;
+        for (number, &index) in listed.iter().take(PAGE_SIZE).enumerate() {
+            let mark = if chosen[index] { 'x' } else { ' ' };
+            writeln!(
+                output,
+                "[{mark}] {:>3}  {}",
+                number + 1,
+                descriptions[index]
+            )?;
+        }
+        if listed.len() > PAGE_SIZE {
+            writeln!(
+                output,
+                "... and {} more, type to narrow the search",
+                listed.len() - PAGE_SIZE
+            )?;
+        }
+        let count = chosen.iter().filter(|&&chosen| chosen).count();
+        write!(
+            output,
+            "{count} of {} projects chosen{}. Search ('/' to clear), toggle by \
+             number (1 3-5), 'a' for all listed, enter to sync, 'q' to quit: ",
+            candidates.len(),
+            if query.is_empty() {
+                String::new()
+            } else {
+                format!(", matching '{query}'")
+            },
+        )?;
+        output.flush()?;
+
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim();
+        let shown = &listed[..listed.len().min(PAGE_SIZE)];
+
+        match line {
+            "" => {
+                let picked = candidates
+                    .into_iter()
+                    .zip(chosen)
+                    .filter_map(|(project, chosen)| chosen.then_some(project))
+                    .collect();
+                return Ok(Some(picked));
+            }
+            "q" => return Ok(None),
+            "a" => {
+                let all = shown.iter().all(|&index| chosen[index]);
+                shown.iter().for_each(|&index| chosen[index] = !all);
+            }
+            "/" => query.clear(),
+            _ => match parse_selection(line, shown.len()) {
+                Some(numbers) => numbers
+                    .into_iter()
+                    .for_each(|number| chosen[shown[number]] = !chosen[shown[number]]),
+                None => query = line.to_string(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fuzzy_matches, parse_selection, pick_projects};
+    use repox_manifest::Manifest;
+
+    #[test]
+    fn test_pick_projects() {
+        let manifest: Manifest = quick_xml::de::from_str(
+            r#"<manifest>
+                <project name="kernel/common" groups="kernel" />
+                <project name="nxp-bsp" path="vendor/nxp/bsp" groups="notdefault,bsp" />
+                <project name="app" />
+            </manifest>"#,
+        )
+        .unwrap();
+        assert!(fuzzy_matches("vendor/nxp/bsp (nxp-bsp)", "VNB"));
+        assert!(!fuzzy_matches("app", "kernel"));
+        assert_eq!(parse_selection("1 2-3", 3), Some(vec![0, 1, 2]));
+        assert_eq!(parse_selection("4", 3), None);
+
+        // Search for the BSP, choose it, clear the search and drop the app.
+        let mut input = "nxp\n1\n/\n3\n\n".as_bytes();
+        let mut output = Vec::new();
+        let picked = pick_projects(
+            manifest.projects(),
+            |project| project.matches_groups("default"),
+            &mut input,
+            &mut output,
+        )
+        .unwrap()
+        .unwrap();
+        let paths: Vec<_> = picked.iter().map(|p| p.checkout_path()).collect();
+        assert_eq!(paths, vec!["kernel/common", "vendor/nxp/bsp"]);
+        assert!(String::from_utf8(output)
+            .unwrap()
+            .contains("[ ]   1  vendor/nxp/bsp (nxp-bsp) [notdefault, bsp]"));
+
+        let mut input = "q\n".as_bytes();
+        assert!(
+            pick_projects(manifest.projects(), |_| true, &mut input, &mut Vec::new())
+                .unwrap()
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,247 @@
+use crate::display::Fields;
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use tracing::{
+    field::{Field, Visit},
+    span, Subscriber,
+};
+use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+
+/// Where the running command spent its time, gathered while `--profile` is on.
+static PROFILE: Mutex<Option<Profile>> = Mutex::new(None);
+
+/// How many of the slowest projects the report lists.
+const TOP_PROJECTS: usize = 10;
+
+/// A part of a command's work the profile tells apart, recognized by the names of its tracing spans.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Phase {
+    Manifest,
+    Network,
+    Checkout,
+    Hooks,
+}
+
+impl Phase {
+    const ALL: [Phase; 4] = [
+        Phase::Manifest,
+        Phase::Network,
+        Phase::Checkout,
+        Phase::Hooks,
+    ];
+
+    /// The phase of the span named `name`, if it is one of the spans the profile times.
+    fn of(name: &str) -> Option<Phase> {
+        match name {
+            "Resolving manifest" => Some(Phase::Manifest),
+            "Fetching" | "Cloning" | "Mirroring" | "Pushing" | "Downloading" => {
+                Some(Phase::Network)
+            }
+            "Checking out worktree" | "Updating worktree" => Some(Phase::Checkout),
+            "Running repo hook" | "Running command hook" => Some(Phase::Hooks),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Phase::Manifest => "Manifest resolution",
+            Phase::Network => "Network",
+            Phase::Checkout => "Checkout",
+            Phase::Hooks => "Hooks",
+        })
+    }
+}
+
+/// The time spent in each phase and on each project.
+#[derive(Debug)]
+struct Profile {
+    started: Instant,
+    phases: HashMap<Phase, Duration>,
+    projects: HashMap<String, Duration>,
+}
+
+impl Profile {
+    /// The report of where the `total` time went, the phases then the slowest projects.
+    fn report(&self, total: Duration) -> String {
+        let seconds = |duration: Duration| format!("{:.2}s", duration.as_secs_f64());
+
+        let mut phases = Fields::new();
+        phases.push("Total", seconds(total));
+        for phase in Phase::ALL {
+            let spent = self.phases.get(&phase).copied().unwrap_or_default();
+            phases.push(phase.to_string(), seconds(spent));
+        }
+        let mut report = format!("Profile:\n{phases}");
+
+        let mut projects: Vec<_> = self.projects.iter().collect();
+        projects.sort_by(|(a_name, a), (b_name, b)| b.cmp(a).then(a_name.cmp(b_name)));
+        if !projects.is_empty() {
+            let mut slowest = Fields::new();
+            for (name, spent) in projects.into_iter().take(TOP_PROJECTS) {
+                slowest.push(name.as_str(), seconds(*spent));
+            }
+            report.push_str(&format!("\nSlowest projects:\n{slowest}"));
+        }
+
+        report
+    }
+}
+
+/// What the layer keeps about an open span.
+struct Timing {
+    opened: Instant,
+    phase: Option<Phase>,
+    /// The project of a per-project span, whose `name` field names the project.
+    project: Option<String>,
+    /// The time spent in spans of other phases nested in this one, which it does not count.
+    nested: Duration,
+}
+
+/// Reads the `name` field of a span.
+#[derive(Default)]
+struct NameVisitor(Option<String>);
+
+impl Visit for NameVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "name" {
+            self.0 = Some(value.to_string());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "name" {
+            self.0 = Some(format!("{value:?}"));
+        }
+    }
+}
+
+/// A tracing layer timing the spans of the running command, for `--profile`.
+///
+/// Time is counted from a span's creation to its close. A phase only counts the time its nested spans of
+/// other phases did not, e.g. the fetch unshallowing a checkout counts as network rather than checkout.
+/// Per-project spans carry the project's name in a `name` field, nested ones are part of the outer one.
+pub struct ProfileLayer;
+
+/// Starts profiling the running command, returning the layer to add to the tracing subscriber.
+pub fn layer() -> ProfileLayer {
+    *PROFILE.lock().unwrap() = Some(Profile {
+        started: Instant::now(),
+        phases: HashMap::new(),
+        projects: HashMap::new(),
+    });
+    ProfileLayer
+}
+
+/// The report of where the running command spent its time, or `None` when it is not profiled.
+pub fn report() -> Option<String> {
+    let profile = PROFILE.lock().unwrap();
+    let profile = profile.as_ref()?;
+    Some(profile.report(profile.started.elapsed()))
+}
+
+impl<S> Layer<S> for ProfileLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut visitor = NameVisitor::default();
+        attrs.record(&mut visitor);
+        let phase = Phase::of(span.name());
+        let project = visitor.0.filter(|_| phase.is_none());
+
+        span.extensions_mut().insert(Timing {
+            opened: Instant::now(),
+            phase,
+            project,
+            nested: Duration::ZERO,
+        });
+    }
+
+    fn on_close(&self, id: span::Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let Some(timing) = span.extensions_mut().remove::<Timing>() else {
+            return;
+        };
+        let elapsed = timing.opened.elapsed();
+
+        let mut profile = PROFILE.lock().unwrap();
+        let Some(profile) = profile.as_mut() else {
+            return;
+        };
+        if let Some(phase) = timing.phase {
+            *profile.phases.entry(phase).or_default() += elapsed.saturating_sub(timing.nested);
+            let parent = span.scope().skip(1).find(|ancestor| {
+                ancestor
+                    .extensions()
+                    .get::<Timing>()
+                    .is_some_and(|timing| timing.phase.is_some())
+            });
+            if let Some(parent) = parent {
+                if let Some(parent) = parent.extensions_mut().get_mut::<Timing>() {
+                    parent.nested += elapsed;
+                }
+            }
+        }
+        if let Some(project) = timing.project {
+            let nested = span.scope().skip(1).any(|ancestor| {
+                ancestor
+                    .extensions()
+                    .get::<Timing>()
+                    .is_some_and(|timing| timing.project.is_some())
+            });
+            if !nested {
+                *profile.projects.entry(project).or_default() += elapsed;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Phase, Profile};
+    use std::{collections::HashMap, time::Duration, time::Instant};
+
+    #[test]
+    fn test_profile_report() {
+        assert_eq!(Phase::of("Fetching"), Some(Phase::Network));
+        assert_eq!(Phase::of("Syncing project"), None);
+
+        let profile = Profile {
+            started: Instant::now(),
+            phases: HashMap::from([
+                (Phase::Network, Duration::from_millis(2500)),
+                (Phase::Checkout, Duration::from_millis(500)),
+            ]),
+            projects: (0..12)
+                .map(|index| (format!("p{index:02}"), Duration::from_millis(index * 100)))
+                .collect(),
+        };
+        let report = profile.report(Duration::from_secs(3));
+        assert!(
+            report.starts_with(
+                "Profile:\nTotal:               3.00s\nManifest resolution: 0.00s\nNetwork:             2.50s\n"
+            ),
+            "{report}"
+        );
+        assert!(
+            report.contains("\nSlowest projects:\np11: 1.10s\np10: 1.00s\n"),
+            "{report}"
+        );
+        assert!(
+            report.ends_with("p02: 0.20s\n") && !report.contains("p01"),
+            "{report}"
+        );
+    }
+}
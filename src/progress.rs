@@ -0,0 +1,190 @@
+use gix::progress::{
+    Count, Id, MessageLevel, NestedProgress, Progress, Step, StepShared, Unit, UNKNOWN,
+};
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+use tracing::{debug, trace, Span};
+
+/// How often a running task's counter is logged, so large packs don't flood the output.
+const REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Bridges gix's progress reporting into the tracing hierarchy, instead of discarding it.
+///
+/// gix works on its own threads while resolving packs, where the current span is lost, so the span
+/// the progress was created in (usually the project being synced) is kept and entered for every event.
+/// Messages, like the negotiation rounds and received objects, are logged at debug level, shown with
+/// `-v`. Counters, like objects and bytes received, are logged at trace level, shown with `-vv`.
+pub struct TracingProgress {
+    name: String,
+    id: Id,
+    span: Span,
+    step: StepShared,
+    max: Option<Step>,
+    unit: Option<Unit>,
+    last_report: Mutex<Option<Instant>>,
+}
+
+impl TracingProgress {
+    /// A root task named `name`, reporting in the current span.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self::with_span(name.into(), UNKNOWN, Span::current())
+    }
+
+    fn with_span(name: String, id: Id, span: Span) -> Self {
+        TracingProgress {
+            name,
+            id,
+            span,
+            step: Arc::new(AtomicUsize::new(0)),
+            max: None,
+            unit: None,
+            last_report: Mutex::new(None),
+        }
+    }
+
+    /// The counter as shown to the user, e.g. `1.2 MB/3.4 MB` or `12/40 objects`.
+    fn describe(&self, step: Step) -> String {
+        match (&self.unit, self.max) {
+            (Some(unit), max) => unit.display(step, max, None).to_string(),
+            (None, Some(max)) => format!("{step}/{max}"),
+            (None, None) => step.to_string(),
+        }
+    }
+
+    fn report(&self) {
+        let now = Instant::now();
+        {
+            let mut last_report = self.last_report.lock().expect("not poisoned");
+            if last_report.is_some_and(|last| now.duration_since(last) < REPORT_INTERVAL) {
+                return;
+            }
+            *last_report = Some(now);
+        }
+        let step = self.step();
+        self.span
+            .in_scope(|| trace!("{}: {}", self.name, self.describe(step)));
+    }
+}
+
+impl Count for TracingProgress {
+    fn set(&self, step: Step) {
+        self.step.store(step, Ordering::SeqCst);
+        self.report();
+    }
+
+    fn step(&self) -> Step {
+        self.step.load(Ordering::Relaxed)
+    }
+
+    fn inc_by(&self, step: Step) {
+        self.step.fetch_add(step, Ordering::Relaxed);
+        self.report();
+    }
+
+    fn counter(&self) -> StepShared {
+        self.step.clone()
+    }
+}
+
+impl Progress for TracingProgress {
+    fn init(&mut self, max: Option<Step>, unit: Option<Unit>) {
+        self.max = max;
+        self.unit = unit;
+        self.step.store(0, Ordering::SeqCst);
+        *self.last_report.lock().expect("not poisoned") = None;
+    }
+
+    fn unit(&self) -> Option<Unit> {
+        self.unit.clone()
+    }
+
+    fn max(&self) -> Option<Step> {
+        self.max
+    }
+
+    fn set_max(&mut self, max: Option<Step>) -> Option<Step> {
+        std::mem::replace(&mut self.max, max)
+    }
+
+    fn set_name(&mut self, name: String) {
+        self.name = name;
+    }
+
+    fn name(&self) -> Option<String> {
+        Some(self.name.clone())
+    }
+
+    fn id(&self) -> Id {
+        self.id
+    }
+
+    fn message(&self, level: MessageLevel, message: String) {
+        // Successes already read like "done 3.0 objects in 0.05s", only failures need telling apart.
+        let failed = if level == MessageLevel::Failure {
+            " (failed)"
+        } else {
+            ""
+        };
+        self.span
+            .in_scope(|| debug!("{}: {message}{failed}", self.name));
+    }
+}
+
+impl NestedProgress for TracingProgress {
+    type SubProgress = Self;
+
+    fn add_child(&mut self, name: impl Into<String>) -> Self {
+        self.add_child_with_id(name, UNKNOWN)
+    }
+
+    fn add_child_with_id(&mut self, name: impl Into<String>, id: Id) -> Self {
+        Self::with_span(
+            format!("{} > {}", self.name, name.into()),
+            id,
+            self.span.clone(),
+        )
+    }
+}
+
+impl Drop for TracingProgress {
+    fn drop(&mut self) {
+        // The final count, which the throttling may have skipped.
+        let step = self.step();
+        if step > 0 {
+            self.span
+                .in_scope(|| trace!("{}: {} in total", self.name, self.describe(step)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TracingProgress;
+    use gix::progress::{Count, NestedProgress, Progress};
+
+    #[test]
+    fn test_tracing_progress() {
+        let mut progress = TracingProgress::new("fetch");
+        let mut child = progress.add_child("receive pack");
+        assert_eq!(child.name().as_deref(), Some("fetch > receive pack"));
+
+        child.init(Some(40), Some("objects".into()));
+        child.inc_by(12);
+        child.inc();
+        assert_eq!(child.step(), 13);
+        assert_eq!(child.describe(child.step()), "13/40 objects");
+
+        // Counters handed out keep reporting into the same task.
+        child
+            .counter()
+            .fetch_add(2, std::sync::atomic::Ordering::SeqCst);
+        assert_eq!(child.step(), 15);
+        assert_eq!(child.set_max(None), Some(40));
+        assert_eq!(child.describe(child.step()), "15 objects");
+    }
+}
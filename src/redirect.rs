@@ -0,0 +1,68 @@
+use crate::{config::TransportConfig, http::client_builder};
+use reqwest::{redirect::Policy, StatusCode};
+use std::time::Duration;
+
+/// The request git's smart HTTP protocol starts with, which servers of moved repositories redirect.
+const DISCOVERY_SUFFIX: &str = "/info/refs?service=git-upload-pack";
+
+/// Where the repository at `url` moved to, according to the redirect its server answers git's first request with.
+///
+/// gix follows redirects within a host on its own, but refuses those to another host, and never says where it
+/// was sent, so this asks again without following, through the proxy and certificates of `transport`. Returns
+/// `None` for repositories that did not move, URLs that are not http(s), and servers that cannot be reached.
+pub fn moved_to(url: &str, transport: &TransportConfig) -> Option<String> {
+    if !url.starts_with("https://") && !url.starts_with("http://") {
+        return None;
+    }
+
+    let client = client_builder(transport)
+        .ok()?
+        .redirect(Policy::none())
+        .timeout(Duration::from_secs(20))
+        .build()
+        .ok()?;
+    let request_url = format!("{}{DISCOVERY_SUFFIX}", url.trim_end_matches('/'));
+    let response = client.get(&request_url).send().ok()?;
+    if !response.status().is_redirection() || response.status() == StatusCode::NOT_MODIFIED {
+        return None;
+    }
+
+    let location = response.headers().get("location")?.to_str().ok()?;
+    let location = response.url().join(location).ok()?;
+    moved_base(url, location.as_str())
+}
+
+/// The repository URL a redirect to `location` stands for, once the request's own tail is taken off.
+fn moved_base(url: &str, location: &str) -> Option<String> {
+    let (base, _) = location.split_once("/info/refs")?;
+    (base != url.trim_end_matches('/')).then(|| base.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::moved_base;
+
+    #[test]
+    fn test_moved_base() {
+        assert_eq!(
+            moved_base(
+                "https://example.com/old/app",
+                "https://git.example.org/new/app.git/info/refs?service=git-upload-pack"
+            )
+            .as_deref(),
+            Some("https://git.example.org/new/app.git")
+        );
+        // A redirect to the same repository, e.g. only adding a trailing slash, is not a move.
+        assert_eq!(
+            moved_base(
+                "https://example.com/app/",
+                "https://example.com/app/info/refs?service=git-upload-pack"
+            ),
+            None
+        );
+        assert_eq!(
+            moved_base("https://example.com/app", "https://example.com/login"),
+            None
+        );
+    }
+}
@@ -0,0 +1,185 @@
+use crate::{
+    backend::GitBackend,
+    config::{ManifestConfig, WorkspaceConfig},
+    git::{DirtyPolicy, GitError, Refspecs},
+    workspace::{Workspace, WorkspaceError},
+};
+use miette::Diagnostic;
+use repox_manifest::{submanifest::Submanifest, url::ProjectUrls, Manifest};
+use std::{
+    collections::BTreeMap,
+    fs::create_dir_all,
+    path::{Component, Path},
+};
+use thiserror::Error;
+use tracing::info;
+
+/// Directory inside a submanifest's state directory holding its manifest project's checkout.
+const MANIFESTS_DIR: &str = "manifests";
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum SubmanifestError {
+    #[error("The submanifest {name} is placed at {path}, outside the workspace")]
+    #[diagnostic(
+        code(repox::submanifest::invalid_path),
+        help("give the submanifest a relative path without `..` components")
+    )]
+    InvalidPath { name: String, path: String },
+
+    #[error("The submanifest {name} references an undefined remote")]
+    #[diagnostic(code(repox::submanifest::unknown_remote))]
+    UnknownRemote { name: String },
+
+    #[error("The manifest project of the submanifest {name} is not checked out")]
+    #[diagnostic(
+        code(repox::submanifest::offline),
+        help("sync once without --offline to check it out")
+    )]
+    OfflineNotCheckedOut { name: String },
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    WorkspaceError(#[from] WorkspaceError),
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    GitError(#[from] GitError),
+}
+
+/// Sets up the workspace of each of `manifest`'s submanifests, in the order they are declared.
+///
+/// A submanifest naming a manifest project has that project checked out into its state directory, updated
+/// unless `offline`; one naming none reads its manifest file from the same directory as `config`'s manifest.
+/// Each workspace's config is then written from `config`, keeping the settings that apply to any manifest.
+pub fn submanifest_workspaces(
+    backend: &dyn GitBackend,
+    workspace: &Workspace,
+    config: &WorkspaceConfig,
+    manifest: &Manifest,
+    offline: bool,
+) -> Result<Vec<Workspace>, SubmanifestError> {
+    manifest
+        .submanifests()
+        .iter()
+        .map(|submanifest| {
+            check_path(submanifest)?;
+            let inner = workspace.submanifest(submanifest.path());
+            create_dir_all(inner.repo_dir()).map_err(WorkspaceError::CreateError)?;
+
+            let manifest_config = match submanifest.project() {
+                Some(project) => checkout_manifest_project(
+                    backend,
+                    &inner,
+                    config,
+                    manifest,
+                    submanifest,
+                    project,
+                    offline,
+                )?,
+                None => ManifestConfig {
+                    url: config.manifest.url.clone(),
+                    branch: config.manifest.branch.clone(),
+                    path: Path::new(&config.manifest.path)
+                        .with_file_name(submanifest.manifest_name())
+                        .display()
+                        .to_string(),
+                },
+            };
+
+            inner.save_config(&WorkspaceConfig {
+                manifest: manifest_config,
+                groups: config
+                    .groups
+                    .clone()
+                    .or_else(|| submanifest.default_groups().map(str::to_string)),
+                // Both name the outer manifest's projects and commands, they would run again for each submanifest.
+                sync_after: BTreeMap::new(),
+                command_hooks: BTreeMap::new(),
+                ..config.clone()
+            })?;
+
+            Ok(inner)
+        })
+        .collect()
+}
+
+/// The workspace of the innermost submanifest whose checkout contains `dir`, or `workspace` itself.
+pub fn innermost(workspace: &Workspace, dir: &Path) -> Result<Workspace, SubmanifestError> {
+    let mut current = workspace.clone();
+    'descend: loop {
+        for submanifest in current.manifest()?.submanifests() {
+            let inner = current.submanifest(submanifest.path());
+            if dir.starts_with(inner.root()) && inner.config_path().exists() {
+                current = inner;
+                continue 'descend;
+            }
+        }
+
+        return Ok(current);
+    }
+}
+
+/// Rejects submanifests placed outside the workspace, whose projects would be checked out anywhere.
+fn check_path(submanifest: &Submanifest) -> Result<(), SubmanifestError> {
+    let path = Path::new(submanifest.path());
+    if path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return Ok(());
+    }
+
+    Err(SubmanifestError::InvalidPath {
+        name: submanifest.name().to_string(),
+        path: submanifest.path().to_string(),
+    })
+}
+
+/// Clones or updates the manifest project `submanifest` names and returns where its manifest is read from.
+fn checkout_manifest_project(
+    backend: &dyn GitBackend,
+    inner: &Workspace,
+    config: &WorkspaceConfig,
+    manifest: &Manifest,
+    submanifest: &Submanifest,
+    project: &str,
+    offline: bool,
+) -> Result<ManifestConfig, SubmanifestError> {
+    let name = submanifest.name().to_string();
+    let remote = manifest
+        .submanifest_remote(submanifest)
+        .ok_or_else(|| SubmanifestError::UnknownRemote { name: name.clone() })?;
+    let revision = manifest.submanifest_revision(submanifest);
+    let url = ProjectUrls::resolve(remote, project, |url| config.rewrite_url(url)).fetch;
+
+    let dst = inner.repo_dir().join(MANIFESTS_DIR);
+    if !dst.exists() {
+        if offline {
+            return Err(SubmanifestError::OfflineNotCheckedOut { name });
+        }
+        info!("Cloning the manifest of submanifest {name} from {url}");
+        backend.clone_project(
+            &url,
+            &dst,
+            remote.git_name(),
+            false,
+            &Refspecs::default(),
+            None,
+        )?;
+    } else if !offline {
+        backend.fetch_project(&dst, remote.git_name(), &Refspecs::default())?;
+    }
+    backend.update_worktree(
+        &dst,
+        &format!("{}/{MANIFESTS_DIR}", submanifest.path()),
+        remote.git_name(),
+        revision.as_deref(),
+        DirtyPolicy::from_flags(false, false),
+    )?;
+
+    Ok(ManifestConfig {
+        url,
+        branch: revision.unwrap_or_else(|| "HEAD".to_string()),
+        path: dst.join(submanifest.manifest_name()).display().to_string(),
+    })
+}
@@ -0,0 +1,62 @@
+use crate::{
+    config::WorkspaceConfig,
+    git::{fetch_superproject_tree, GitError},
+    workspace::Workspace,
+};
+use miette::Diagnostic;
+use repox_manifest::{superproject::parse_gitlinks, url::ProjectUrls, Manifest};
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+/// Directory inside `.repo/` holding the superproject's bare repository, named as repo names it.
+pub const SUPERPROJECT_DIR: &str = "exp-superproject";
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum SuperprojectError {
+    #[error("The superproject {name} references an undefined remote")]
+    #[diagnostic(code(repox::superproject::unknown_remote))]
+    UnknownRemote { name: String },
+
+    #[error("The superproject {name} has no revision to fetch")]
+    #[diagnostic(
+        code(repox::superproject::no_revision),
+        help(
+            "give the superproject element, its remote or the default element a revision attribute"
+        )
+    )]
+    NoRevision { name: String },
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    GitError(#[from] GitError),
+}
+
+/// Fetches the superproject `manifest` names and returns the commit it pins each project to, by checkout path.
+///
+/// Returns `None` when the manifest has no superproject element. The superproject's bare repository is kept
+/// in `.repo/exp-superproject/`, so later syncs only fetch the new tip.
+pub fn pinned_revisions(
+    workspace: &Workspace,
+    config: &WorkspaceConfig,
+    manifest: &Manifest,
+) -> Result<Option<BTreeMap<String, String>>, SuperprojectError> {
+    let Some(superproject) = manifest.superproject() else {
+        return Ok(None);
+    };
+    let name = superproject.name().to_string();
+    let remote = manifest
+        .superproject_remote()
+        .ok_or_else(|| SuperprojectError::UnknownRemote { name: name.clone() })?;
+    let revision = manifest
+        .superproject_revision()
+        .ok_or_else(|| SuperprojectError::NoRevision { name: name.clone() })?;
+
+    let url = ProjectUrls::resolve(remote, &name, |url| config.rewrite_url(url)).fetch;
+    let git_dir = workspace
+        .repo_dir()
+        .join(SUPERPROJECT_DIR)
+        .join(format!("{}.git", name.replace('/', "_")));
+    let tree = fetch_superproject_tree(&git_dir, &url, &revision)?;
+
+    Ok(Some(parse_gitlinks(&tree)))
+}
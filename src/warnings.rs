@@ -0,0 +1,81 @@
+use crate::redact::redact;
+use std::{fmt, sync::Mutex};
+
+/// The warnings of the running command, in the order they were raised.
+static WARNINGS: Mutex<Vec<Warning>> = Mutex::new(Vec::new());
+
+/// What a [`Warning`] is about, so CI can tell them apart in the output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum WarningKind {
+    /// A manifest attribute repox does not read, usually a typo.
+    UnknownAttribute,
+    /// A manifest element repox does not read, usually a typo.
+    UnknownElement,
+    /// A project left as it was, e.g. because it has local commits the new revision would drop.
+    SkippedProject,
+    /// A group of the workspace's filter that no project of the manifest is in.
+    MissingGroup,
+    /// A manifest remote no project fetches from.
+    UnusedRemote,
+    /// A command hook that failed after its command was done.
+    FailedHook,
+    /// A project repository whose server redirects to where it moved.
+    MovedRepository,
+    /// A filesystem lacking something repox works around, e.g. symlinks on a network share.
+    LimitedFilesystem,
+}
+
+impl fmt::Display for WarningKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            WarningKind::UnknownAttribute => "unknown-attribute",
+            WarningKind::UnknownElement => "unknown-element",
+            WarningKind::SkippedProject => "skipped-project",
+            WarningKind::MissingGroup => "missing-group",
+            WarningKind::UnusedRemote => "unused-remote",
+            WarningKind::FailedHook => "failed-hook",
+            WarningKind::MovedRepository => "moved-repository",
+            WarningKind::LimitedFilesystem => "limited-filesystem",
+        })
+    }
+}
+
+/// Something a command could carry on past, but that the user should look at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    pub kind: WarningKind,
+    pub message: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "warning[{}]: {}", self.kind, self.message)
+    }
+}
+
+/// Records a warning, to be reported once the command is done. A warning raised twice is reported once.
+///
+/// Credentials in the URLs the message mentions are redacted.
+pub fn warn(kind: WarningKind, message: impl Into<String>) {
+    let warning = Warning {
+        kind,
+        message: redact(&message.into()).into_owned(),
+    };
+    let mut warnings = WARNINGS.lock().unwrap_or_else(|error| error.into_inner());
+    if !warnings.contains(&warning) {
+        warnings.push(warning);
+    }
+}
+
+/// How many warnings were recorded so far.
+pub fn count() -> usize {
+    WARNINGS
+        .lock()
+        .unwrap_or_else(|error| error.into_inner())
+        .len()
+}
+
+/// Takes the warnings recorded so far.
+pub fn take() -> Vec<Warning> {
+    std::mem::take(&mut *WARNINGS.lock().unwrap_or_else(|error| error.into_inner()))
+}
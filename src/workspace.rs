@@ -0,0 +1,689 @@
+use crate::{
+    config::{ConfigError, WorkspaceConfig},
+    filesystem::{self, FilesystemSupport},
+    warnings::{self, warn, WarningKind},
+};
+use miette::{Diagnostic, NamedSource, SourceSpan};
+use quick_xml::{de::from_str, events::Event, DeError, Reader};
+use repox_manifest::{
+    error::{IncludeError, ManifestError},
+    known_attributes, Manifest,
+};
+use serde::{Deserialize, Serialize};
+use sha1_smol::Sha1;
+use std::{
+    cell::RefCell,
+    collections::BTreeSet,
+    fmt,
+    fs::{create_dir_all, metadata, read_dir, read_to_string, write},
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+use thiserror::Error;
+use tracing::info_span;
+
+/// Name of the directory holding repox's state at the top of a repo client.
+pub const REPO_DIR: &str = ".repo";
+
+/// Name of the workspace configuration file inside [`REPO_DIR`].
+pub const CONFIG_FILE: &str = "repox.toml";
+
+/// Name of the file inside [`REPO_DIR`] listing the checkouts sync created, one path per line, as repo keeps it.
+pub const PROJECT_LIST_FILE: &str = "project.list";
+
+/// Name of the file inside [`REPO_DIR`] caching the resolved manifest, see [`Workspace::cached_manifest`].
+pub const MANIFEST_CACHE_FILE: &str = "manifest-cache.json";
+
+/// Name of the directory inside [`REPO_DIR`] keeping the state of each submanifest, by its path.
+pub const SUBMANIFESTS_DIR: &str = "submanifests";
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum WorkspaceError {
+    #[error("Not inside a repo client checkout")]
+    #[diagnostic(
+        code(repox::workspace::not_found),
+        help("run `repox init` to initialize a repo client checkout in the current directory")
+    )]
+    NotFound,
+
+    #[error("Could not determine the current directory")]
+    #[diagnostic(code(repox::workspace::current_dir))]
+    CurrentDirError(#[source] std::io::Error),
+
+    #[error("Could not create the {REPO_DIR} directory")]
+    #[diagnostic(code(repox::workspace::create))]
+    CreateError(#[source] std::io::Error),
+
+    #[error("Could not read manifest file")]
+    #[diagnostic(code(repox::workspace::manifest_read))]
+    ManifestReadError(#[source] std::io::Error),
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    ManifestParseError(Box<ManifestParseError>),
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    ConfigError(#[from] ConfigError),
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    ManifestError(#[from] ManifestError),
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    IncludeError(#[from] IncludeError),
+
+    #[error("{path}:{line}:{column}: {name}")]
+    #[diagnostic(
+        code(repox::workspace::unknown_name),
+        help("fix the typo, or set strict-manifest to false to only be warned about it")
+    )]
+    UnknownName {
+        path: String,
+        line: usize,
+        column: usize,
+        name: UnknownName,
+    },
+
+    #[error("Could not access {REPO_DIR}/{PROJECT_LIST_FILE}")]
+    #[diagnostic(code(repox::workspace::project_list))]
+    ProjectListError(#[source] std::io::Error),
+
+    #[error("The workspace at {path} cannot be written to")]
+    #[diagnostic(
+        code(repox::workspace::read_only),
+        help("the workspace is likely on a read-only filesystem, such as a snapshot; run this in a writable copy, commands that only read, like status and list, work as they are")
+    )]
+    ReadOnly {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+/// A manifest file that does not parse, pointing at where in it.
+#[derive(Debug, Error, Diagnostic)]
+#[error("Could not parse the manifest {path}")]
+#[diagnostic(code(repox::workspace::manifest_parse))]
+pub struct ManifestParseError {
+    path: String,
+    message: String,
+    #[source_code]
+    contents: NamedSource<String>,
+    #[label("{message}")]
+    span: SourceSpan,
+}
+
+/// A repo client checkout: the directory containing `.repo/` and every project checked out beneath it.
+///
+/// A submanifest's checkout is a workspace of its own, nested in the outer one's directory.
+#[derive(Debug, Clone)]
+pub struct Workspace {
+    root: PathBuf,
+    repo_dir: PathBuf,
+}
+
+impl Workspace {
+    /// Creates the `.repo/` directory under `root`, if it does not already exist.
+    pub fn create(root: impl Into<PathBuf>) -> Result<Self, WorkspaceError> {
+        let root = root.into();
+        let workspace = Self {
+            repo_dir: root.join(REPO_DIR),
+            root,
+        };
+        create_dir_all(workspace.repo_dir()).map_err(WorkspaceError::CreateError)?;
+        workspace.check_writable()?;
+
+        Ok(workspace)
+    }
+
+    /// Finds the workspace containing the current directory.
+    pub fn discover() -> Result<Self, WorkspaceError> {
+        let current_dir = std::env::current_dir().map_err(WorkspaceError::CurrentDirError)?;
+
+        Self::discover_from(&current_dir)
+    }
+
+    /// Finds the workspace containing `start`, searching its ancestors.
+    pub fn discover_from(start: &Path) -> Result<Self, WorkspaceError> {
+        start
+            .ancestors()
+            .find(|dir| dir.join(REPO_DIR).is_dir())
+            .map(|root| Self {
+                root: root.to_path_buf(),
+                repo_dir: root.join(REPO_DIR),
+            })
+            .ok_or(WorkspaceError::NotFound)
+    }
+
+    /// The top of the repo client.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The `.repo/` directory, or for a submanifest the directory keeping its state.
+    pub fn repo_dir(&self) -> PathBuf {
+        self.repo_dir.clone()
+    }
+
+    /// The workspace of the submanifest checked out at `path`, relative to the top of this one.
+    ///
+    /// Its projects are checked out beneath `path`, while its state is kept in `.repo/submanifests/<path>/`
+    /// rather than in a `.repo/` directory of its own, as repo keeps it.
+    pub fn submanifest(&self, path: &str) -> Self {
+        Self {
+            root: self.root.join(path),
+            repo_dir: self.repo_dir.join(SUBMANIFESTS_DIR).join(path),
+        }
+    }
+
+    /// Fails when the workspace cannot be written to, e.g. on a read-only snapshot, so commands changing it
+    /// say so up front rather than failing on whichever file they write first.
+    pub fn check_writable(&self) -> Result<(), WorkspaceError> {
+        filesystem::check_writable(&self.repo_dir).map_err(|source| WorkspaceError::ReadOnly {
+            path: self.root.display().to_string(),
+            source,
+        })
+    }
+
+    /// What the filesystem the workspace is on supports, tried out in `.repo/`.
+    pub fn filesystem(&self) -> FilesystemSupport {
+        filesystem::probe(&self.repo_dir)
+    }
+
+    pub fn config_path(&self) -> PathBuf {
+        self.repo_dir().join(CONFIG_FILE)
+    }
+
+    pub fn config(&self) -> Result<WorkspaceConfig, WorkspaceError> {
+        Ok(WorkspaceConfig::load(&self.config_path())?)
+    }
+
+    pub fn save_config(&self, config: &WorkspaceConfig) -> Result<(), WorkspaceError> {
+        Ok(config.save(&self.config_path())?)
+    }
+
+    /// The `.repo/local_manifests/` directory, holding the user's additions to the manifest.
+    pub fn local_manifests_dir(&self) -> PathBuf {
+        self.repo_dir().join("local_manifests")
+    }
+
+    /// The checkout paths sync has created, which tell checkouts the manifest dropped apart from other directories.
+    ///
+    /// Workspaces that were never synced, or last synced before the list was kept, have none.
+    pub fn project_list(&self) -> Result<Vec<String>, WorkspaceError> {
+        match read_to_string(self.repo_dir().join(PROJECT_LIST_FILE)) {
+            Ok(contents) => Ok(contents
+                .lines()
+                .filter(|line| !line.is_empty())
+                .map(str::to_string)
+                .collect()),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(error) => Err(WorkspaceError::ProjectListError(error)),
+        }
+    }
+
+    pub fn save_project_list(&self, paths: &BTreeSet<String>) -> Result<(), WorkspaceError> {
+        let contents: String = paths.iter().map(|path| format!("{path}\n")).collect();
+        write(self.repo_dir().join(PROJECT_LIST_FILE), contents)
+            .map_err(WorkspaceError::ProjectListError)
+    }
+
+    /// Adds the checkouts of `manifest`'s projects to the project list, dropping those no longer on disk.
+    pub fn record_checkouts(&self, manifest: &Manifest) -> Result<(), WorkspaceError> {
+        let listed: BTreeSet<String> = self
+            .project_list()?
+            .into_iter()
+            .chain(
+                manifest
+                    .projects()
+                    .iter()
+                    .map(|project| project.checkout_path().to_string()),
+            )
+            .filter(|path| self.project_dir(path).exists())
+            .collect();
+        self.save_project_list(&listed)
+    }
+
+    /// Resolves a project's path relative to the top of the repo client.
+    pub fn project_dir(&self, path: &str) -> PathBuf {
+        self.root.join(path)
+    }
+
+    /// Reads and parses the manifest recorded at init time with the local manifests applied on top,
+    /// rejecting projects whose checkouts would collide.
+    pub fn manifest(&self) -> Result<Manifest, WorkspaceError> {
+        self.read_manifest(self.config()?.manifest.path)
+    }
+
+    /// Reads and parses the manifest at `path` with the local manifests applied on top, like [`Workspace::manifest`].
+    ///
+    /// Paths that differ only in case are rejected too where they [collide](Workspace::check_case_collisions).
+    pub fn read_manifest(&self, path: impl AsRef<Path>) -> Result<Manifest, WorkspaceError> {
+        let local_manifests = local_manifest_paths(&self.local_manifests_dir());
+        let manifest = read_manifests(
+            path.as_ref(),
+            &local_manifests,
+            self.strict_manifest(),
+            &mut Vec::new(),
+        )?;
+        self.check_case_collisions(manifest)
+    }
+
+    /// Reads the workspace's manifest like [`Workspace::manifest`], from the resolved manifest cached in
+    /// [`MANIFEST_CACHE_FILE`] when none of the files it was read from changed since, which skips parsing
+    /// them altogether.
+    ///
+    /// Files are told changed by their modification times and sizes, and adding or removing a local manifest
+    /// changes the manifest too. A manifest that raises warnings is not cached, so they are reported every
+    /// time. Meant for commands that only read the manifest, such as `list`, `forall` and `status`.
+    pub fn cached_manifest(&self) -> Result<Manifest, WorkspaceError> {
+        let path = PathBuf::from(self.config()?.manifest.path);
+        let local_manifests = local_manifest_paths(&self.local_manifests_dir());
+        let strict = self.strict_manifest();
+        let cache_file = self.repo_dir.join(MANIFEST_CACHE_FILE);
+
+        let cached = read_to_string(&cache_file)
+            .ok()
+            .and_then(|contents| serde_json::from_str::<CachedManifest>(&contents).ok())
+            .filter(|cached| {
+                cached.files.first() == Some(&path)
+                    && cache_key(&cached.files, &local_manifests, strict).as_ref()
+                        == Some(&cached.key)
+            });
+        if let Some(cached) = cached {
+            return self.check_case_collisions(cached.manifest);
+        }
+
+        let warnings = warnings::count();
+        let mut files = Vec::new();
+        let manifest = read_manifests(&path, &local_manifests, strict, &mut files)?;
+        if warnings::count() == warnings {
+            if let Some(key) = cache_key(&files, &local_manifests, strict) {
+                let cached = CachedManifest {
+                    key,
+                    files,
+                    manifest,
+                };
+                // The cache only saves time, a workspace it cannot be written to reads the manifest every time.
+                if let Ok(contents) = serde_json::to_string(&cached) {
+                    let _ = write(&cache_file, contents);
+                }
+                return self.check_case_collisions(cached.manifest);
+            }
+        }
+
+        self.check_case_collisions(manifest)
+    }
+
+    /// Returns `manifest`, unless it has paths differing only in case on a filesystem where they collide.
+    ///
+    /// On case-insensitive filesystems, and for workspaces protecting Windows paths, such paths are
+    /// rejected before any checkout is written over another.
+    fn check_case_collisions(&self, manifest: Manifest) -> Result<Manifest, WorkspaceError> {
+        if self.case_insensitive() {
+            manifest.check_case_collisions()?;
+        }
+
+        Ok(manifest)
+    }
+
+    /// Reads the workspace's manifest like [`Workspace::manifest`], but without rejecting anything it gets
+    /// wrong, for [`Manifest::validate`] to report every problem at once.
+    pub fn unchecked_manifest(&self) -> Result<Manifest, WorkspaceError> {
+        let local_manifests = local_manifest_paths(&self.local_manifests_dir());
+        resolve_manifests(
+            Path::new(&self.config()?.manifest.path),
+            &local_manifests,
+            self.strict_manifest(),
+            &mut Vec::new(),
+        )
+    }
+
+    /// Whether paths differing only in case are the same path in the workspace.
+    fn case_insensitive(&self) -> bool {
+        self.config().is_ok_and(|config| config.protect_windows) || {
+            // `.repo` exists, so `.REPO` only does when the filesystem ignores case, as macOS and Windows do
+            // by default. A submanifest's state directory is named after its path, which may be upper case.
+            let name = self
+                .repo_dir
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy();
+            let flipped = match name.to_uppercase() {
+                upper if upper != name => upper,
+                _ => name.to_lowercase(),
+            };
+            flipped != name && self.repo_dir.with_file_name(flipped).exists()
+        }
+    }
+
+    /// Whether the workspace's configuration asks for unknown manifest names to be errors. Workspaces being
+    /// initialized have no configuration yet, and parse leniently.
+    fn strict_manifest(&self) -> bool {
+        self.config().is_ok_and(|config| config.strict_manifest)
+    }
+}
+
+/// The XML files in a `local_manifests/` directory, in the filename order repo applies them in.
+pub fn local_manifest_paths(dir: &Path) -> Vec<PathBuf> {
+    let mut paths: Vec<_> = read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| path.extension().is_some_and(|extension| extension == "xml"))
+        .collect();
+    paths.sort();
+    paths
+}
+
+/// Reads and parses the manifest at `path` and the manifests it includes, rejecting projects whose checkouts would collide.
+///
+/// Attributes the manifest format does not have and remotes no project uses are warned about.
+pub fn read_manifest(path: impl AsRef<Path>) -> Result<Manifest, WorkspaceError> {
+    read_manifests(path.as_ref(), &[], false, &mut Vec::new())
+}
+
+/// The resolved manifest as [`Workspace::cached_manifest`] caches it.
+#[derive(Serialize, Deserialize)]
+struct CachedManifest {
+    /// The [`cache_key`] of the files the manifest was read from.
+    key: String,
+    /// The files the manifest was read from, the workspace's manifest first.
+    files: Vec<PathBuf>,
+    manifest: Manifest,
+}
+
+/// A key changing whenever one of `files` is modified, the set of `local_manifests` changes, or the
+/// manifest would be parsed differently, by another version of repox or with another `strict` setting.
+///
+/// `None` when one of the files cannot be read, as when it was removed.
+fn cache_key(files: &[PathBuf], local_manifests: &[PathBuf], strict: bool) -> Option<String> {
+    let mut hasher = Sha1::new();
+    hasher.update(env!("CARGO_PKG_VERSION").as_bytes());
+    hasher.update(&[u8::from(strict)]);
+    for file in files {
+        let metadata = metadata(file).ok()?;
+        let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+        hasher.update(file.as_os_str().as_encoded_bytes());
+        hasher.update(&modified.as_nanos().to_le_bytes());
+        hasher.update(&metadata.len().to_le_bytes());
+    }
+    for local in local_manifests {
+        hasher.update(local.as_os_str().as_encoded_bytes());
+    }
+
+    Some(hasher.digest().to_string())
+}
+
+/// Reads the manifest at `path` and applies `local_manifests` on top of it in order.
+///
+/// Includes are resolved relative to the manifest's directory, the root of the manifest repository, for
+/// local manifests too. Paths are checked and remotes warned about once everything is applied, and the
+/// result is [flattened](Manifest::flatten) for commands to work from. With `strict`, elements and
+/// attributes repox does not read are errors rather than warnings. The files read are added to `files`.
+fn read_manifests(
+    path: &Path,
+    local_manifests: &[PathBuf],
+    strict: bool,
+    files: &mut Vec<PathBuf>,
+) -> Result<Manifest, WorkspaceError> {
+    let manifest = resolve_manifests(path, local_manifests, strict, files)?;
+    manifest.check_extend_projects()?;
+    manifest.check_paths()?;
+
+    for remote in manifest.unused_remotes() {
+        warn(
+            WarningKind::UnusedRemote,
+            format!("{}: no project uses remote {}", path.display(), remote.name),
+        );
+    }
+
+    Ok(manifest.flatten()?)
+}
+
+/// Reads the manifest at `path`, resolving its includes, and applies `local_manifests` on top of it in order.
+/// The files read are added to `files`, in the order they are read.
+fn resolve_manifests(
+    path: &Path,
+    local_manifests: &[PathBuf],
+    strict: bool,
+    files: &mut Vec<PathBuf>,
+) -> Result<Manifest, WorkspaceError> {
+    let _manifest_span = info_span!("Resolving manifest", path = %path.display()).entered();
+    let read = RefCell::new(Vec::new());
+    let parse = |path: PathBuf| {
+        let manifest = parse_manifest_file(&path, strict);
+        read.borrow_mut().push(path);
+        manifest
+    };
+    let manifest_dir = path.parent().unwrap_or(Path::new("."));
+    let mut load = |include: &str| parse(manifest_dir.join(include));
+    let file_name = |path: &Path| {
+        path.file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    };
+    // Projects of local manifests are reported as declared in local_manifests/<file>.
+    let mut resolve = |path: &Path, name: &str| -> Result<Manifest, WorkspaceError> {
+        Ok(parse(path.to_path_buf())?.resolve_includes(name, &mut load)?)
+    };
+
+    let mut manifest = resolve(path, &file_name(path))?;
+    for local in local_manifests {
+        let name = format!("local_manifests/{}", file_name(local));
+        manifest.apply_local_manifest(resolve(local, &name)?);
+    }
+    files.extend(read.into_inner());
+
+    Ok(manifest)
+}
+
+/// Parses a single manifest file, leaving its include elements unresolved.
+///
+/// Elements and attributes repox does not read are dropped by the parser. They are warned about, or with
+/// `strict` the first of them fails the parse.
+fn parse_manifest_file(path: &Path, strict: bool) -> Result<Manifest, WorkspaceError> {
+    let contents = read_to_string(path).map_err(WorkspaceError::ManifestReadError)?;
+    let manifest = match from_str(&contents) {
+        Ok(manifest) => manifest,
+        Err(error) => {
+            let span = parse_error_span(&contents, &error);
+            return Err(WorkspaceError::ManifestParseError(Box::new(
+                ManifestParseError {
+                    path: path.display().to_string(),
+                    // serde names attributes as `@`-prefixed fields.
+                    message: error.to_string().replace("field `@", "attribute `"),
+                    contents: NamedSource::new(path.display().to_string(), contents),
+                    span,
+                },
+            )));
+        }
+    };
+
+    for (line, column, name) in unknown_names(&contents) {
+        if strict {
+            return Err(WorkspaceError::UnknownName {
+                path: path.display().to_string(),
+                line,
+                column,
+                name,
+            });
+        }
+        let kind = match name {
+            UnknownName::Element { .. } => WarningKind::UnknownElement,
+            UnknownName::Attribute { .. } => WarningKind::UnknownAttribute,
+        };
+        warn(kind, format!("{}:{line}: {name}", path.display()));
+    }
+
+    Ok(manifest)
+}
+
+/// Where in the manifest `contents` parsing fails with `error`.
+///
+/// Malformed XML is pointed at the tag reading it stopped at. Otherwise the manifest's elements are parsed
+/// one at a time to find the one at fault, and within it the [culprit](culprit_span) of the error. The
+/// manifest element is pointed at when no single element fails on its own.
+fn parse_error_span(contents: &str, error: &DeError) -> SourceSpan {
+    let mut reader = Reader::from_str(contents);
+    let mut root = (0, 0);
+    let mut depth = 0;
+    let mut child_start = 0;
+    loop {
+        let start = reader.buffer_position();
+        let event = match reader.read_event() {
+            Ok(Event::Eof) => break,
+            Ok(event) => event,
+            Err(_) => {
+                let end = contents[start..]
+                    .find('>')
+                    .map_or(contents.len(), |index| start + index + 1);
+                return (start..end).into();
+            }
+        };
+        let end = reader.buffer_position();
+        let child = match event {
+            Event::Start(_) | Event::Empty(_) if depth == 0 => {
+                root = (start, end - start);
+                depth += usize::from(matches!(event, Event::Start(_)));
+                continue;
+            }
+            Event::Start(_) => {
+                if depth == 1 {
+                    child_start = start;
+                }
+                depth += 1;
+                continue;
+            }
+            Event::End(_) => {
+                depth -= 1;
+                if depth != 1 {
+                    continue;
+                }
+                child_start..end
+            }
+            Event::Empty(_) if depth == 1 => start..end,
+            _ => continue,
+        };
+
+        let element = &contents[child.clone()];
+        if from_str::<Manifest>(&format!("<manifest>{element}</manifest>")).is_err() {
+            return culprit_span(element, &error.to_string())
+                .map(|(offset, len)| (child.start + offset, len))
+                .unwrap_or_else(|| {
+                    let tag_end = element.find('>').map_or(element.len(), |index| index + 1);
+                    (child.start, tag_end)
+                })
+                .into();
+        }
+    }
+
+    root.into()
+}
+
+/// The offset and length in `element`, a manifest element failing to parse with `message`, of what the
+/// message is about: the tag missing the attribute it names, or the attribute whose value it quotes, as
+/// serde quotes the values it rejects.
+fn culprit_span(element: &str, message: &str) -> Option<(usize, usize)> {
+    let missing = message
+        .strip_prefix("missing field `@")
+        .and_then(|rest| rest.strip_suffix('`'));
+    let quoted: Vec<&str> = message
+        .split(['`', '\''])
+        .skip(1)
+        .step_by(2)
+        .filter(|value| !value.is_empty())
+        .collect();
+
+    let mut reader = Reader::from_str(element);
+    loop {
+        let tag_start = reader.buffer_position();
+        let tag = match reader.read_event().ok()? {
+            Event::Eof => return None,
+            Event::Start(tag) | Event::Empty(tag) => tag,
+            _ => continue,
+        };
+        let raw = &element[tag_start..reader.buffer_position()];
+
+        if let Some(missing) = missing {
+            let name = String::from_utf8_lossy(tag.name().as_ref()).into_owned();
+            let takes = known_attributes(&name).is_some_and(|known| known.contains(&missing));
+            if takes && !matches!(tag.try_get_attribute(missing), Ok(Some(_))) {
+                return Some((tag_start, raw.len()));
+            }
+            continue;
+        }
+        for attribute in tag.attributes().flatten() {
+            let value = attribute.unescape_value().ok()?;
+            if !quoted.contains(&value.as_ref()) {
+                continue;
+            }
+            let key = String::from_utf8_lossy(attribute.key.as_ref());
+            let raw_value = String::from_utf8_lossy(&attribute.value);
+            for quote in ['"', '\''] {
+                let written = format!("{key}={quote}{raw_value}{quote}");
+                if let Some(offset) = raw.find(&written) {
+                    return Some((tag_start + offset, written.len()));
+                }
+            }
+        }
+    }
+}
+
+/// An element or attribute of a manifest that repox does not read, usually a typo.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UnknownName {
+    Element { element: String },
+    Attribute { element: String, attribute: String },
+}
+
+impl fmt::Display for UnknownName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnknownName::Element { element } => write!(f, "<{element}> is not a manifest element"),
+            UnknownName::Attribute { element, attribute } => {
+                write!(f, "<{element}> has no {attribute} attribute")
+            }
+        }
+    }
+}
+
+/// The elements and attributes of the manifest `contents` that are dropped when parsing it, with the line
+/// and column of the element they are found in.
+fn unknown_names(contents: &str) -> Vec<(usize, usize, UnknownName)> {
+    let mut unknown = Vec::new();
+    let mut reader = Reader::from_str(contents);
+    loop {
+        let start = reader.buffer_position();
+        let element = match reader.read_event() {
+            Ok(Event::Start(element) | Event::Empty(element)) => element,
+            Ok(Event::Eof) | Err(_) => break,
+            Ok(_) => continue,
+        };
+        let line = contents[..start].matches('\n').count() + 1;
+        let column = start - contents[..start].rfind('\n').map_or(0, |index| index + 1) + 1;
+        let name = String::from_utf8_lossy(element.name().as_ref()).into_owned();
+        let Some(known) = known_attributes(&name) else {
+            unknown.push((line, column, UnknownName::Element { element: name }));
+            continue;
+        };
+
+        for attribute in element.attributes().flatten() {
+            let key = String::from_utf8_lossy(attribute.key.as_ref());
+            if !known.contains(&key.as_ref()) {
+                unknown.push((
+                    line,
+                    column,
+                    UnknownName::Attribute {
+                        element: name.clone(),
+                        attribute: key.into_owned(),
+                    },
+                ));
+            }
+        }
+    }
+
+    unknown
+}
@@ -0,0 +1,248 @@
+//! Local git fixtures for driving the repox binary end to end, without network access.
+
+use std::{
+    fs::{create_dir_all, write},
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+use tempfile::TempDir;
+
+/// Identity for commits and reflogs, so tests do not depend on the user's git configuration.
+const GIT_ENV: [(&str, &str); 4] = [
+    ("GIT_AUTHOR_NAME", "repox"),
+    ("GIT_AUTHOR_EMAIL", "repox@example.com"),
+    ("GIT_COMMITTER_NAME", "repox"),
+    ("GIT_COMMITTER_EMAIL", "repox@example.com"),
+];
+
+/// Runs git in `dir`, panicking with its output if it fails.
+pub fn git(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .envs(GIT_ENV)
+        .output()
+        .expect("git should be installed");
+    assert!(
+        output.status.success(),
+        "git {args:?} failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
+/// A directory of bare upstream repositories, a manifest describing them and an empty workspace.
+pub struct Fixture {
+    root: TempDir,
+}
+
+impl Fixture {
+    pub fn new() -> Self {
+        let fixture = Self {
+            root: tempfile::tempdir().unwrap(),
+        };
+        create_dir_all(fixture.upstream()).unwrap();
+        create_dir_all(fixture.workspace()).unwrap();
+        fixture
+    }
+
+    /// The directory holding the bare repositories, used as the manifest remote's fetch URL.
+    pub fn upstream(&self) -> PathBuf {
+        self.root.path().join("upstream")
+    }
+
+    /// The repo client checkout the commands run in.
+    pub fn workspace(&self) -> PathBuf {
+        self.root.path().join("workspace")
+    }
+
+    pub fn fetch_url(&self) -> String {
+        format!("file://{}", self.upstream().display())
+    }
+
+    /// Creates a bare repository `<name>.git` with a single commit on `main` adding `files`.
+    pub fn add_project(&self, name: &str, files: &[(&str, &str)]) {
+        let scratch = self.root.path().join("scratch").join(name);
+        create_dir_all(&scratch).unwrap();
+        git(&scratch, &["init", "--quiet", "--initial-branch", "main"]);
+        self.commit(&scratch, files, "initial commit");
+
+        let bare = self.upstream().join(format!("{name}.git"));
+        git(
+            self.root.path(),
+            &[
+                "clone",
+                "--quiet",
+                "--bare",
+                scratch.to_str().unwrap(),
+                bare.to_str().unwrap(),
+            ],
+        );
+    }
+
+    /// Adds a commit to `main` of an upstream project and returns its id.
+    pub fn push_commit(&self, name: &str, files: &[(&str, &str)], message: &str) -> String {
+        let scratch = self.root.path().join("scratch").join(name);
+        self.commit(&scratch, files, message);
+
+        let bare = self.upstream().join(format!("{name}.git"));
+        git(
+            &scratch,
+            &["push", "--quiet", bare.to_str().unwrap(), "main"],
+        );
+        git(&scratch, &["rev-parse", "HEAD"])
+    }
+
+    /// Uploads a commit on top of `main` as the Gerrit patchset `change`, e.g.
+    /// `refs/changes/01/1/1`, and returns its id.
+    pub fn push_change(
+        &self,
+        name: &str,
+        change: &str,
+        files: &[(&str, &str)],
+        message: &str,
+    ) -> String {
+        let scratch = self.root.path().join("scratch").join(name);
+        git(&scratch, &["checkout", "--quiet", "--detach"]);
+        self.commit(&scratch, files, message);
+
+        let bare = self.upstream().join(format!("{name}.git"));
+        git(
+            &scratch,
+            &[
+                "push",
+                "--quiet",
+                bare.to_str().unwrap(),
+                &format!("HEAD:{change}"),
+            ],
+        );
+        let id = git(&scratch, &["rev-parse", "HEAD"]);
+        git(&scratch, &["checkout", "--quiet", "main"]);
+        id
+    }
+
+    /// Adds upstream project `submodule` to `name` as a submodule at `path`, with a URL relative to
+    /// `name`'s own, and pushes the commit.
+    pub fn push_submodule(&self, name: &str, submodule: &str, path: &str) {
+        let scratch = self.root.path().join("scratch").join(name);
+        let url = self.upstream().join(format!("{submodule}.git"));
+        git(
+            &scratch,
+            &[
+                "-c",
+                "protocol.file.allow=always",
+                "submodule",
+                "add",
+                "--quiet",
+                url.to_str().unwrap(),
+                path,
+            ],
+        );
+        git(
+            &scratch,
+            &[
+                "config",
+                "--file",
+                ".gitmodules",
+                &format!("submodule.{path}.url"),
+                &format!("../{submodule}.git"),
+            ],
+        );
+        self.push_commit(name, &[], &format!("add {submodule} submodule"));
+    }
+
+    /// Renames the default branch of an upstream project, e.g. from `main` to `trunk`.
+    pub fn rename_default_branch(&self, name: &str, from: &str, to: &str) {
+        let bare = self.upstream().join(format!("{name}.git"));
+        git(&bare, &["branch", "--move", from, to]);
+        git(
+            &bare,
+            &["symbolic-ref", "HEAD", &format!("refs/heads/{to}")],
+        );
+    }
+
+    fn commit(&self, dir: &Path, files: &[(&str, &str)], message: &str) {
+        for (path, contents) in files {
+            let path = dir.join(path);
+            create_dir_all(path.parent().unwrap()).unwrap();
+            write(path, contents).unwrap();
+        }
+        git(dir, &["add", "--all"]);
+        git(
+            dir,
+            &["commit", "--quiet", "--allow-empty", "--message", message],
+        );
+    }
+
+    /// Writes the manifest, with a remote named `origin` pointing at the upstream directory
+    /// and `projects` as the body, and returns its path.
+    pub fn write_manifest(&self, projects: &str) -> PathBuf {
+        self.write_manifest_with_remote("", projects)
+    }
+
+    /// Writes the manifest like [`Fixture::write_manifest`], with `attributes` added to the `origin` remote.
+    fn write_manifest_with_remote(&self, attributes: &str, projects: &str) -> PathBuf {
+        let path = self.root.path().join("default.xml");
+        write(
+            &path,
+            format!(
+                r#"<manifest>
+                    <remote name="origin" fetch="{}" {attributes} />
+                    <default remote="origin" revision="main" />
+                    {projects}
+                </manifest>"#,
+                self.fetch_url()
+            ),
+        )
+        .unwrap();
+        path
+    }
+
+    /// Runs repox in the workspace.
+    pub fn repox(&self, args: &[&str]) -> Output {
+        Command::new(env!("CARGO_BIN_EXE_repox"))
+            .args(args)
+            .current_dir(self.workspace())
+            .envs(GIT_ENV)
+            .env("NO_COLOR", "1")
+            .output()
+            .unwrap()
+    }
+
+    /// Runs repox in the workspace, panicking with its output if it fails, and returns its stdout.
+    pub fn repox_ok(&self, args: &[&str]) -> String {
+        let output = self.repox(args);
+        assert!(
+            output.status.success(),
+            "repox {args:?} failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    }
+
+    /// Runs `repox init` against the fixture's manifest.
+    pub fn init(&self, projects: &str, extra_args: &[&str]) {
+        self.init_manifest(&self.write_manifest(projects), extra_args);
+    }
+
+    /// Runs `repox init` against the fixture's manifest, with a review host set on the `origin` remote, as
+    /// uploads for review need.
+    pub fn init_for_review(&self, projects: &str) {
+        let manifest = self.write_manifest_with_remote(r#"review="review.example.com""#, projects);
+        self.init_manifest(&manifest, &[]);
+    }
+
+    fn init_manifest(&self, manifest: &Path, extra_args: &[&str]) {
+        let mut args = vec![
+            "init",
+            "--manifest-url",
+            "file:///unused",
+            "--manifest-path",
+            manifest.to_str().unwrap(),
+        ];
+        args.extend(extra_args);
+        self.repox_ok(&args);
+    }
+}
@@ -0,0 +1,69 @@
+use clap::Parser;
+use repox::{
+    backend::fake::{Action, FakeBackend},
+    command::{
+        init::{init, InitArgs},
+        sync::{sync, SyncArgs},
+    },
+    workspace::Workspace,
+};
+use std::fs::write;
+
+#[derive(Parser)]
+struct InitCli {
+    #[command(flatten)]
+    args: InitArgs,
+}
+
+#[derive(Parser)]
+struct SyncCli {
+    #[command(flatten)]
+    args: SyncArgs,
+}
+
+#[test]
+fn test_init_then_sync_without_network() {
+    let dir = tempfile::tempdir().unwrap();
+    let manifest = dir.path().join("default.xml");
+    write(
+        &manifest,
+        r#"<manifest>
+            <remote name="origin" fetch="https://example.com" />
+            <default remote="origin" revision="main" />
+            <project name="app" path="src/app" />
+        </manifest>"#,
+    )
+    .unwrap();
+    let workspace = Workspace::create(dir.path().join("workspace")).unwrap();
+    let backend = FakeBackend::default();
+    let app = workspace.project_dir("src/app");
+
+    let args = InitCli::parse_from([
+        "init",
+        "--manifest-url",
+        "https://example.com/manifest",
+        "--manifest-path",
+        manifest.to_str().unwrap(),
+    ])
+    .args;
+    init(&backend, &workspace, &args.config().unwrap(), &args).unwrap();
+    assert_eq!(
+        backend.take_actions(),
+        [Action::Clone {
+            url: "https://example.com/app.git".to_string(),
+            dst: app.clone(),
+        }]
+    );
+
+    sync(&backend, &workspace, SyncCli::parse_from(["sync"]).args).unwrap();
+    assert_eq!(
+        backend.take_actions(),
+        [
+            Action::Fetch { dst: app.clone() },
+            Action::Update {
+                dst: app,
+                revision: Some("main".to_string()),
+            },
+        ]
+    );
+}